@@ -0,0 +1,102 @@
+//! Comparing the ways of decoding and walking the tree on a large tree, built by repeating the
+//! outputs of the `multi-output` fixture. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::Value;
+
+use haswaynav::tree::{
+    arena::Arena,
+    cursor::{find_focused, find_focused_fast},
+    TreeNode, TreeNodeSlim,
+};
+use haswaynav::Cursor;
+
+/// How often the outputs of the fixture are repeated.
+const COPIES: i64 = 50;
+
+/// Offset the ids of the node and its descendants, unfocusing the copies.
+fn offset_ids(node: &mut Value, offset: i64) {
+    if let Some(id) = node["id"].as_i64() {
+        node["id"] = (id + offset).into();
+    }
+    if let Some(focus) = node["focus"].as_array_mut() {
+        for id in focus {
+            *id = (id.as_i64().unwrap_or_default() + offset).into();
+        }
+    }
+    node["focused"] = false.into();
+    for key in ["nodes", "floating_nodes"] {
+        if let Some(children) = node[key].as_array_mut() {
+            children.iter_mut().for_each(|c| offset_ids(c, offset));
+        }
+    }
+}
+
+/// The JSON of a tree with the outputs of the fixture repeated, the focused window is in the last
+/// copy so finding it by search visits the whole tree.
+fn large_tree_json() -> Vec<u8> {
+    let json = include_str!("../tests/fixtures/trees/multi-output.json");
+    let mut tree: Value = serde_json::from_str(json).unwrap();
+    let outputs = tree["nodes"].as_array().unwrap().clone();
+    for copy in 1..=COPIES {
+        for output in outputs.iter().filter(|o| o["name"] != "__i3") {
+            let mut output = output.clone();
+            offset_ids(&mut output, copy * 100_000);
+            tree["nodes"].as_array_mut().unwrap().insert(0, output);
+        }
+    }
+    serde_json::to_vec(&tree).unwrap()
+}
+
+fn benches(c: &mut Criterion) {
+    let json = large_tree_json();
+    let tree: TreeNode = serde_json::from_slice(&json).unwrap();
+
+    let mut group = c.benchmark_group("decode");
+    group.bench_function("full", |b| {
+        b.iter(|| serde_json::from_slice::<TreeNode>(black_box(&json)).unwrap())
+    });
+    group.bench_function("slim", |b| {
+        b.iter(|| TreeNode::from(serde_json::from_slice::<TreeNodeSlim>(black_box(&json)).unwrap()))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("find_focused");
+    group.bench_function("search", |b| {
+        b.iter(|| find_focused(black_box(&tree)).map(|c| c.get_node().id))
+    });
+    group.bench_function("focus_lists", |b| {
+        b.iter(|| find_focused_fast(black_box(&tree)).map(|c| c.get_node().id))
+    });
+    group.bench_function("arena", |b| {
+        b.iter(|| {
+            Arena::new(black_box(&tree))
+                .find_focused()
+                .map(|c| c.get_node().id)
+        })
+    });
+    group.finish();
+
+    // Walk every node and look up its workspace, like the planners matching windows do.
+    let mut group = c.benchmark_group("traversal");
+    group.bench_function("rc_cursor", |b| {
+        b.iter(|| {
+            Cursor::new(black_box(&tree))
+                .iter()
+                .filter(|c| c.workspace().is_some())
+                .count()
+        })
+    });
+    group.bench_function("arena", |b| {
+        b.iter(|| {
+            Arena::new(black_box(&tree))
+                .iter()
+                .filter(|c| c.workspace().is_some())
+                .count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(cursor, benches);
+criterion_main!(cursor);
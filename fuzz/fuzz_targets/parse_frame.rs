@@ -0,0 +1,19 @@
+//! Feed arbitrary bytes to the IPC frame parser and decode the payloads of the frames it accepts
+//! as trees, neither may panic or allocate beyond the payload limit.
+
+#![no_main]
+
+use haswaynav::{
+    messages::{parse_frame, MAX_PAYLOAD_LEN},
+    tree::TreeNodeSlim,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut rest = data;
+    while let Ok(Some(frame)) = parse_frame(rest) {
+        assert!(frame.payload.len() <= MAX_PAYLOAD_LEN);
+        let _ = serde_json::from_slice::<TreeNodeSlim>(frame.payload);
+        rest = &rest[frame.len..];
+    }
+});
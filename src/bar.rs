@@ -0,0 +1,219 @@
+//! A status line for bars like waybar showing the binding mode, the layout of the focused container,
+//! do-not-disturb and the split chosen by `split auto --indicate`. A new line is printed whenever
+//! one of them changes, driven by sway's events instead of polling.
+
+use std::{
+    io::{self, Write},
+    os::unix::net::UnixStream,
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    config::BarConfig,
+    daemon::{default_socket_path, request, Request, Response},
+    messages::{get_binding_state, get_tree_slim, receive_event, subscribe},
+    query::focused_info,
+    split::{Split, SplitDirection},
+    sway_connect,
+    text::{render_template, strip_control},
+    tree::{Event, TreeNode},
+};
+
+/// How often the daemon is asked for do-not-disturb and the split without bindings from sway, those
+/// change through requests to the daemon which sway does not report.
+const DAEMON_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone, Default)]
+/// What the bar shows, see [status].
+pub struct BarStatus {
+    /// The active binding mode
+    pub mode: String,
+    /// The layout of the container holding the focused window, e.g. `splith` or `tabbed`
+    pub layout: String,
+    /// Whether do-not-disturb is on
+    pub dnd: bool,
+    /// The split chosen for the focused window by `split auto --indicate`
+    pub split: Option<SplitDirection>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// A line of the waybar `custom` module protocol.
+pub struct BarLine {
+    pub text: String,
+    /// The active binding mode
+    pub alt: String,
+    /// `mode` outside of the default mode, `dnd` and `split`, for styling the module
+    pub class: Vec<String>,
+}
+
+/// The status from the tree, the binding mode and the state of the daemon, if it runs. Control
+/// characters in the name of the mode are removed so it cannot break the lines.
+pub fn status(tree: &TreeNode, mode: &str, dnd: bool, split: Option<Split>) -> BarStatus {
+    let focused = focused_info(tree);
+    let layout = focused
+        .as_ref()
+        .and_then(|w| w.field("layout"))
+        .unwrap_or_default();
+    BarStatus {
+        mode: strip_control(mode),
+        layout,
+        dnd,
+        split: split
+            .filter(|s| focused.as_ref().is_some_and(|w| w.id == s.id))
+            .map(|s| s.direction),
+    }
+}
+
+/// Render the status with the formats. The part of the mode is left out in the default mode, the
+/// parts of do-not-disturb and the split while they are off.
+pub fn render(status: &BarStatus, config: &BarConfig) -> Result<BarLine> {
+    let mode = if status.mode == "default" {
+        String::new()
+    } else {
+        render_template(&config.mode, |name| {
+            (name == "mode").then(|| status.mode.clone())
+        })?
+    };
+    let layout = render_template(&config.layout, |name| {
+        (name == "layout").then(|| status.layout.clone())
+    })?;
+    let dnd = if status.dnd {
+        render_template(&config.dnd, |_| None)?
+    } else {
+        String::new()
+    };
+    let split = match status.split {
+        Some(direction) => render_template(&config.split, |name| {
+            (name == "direction").then(|| match direction {
+                SplitDirection::Horizontal => "horizontal".to_string(),
+                SplitDirection::Vertical => "vertical".to_string(),
+            })
+        })?,
+        None => String::new(),
+    };
+    let text = render_template(&config.format, |name| match name {
+        "mode" => Some(mode.clone()),
+        "layout" => Some(layout.clone()),
+        "dnd" => Some(dnd.clone()),
+        "split" => Some(split.clone()),
+        _ => None,
+    })?;
+    let class = [
+        (status.mode != "default", "mode"),
+        (status.dnd, "dnd"),
+        (status.split.is_some(), "split"),
+    ]
+    .into_iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, class)| class.to_string())
+    .collect();
+    Ok(BarLine {
+        text,
+        alt: status.mode.clone(),
+        class,
+    })
+}
+
+/// Do-not-disturb and the split from the daemon, off when it does not run.
+fn daemon_status() -> (bool, Option<Split>) {
+    match default_socket_path().and_then(|path| request(&path, &Request::Bar)) {
+        Ok(Response::Bar { dnd, split }) => (dnd, split),
+        Ok(response) => {
+            tracing::debug!(?response, "unexpected reply to the bar request");
+            (false, None)
+        }
+        Err(err) => {
+            tracing::debug!("no state from the daemon: {:#}", err);
+            (false, None)
+        }
+    }
+}
+
+/// Did reading the event time out rather than fail.
+fn timed_out(err: &anyhow::Error) -> bool {
+    err.chain().any(|e| {
+        e.downcast_ref::<io::Error>().is_some_and(|e| {
+            matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            )
+        })
+    })
+}
+
+/// Print a line for the bar whenever the status changes, as waybar JSON or with `plain` as text.
+pub fn run_bar(config: &BarConfig, plain: bool, out: &mut dyn Write) -> Result<()> {
+    let mut events = sway_connect()?;
+    subscribe(&mut events, &["mode", "window", "binding", "workspace"])?;
+    events.set_read_timeout(Some(DAEMON_INTERVAL))?;
+    let mut commands: UnixStream = sway_connect()?;
+    let mut mode = get_binding_state(&mut commands)?.name;
+    let mut shown = None;
+    let mut ask_daemon = true;
+    let (mut dnd, mut split) = (false, None);
+    loop {
+        if ask_daemon {
+            (dnd, split) = daemon_status();
+        }
+        let tree = get_tree_slim(&mut commands)?;
+        let line = render(&status(&tree, &mode, dnd, split), config)?;
+        if shown.as_ref() != Some(&line) {
+            if plain {
+                writeln!(out, "{}", line.text)?;
+            } else {
+                writeln!(out, "{}", serde_json::to_string(&line)?)?;
+            }
+            out.flush()?;
+            shown = Some(line);
+        }
+        // Do-not-disturb and the split only change when a binding or a script runs haswaynav, other
+        // events leave them alone and the daemon is not asked again.
+        ask_daemon = match receive_event(&mut events) {
+            Ok(event) => matches!(event, Event::Binding(_)),
+            Err(err) if timed_out(&err) => true,
+            Err(err) => return Err(err),
+        };
+        mode = get_binding_state(&mut commands)?.name;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn render_status() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let config = BarConfig::default();
+        let split = Split {
+            id: 126,
+            direction: SplitDirection::Vertical,
+        };
+
+        let plain = status(&tree, "default", false, None);
+        assert_eq!(plain.layout, "splith");
+        let line = render(&plain, &config).unwrap();
+        assert_eq!(line.text, "splith");
+        assert!(line.class.is_empty());
+
+        let busy = status(&tree, "resize", true, Some(split));
+        let line = render(&busy, &config).unwrap();
+        assert_eq!(line.text, "resize splith dnd vertical");
+        assert_eq!(line.class, ["mode", "dnd", "split"]);
+        assert_eq!(line.alt, "resize");
+
+        // The split of another window is not shown.
+        let other = Split { id: 1, ..split };
+        assert_eq!(status(&tree, "default", false, Some(other)).split, None);
+
+        let bad = BarConfig {
+            format: "{window}".to_string(),
+            ..BarConfig::default()
+        };
+        assert!(render(&plain, &bad).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Batch mode which runs many subcommands over a single connection to sway.
+
+use std::{
+    io::{BufRead, Write},
+    os::unix::net::UnixStream,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{cli::Cli, config::Config, execute};
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// The outcome of one line of input, written as a JSON line.
+pub struct BatchResult {
+    /// The line number of the command in the input, starting at 1
+    pub line: usize,
+    /// The command as read from the input
+    pub command: String,
+    /// Whether the command succeeded, blank and comment lines always do
+    pub success: bool,
+    /// The line was blank or a comment and nothing was run
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// What the command printed, such as the reply of a `get` command
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub output: String,
+    /// The reason the command failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Read one subcommand per line from the input and execute it, writing one [BatchResult] per
+/// line of input so results line up with the input. The connection to sway is opened once on first
+/// use and shared by all commands. Blank lines and lines starting with `#` are skipped. `offline`
+/// is passed on to [execute].
+pub fn run_batch(
+    input: &mut dyn BufRead,
+    out: &mut dyn Write,
+    config: &Config,
+    connect: &mut dyn FnMut() -> Result<UnixStream>,
+    offline: bool,
+) -> Result<()> {
+    let mut socket: Option<UnixStream> = None;
+    let mut shared = || -> Result<UnixStream> {
+        if socket.is_none() {
+            socket = Some(connect()?);
+        }
+        Ok(socket.as_ref().expect("connected above").try_clone()?)
+    };
+
+    for (idx, line) in input.lines().enumerate() {
+        let line = line?;
+        let command = line.trim();
+        let skipped = command.is_empty() || command.starts_with('#');
+        let mut output = Vec::new();
+        let result = if skipped {
+            Ok(())
+        } else {
+            let args = std::iter::once("haswaynav").chain(command.split_whitespace());
+            Cli::try_parse_args_from(args)
+                .map_err(anyhow::Error::from)
+                .and_then(|cli| execute(cli.command, config, &mut shared, offline, &mut output))
+        };
+        let result = BatchResult {
+            line: idx + 1,
+            command: command.to_string(),
+            success: result.is_ok(),
+            skipped,
+            output: String::from_utf8_lossy(&output).trim_end().to_string(),
+            error: result.err().map(|err| format!("{:#}", err)),
+        };
+        serde_json::to_writer(&mut *out, &result)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_run_batch() {
+    let mut input = "\n# comment\nfocus left\nbogus\ncompletions bash\n".as_bytes();
+    let mut out = Vec::new();
+    let mut connects = 0;
+    let mut connect = || -> Result<UnixStream> {
+        connects += 1;
+        anyhow::bail!("no sway")
+    };
+
+    run_batch(&mut input, &mut out, &Config::default(), &mut connect, true).unwrap();
+
+    let lines: Vec<serde_json::Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0]["line"], 1);
+    assert_eq!(lines[0]["skipped"], true);
+    assert_eq!(lines[1]["command"], "# comment");
+    assert_eq!(lines[1]["success"], true);
+    assert_eq!(lines[2]["line"], 3);
+    assert_eq!(lines[2]["command"], "focus left");
+    assert_eq!(lines[2]["success"], false);
+    assert_eq!(lines[2]["error"], "no sway");
+    assert_eq!(lines[3]["line"], 4);
+    assert_eq!(lines[3]["success"], false);
+    assert_eq!(lines[4]["success"], true);
+    assert!(lines[4]["output"].as_str().unwrap().contains("complete"));
+    assert!(lines[4].get("skipped").is_none());
+    assert_eq!(connects, 1);
+}
@@ -0,0 +1,129 @@
+//! The `bench` command which measures the latency of the steps of a navigation.
+
+use std::{
+    io::Write,
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    messages::{get_outputs, get_snapshot, get_tree, get_tree_json, get_workspaces, run_command},
+    tree::{cursor::find_focused_fast, TreeNode, TreeNodeSlim},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// The percentiles of the timings of one step.
+pub struct Summary {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Summary {
+    /// Summarize the timings, `None` when there are none.
+    pub fn new(mut timings: Vec<Duration>) -> Option<Summary> {
+        timings.sort();
+        let max = *timings.last()?;
+        let percentile = |p: usize| timings[(timings.len() - 1) * p / 100];
+        Some(Summary {
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max,
+        })
+    }
+}
+
+/// Time the given closure.
+fn time<T>(f: impl FnOnce() -> Result<T>) -> Result<(T, Duration)> {
+    let start = Instant::now();
+    let result = f()?;
+    Ok((result, start.elapsed()))
+}
+
+/// Run every step the given number of times and write the percentiles of their timings.
+pub fn run_bench(socket: &mut UnixStream, iterations: usize, out: &mut dyn Write) -> Result<()> {
+    let mut fetch = Vec::with_capacity(iterations);
+    let mut decode = Vec::with_capacity(iterations);
+    let mut decode_slim = Vec::with_capacity(iterations);
+    let mut lookup = Vec::with_capacity(iterations);
+    let mut command = Vec::with_capacity(iterations);
+    let mut serial = Vec::with_capacity(iterations);
+    let mut pipelined = Vec::with_capacity(iterations);
+    let mut size = 0;
+    for _ in 0..iterations {
+        let (json, elapsed) = time(|| get_tree_json(socket))?;
+        fetch.push(elapsed);
+        size = json.len();
+
+        let (tree, elapsed) =
+            time(|| serde_json::from_slice::<TreeNode>(&json).context("decoding the tree"))?;
+        decode.push(elapsed);
+
+        let (_, elapsed) = time(|| {
+            serde_json::from_slice::<TreeNodeSlim>(&json)
+                .map(TreeNode::from)
+                .context("decoding the slim tree")
+        })?;
+        decode_slim.push(elapsed);
+
+        let (_, elapsed) = time(|| Ok(find_focused_fast(&tree).map(|c| c.get_node().id)))?;
+        lookup.push(elapsed);
+
+        let (_, elapsed) = time(|| run_command(socket, "nop"))?;
+        command.push(elapsed);
+
+        let (_, elapsed) = time(|| {
+            Ok((
+                get_tree(socket)?,
+                get_workspaces(socket)?,
+                get_outputs(socket)?,
+            ))
+        })?;
+        serial.push(elapsed);
+
+        let (_, elapsed) = time(|| get_snapshot(socket))?;
+        pipelined.push(elapsed);
+    }
+
+    writeln!(out, "{} iterations, tree of {} bytes", iterations, size)?;
+    writeln!(
+        out,
+        "{:<16} {:>10} {:>10} {:>10} {:>10}",
+        "step", "p50", "p90", "p99", "max"
+    )?;
+    for (name, timings) in [
+        ("GET_TREE", fetch),
+        ("decode", decode),
+        ("decode slim", decode_slim),
+        ("focused lookup", lookup),
+        ("RUN_COMMAND nop", command),
+        ("serial queries", serial),
+        ("pipelined", pipelined),
+    ] {
+        if let Some(s) = Summary::new(timings) {
+            writeln!(
+                out,
+                "{:<16} {:>10.1?} {:>10.1?} {:>10.1?} {:>10.1?}",
+                name, s.p50, s.p90, s.p99, s.max
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_summary() {
+    let timings = (1..=100).rev().map(Duration::from_millis).collect();
+
+    let summary = Summary::new(timings).unwrap();
+
+    assert_eq!(summary.p50, Duration::from_millis(50));
+    assert_eq!(summary.p90, Duration::from_millis(90));
+    assert_eq!(summary.p99, Duration::from_millis(99));
+    assert_eq!(summary.max, Duration::from_millis(100));
+    assert_eq!(Summary::new(Vec::new()), None);
+}
@@ -1,20 +1,126 @@
-use clap::Parser;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
 use haswaynav::{
-    change_focus,
-    cli::{Commands, FocusArgs},
-    sway_connect,
+    capture::{collect_frames, commands, start_trace, take_frames},
+    cli::Cli,
+    config::Config,
+    execute,
+    invocations::{append_invocation, default_log_path, Invocation},
+    logging::init_logging,
+    notify::{enable_notifications, notify},
+    run_once,
+    snapshot::Bundle,
+    sway_connect, wait_for_sway,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 fn main() -> Result<()> {
-    let command = Commands::parse();
+    let cli = Cli::parse_args();
+    init_logging(cli.verbose, cli.quiet);
+    if cli.notify {
+        enable_notifications();
+    }
+    let bundle = match (&cli.snapshot, &cli.tree_file) {
+        (Some(path), _) => Some(Bundle::load(path)?),
+        (None, Some(path)) => Some(Bundle::from_tree(path)?),
+        (None, None) => None,
+    };
+    let bundled_config = match &bundle {
+        Some(bundle) if cli.config.is_none() => bundle.config()?,
+        _ => None,
+    };
+    let config = match bundled_config {
+        Some(config) => config,
+        None => {
+            Config::load(cli.config.as_deref()).inspect_err(|err| notify(&format!("{:#}", err)))?
+        }
+    };
+    if config.notify {
+        enable_notifications();
+    }
+
+    if let Some(seconds) = cli.wait_for_sway.filter(|_| bundle.is_none()) {
+        wait_for_sway(Duration::from_secs(seconds))?;
+    }
+    if let Some(path) = &cli.trace_ipc {
+        start_trace(path, std::env::args().collect())?;
+    }
+    let log_file =
+        match cli.log_file {
+            None => None,
+            Some(Some(path)) => Some(path),
+            Some(None) => Some(default_log_path().context(
+                "Neither XDG_STATE_HOME nor HOME is set to find the default log file in",
+            )?),
+        };
+    if log_file.is_some() {
+        collect_frames();
+    }
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    let start = Instant::now();
 
-    let mut socket = sway_connect()?;
+    let result = match &bundle {
+        Some(bundle) => {
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            let result = execute(
+                cli.command,
+                &config,
+                &mut || bundle.connect(sent.clone()),
+                true,
+                &mut std::io::stdout(),
+            );
+            for commands in sent.lock().expect("sent lock poisoned").iter() {
+                println!("would run: {}", commands);
+            }
+            result
+        }
+        None if run_once::active(&config) => {
+            record_focus();
+            let result = execute(
+                cli.command,
+                &config,
+                &mut sway_connect,
+                false,
+                &mut std::io::stdout(),
+            );
+            record_focus();
+            result
+        }
+        None => execute(
+            cli.command,
+            &config,
+            &mut sway_connect,
+            false,
+            &mut std::io::stdout(),
+        ),
+    };
 
-    match command {
-        Commands::Focus(FocusArgs { direction }) => change_focus(&mut socket, direction)?,
+    if let Some(path) = log_file {
+        let invocation = Invocation {
+            time,
+            args: std::env::args().collect(),
+            commands: commands(&take_frames()),
+            error: result.as_ref().err().map(|err| format!("{:#}", err)),
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+        };
+        if let Err(err) = append_invocation(&path, &invocation) {
+            tracing::warn!("failed logging the invocation: {:#}", err);
+        }
     }
+    result.inspect_err(|err| notify(&format!("{:#}", err)))
+}
 
-    Ok(())
+/// Record the focus before and after the command without a daemon, see [run_once]. Failing to is
+/// only logged so it never fails the command.
+fn record_focus() {
+    if let Err(err) = sway_connect().and_then(|mut socket| run_once::record(&mut socket)) {
+        tracing::debug!("not recording the focus: {:#}", err);
+    }
 }
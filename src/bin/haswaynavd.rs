@@ -0,0 +1,65 @@
+use std::{os::unix::process::CommandExt, process::Stdio, time::Duration};
+
+use clap::Parser;
+use haswaynav::{
+    cli::DaemonArgs,
+    daemon::{bind, listen_fds, lock_session, sd_notify, serve, session_socket_path, SharedConfig},
+    logging::init_logging,
+    wait_for_sway,
+};
+
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    let args = DaemonArgs::parse();
+    init_logging(args.verbose, args.quiet);
+    let config = SharedConfig::load(args.config.clone())?;
+    if let Some(seconds) = args.wait_for_sway {
+        wait_for_sway(Duration::from_secs(seconds))?;
+    }
+
+    if !args.foreground && !args.systemd {
+        // Detach by re-running ourselves in the foreground in a new process group.
+        let mut command = std::process::Command::new(std::env::current_exe()?);
+        command
+            .arg("--foreground")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0);
+        if let Some(socket) = &args.socket {
+            command.arg("--socket").arg(socket);
+        }
+        if let Some(config) = &args.config {
+            command.arg("--config").arg(config);
+        }
+        if args.verbose > 0 {
+            command.arg(format!("-{}", "v".repeat(args.verbose.into())));
+        }
+        if args.quiet {
+            command.arg("--quiet");
+        }
+        command.spawn()?;
+        return Ok(());
+    }
+
+    // Held until the daemon exits.
+    let _lock = lock_session()?;
+    let inherited = if args.systemd { listen_fds()? } else { None };
+    let listener = match inherited {
+        Some(listener) => listener,
+        None => {
+            let path = match args.socket {
+                Some(path) => path,
+                None => session_socket_path()?,
+            };
+            bind(&path)?
+        }
+    };
+
+    if args.systemd {
+        sd_notify("READY=1")?;
+    }
+
+    serve(listener, config)
+}
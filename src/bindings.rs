@@ -0,0 +1,141 @@
+//! The `init-bindings` command which prints a sway config fragment binding the haswaynav commands.
+
+use std::{collections::HashMap, io::Write};
+
+use anyhow::Result;
+
+use crate::cli::BindingStyle;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// A key binding of the generated fragment.
+pub struct Binding {
+    /// The key combination, e.g. `Mod4+h`
+    pub keys: String,
+    /// The haswaynav arguments run for the keys
+    pub command: String,
+}
+
+/// The bindings for the given modifier and style.
+pub fn bindings(modifier: &str, style: BindingStyle) -> Vec<Binding> {
+    let keys = match style {
+        BindingStyle::Vim => ["h", "l", "k", "j"],
+        BindingStyle::Arrows => ["Left", "Right", "Up", "Down"],
+    };
+    keys.iter()
+        .zip(["left", "right", "up", "down"])
+        .map(|(key, direction)| Binding {
+            keys: format!("{}+{}", modifier, key),
+            command: format!("focus {}", direction),
+        })
+        .collect()
+}
+
+/// Normalize a key combination so that equal combinations compare equal, modifiers are sorted and
+/// case is ignored.
+pub fn normalize_keys(keys: &str) -> String {
+    let mut parts: Vec<String> = keys.split('+').map(str::to_lowercase).collect();
+    let key = parts.pop().unwrap_or_default();
+    parts.sort();
+    parts.push(key);
+    parts.join("+")
+}
+
+/// The `bindsym` lines of the default mode of a sway config keyed by their normalized key
+/// combination. Variables defined with `set` are expanded.
+pub fn existing_bindings(config: &str) -> HashMap<String, String> {
+    let mut variables: Vec<(String, String)> = Vec::new();
+    let mut bindings = HashMap::new();
+    let mut depth = 0;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("set") => {
+                if let (Some(name), Some(value)) = (words.next(), words.next()) {
+                    variables.push((name.to_string(), value.to_string()));
+                    // Longest names first so `$mod` does not replace the start of `$modifier`.
+                    variables.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+                }
+            }
+            Some("bindsym") if depth == 0 => {
+                if let Some(keys) = words.find(|w| !w.starts_with("--")) {
+                    let keys = variables
+                        .iter()
+                        .fold(keys.to_string(), |keys, (name, value)| {
+                            keys.replace(name.as_str(), value)
+                        });
+                    bindings.insert(normalize_keys(&keys), line.to_string());
+                }
+            }
+            _ => (),
+        }
+        if line.ends_with('{') {
+            depth += 1;
+        } else if line.starts_with('}') {
+            depth -= 1;
+        }
+    }
+    bindings
+}
+
+/// Write the sway config fragment. Bindings conflicting with one of the existing sway config are
+/// preceded by a comment quoting the existing binding. Returns the number of conflicts.
+pub fn write_bindings(
+    out: &mut dyn Write,
+    bindings: &[Binding],
+    existing: Option<&str>,
+) -> Result<usize> {
+    let existing = existing.map(existing_bindings).unwrap_or_default();
+    let mut conflicts = 0;
+    writeln!(out, "# Generated by `haswaynav init-bindings`")?;
+    for binding in bindings {
+        if let Some(line) = existing.get(&normalize_keys(&binding.keys)) {
+            conflicts += 1;
+            writeln!(out, "# conflicts with the existing binding: {}", line)?;
+        }
+        writeln!(
+            out,
+            "bindsym {} exec haswaynav {}",
+            binding.keys, binding.command
+        )?;
+    }
+    Ok(conflicts)
+}
+
+#[test]
+fn test_existing_bindings() {
+    let config = "set $mod Mod4\n\
+                  # bindsym $mod+x kill\n\
+                  bindsym --release $mod+H focus left\n\
+                  mode \"resize\" {\n    bindsym $mod+j resize grow height\n}\n";
+
+    let existing = existing_bindings(config);
+
+    assert_eq!(
+        existing.get("mod4+h").map(String::as_str),
+        Some("bindsym --release $mod+H focus left")
+    );
+    assert_eq!(existing.len(), 1);
+}
+
+#[test]
+fn test_write_bindings() {
+    let mut out = Vec::new();
+
+    let conflicts = write_bindings(
+        &mut out,
+        &bindings("Mod4", BindingStyle::Arrows),
+        Some("bindsym Mod4+Left focus left"),
+    )
+    .unwrap();
+
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(conflicts, 1);
+    assert!(out.contains(
+        "# conflicts with the existing binding: bindsym Mod4+Left focus left\n\
+         bindsym Mod4+Left exec haswaynav focus left\n"
+    ));
+    assert!(out.contains("bindsym Mod4+Down exec haswaynav focus down\n"));
+}
@@ -0,0 +1,276 @@
+//! Recording the IPC traffic with sway to a capture file and replaying it offline.
+//!
+//! A capture is a JSON lines file. The first line holds the command line arguments of the
+//! invocation which was traced and every following line one frame sent to or received from sway.
+//! Replaying a capture runs the same command again against a fake sway which answers every
+//! message with the reply recorded for it, so the decision logic can be reproduced without the
+//! original tree.
+
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+#[cfg(feature = "cli")]
+use std::{os::unix::net::UnixStream, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::messages::MessageType;
+#[cfg(feature = "cli")]
+use crate::{
+    cli::Cli,
+    config::Config,
+    execute,
+    messages::{read_frame, write_frame},
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+/// See [Frame::direction]
+pub enum FrameDirection {
+    Sent,
+    Received,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// A single message exchanged with sway.
+pub struct Frame {
+    /// Seconds since the unix epoch when the frame was sent or received
+    pub time: f64,
+    /// Whether the frame was sent to or received from sway
+    pub direction: FrameDirection,
+    /// The message type, with the high bit set for events
+    pub message_type: u32,
+    /// The length of the payload in bytes
+    pub length: usize,
+    /// The payload, sway only sends JSON so it is kept as text
+    pub payload: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+/// A line of a capture file.
+pub enum CaptureLine {
+    /// The command line arguments of the traced invocation
+    Args {
+        args: Vec<String>,
+    },
+    Frame(Frame),
+}
+
+/// The file frames are recorded to when tracing is enabled.
+static TRACE: Mutex<Option<BufWriter<File>>> = Mutex::new(None);
+
+/// The frames collected in memory since [collect_frames] was called.
+static COLLECTED: Mutex<Option<Vec<Frame>>> = Mutex::new(None);
+
+/// Start collecting every frame exchanged with sway in memory, see [take_frames].
+pub fn collect_frames() {
+    *COLLECTED.lock().expect("collect lock poisoned") = Some(Vec::new());
+}
+
+/// Take the frames collected since the last call, collection continues if it was started.
+pub fn take_frames() -> Vec<Frame> {
+    COLLECTED
+        .lock()
+        .expect("collect lock poisoned")
+        .as_mut()
+        .map(std::mem::take)
+        .unwrap_or_default()
+}
+
+/// Start recording every frame exchanged with sway to the given file, starting it with the
+/// command line arguments so that the capture can be replayed.
+pub fn start_trace(path: &Path, args: Vec<String>) -> Result<()> {
+    let mut file = BufWriter::new(
+        File::create(path)
+            .with_context(|| format!("creating IPC trace file '{}'", path.display()))?,
+    );
+    serde_json::to_writer(&mut file, &CaptureLine::Args { args })?;
+    file.write_all(b"\n")?;
+    file.flush()?;
+    *TRACE.lock().expect("trace lock poisoned") = Some(file);
+    Ok(())
+}
+
+/// Record a frame if tracing or collection is enabled, failures to record are logged rather than
+/// interrupting the navigation.
+pub(crate) fn record(direction: FrameDirection, message_type: u32, payload: &[u8]) {
+    let mut trace = TRACE.lock().expect("trace lock poisoned");
+    let mut collected = COLLECTED.lock().expect("collect lock poisoned");
+    if trace.is_none() && collected.is_none() {
+        return;
+    }
+    let frame = Frame {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default(),
+        direction,
+        message_type,
+        length: payload.len(),
+        payload: String::from_utf8_lossy(payload).into_owned(),
+    };
+    if let Some(file) = trace.as_mut() {
+        let result = serde_json::to_writer(&mut *file, &CaptureLine::Frame(frame.clone()))
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(file.write_all(b"\n")?))
+            .and_then(|_| Ok(file.flush()?));
+        if let Err(err) = result {
+            tracing::warn!("failed recording IPC frame: {:#}", err);
+        }
+    }
+    if let Some(frames) = collected.as_mut() {
+        frames.push(frame);
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+/// The contents of a capture file.
+pub struct Capture {
+    pub args: Vec<String>,
+    pub frames: Vec<Frame>,
+}
+
+impl Capture {
+    /// Read a capture file.
+    pub fn load(path: &Path) -> Result<Capture> {
+        let file = File::open(path)
+            .with_context(|| format!("opening capture file '{}'", path.display()))?;
+        let mut capture = Capture::default();
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line)
+                .with_context(|| format!("decoding line {} of '{}'", idx + 1, path.display()))?
+            {
+                CaptureLine::Args { args } => capture.args = args,
+                CaptureLine::Frame(frame) => capture.frames.push(frame),
+            }
+        }
+        Ok(capture)
+    }
+
+    /// The payloads of the `RUN_COMMAND` messages sent to sway.
+    pub fn commands(&self) -> Vec<String> {
+        commands(&self.frames)
+    }
+}
+
+/// The payloads of the `RUN_COMMAND` messages sent to sway among the frames.
+pub fn commands(frames: &[Frame]) -> Vec<String> {
+    frames
+        .iter()
+        .filter(|f| {
+            f.direction == FrameDirection::Sent && f.message_type == MessageType::RunCommand as u32
+        })
+        .map(|f| f.payload.clone())
+        .collect()
+}
+
+#[cfg(feature = "cli")]
+/// Act as sway on the socket answering each message with the next recorded reply of the same type.
+/// Commands without a recorded reply are reported as successful.
+fn serve_capture(
+    mut sock: UnixStream,
+    mut replies: Vec<Frame>,
+    sent: Arc<Mutex<Vec<Frame>>>,
+) -> Result<()> {
+    loop {
+        let (message_type, payload) = match read_frame(&mut sock) {
+            Ok(frame) => frame,
+            // The client hung up.
+            Err(_) => return Ok(()),
+        };
+        let payload = String::from_utf8_lossy(&payload).into_owned();
+        sent.lock().expect("sent lock poisoned").push(Frame {
+            time: 0.0,
+            direction: FrameDirection::Sent,
+            message_type,
+            length: payload.len(),
+            payload: payload.clone(),
+        });
+
+        let reply = match replies.iter().position(|f| f.message_type == message_type) {
+            Some(idx) => replies.remove(idx).payload,
+            None if message_type == MessageType::RunCommand as u32 => {
+                let count = payload.split([';', ',']).count();
+                serde_json::to_string(&vec![serde_json::json!({"success": true}); count])?
+            }
+            None => anyhow::bail!(
+                "the capture has no reply left for a message of type {}",
+                message_type
+            ),
+        };
+        write_frame(&mut sock, message_type, reply.as_bytes())?;
+    }
+}
+
+#[cfg(feature = "cli")]
+/// Replay the capture file, running the traced command again against the recorded replies, and
+/// print the commands sent to sway when recording next to the ones sent now.
+pub fn replay(path: &Path, config: &Config, out: &mut dyn Write) -> Result<()> {
+    let capture = Capture::load(path)?;
+    let cli = Cli::try_parse_args_from(&capture.args)
+        .with_context(|| format!("parsing the recorded arguments {:?}", capture.args))?;
+    let replies: Vec<Frame> = capture
+        .frames
+        .iter()
+        .filter(|f| f.direction == FrameDirection::Received)
+        .cloned()
+        .collect();
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut servers = Vec::new();
+    let mut connect = || -> Result<UnixStream> {
+        let (client, server) = UnixStream::pair()?;
+        let replies = replies.clone();
+        let sent = sent.clone();
+        servers.push(std::thread::spawn(move || {
+            serve_capture(server, replies, sent)
+        }));
+        Ok(client)
+    };
+    let result = execute(cli.command, config, &mut connect, true, out);
+    for server in servers {
+        if let Err(err) = server.join().expect("replay thread panicked") {
+            tracing::warn!("replaying the capture: {:#}", err);
+        }
+    }
+
+    let recorded = capture.commands();
+    let replayed = commands(&sent.lock().expect("sent lock poisoned"));
+    writeln!(out, "recorded: {:?}", recorded)?;
+    writeln!(out, "replayed: {:?}", replayed)?;
+    if recorded != replayed {
+        writeln!(out, "the replayed commands differ from the recorded ones")?;
+    }
+    result
+}
+
+#[test]
+fn test_capture_line_round_trip() {
+    let lines = [
+        CaptureLine::Args {
+            args: vec!["haswaynav".to_string(), "focus".to_string()],
+        },
+        CaptureLine::Frame(Frame {
+            time: 1.5,
+            direction: FrameDirection::Received,
+            message_type: 4,
+            length: 2,
+            payload: "{}".to_string(),
+        }),
+    ];
+    for line in lines {
+        let json = serde_json::to_string(&line).unwrap();
+        let parsed: CaptureLine = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, line);
+    }
+}
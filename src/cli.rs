@@ -1,27 +1,940 @@
 //! All the types related to the CLI
 
-use clap::Parser;
+use clap::{
+    builder::{PossibleValue, TypedValueParser, ValueParserFactory},
+    error::ErrorKind,
+    ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum,
+};
+use std::{ffi::OsString, marker::PhantomData, path::PathBuf, time::Duration};
+
+pub use crate::Direction;
+use crate::{
+    config::{Axes, Boundary, CrossOutput, FocusConfig, OnEdge, Pattern},
+    daemon::DndMode,
+    geometry::parse_dimensions,
+    launch::Size,
+    mode::parse_duration,
+    output::OutputTarget,
+    pointer::PointerTarget,
+    profile::LayoutMode,
+};
 
 #[derive(Debug, Parser)]
-#[clap(long_about= None)]
+#[clap(long_about = None)]
 /// Custom navigation commands for sway
+pub struct Cli {
+    #[arg(long, global = true)]
+    /// Path of the configuration file, defaults to `$XDG_CONFIG_HOME/haswaynav/config.toml`.
+    pub config: Option<PathBuf>,
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    /// Log more details to stderr, repeat for even more. `RUST_LOG` takes precedence when set.
+    pub verbose: u8,
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    /// Only log errors.
+    pub quiet: bool,
+    #[arg(long, global = true, value_name = "FILE")]
+    /// Record every message exchanged with sway to the file, see the `replay` command.
+    pub trace_ipc: Option<PathBuf>,
+    #[arg(long, global = true)]
+    /// Show a desktop notification when the command fails or a move finds nothing to focus.
+    pub notify: bool,
+    #[arg(long, global = true, value_name = "FILE", num_args = 0..=1, require_equals = true)]
+    /// Append the arguments, the commands sent to sway, the result and the timing of the
+    /// invocation to the log file, defaults to `$XDG_STATE_HOME/haswaynav/invocations.log`.
+    pub log_file: Option<Option<PathBuf>>,
+    #[arg(long, global = true, value_name = "FILE")]
+    /// Run against the replies saved by `snapshot` instead of sway, printing the commands which
+    /// would be sent. Uses the bundled config file unless `--config` is given.
+    pub snapshot: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "FILE", conflicts_with = "snapshot")]
+    /// Plan against the tree saved as JSON instead of asking sway, printing the commands which
+    /// would be sent, e.g. to triage a tree from `swaymsg -t get_tree` without sway.
+    pub tree_file: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "SECONDS")]
+    /// Wait up to this long for sway to accept connections instead of failing right away, for
+    /// autostart scripts which may run before sway is up.
+    pub wait_for_sway: Option<u64>,
+    #[arg(long, global = true)]
+    /// Reject directions in another case or cut short, e.g. `Right` or `ri`, instead of guessing.
+    /// The vim keys and the names of the arrow keys are always accepted.
+    pub strict: bool,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+impl Cli {
+    /// Parse the arguments of the process, see [Cli::try_parse_args_from].
+    pub fn parse_args() -> Cli {
+        Cli::try_parse_args_from(std::env::args_os()).unwrap_or_else(|err| err.exit())
+    }
+
+    /// Parse the arguments, checking the spelling of the [LenientValue] arguments strictly when
+    /// `--strict` is among them.
+    pub fn try_parse_args_from<I, T>(args: I) -> Result<Cli, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut command = Cli::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        let cli = Cli::from_arg_matches(&matches).map_err(|err| err.format(&mut command))?;
+        if cli.strict {
+            check_strict(&matches).map_err(|err| err.format(&mut command))?;
+        }
+        Ok(cli)
+    }
+}
+
+/// Reject the arguments of the subcommand and the ones below it which are only accepted leniently.
+fn check_strict(matches: &ArgMatches) -> Result<(), clap::Error> {
+    for id in matches.ids() {
+        check_strict_values::<Direction>(matches, id.as_str())?;
+        check_strict_values::<OutputTarget>(matches, id.as_str())?;
+        check_strict_values::<PointerTarget>(matches, id.as_str())?;
+    }
+    match matches.subcommand() {
+        Some((_, matches)) => check_strict(matches),
+        None => Ok(()),
+    }
+}
+
+/// Parse the values given for the argument strictly if it is of type `T`.
+fn check_strict_values<T: LenientValue>(matches: &ArgMatches, id: &str) -> Result<(), clap::Error> {
+    if matches.try_get_many::<T>(id).is_err() {
+        return Ok(());
+    }
+    for value in matches.try_get_raw(id).ok().flatten().into_iter().flatten() {
+        T::parse(&value.to_string_lossy(), true)
+            .map_err(|err| clap::Error::raw(ErrorKind::InvalidValue, format!("{}\n", err)))?;
+    }
+    Ok(())
+}
+
+/// A value accepted in other spellings as well unless `--strict` is given, see [Direction::parse].
+pub trait LenientValue: Sized + Clone + Send + Sync + 'static {
+    /// The names offered for completion
+    const NAMES: &'static [&'static str];
+
+    fn parse(value: &str, strict: bool) -> anyhow::Result<Self>;
+}
+
+impl LenientValue for Direction {
+    const NAMES: &'static [&'static str] = &["left", "right", "up", "down"];
+
+    fn parse(value: &str, strict: bool) -> anyhow::Result<Self> {
+        Direction::parse(value, strict)
+    }
+}
+
+impl LenientValue for OutputTarget {
+    const NAMES: &'static [&'static str] = &OutputTarget::NAMES;
+
+    fn parse(value: &str, strict: bool) -> anyhow::Result<Self> {
+        OutputTarget::parse(value, strict)
+    }
+}
+
+impl LenientValue for PointerTarget {
+    const NAMES: &'static [&'static str] = &[];
+
+    fn parse(value: &str, strict: bool) -> anyhow::Result<Self> {
+        PointerTarget::parse(value, strict)
+    }
+}
+
+/// Parses the [LenientValue] arguments leniently, strict parsing follows in
+/// [Cli::try_parse_args_from] once `--strict` is known.
+#[derive(Clone)]
+pub struct LenientParser<T>(PhantomData<T>);
+
+impl<T: LenientValue> TypedValueParser for LenientParser<T> {
+    type Value = T;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<T, clap::Error> {
+        T::parse(&value.to_string_lossy(), false).map_err(|err| {
+            clap::Error::raw(ErrorKind::InvalidValue, format!("{}\n", err)).with_cmd(cmd)
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        if T::NAMES.is_empty() {
+            return None;
+        }
+        Some(Box::new(
+            T::NAMES.iter().map(|name| PossibleValue::new(*name)),
+        ))
+    }
+}
+
+impl ValueParserFactory for Direction {
+    type Parser = LenientParser<Direction>;
+
+    fn value_parser() -> Self::Parser {
+        LenientParser(PhantomData)
+    }
+}
+
+impl ValueParserFactory for OutputTarget {
+    type Parser = LenientParser<OutputTarget>;
+
+    fn value_parser() -> Self::Parser {
+        LenientParser(PhantomData)
+    }
+}
+
+impl ValueParserFactory for PointerTarget {
+    type Parser = LenientParser<PointerTarget>;
+
+    fn value_parser() -> Self::Parser {
+        LenientParser(PhantomData)
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The subcommands of haswaynav
 pub enum Commands {
     #[command(name = "focus")]
     /// Perform a change of focus in the given direction skipping over tabbed and stacked siblings.
     Focus(FocusArgs),
+    #[command(name = "run")]
+    /// Run the named alias from the config file.
+    Run {
+        #[arg(value_name = "ALIAS")]
+        name: String,
+    },
+    #[command(name = "completions")]
+    /// Print the completion script for the given shell.
+    Completions { shell: clap_complete::Shell },
+    #[command(name = "complete", hide = true)]
+    /// Print the live values used by the completion scripts, one per line.
+    Complete { kind: CompletionKind },
+    #[command(name = "doctor")]
+    /// Diagnose common problems with the setup.
+    Doctor,
+    #[command(name = "reload")]
+    /// Ask the daemon to reload the configuration file.
+    Reload,
+    #[command(name = "replay")]
+    /// Run the command recorded with `--trace-ipc` again against the recorded replies from sway.
+    Replay { file: PathBuf },
+    #[command(name = "repl")]
+    /// Interactively run haswaynav and sway commands, showing the commands sent to sway.
+    Repl,
+    #[command(name = "batch", alias = "-")]
+    /// Read one subcommand per line from stdin and run them over a single connection to sway,
+    /// printing one JSON result per line.
+    Batch,
+    #[command(name = "record", subcommand)]
+    /// Record the focus and run commands executed into a macro, needs the daemon.
+    Record(RecordCommand),
+    #[command(name = "init-bindings")]
+    /// Print a sway config fragment binding the haswaynav commands, warning about conflicts with
+    /// the bindings of the running sway.
+    InitBindings {
+        #[arg(long, default_value = "Mod4")]
+        /// The modifier of every binding.
+        modifier: String,
+        #[arg(long, value_enum, default_value_t = BindingStyle::Vim)]
+        /// The keys used for the directions.
+        style: BindingStyle,
+    },
+    #[command(name = "bench")]
+    /// Measure the latency of fetching and decoding the tree, finding the focused node and running
+    /// a command.
+    Bench {
+        #[arg(long, default_value_t = 100)]
+        /// How often to repeat every step.
+        iterations: usize,
+    },
+    #[command(name = "output", subcommand)]
+    /// Focus outputs and send windows to them by their position.
+    Output(OutputCommand),
+    #[command(name = "play")]
+    /// Play the named macro recorded with `record`.
+    Play {
+        #[arg(value_name = "MACRO")]
+        name: String,
+    },
+    #[command(name = "workspace")]
+    /// Switch to the workspace with the number, focusing the window focused last on it when the
+    /// daemon is running.
+    Workspace { number: i32 },
+    #[command(name = "isolate-app")]
+    /// Move every window of the focused app to a workspace of its own, arranged as tabs, and
+    /// follow them there.
+    IsolateApp {
+        #[arg(long)]
+        /// The workspace to move the windows to instead of the lowest numbered unused one.
+        workspace: Option<String>,
+    },
+    #[command(name = "send-home")]
+    /// Move the focused window to the home workspace given by the first of the daemon's rules
+    /// matching it which has a `workspace`, creating the workspace if needed.
+    SendHome,
+    #[command(name = "move", subcommand)]
+    /// Move the focused window to where the mouse pointer is.
+    Move(MoveCommand),
+    #[command(name = "swap", subcommand)]
+    /// Exchange the places of windows or the contents of workspaces.
+    Swap(SwapCommand),
+    #[command(name = "gaps", subcommand)]
+    /// Change the gaps of the focused workspace, kept by the daemon.
+    Gaps(GapsCommand),
+    #[command(name = "drag", subcommand)]
+    /// Rearrange tiled windows with the pointer through the daemon, see `drag start`.
+    Drag(DragCommand),
+    #[command(name = "split", subcommand)]
+    /// Split the focused window.
+    Split(SplitCommand),
+    #[command(name = "input", subcommand)]
+    /// List the input devices and switch keyboard layouts.
+    Input(InputCommand),
+    #[command(name = "pointer", subcommand)]
+    /// Move the mouse pointer to windows and outputs.
+    Pointer(PointerCommand),
+    #[command(name = "resize", subcommand)]
+    /// Resize the windows beside the focused one.
+    Resize(ResizeCommand),
+    #[command(name = "tag", subcommand)]
+    /// Group windows by tags independent of workspaces, kept as marks on the windows.
+    Tag(TagCommand),
+    #[command(name = "get")]
+    /// Print what a filter selects from the tree with `--path`, or one of the views of the tree
+    /// such as the focused window, as JSON or using a template.
+    Get(GetArgs),
+    #[command(name = "state", subcommand)]
+    /// Show the state tracked by the daemon.
+    State(StateCommand),
+    #[command(name = "exec")]
+    /// Run the command and place its first window, e.g.
+    /// `exec --workspace 3 --float --size 50%x50% -- foot`.
+    Exec {
+        #[arg(long)]
+        /// The workspace the window is moved to.
+        workspace: Option<String>,
+        #[arg(long, value_enum)]
+        /// The layout of the container holding the window.
+        layout: Option<LayoutMode>,
+        #[arg(long)]
+        /// Make the window floating.
+        float: bool,
+        #[arg(long, value_name = "WxH")]
+        /// The size of the window in pixels or percent, e.g. `800x600` or `50%x50%`.
+        size: Option<Size>,
+        #[arg(long, value_name = "REGEX")]
+        /// Also recognise the window by its app id, for apps whose windows are opened by another
+        /// process than the one started.
+        app_id: Option<Pattern>,
+        #[arg(long, default_value_t = 10000, value_name = "MS")]
+        /// How long to wait for the window.
+        timeout: u64,
+        #[arg(required = true, last = true)]
+        command: Vec<String>,
+    },
+    #[command(name = "wait")]
+    /// Wait until a window with all the given properties exists and print it like `get focused`,
+    /// or with `--closed` until none is left, e.g. `wait --app-id firefox --timeout 5s`.
+    Wait {
+        #[arg(long, value_name = "REGEX")]
+        #[arg(required_unless_present_any = ["title", "workspace"])]
+        /// The app id of the window.
+        app_id: Option<Pattern>,
+        #[arg(long, value_name = "REGEX")]
+        /// The title of the window.
+        title: Option<Pattern>,
+        #[arg(long)]
+        /// The workspace the window is on.
+        workspace: Option<String>,
+        #[arg(long)]
+        /// Wait for the matching windows to close instead.
+        closed: bool,
+        #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+        /// Fail when the window did not appear or close after this long, e.g. `5s`.
+        timeout: Option<Duration>,
+    },
+    #[command(name = "bar")]
+    /// Print a status line for a bar with the binding mode, the layout of the focused container,
+    /// do-not-disturb and the indicated split, and a new one whenever they change. Lines are JSON
+    /// for a waybar `custom` module with `return-type` `json`, formatted as configured in `[bar]`.
+    Bar {
+        #[arg(long)]
+        /// Print only the text, for bars reading plain lines.
+        plain: bool,
+    },
+    #[command(name = "expose")]
+    /// Print the windows of every workspace with their geometry relative to the workspace and the
+    /// order the workspace focuses them in, the data for an overview of all workspaces drawn by
+    /// another tool.
+    Expose {
+        #[arg(long)]
+        /// Print one JSON object per workspace instead of an outline.
+        json: bool,
+    },
+    #[command(name = "daemon", subcommand)]
+    /// Ask about the daemon serving this sway session.
+    Daemon(DaemonCommand),
+    #[command(name = "dnd")]
+    /// Switch do-not-disturb of the daemon on or off, `status` prints `on` or `off`.
+    Dnd {
+        #[arg(value_enum)]
+        mode: DndMode,
+    },
+    #[command(name = "float-size")]
+    /// Make the focused window floating with a physical size on the focused output, e.g.
+    /// `float-size --mm 400x300`, keeping it the same size across monitors.
+    FloatSize {
+        #[arg(long, value_name = "WxH", value_parser = parse_dimensions)]
+        #[arg(required_unless_present = "inches", conflicts_with = "inches")]
+        /// The size in millimetres.
+        mm: Option<(f64, f64)>,
+        #[arg(long = "in", value_name = "WxH", value_parser = parse_dimensions)]
+        /// The size in inches.
+        inches: Option<(f64, f64)>,
+    },
+    #[command(name = "undo")]
+    /// Roll back the latest window move, floating change or composite operation, such as
+    /// `isolate-app` or `profile apply`.
+    Undo,
+    #[command(name = "snapshot")]
+    /// Save the replies of sway, the versions and the config file to a tar file in the directory,
+    /// to reproduce a problem offline with `--snapshot <file>`.
+    Snapshot { dir: PathBuf },
+    #[command(name = "mode", subcommand)]
+    /// Enter sway binding modes which are left again on their own.
+    Mode(ModeCommand),
+    #[command(name = "profile", subcommand)]
+    /// Set up workspaces declared in a profile.
+    Profile(ProfileCommand),
+}
+
+impl Commands {
+    /// The command line which runs the command again when it is recorded into a macro, `None`
+    /// for commands which are not recorded.
+    pub fn recordable_line(&self) -> Option<String> {
+        match self {
+            Commands::Focus(FocusArgs {
+                command: Some(FocusCommand::ToggleLayer),
+                ..
+            }) => Some("focus toggle-layer".to_string()),
+            Commands::Focus(
+                args @ FocusArgs {
+                    command: Some(FocusCommand::Into { direction }),
+                    ..
+                },
+            ) => Some(format!("focus{} into {}", args.overrides(), direction)),
+            Commands::Focus(FocusArgs {
+                command: Some(FocusCommand::Back),
+                ..
+            }) => Some("focus back".to_string()),
+            Commands::Focus(FocusArgs { explain: true, .. }) => None,
+            Commands::Focus(args) => Some(format!(
+                "focus {}{}",
+                args.direction.map(|d| d.to_string()).unwrap_or_default(),
+                args.overrides()
+            )),
+            Commands::Run { name } => Some(format!("run {}", name)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Changes of focus other than moving in a direction.
+pub enum FocusCommand {
+    #[command(name = "toggle-layer")]
+    /// Switch between the tiling and floating windows of the workspace, focusing the floating window
+    /// nearest to the focused one and returning to the tiled window focused last.
+    ToggleLayer,
+    #[command(name = "into")]
+    /// Move in the direction like `focus <direction>` and descend into the adjacent container to the
+    /// window aligned with the one focused, like moving between splits in Vim, rather than the one
+    /// focused last in the container.
+    Into { direction: Direction },
+    #[command(name = "back")]
+    /// Retrace the latest directional move, focusing the window it came from. Repeating it walks
+    /// back through a chain of moves until the focus changed some other way. Needs the daemon.
+    Back,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The things which can be swapped.
+pub enum SwapCommand {
+    #[command(name = "workspace-contents")]
+    /// Exchange everything on the two workspaces, keeping the layouts of the containers. Can be
+    /// rolled back with `undo`.
+    WorkspaceContents {
+        /// The name of the first workspace.
+        first: String,
+        /// The name of the second workspace, the focused one if not given.
+        second: Option<String>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The ways to move the focused window.
+pub enum MoveCommand {
+    #[command(name = "to-pointer")]
+    /// Move the focused window under the mouse pointer: a tiled window swaps places with the window
+    /// under the pointer, or moves to the output there when it shows no window, and a floating
+    /// window is centered on the pointer. Tiled windows need the position `pointer move-to` moved
+    /// the pointer to, as remembered by the daemon, and the window focused then to be still focused.
+    ToPointer,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The commands acting on outputs picked relative to the focused one.
+pub enum OutputCommand {
+    #[command(name = "focus")]
+    /// Focus the output.
+    Focus {
+        target: OutputTarget,
+        #[arg(long)]
+        /// Focus the window focused last on the output, as remembered by the daemon, rather than
+        /// the one sway picks.
+        restore: bool,
+    },
+    #[command(name = "send")]
+    /// Move the focused window to the output.
+    Send {
+        target: OutputTarget,
+        #[arg(long)]
+        /// Focus the output the window was moved to.
+        follow: bool,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The ways to change the gaps of the focused workspace.
+pub enum GapsCommand {
+    #[command(name = "cycle")]
+    /// Use the next of the presets in `[daemon.gaps]`.
+    Cycle,
+    #[command(name = "set")]
+    /// Use these inner and outer gaps in pixels.
+    Set { inner: u32, outer: u32 },
+    #[command(name = "toggle")]
+    /// Remove the gaps or bring them back.
+    Toggle,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Asking about the daemon.
+pub enum DaemonCommand {
+    #[command(name = "status")]
+    /// Print the socket of the daemon serving this sway session, its process id and the sway
+    /// socket it talks to as JSON, with whether that is the session of this shell.
+    Status,
+    #[command(name = "run-once")]
+    /// Record the focused window in the state file kept instead of a daemon, see `run_once` in
+    /// the `[daemon]` configuration. Every haswaynav invocation does so already when it is on.
+    RunOnce,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Listing the input devices and switching keyboard layouts.
+pub enum InputCommand {
+    #[command(name = "list")]
+    /// Print the input devices reported by sway as JSON, one per line, with the keyboard layouts
+    /// of keyboards.
+    List,
+    #[command(name = "kb-layout", subcommand)]
+    /// Switch the layout of keyboards.
+    KbLayout(KbLayoutCommand),
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The ways to switch the layout of keyboards.
+pub enum KbLayoutCommand {
+    #[command(name = "next")]
+    /// Switch to the next layout, wrapping around after the last one.
+    Next {
+        #[arg(long, value_name = "IDENTIFIER")]
+        /// The identifier of the keyboard as shown by `input list`, all keyboards switch without
+        /// one.
+        device: Option<String>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Moving the mouse pointer and asking where it is.
+pub enum PointerCommand {
+    #[command(name = "get")]
+    /// Print the position `pointer move-to` moved the pointer to last, as remembered by the daemon,
+    /// with the output and the window there as JSON. Sway does not report where the pointer is, so
+    /// moves made with the mouse are not known.
+    Get,
+    #[command(name = "move-to")]
+    /// Warp the pointer to the center of a window or output: a direction for the window `focus`
+    /// would move to, `focused`, `output:NAME`, `mark:NAME` or `id:ID`.
+    MoveTo {
+        #[arg(value_name = "TARGET")]
+        target: PointerTarget,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The ways to resize the windows beside the focused one.
+pub enum ResizeCommand {
+    #[command(name = "distribute")]
+    /// Give the windows of the split container holding the focused window the shares of it, e.g.
+    /// `50 25 25` or `2 1 1`, or equal sizes without shares.
+    Distribute {
+        #[arg(value_name = "SHARE")]
+        shares: Vec<f64>,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Dragging tiled windows with the pointer.
+pub enum DragCommand {
+    #[command(name = "start")]
+    /// Drag the focused tiled window: until `drag stop` it swaps places with every tiled window
+    /// the pointer enters. Needs the daemon and `focus_follows_mouse yes`, bind it to a key and
+    /// `drag stop` to the release of that key.
+    Start,
+    #[command(name = "stop")]
+    /// Stop dragging, leaving the window where it is.
+    Stop,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The ways to split the focused window.
+pub enum SplitCommand {
+    #[command(name = "auto")]
+    /// Split the focused window along its longer side, so the next window opens beside it when it
+    /// is wide and below it when it is tall.
+    Auto {
+        #[arg(long)]
+        /// Tell the daemon the chosen direction, which `state split` prints for status bars.
+        indicate: bool,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Adding tags to windows and viewing them.
+pub enum TagCommand {
+    #[command(name = "add")]
+    /// Add the tag to the focused window.
+    Add { tag: String },
+    #[command(name = "remove")]
+    /// Remove the tag from the focused window.
+    Remove { tag: String },
+    #[command(name = "toggle")]
+    /// Add the tag to the focused window, or remove it when the window has it.
+    Toggle { tag: String },
+    #[command(name = "view")]
+    /// Gather the windows with the tag on the focused workspace and move the other windows there
+    /// to the `haswaynav-tags` workspace. While the daemon runs new windows opening on the
+    /// workspace get the tag too.
+    View { tag: String },
 }
 
 #[derive(Debug, clap::Args)]
-/// The only arguments to the focus command is the direction
+#[command(subcommand_negates_reqs = true)]
+/// The arguments to the get command, either a filter over the whole tree or one of the views.
+pub struct GetArgs {
+    #[arg(long, required = true, value_name = "FILTER")]
+    /// Print what the filter selects from the tree, one JSON value per line, e.g.
+    /// `.nodes[] | select(.type == "output") | {name, rect}`.
+    pub path: Option<String>,
+    #[arg(long, requires = "path")]
+    /// Print strings selected by the filter without quotes.
+    pub raw: bool,
+    #[command(subcommand)]
+    pub command: Option<GetCommand>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The information printed by `get`.
+pub enum GetCommand {
+    #[command(name = "tab-info")]
+    /// The position of the focused window among the tabs of its nearest tabbed or stacked
+    /// container, `null` when it is not in one.
+    TabInfo,
+    #[command(name = "focused")]
+    /// The focused window, its workspace and its output.
+    Focused {
+        #[arg(long, value_name = "TEMPLATE")]
+        /// Print the template filling in fields like `{app_id} — {title} [{workspace}]` instead of
+        /// JSON. The fields are id, app_id, title, pid, marks, floating, layout, workspace, output,
+        /// children and container_layout. Modifiers follow a colon: a length cuts the value short,
+        /// `lower` and `upper` change the case and `clean` strips control characters, e.g.
+        /// `{title:40}` or `{app_id:lower}`.
+        format: Option<String>,
+    },
+    #[command(name = "at-point")]
+    /// The window shown at the position, `null` if there is none. Sway does not report where the
+    /// pointer is, pass the position from a tool which knows it.
+    AtPoint {
+        #[arg(allow_negative_numbers = true)]
+        x: i32,
+        #[arg(allow_negative_numbers = true)]
+        y: i32,
+        #[arg(long, value_name = "TEMPLATE")]
+        /// Print the template filling in fields like for `get focused`.
+        format: Option<String>,
+    },
+    #[command(name = "workspace-layout")]
+    /// The focused workspace: its windows in order, the nesting of its containers as an
+    /// S-expression like `(splith 1 (tabbed *2 3))` and which tabs are shown.
+    WorkspaceLayout,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// The views of the state tracked by the daemon.
+pub enum StateCommand {
+    #[command(name = "dump")]
+    /// Print all of the state as JSON.
+    Dump,
+    #[command(name = "history")]
+    /// Print the latest focus changes as JSON, oldest first.
+    History,
+    #[command(name = "split")]
+    /// Print the split chosen last by `split auto --indicate` as JSON, `null` once the window is
+    /// closed.
+    Split,
+    #[command(name = "mru")]
+    /// Print the ids of the open windows as JSON, most recently focused first.
+    Mru,
+    #[command(name = "rules")]
+    /// Print the rules the daemon applies to new windows as JSON.
+    Rules,
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Entering sway binding modes.
+pub enum ModeCommand {
+    #[command(name = "enter")]
+    /// Switch to the binding mode, returning to the default mode after the timeout or after one
+    /// binding with `--once`. Leaving the mode on its own needs the daemon.
+    Enter {
+        #[arg(value_name = "MODE")]
+        name: String,
+        #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+        /// Return to the default mode after this long, e.g. `3s` or `500ms`.
+        timeout: Option<Duration>,
+        #[arg(long)]
+        /// Return to the default mode after the first binding ran in the mode.
+        once: bool,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Working with the profiles under `$XDG_CONFIG_HOME/haswaynav/profiles`.
+pub enum ProfileCommand {
+    #[command(name = "apply")]
+    /// Launch the apps of the profile which have no window yet and arrange all of them on their
+    /// workspaces.
+    Apply {
+        #[arg(value_name = "PROFILE")]
+        name: String,
+    },
+}
+
+#[derive(Debug, clap::Subcommand)]
+/// Starting and stopping the recording of a macro.
+pub enum RecordCommand {
+    #[command(name = "start")]
+    /// Start recording a macro with the given name, replacing an existing one on stop.
+    Start {
+        #[arg(value_name = "MACRO")]
+        name: String,
+    },
+    #[command(name = "stop")]
+    /// Stop recording and save the macro.
+    Stop,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(subcommand_negates_reqs = true)]
+/// The arguments to the focus command, the options override the defaults from the config file for
+/// directional moves and for `focus into`, given before the subcommand.
 pub struct FocusArgs {
-    pub direction: Direction,
+    #[arg(required = true)]
+    pub direction: Option<Direction>,
+    #[command(subcommand)]
+    pub command: Option<FocusCommand>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    /// Allow focus to wrap around when there is no output in the requested direction.
+    pub wrap: Option<bool>,
+    #[arg(long, value_name = "POLICY")]
+    /// What to do when the move would leave the current output.
+    pub cross_output: Option<CrossOutput>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    /// From a floating window move through the tiling layer instead of between floating windows.
+    pub skip_floating: Option<bool>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    /// Warp the mouse pointer to the center of the newly focused container.
+    pub warp_cursor: Option<bool>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    /// Check that the focus changed and retry once if it did not.
+    pub verify: Option<bool>,
+    #[arg(long, value_name = "AXES")]
+    /// The moves which skip over the siblings in a tabbed container.
+    pub skip_tabbed: Option<Axes>,
+    #[arg(long, value_name = "AXES")]
+    /// The moves which skip over the siblings in a stacked container.
+    pub skip_stacked: Option<Axes>,
+    #[arg(long, value_name = "BOUNDARY")]
+    /// How far the move may go from the edge of the workspace.
+    pub boundary: Option<Boundary>,
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    /// Let the move enter a floating window in the direction when it is nearer than the next tiled
+    /// window.
+    pub enter_floating: Option<bool>,
+    #[arg(long, value_name = "POLICY")]
+    /// What to do when the focused workspace has no tiled windows.
+    pub empty_workspace: Option<OnEdge>,
+    #[arg(long, value_name = "POLICY")]
+    /// What to do at the edge of the workspace instead of wrapping or crossing outputs.
+    pub on_edge: Option<OnEdge>,
+    #[arg(long)]
+    /// Move even when the focused window is fullscreen on all outputs and `fullscreen_guard` is
+    /// set in the config.
+    pub force: bool,
+    #[arg(long)]
+    /// Print why the move chooses its commands instead of running them: the focused node, the
+    /// ancestors inspected while climbing, the predicted target and the commands.
+    pub explain: bool,
+}
+
+impl FocusArgs {
+    /// The options given on the command line as flags running the move again, each starting with a
+    /// space.
+    fn overrides(&self) -> String {
+        let name = |v: &dyn Fn() -> Option<clap::builder::PossibleValue>| {
+            v().map(|v| v.get_name().to_string())
+        };
+        let mut line = String::new();
+        let mut flag = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                line.push_str(&format!(" --{}={}", name, value));
+            }
+        };
+        flag("wrap", self.wrap.map(|v| v.to_string()));
+        flag(
+            "cross-output",
+            name(&|| self.cross_output.and_then(|v| v.to_possible_value())),
+        );
+        flag("skip-floating", self.skip_floating.map(|v| v.to_string()));
+        flag("warp-cursor", self.warp_cursor.map(|v| v.to_string()));
+        flag("verify", self.verify.map(|v| v.to_string()));
+        flag(
+            "skip-tabbed",
+            name(&|| self.skip_tabbed.and_then(|v| v.to_possible_value())),
+        );
+        flag(
+            "skip-stacked",
+            name(&|| self.skip_stacked.and_then(|v| v.to_possible_value())),
+        );
+        flag(
+            "boundary",
+            name(&|| self.boundary.and_then(|v| v.to_possible_value())),
+        );
+        flag("enter-floating", self.enter_floating.map(|v| v.to_string()));
+        flag(
+            "empty-workspace",
+            name(&|| self.empty_workspace.and_then(|v| v.to_possible_value())),
+        );
+        flag(
+            "on-edge",
+            name(&|| self.on_edge.and_then(|v| v.to_possible_value())),
+        );
+        if self.force {
+            line.push_str(" --force");
+        }
+        line
+    }
+
+    /// Fail for arguments which do not go with the subcommand: a direction, and `--explain` which
+    /// only explains directional moves.
+    pub fn check_subcommand(&self) -> anyhow::Result<()> {
+        if let Some(direction) = self.direction {
+            anyhow::bail!(
+                "focus takes either a direction or a subcommand, not also '{}'",
+                direction
+            );
+        }
+        if self.explain {
+            anyhow::bail!("--explain only explains directional moves");
+        }
+        Ok(())
+    }
+
+    /// Override the focus options from the config file with the ones given on the command line.
+    pub fn apply(&self, config: &FocusConfig) -> FocusConfig {
+        FocusConfig {
+            wrap: self.wrap.unwrap_or(config.wrap),
+            cross_output: self.cross_output.unwrap_or(config.cross_output),
+            skip_floating: self.skip_floating.unwrap_or(config.skip_floating),
+            warp_cursor: self.warp_cursor.unwrap_or(config.warp_cursor),
+            verify: self.verify.unwrap_or(config.verify),
+            skip_tabbed: self.skip_tabbed.unwrap_or(config.skip_tabbed),
+            skip_stacked: self.skip_stacked.unwrap_or(config.skip_stacked),
+            boundary: self.boundary.unwrap_or(config.boundary),
+            enter_floating: self.enter_floating.unwrap_or(config.enter_floating),
+            empty_workspace: self.empty_workspace.unwrap_or(config.empty_workspace),
+            on_edge: self.on_edge.or(config.on_edge),
+            fullscreen_guard: config.fullscreen_guard && !self.force,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+#[clap(long_about = None)]
+/// Daemon serving haswaynav requests over a unix domain socket
+pub struct DaemonArgs {
+    #[arg(long)]
+    /// Stay attached to the terminal instead of detaching into the background.
+    pub foreground: bool,
+    #[arg(long)]
+    /// Run as a systemd service, implies `--foreground`. The listening socket is taken from
+    /// `LISTEN_FDS` when socket activated and readiness is reported via `NOTIFY_SOCKET`.
+    pub systemd: bool,
+    #[arg(long)]
+    /// Path of the socket to listen on, defaults to `$XDG_RUNTIME_DIR/haswaynav.<session>` named
+    /// after the sway socket, or `$XDG_RUNTIME_DIR/haswaynav.sock` outside of a sway session.
+    pub socket: Option<PathBuf>,
+    #[arg(long)]
+    /// Path of the configuration file, defaults to `$XDG_CONFIG_HOME/haswaynav/config.toml`.
+    pub config: Option<PathBuf>,
+    #[arg(long, value_name = "SECONDS")]
+    /// Wait up to this long for sway to accept connections before starting, for autostart
+    /// scripts which may run before sway is up.
+    pub wait_for_sway: Option<u64>,
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    /// Log more details to stderr, repeat for even more. `RUST_LOG` takes precedence when set.
+    pub verbose: u8,
+    #[arg(short, long, conflicts_with = "verbose")]
+    /// Only log errors.
+    pub quiet: bool,
+}
+
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+/// The kinds of values which are completed dynamically.
+pub enum CompletionKind {
+    /// The aliases in the config file
+    Aliases,
+    /// The tags on the windows, see [crate::tag]
+    Tags,
+    /// The numbers of the workspaces
+    Workspaces,
+    /// The names of the workspaces
+    WorkspaceNames,
+    /// The marks on the windows, except the hidden ones starting with `_`, as `mark:NAME` targets
+    Marks,
 }
 
-#[derive(Debug, clap::ValueEnum, Clone)]
-/// The enumeration of directions used with focus to change focus in a specified direction.
-pub enum Direction {
-    Left,
-    Right,
-    Up,
-    Down,
+#[derive(Debug, clap::ValueEnum, Clone, Copy, PartialEq, Eq)]
+/// The keys used for the directions by `init-bindings`.
+pub enum BindingStyle {
+    /// h, j, k and l
+    Vim,
+    /// The arrow keys
+    Arrows,
 }
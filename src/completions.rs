@@ -0,0 +1,257 @@
+//! Shell completion scripts.
+//!
+//! The scripts are generated by `clap_complete` and extended with shell specific snippets which
+//! complete some arguments with live values, e.g. alias names from the config file or tags,
+//! workspaces and marks fetched from sway, by calling the hidden `haswaynav complete <kind>`
+//! command.
+
+use std::{collections::BTreeSet, io::Write};
+
+use anyhow::Result;
+use clap::{CommandFactory, ValueEnum};
+use clap_complete::Shell;
+
+use crate::{
+    cli::{Cli, CompletionKind},
+    config::Config,
+    messages::{get_marks, get_workspaces},
+    sway_connect,
+    tag::TAG_MARK_PREFIX,
+};
+
+/// The arguments completed with live values: the words naming the subcommand, the option taking
+/// the value or `None` for its positional arguments, and the kind of the values.
+const DYNAMIC: &[(&str, Option<&str>, CompletionKind)] = &[
+    ("run", None, CompletionKind::Aliases),
+    ("workspace", None, CompletionKind::Workspaces),
+    ("tag add", None, CompletionKind::Tags),
+    ("tag remove", None, CompletionKind::Tags),
+    ("tag toggle", None, CompletionKind::Tags),
+    ("tag view", None, CompletionKind::Tags),
+    (
+        "swap workspace-contents",
+        None,
+        CompletionKind::WorkspaceNames,
+    ),
+    (
+        "isolate-app",
+        Some("--workspace"),
+        CompletionKind::WorkspaceNames,
+    ),
+    ("exec", Some("--workspace"), CompletionKind::WorkspaceNames),
+    ("wait", Some("--workspace"), CompletionKind::WorkspaceNames),
+    ("pointer move-to", None, CompletionKind::Marks),
+];
+
+/// The name of the kind as accepted by `haswaynav complete`.
+fn kind_name(kind: CompletionKind) -> String {
+    kind.to_possible_value()
+        .expect("no skipped variants")
+        .get_name()
+        .to_string()
+}
+
+/// The shell patterns matching the words after the subcommand's path when the argument is
+/// completed: the path alone, or followed by other words for the later positional arguments, or
+/// ending with the option.
+fn patterns(path: &str, option: Option<&str>) -> String {
+    if let Some(option) = option {
+        return format!("\"{path} {option}\"|\"{path} \"*\" {option}\"");
+    }
+    let mut command = Cli::command();
+    let mut subcommand = &mut command;
+    for word in path.split(' ') {
+        subcommand = subcommand
+            .find_subcommand_mut(word)
+            .expect("DYNAMIC names existing subcommands");
+    }
+    if subcommand.get_positionals().count() > 1 {
+        format!("\"{path}\"|\"{path} \"*")
+    } else {
+        format!("\"{path}\"")
+    }
+}
+
+/// The global options which take their value as the next word, which is skipped along with them
+/// before matching the subcommand.
+fn options_with_values() -> Vec<String> {
+    Cli::command()
+        .get_arguments()
+        .filter(|a| {
+            a.is_global_set() && a.get_action().takes_values() && !a.is_require_equals_set()
+        })
+        .flat_map(|a| {
+            let long = a.get_long().map(|l| format!("--{}", l));
+            let short = a.get_short().map(|s| format!("-{}", s));
+            long.into_iter().chain(short)
+        })
+        .collect()
+}
+
+/// Write the completion script for the given shell.
+pub fn write_completions(shell: Shell, out: &mut dyn Write) -> Result<()> {
+    clap_complete::generate(shell, &mut Cli::command(), "haswaynav", out);
+    let with_values = options_with_values().join("|");
+
+    match shell {
+        Shell::Bash => {
+            writeln!(out)?;
+            writeln!(out, "_haswaynav_dynamic() {{")?;
+            // COMP_WORDS also splits at `:` and `=`, which breaks up `mark:NAME`.
+            writeln!(out, "    local line=${{COMP_LINE:0:COMP_POINT}} words kind")?;
+            writeln!(out, "    read -ra words <<< \"$line\"")?;
+            writeln!(out, "    [[ $line == *\" \" ]] && words+=(\"\")")?;
+            writeln!(out, "    local cword=$(( ${{#words[@]}} - 1 )) i=1")?;
+            writeln!(
+                out,
+                "    while (( i < cword )) && [[ ${{words[i]}} == -* ]]; do"
+            )?;
+            writeln!(out, "        case \"${{words[i]}}\" in")?;
+            writeln!(out, "            {}) i=$((i + 2)) ;;", with_values)?;
+            writeln!(out, "            *) i=$((i + 1)) ;;")?;
+            writeln!(out, "        esac")?;
+            writeln!(out, "    done")?;
+            writeln!(out, "    (( i > cword )) && i=$cword")?;
+            writeln!(out, "    case \"${{words[*]:i:cword-i}}\" in")?;
+            for (path, option, kind) in DYNAMIC {
+                writeln!(
+                    out,
+                    "        {}) kind={} ;;",
+                    patterns(path, *option),
+                    kind_name(*kind)
+                )?;
+            }
+            writeln!(out, "        *) _haswaynav \"$@\"; return ;;")?;
+            writeln!(out, "    esac")?;
+            writeln!(out, "    local cur=${{words[cword]}}")?;
+            writeln!(
+                out,
+                "    COMPREPLY=( $(compgen -W \"$(haswaynav complete $kind 2>/dev/null)\" -- \"$cur\") )"
+            )?;
+            // Bash replaces only the part of the word after its last `:` or `=`.
+            writeln!(out, "    local split=${{cur%\"${{cur##*[:=]}}\"}}")?;
+            writeln!(out, "    COMPREPLY=( \"${{COMPREPLY[@]#\"$split\"}}\" )")?;
+            writeln!(out, "}}")?;
+            writeln!(
+                out,
+                "complete -F _haswaynav_dynamic -o bashdefault -o default haswaynav"
+            )?;
+        }
+        Shell::Zsh => {
+            writeln!(out)?;
+            writeln!(out, "_haswaynav_dynamic() {{")?;
+            writeln!(out, "    local i=2")?;
+            writeln!(
+                out,
+                "    while (( i < CURRENT )) && [[ ${{words[i]}} == -* ]]; do"
+            )?;
+            writeln!(out, "        case \"${{words[i]}}\" in")?;
+            writeln!(out, "            ({}) (( i += 2 )) ;;", with_values)?;
+            writeln!(out, "            (*) (( i += 1 )) ;;")?;
+            writeln!(out, "        esac")?;
+            writeln!(out, "    done")?;
+            writeln!(out, "    (( i > CURRENT )) && i=$CURRENT")?;
+            writeln!(out, "    case \"${{words[i,CURRENT-1]}}\" in")?;
+            for (path, option, kind) in DYNAMIC {
+                writeln!(out, "        ({})", patterns(path, *option))?;
+                writeln!(
+                    out,
+                    "            compadd -- ${{(f)\"$(haswaynav complete {} 2>/dev/null)\"}}",
+                    kind_name(*kind)
+                )?;
+                writeln!(out, "            return")?;
+                writeln!(out, "            ;;")?;
+            }
+            writeln!(out, "    esac")?;
+            writeln!(out, "    _haswaynav \"$@\"")?;
+            writeln!(out, "}}")?;
+            writeln!(out, "compdef _haswaynav_dynamic haswaynav")?;
+        }
+        Shell::Fish => {
+            for (path, option, kind) in DYNAMIC {
+                let seen: Vec<String> = path
+                    .split(' ')
+                    .map(|word| format!("__fish_seen_subcommand_from {}", word))
+                    .collect();
+                let option = option
+                    .and_then(|o| o.strip_prefix("--"))
+                    .map(|o| format!(" -l {}", o))
+                    .unwrap_or_default();
+                writeln!(
+                    out,
+                    "complete -c haswaynav -n \"{}\"{} -f -a \"(haswaynav complete {} 2>/dev/null)\"",
+                    seen.join("; and "),
+                    option,
+                    kind_name(*kind)
+                )?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Get the live values of the given kind for completion.
+pub fn complete_values(kind: CompletionKind, config: &Config) -> Result<Vec<String>> {
+    let values = match kind {
+        CompletionKind::Aliases => config.aliases.keys().cloned().collect(),
+        CompletionKind::Tags => tags(&get_marks(&mut sway_connect()?)?),
+        CompletionKind::Workspaces => get_workspaces(&mut sway_connect()?)?
+            .into_iter()
+            .filter(|w| w.num >= 0)
+            .map(|w| w.num.to_string())
+            .collect(),
+        CompletionKind::WorkspaceNames => get_workspaces(&mut sway_connect()?)?
+            .into_iter()
+            .map(|w| w.name)
+            .collect(),
+        CompletionKind::Marks => get_marks(&mut sway_connect()?)?
+            .into_iter()
+            .filter(|mark| !mark.starts_with('_'))
+            .map(|mark| format!("mark:{}", mark))
+            .collect(),
+    };
+    Ok(values)
+}
+
+/// The tags held by the marks, see [crate::tag::tag_mark], each once.
+fn tags(marks: &[String]) -> Vec<String> {
+    let tags: BTreeSet<&str> = marks
+        .iter()
+        .filter_map(|mark| mark.strip_prefix(TAG_MARK_PREFIX))
+        .filter_map(|rest| rest.rsplit_once(':'))
+        .map(|(tag, _)| tag)
+        .collect();
+    tags.into_iter().map(str::to_string).collect()
+}
+
+#[test]
+fn test_completions() {
+    let marks = ["_tag:work:12", "_tag:work:14", "_tag:a:b:3", "dnd"].map(String::from);
+    assert_eq!(tags(&marks), ["a:b", "work"]);
+
+    let mut script = Vec::new();
+    write_completions(Shell::Bash, &mut script).unwrap();
+    let script = String::from_utf8(script).unwrap();
+    assert!(script.contains("        \"tag view\") kind=tags ;;\n"));
+    assert!(script.contains(
+        "        \"swap workspace-contents\"|\"swap workspace-contents \"*) kind=workspace-names ;;\n"
+    ));
+    assert!(script.contains(
+        "        \"isolate-app --workspace\"|\"isolate-app \"*\" --workspace\") kind=workspace-names ;;\n"
+    ));
+    assert!(script.contains("        \"pointer move-to\") kind=marks ;;\n"));
+    // The global options taking a value are skipped with it, `--log-file` needs an `=`.
+    let with_values = options_with_values();
+    assert!(with_values.contains(&"--config".to_string()));
+    assert!(with_values.contains(&"--trace-ipc".to_string()));
+    assert!(!with_values.iter().any(|o| o == "--log-file" || o == "-v"));
+
+    let mut script = Vec::new();
+    write_completions(Shell::Fish, &mut script).unwrap();
+    let script = String::from_utf8(script).unwrap();
+    assert!(script.contains(
+        "-n \"__fish_seen_subcommand_from isolate-app\" -l workspace -f -a \"(haswaynav complete workspace-names 2>/dev/null)\""
+    ));
+}
@@ -0,0 +1,870 @@
+//! The configuration file, `$XDG_CONFIG_HOME/haswaynav/config.toml`.
+//!
+//! Every option has a built-in default which the configuration file overrides, command line flags
+//! in turn override the configuration file. A missing configuration file is the same as an empty
+//! one.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    gaps::Gaps,
+    profile::LayoutMode,
+    text::quote,
+    tree::{FullScreenMode, Transform, TreeNode},
+    Direction,
+};
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// The root of the configuration file.
+pub struct Config {
+    /// The file the configuration was loaded from, if any
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// Defaults for the `focus` command.
+    pub focus: FocusConfig,
+    /// Settings for the `haswaynavd` daemon.
+    pub daemon: DaemonConfig,
+    /// Named sequences of commands.
+    pub aliases: BTreeMap<String, Vec<String>>,
+    /// What `focus <direction>` runs instead while a binding mode is active, keyed by the mode.
+    pub modes: BTreeMap<String, ModeConfig>,
+    /// How the directions of moves are remapped on an output, keyed by the output name.
+    pub remap: BTreeMap<String, Remap>,
+    /// Settings for individual outputs, keyed by the output name.
+    pub outputs: BTreeMap<String, OutputConfig>,
+    /// Settings for individual workspaces, keyed by the workspace name.
+    pub workspace: BTreeMap<String, WorkspaceConfig>,
+    /// Show a desktop notification when a command fails or a move finds nothing to focus.
+    pub notify: bool,
+    /// The formats of `haswaynav bar`.
+    pub bar: BarConfig,
+    /// How titles are printed.
+    pub text: TextConfig,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+/// What to do when a directional move would leave the current output.
+pub enum CrossOutput {
+    #[default]
+    /// Move to the adjacent output like sway does.
+    Allow,
+    /// Stay on the current output.
+    Deny,
+    /// Move to the adjacent output sharing the longest stretch of edge with the focused window
+    /// projected across the gap between the outputs, and there to the window nearest to the edge
+    /// which overlaps the projection most. Unlike sway this does not depend on where the outputs'
+    /// centers are when they differ in size or position.
+    Geometric,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+/// How far a directional move may go from the edge of the focused workspace.
+pub enum Boundary {
+    /// Stop at the edge of the workspace, neither wrapping nor moving to another output.
+    Workspace,
+    /// Stay on the output, wrapping around the workspace if `wrap` allows it.
+    Output,
+    #[default]
+    /// No limit beyond the `wrap` and `cross_output` options.
+    None,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+/// What a directional move does when it leaves the workspace, from the window at its edge or from a
+/// workspace without tiled windows, see [FocusConfig::on_edge] and [FocusConfig::empty_workspace].
+pub enum OnEdge {
+    #[default]
+    /// Focus the adjacent output in the direction, if there is one. From an empty workspace only as
+    /// `cross_output` allows.
+    FocusOutput,
+    /// Switch to the previous workspace on the output for moves to the left and up, the next one
+    /// for moves to the right and down.
+    SwitchWorkspace,
+    /// Wrap around to the window at the opposite edge of the workspace, even when there is an
+    /// output in the direction. An empty workspace has nothing to wrap to and stays focused.
+    Wrap,
+    /// Stay on the window or the workspace.
+    Nothing,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+/// The directions of moves for which the siblings in a tabbed or stacked container are skipped.
+pub enum Axes {
+    /// Moves to the left and right
+    Horizontal,
+    /// Moves up and down
+    Vertical,
+    Both,
+    None,
+}
+
+impl Axes {
+    /// Does a move in the direction lie on one of the axes.
+    pub fn contains(self, dir: Direction) -> bool {
+        let horizontal = dir == Direction::Left || dir == Direction::Right;
+        match self {
+            Axes::Horizontal => horizontal,
+            Axes::Vertical => !horizontal,
+            Axes::Both => true,
+            Axes::None => false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::focus]
+pub struct FocusConfig {
+    /// Allow focus to wrap around to the opposite side of the workspace when there is no output in
+    /// the requested direction.
+    pub wrap: bool,
+    /// What to do when a move would leave the current output.
+    pub cross_output: CrossOutput,
+    /// When a floating window is focused move through the tiling layer instead of between floating
+    /// windows.
+    pub skip_floating: bool,
+    /// Warp the mouse pointer to the center of the newly focused container.
+    pub warp_cursor: bool,
+    /// Check that the focus changed after navigating and retry once with a fresh tree if it did
+    /// not, e.g. because a new window took the focus in the meantime.
+    pub verify: bool,
+    /// The moves which skip over the siblings in a tabbed container instead of moving between tabs.
+    pub skip_tabbed: Axes,
+    /// The moves which skip over the siblings in a stacked container instead of moving between
+    /// them.
+    pub skip_stacked: Axes,
+    /// How far a move may go from the edge of the workspace.
+    pub boundary: Boundary,
+    /// Let moves from a tiled window enter floating windows in the direction when they are nearer
+    /// than the next tiled window.
+    pub enter_floating: bool,
+    /// What to do when the focused workspace has no tiled windows.
+    pub empty_workspace: OnEdge,
+    /// What to do at the edge of the workspace, overriding `wrap` and `cross_output`. A
+    /// `boundary` of `workspace` still stops the move.
+    pub on_edge: Option<OnEdge>,
+    /// Ignore directional moves while the focused window is fullscreen on all outputs, so a
+    /// stray key press does not leave it. `--force` moves anyway.
+    pub fullscreen_guard: bool,
+}
+
+impl Default for FocusConfig {
+    fn default() -> Self {
+        FocusConfig {
+            wrap: true,
+            cross_output: CrossOutput::Allow,
+            skip_floating: false,
+            warp_cursor: false,
+            verify: false,
+            skip_tabbed: Axes::Horizontal,
+            skip_stacked: Axes::Vertical,
+            boundary: Boundary::None,
+            enter_floating: false,
+            empty_workspace: OnEdge::FocusOutput,
+            on_edge: None,
+            fullscreen_guard: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::outputs]
+pub struct OutputConfig {
+    /// The width and height of the display in millimetres, for outputs whose size sway does not
+    /// report
+    pub size_mm: Option<(u32, u32)>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::workspace]
+pub struct WorkspaceConfig {
+    /// The layout the daemon gives the workspace when its first tiled window appears
+    pub default_layout: Option<LayoutMode>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::remap]
+pub struct Remap {
+    /// Turn the directions with the transform of the output reported by sway, e.g. `up` becomes
+    /// `right` on an output rotated by 90 degrees
+    pub follow_transform: bool,
+    /// The direction moved in for `left`, applied after the transform
+    pub left: Option<Direction>,
+    /// The direction moved in for `right`
+    pub right: Option<Direction>,
+    /// The direction moved in for `up`
+    pub up: Option<Direction>,
+    /// The direction moved in for `down`
+    pub down: Option<Direction>,
+}
+
+impl Remap {
+    /// The direction to move in when the direction was asked for on an output with the transform.
+    pub fn apply(&self, direction: Direction, transform: Transform) -> Direction {
+        let mut direction = direction;
+        if self.follow_transform {
+            let (flipped, turns) = transform.flip_and_quarter_turns();
+            if flipped {
+                direction = direction.mirrored();
+            }
+            for _ in 0..turns {
+                direction = direction.clockwise();
+            }
+        }
+        let explicit = match direction {
+            Direction::Left => self.left,
+            Direction::Right => self.right,
+            Direction::Up => self.up,
+            Direction::Down => self.down,
+        };
+        explicit.unwrap_or(direction)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::modes]
+pub struct ModeConfig {
+    /// The steps run for `focus left`, like the steps of an alias
+    pub left: Vec<String>,
+    /// The steps run for `focus right`
+    pub right: Vec<String>,
+    /// The steps run for `focus up`
+    pub up: Vec<String>,
+    /// The steps run for `focus down`
+    pub down: Vec<String>,
+}
+
+impl ModeConfig {
+    /// The steps run for a move in the direction, empty to move the focus as usual.
+    pub fn steps(&self, direction: Direction) -> &[String] {
+        match direction {
+            Direction::Left => &self.left,
+            Direction::Right => &self.right,
+            Direction::Up => &self.up,
+            Direction::Down => &self.down,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::daemon]
+pub struct DaemonConfig {
+    /// Rules applied by the daemon to newly created windows.
+    pub rules: Vec<Rule>,
+    /// Rules rewriting the titles shown in the title bars, applied when a window is created and
+    /// whenever its title changes.
+    pub titles: Vec<TitleRule>,
+    /// When new windows may take the focus.
+    pub focus_policy: FocusPolicy,
+    /// Mark added to windows becoming urgent while do-not-disturb is on, followed by `:` and the
+    /// id of the window since marks are unique, e.g. `missed:126`.
+    pub dnd_mark: Option<String>,
+    /// How many changes `haswaynav undo` can roll back, 20 unless set.
+    pub undo_depth: Option<usize>,
+    /// The outputs preferred for each workspace, keyed by the workspace name. Whenever outputs are
+    /// connected or disconnected the workspaces are moved to the first of them which is active.
+    pub workspace_outputs: BTreeMap<String, Vec<String>>,
+    /// The gaps `haswaynav gaps` chooses from.
+    pub gaps: GapsConfig,
+    /// Hide the title bars of tiled windows alone in their container and show them for windows
+    /// with siblings or in a tabbed or stacked container, switching their borders between `pixel`
+    /// and `normal` as the tree changes.
+    pub smart_title_bars: bool,
+    /// Remember the keyboard layout active in every window and switch the keyboards back to it
+    /// whenever the window regains the focus.
+    pub keyboard_layout_per_window: bool,
+    /// Without a running daemon keep its state in a file instead, recording the focus whenever
+    /// haswaynav runs, so `focus back` and restoring the focus of outputs and workspaces work
+    /// without a resident process.
+    pub run_once: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::text]
+pub struct TextConfig {
+    /// The most graphemes of a title printed, longer ones are cut short with an ellipsis.
+    pub max_title_length: usize,
+    /// The template `get focused` and `get at-point` print instead of JSON without `--format`, see
+    /// [crate::text::render_template].
+    pub window_format: Option<String>,
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        TextConfig {
+            max_title_length: 100,
+            window_format: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(default, deny_unknown_fields)]
+/// See [Config::bar], the formats take the fields in braces with modifiers like
+/// `haswaynav get --format`, see [crate::text::render_template].
+pub struct BarConfig {
+    /// The whole line with the fields `{mode}`, `{layout}`, `{dnd}` and `{split}` filled in by the
+    /// formats below
+    pub format: String,
+    /// The binding mode as `{mode}`, left out in the default mode
+    pub mode: String,
+    /// The layout of the container holding the focused window as `{layout}`
+    pub layout: String,
+    /// Shown while do-not-disturb is on
+    pub dnd: String,
+    /// The split chosen by `split auto --indicate` for the focused window as `{direction}`
+    pub split: String,
+}
+
+impl Default for BarConfig {
+    fn default() -> Self {
+        BarConfig {
+            format: "{mode}{layout}{dnd}{split}".to_string(),
+            mode: "{mode} ".to_string(),
+            layout: "{layout}".to_string(),
+            dnd: " dnd".to_string(),
+            split: " {direction}".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [DaemonConfig::gaps]
+pub struct GapsConfig {
+    /// The gaps `haswaynav gaps cycle` steps through, the first one is also taken as the gaps of
+    /// workspaces whose gaps were not chosen yet
+    pub presets: Vec<Gaps>,
+    /// Remove the gaps of workspaces showing a single tiled window, restoring them once there are
+    /// more
+    pub smart: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+/// See [DaemonConfig::focus_policy]
+pub struct FocusPolicy {
+    /// Keep the focus on a fullscreen window when a new window appears
+    pub protect_fullscreen: bool,
+    /// Keep the focus on a window fullscreen on all outputs when a new window appears or an
+    /// urgent window takes the focus
+    pub protect_global_fullscreen: bool,
+    /// Leave the global fullscreen of a window once another workspace is focused, instead of
+    /// the window covering the workspace switched to
+    pub unfullscreen_on_workspace_switch: bool,
+    /// Windows which keep the focus when a new window appears
+    pub protect: Vec<Criteria>,
+}
+
+impl FocusPolicy {
+    /// Does the focused window keep the focus when a new window appears.
+    pub fn protects(&self, focused: &TreeNode) -> bool {
+        (self.protect_fullscreen && focused.fullscreen_mode != FullScreenMode::None)
+            || self.protects_global_fullscreen(focused)
+            || self.protect.iter().any(|c| c.matches(focused))
+    }
+
+    /// Does the focused window keep the focus from urgent windows as well as from new ones.
+    pub fn protects_global_fullscreen(&self, focused: &TreeNode) -> bool {
+        self.protect_global_fullscreen
+            && focused.fullscreen_mode == FullScreenMode::GlobalFullScreen
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+/// Criteria matching windows by their properties, a window matches when all given patterns do.
+pub struct Criteria {
+    /// Pattern matched against [TreeNode::app_id]
+    pub app_id: Option<Pattern>,
+    /// Pattern matched against [TreeNode::name], the window title
+    pub title: Option<Pattern>,
+}
+
+impl Criteria {
+    /// Does the node match the criteria.
+    pub fn matches(&self, node: &TreeNode) -> bool {
+        Criteria::match_patterns(self.app_id.as_ref(), self.title.as_ref(), node)
+    }
+
+    /// Does the node match the patterns for its app id and title like [Criteria::matches]. For
+    /// [Rule] and [crate::profile::ProfileApp], which keep the patterns as keys of their own since a
+    /// flattened [Criteria] would lose the key from the errors in the config.
+    pub(crate) fn match_patterns(
+        app_id: Option<&Pattern>,
+        title: Option<&Pattern>,
+        node: &TreeNode,
+    ) -> bool {
+        app_id.is_none_or(|p| p.matches(node.app_id.as_deref()))
+            && title.is_none_or(|p| p.matches(node.name.as_deref()))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A regular expression matched against a window property, like sway's criteria.
+pub struct Pattern(pub regex::Regex);
+
+impl Pattern {
+    /// Does the pattern match the given property, a missing property never matches.
+    pub fn matches(&self, value: Option<&str>) -> bool {
+        value.is_some_and(|v| self.0.is_match(v))
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = regex::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        regex::Regex::new(s).map(Pattern)
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+/// A rule matching windows by their properties and the sway commands to run for them. A rule
+/// without any criteria matches every window.
+pub struct Rule {
+    /// Pattern matched against [TreeNode::app_id]
+    pub app_id: Option<Pattern>,
+    /// Pattern matched against [TreeNode::name], the window title
+    pub title: Option<Pattern>,
+    /// Sway commands run for the matching window when it appears
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Border style of the matching window, e.g. `pixel 2` or `none`
+    pub border: Option<String>,
+    /// Opacity of the matching window from 0 to 1
+    pub opacity: Option<f64>,
+    /// Inner gaps of the workspace holding the matching window
+    pub gaps: Option<u32>,
+    /// The home workspace of the matching window, which it is moved to when it appears and by
+    /// `haswaynav send-home`
+    pub workspace: Option<String>,
+}
+
+impl Rule {
+    /// Does the rule apply to the given node.
+    pub fn matches(&self, node: &TreeNode) -> bool {
+        Criteria::match_patterns(self.app_id.as_ref(), self.title.as_ref(), node)
+    }
+
+    /// The sway commands applying the appearance set by the rule, which are run again whenever the
+    /// window moves so the gaps follow it to another workspace.
+    pub fn profile_commands(&self) -> Vec<String> {
+        let border = self.border.iter().map(|b| format!("border {}", b));
+        let opacity = self.opacity.iter().map(|o| format!("opacity {}", o));
+        let gaps = self
+            .gaps
+            .iter()
+            .map(|g| format!("gaps inner current set {}", g));
+        border.chain(opacity).chain(gaps).collect()
+    }
+}
+
+/// The home workspace of the node from the first of the rules applying to it which has one.
+pub fn home_workspace<'a>(rules: &'a [Rule], node: &TreeNode) -> Option<&'a str> {
+    rules
+        .iter()
+        .filter(|r| r.matches(node))
+        .find_map(|r| r.workspace.as_deref())
+}
+
+/// The command moving the node to its home workspace, see [home_workspace].
+pub fn home_command(rules: &[Rule], node: &TreeNode) -> Option<String> {
+    home_workspace(rules, node).map(|w| format!("move container to workspace {}", quote(w)))
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+/// A rule rewriting the title of matching windows with sway's `title_format`.
+pub struct TitleRule {
+    /// Pattern matched against [TreeNode::app_id]
+    pub app_id: Option<Pattern>,
+    /// Pattern matched against the window title, its captures can be used in [TitleRule::replace]
+    pub title: Option<Pattern>,
+    /// Replacement for the part of the title matched by [TitleRule::title], e.g. `$1`
+    pub replace: Option<String>,
+    /// Longest title shown in characters, longer titles are cut short with an ellipsis
+    pub max_length: Option<usize>,
+}
+
+impl TitleRule {
+    /// The title shown for the node, or `None` when the rule does not apply to it.
+    pub fn format(&self, node: &TreeNode) -> Option<String> {
+        let title = node.name.as_deref()?;
+        if !self
+            .app_id
+            .as_ref()
+            .is_none_or(|p| p.matches(node.app_id.as_deref()))
+        {
+            return None;
+        }
+        let mut title = match (&self.title, &self.replace) {
+            (Some(pattern), _) if !pattern.0.is_match(title) => return None,
+            (Some(pattern), Some(replace)) => {
+                pattern.0.replace(title, replace.as_str()).into_owned()
+            }
+            _ => title.to_string(),
+        };
+        if let Some(max) = self.max_length {
+            if let Some((cut, _)) = title.char_indices().nth(max) {
+                let keep = title[..cut]
+                    .char_indices()
+                    .nth(max.saturating_sub(1))
+                    .map_or(cut, |(idx, _)| idx);
+                title.truncate(keep);
+                title.push('…');
+            }
+        }
+        Some(title)
+    }
+}
+
+/// The directory holding the configuration file, `$XDG_CONFIG_HOME/haswaynav` falling back to
+/// `~/.config/haswaynav`.
+pub fn default_config_dir() -> Option<PathBuf> {
+    let non_empty = |name| std::env::var_os(name).filter(|s| !s.is_empty());
+    let config_home = non_empty("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("haswaynav"))
+}
+
+/// Decode a TOML document like `toml::from_str`, adding to the line and column of a value which
+/// does not fit the key it belongs to, e.g. `daemon.rules[2].app_id`, and the accepted name closest
+/// to a misspelt key or variant.
+pub fn from_toml<T: DeserializeOwned>(contents: &str) -> Result<T> {
+    serde_path_to_error::deserialize(toml::Deserializer::new(contents)).map_err(|err| {
+        let path = err.path().to_string();
+        let err = err.into_inner();
+        let hint = did_you_mean(err.message())
+            .map(|name| format!("did you mean `{}`?\n", name))
+            .unwrap_or_default();
+        if path == "." {
+            anyhow::format_err!("{}{}", err, hint)
+        } else {
+            anyhow::format_err!("invalid `{}`: {}{}", path, err, hint)
+        }
+    })
+}
+
+/// The expected name closest to the unknown one in a serde error message like "unknown field
+/// `warp`, expected one of `wrap`, `verify`", if it is close enough to be a typo.
+fn did_you_mean(message: &str) -> Option<&str> {
+    let mut quoted = message.split('`').skip(1).step_by(2);
+    let unknown = quoted.next()?;
+    if !message.starts_with("unknown ") {
+        return None;
+    }
+    quoted
+        .map(|name| (edit_distance(unknown, name), name))
+        .filter(|(distance, name)| *distance <= 2 && *distance < name.len())
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// The number of characters to insert, delete or replace to turn one string into the other.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// The default path of the configuration file, `$XDG_CONFIG_HOME/haswaynav/config.toml` falling
+/// back to `~/.config/haswaynav/config.toml`.
+pub fn default_config_path() -> Option<PathBuf> {
+    default_config_dir().map(|dir| dir.join("config.toml"))
+}
+
+impl Config {
+    /// Parse the configuration from the contents of a configuration file.
+    pub fn parse(contents: &str) -> Result<Config> {
+        from_toml(contents)
+    }
+
+    /// Load the configuration from the given path or the default path, a missing file results in
+    /// the built-in defaults.
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let path = match path.map(Path::to_path_buf).or_else(default_config_path) {
+            None => return Ok(Default::default()),
+            Some(path) => path,
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Ok(Config {
+                path: Some(path.clone()),
+                ..Config::parse(&contents)
+                    .with_context(|| format!("parsing config file '{}'", path.display()))?
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Default::default()),
+            Err(err) => {
+                Err(err).with_context(|| format!("reading config file '{}'", path.display()))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_config_empty() {
+    let parsed = Config::parse("").unwrap();
+
+    assert_eq!(parsed.focus, FocusConfig::default());
+    assert!(parsed.daemon.rules.is_empty());
+    assert!(parsed.aliases.is_empty());
+}
+
+#[test]
+fn test_config_parse() {
+    let toml = r#"
+        [focus]
+        wrap = false
+        cross_output = "deny"
+        skip_stacked = "both"
+
+        [[daemon.rules]]
+        app_id = "^kitty$"
+        commands = ["border pixel 2"]
+
+        [aliases]
+        dev = ["workspace 2", "layout tabbed"]
+
+        [modes.resize]
+        left = ["resize shrink width 10px"]
+
+        [outputs.DP-1]
+        size_mm = [597, 336]
+
+        [workspace.3]
+        default_layout = "tabbed"
+    "#;
+
+    let parsed = Config::parse(toml).unwrap();
+
+    assert_eq!(
+        parsed.focus,
+        FocusConfig {
+            wrap: false,
+            cross_output: CrossOutput::Deny,
+            skip_stacked: Axes::Both,
+            ..Default::default()
+        }
+    );
+    assert_eq!(parsed.daemon.rules.len(), 1);
+    assert_eq!(parsed.daemon.rules[0].commands, ["border pixel 2"]);
+    assert_eq!(parsed.aliases["dev"], ["workspace 2", "layout tabbed"]);
+    let resize = &parsed.modes["resize"];
+    assert_eq!(resize.steps(Direction::Left), ["resize shrink width 10px"]);
+    assert!(resize.steps(Direction::Up).is_empty());
+    assert_eq!(parsed.outputs["DP-1"].size_mm, Some((597, 336)));
+    assert_eq!(
+        parsed.workspace["3"].default_layout,
+        Some(LayoutMode::Tabbed)
+    );
+}
+
+#[test]
+fn test_config_unknown_key() {
+    let err = Config::parse("[focus]\nwarp = true")
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("invalid `focus.warp`: TOML parse error at line 2, column 1"));
+    assert!(err.ends_with("did you mean `wrap`?\n"));
+
+    let err = Config::parse("[[daemon.rules]]\n[[daemon.rules]]\napp_id = \"([\"")
+        .unwrap_err()
+        .to_string();
+    assert!(err.starts_with("invalid `daemon.rules[1].app_id`"));
+    let err = Config::parse("[focus]\nboundary = \"nowhere\"").unwrap_err();
+    assert!(!err.to_string().contains("did you mean"));
+
+    assert_eq!(
+        did_you_mean("unknown variant `dney`, expected `allow` or `deny`"),
+        Some("deny")
+    );
+    assert_eq!(did_you_mean("invalid type: string `x`, expected `y`"), None);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+    assert_eq!(edit_distance("", "ab"), 2);
+}
+
+#[test]
+fn test_rule_matches() {
+    let rule: Rule = toml::from_str(
+        r#"
+        app_id = "^kitty$"
+        title = "vim"
+        commands = []
+        "#,
+    )
+    .unwrap();
+    let node = |app_id: &str, title: &str| TreeNode {
+        app_id: Some(app_id.to_string()),
+        name: Some(title.to_string()),
+        ..Default::default()
+    };
+
+    assert!(rule.matches(&node("kitty", "nvim README.md")));
+    assert!(!rule.matches(&node("kitty", "bash")));
+    assert!(!rule.matches(&node("kitty-alt", "vim")));
+    assert!(!rule.matches(&TreeNode::default()));
+}
+
+#[test]
+fn test_rule_profile_commands() {
+    let rule: Rule = toml::from_str(
+        r#"
+        app_id = "^mpv$"
+        border = "none"
+        opacity = 0.9
+        gaps = 0
+        "#,
+    )
+    .unwrap();
+
+    assert!(rule.commands.is_empty());
+    assert_eq!(
+        rule.profile_commands(),
+        ["border none", "opacity 0.9", "gaps inner current set 0"]
+    );
+}
+
+#[test]
+fn test_title_rule_format() {
+    let rule: TitleRule = toml::from_str(
+        r#"
+        title = "^(.*) — Mozilla Firefox$"
+        replace = "$1"
+        max_length = 10
+        "#,
+    )
+    .unwrap();
+    let node = |title: &str| TreeNode {
+        name: Some(title.to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        rule.format(&node("Docs — Mozilla Firefox")).as_deref(),
+        Some("Docs")
+    );
+    assert_eq!(
+        rule.format(&node("The Rust Book — Mozilla Firefox"))
+            .as_deref(),
+        Some("The Rust …")
+    );
+    assert_eq!(rule.format(&node("bash")), None);
+    assert_eq!(rule.format(&TreeNode::default()), None);
+}
+
+#[test]
+fn test_focus_policy_protects() {
+    let config = Config::parse(
+        r#"
+        [daemon.focus_policy]
+        protect_fullscreen = true
+        [[daemon.focus_policy.protect]]
+        app_id = "^zoom$"
+        "#,
+    )
+    .unwrap();
+    let policy = &config.daemon.focus_policy;
+    let node = |app_id: &str, fullscreen_mode| TreeNode {
+        app_id: Some(app_id.to_string()),
+        fullscreen_mode,
+        ..Default::default()
+    };
+
+    assert!(policy.protects(&node("zoom", FullScreenMode::None)));
+    assert!(policy.protects(&node("mpv", FullScreenMode::FullWorkspace)));
+    assert!(!policy.protects(&node("mpv", FullScreenMode::None)));
+    assert!(!FocusPolicy::default().protects(&node("mpv", FullScreenMode::FullWorkspace)));
+}
+
+#[test]
+fn test_remap_apply() {
+    let config = Config::parse(
+        r#"
+        [remap.HDMI-A-1]
+        follow_transform = true
+        [remap.DP-1]
+        up = "left"
+        "#,
+    )
+    .unwrap();
+    let rotated = &config.remap["HDMI-A-1"];
+    let explicit = &config.remap["DP-1"];
+
+    assert_eq!(
+        rotated.apply(Direction::Up, Transform::Rotate90),
+        Direction::Right
+    );
+    assert_eq!(
+        rotated.apply(Direction::Left, Transform::Rotate270),
+        Direction::Down
+    );
+    assert_eq!(
+        rotated.apply(Direction::Left, Transform::Flipped),
+        Direction::Right
+    );
+    assert_eq!(
+        rotated.apply(Direction::Up, Transform::Normal),
+        Direction::Up
+    );
+    assert_eq!(
+        explicit.apply(Direction::Up, Transform::Rotate90),
+        Direction::Left
+    );
+    assert_eq!(
+        explicit.apply(Direction::Down, Transform::Normal),
+        Direction::Down
+    );
+}
@@ -0,0 +1,2301 @@
+//! The `haswaynavd` daemon and the client side of its IPC.
+//!
+//! The daemon listens on a unix domain socket and handles one JSON encoded [Request] per line,
+//! answering each with one JSON encoded [Response] line. It can be started directly, or on demand
+//! by systemd through socket activation in which case the listening socket is inherited via
+//! `LISTEN_FDS` and readiness is reported via `sd_notify`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::{BufRead, BufReader, Write},
+    os::{
+        fd::{FromRawFd, RawFd},
+        unix::net::{UnixDatagram, UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use inotify::{EventMask, Inotify, WatchMask};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{
+        default_config_path, home_command, Config, DaemonConfig, FocusPolicy, GapsConfig, Rule,
+        TitleRule, WorkspaceConfig,
+    },
+    drag::drag_commands,
+    gaps::{Gaps, GapsChange, WorkspaceGaps},
+    input::switch_layout_command,
+    messages::{
+        check_results, get_inputs, get_outputs, get_tree, get_tree_slim, get_workspaces,
+        receive_event, run_command, subscribe,
+    },
+    mode::leave_mode_later,
+    output::remap_direction,
+    pointer::PointerWarp,
+    recording::{macro_path, save_macro},
+    run_checked, run_once,
+    split::{Split, SplitDirection},
+    sway_connect,
+    tag::tag_mark,
+    text::quote,
+    tree::{
+        cursor::{find_focused_fast, Cursor},
+        windows, Border, Event, FullScreenMode, Layout, NodeType, TreeNode, WindowChange,
+    },
+    workspace::plan_workspace_outputs,
+    Direction, FocusMove, SwayClient,
+};
+
+/// The first file descriptor passed by systemd, see `man sd_listen_fds`.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(tag = "request", rename_all = "snake_case")]
+/// A request sent from a client to the daemon.
+pub enum Request {
+    /// Check that the daemon is alive.
+    Ping,
+    /// Change the focus in the given direction, see [crate::change_focus].
+    Focus { direction: Direction },
+    /// Reload the configuration file.
+    Reload,
+    /// Start recording the executed commands into the named macro.
+    RecordStart { name: String },
+    /// Stop recording and save the macro.
+    RecordStop,
+    /// A client executed the command line, recorded when a macro is being recorded.
+    Executed { line: String },
+    /// Get the window focused last on the named output, answered with [Response::Window].
+    LastFocused { output: String },
+    /// Get the window focused last on the named workspace, answered with [Response::Window].
+    LastFocusedOnWorkspace { workspace: String },
+    /// Get the state tracked by the daemon, answered with [Response::State].
+    State,
+    /// Ask which daemon answers and which sway session it serves, answered with
+    /// [Response::Status].
+    Status,
+    /// Get do-not-disturb and the split for status bars, answered with [Response::Bar].
+    Bar,
+    /// Switch do-not-disturb on or off, answered with [Response::Dnd].
+    Dnd { mode: DndMode },
+    /// Roll back the latest entry of [DaemonState::journal].
+    Undo,
+    /// A client changed the focus with a directional move, added to [DaemonState::moves].
+    Moved {
+        from: i32,
+        to: i32,
+        direction: Direction,
+    },
+    /// Retrace the latest entry of [DaemonState::moves], focusing the window it came from.
+    Back,
+    /// A client split the window, kept in [DaemonState::split] for status bars.
+    Split { id: i32, direction: SplitDirection },
+    /// A client moved the pointer, kept in [DaemonState::pointer].
+    Pointer { warp: PointerWarp },
+    /// Start dragging the window with the id or stop with `None`, kept in [DaemonState::drag].
+    Drag { id: Option<i32> },
+    /// Change the gaps of the focused workspace, kept in [DaemonState::gaps].
+    Gaps { change: GapsChange },
+    /// A client viewed the tag on the workspace, kept in [DaemonState::views].
+    TagView { workspace: String, tag: String },
+    /// Add the commands rolling back a composite operation to [DaemonState::journal], moves of the
+    /// windows caused by the operation itself are not journaled.
+    Journal {
+        ids: Vec<i32>,
+        commands: Vec<String>,
+    },
+    /// Return from the binding mode to the default mode after the timeout or after one binding,
+    /// see [crate::mode::leave_mode_later].
+    LeaveMode {
+        mode: String,
+        timeout_ms: Option<u64>,
+        once: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "snake_case")]
+/// How to change do-not-disturb, see [DaemonState::dnd].
+pub enum DndMode {
+    On,
+    Off,
+    Toggle,
+    /// Leave it as it is, only asking whether it is on.
+    Status,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "response", rename_all = "snake_case")]
+/// The daemon's reply to a [Request].
+pub enum Response {
+    /// The request was handled successfully.
+    Ok,
+    /// Handling the request failed with the given message.
+    Error { message: String },
+    /// The id of the window asked for, if there is one.
+    Window { id: Option<i32> },
+    /// The state tracked by the daemon and the rules it applies to new windows.
+    State {
+        state: Box<DaemonState>,
+        rules: Vec<Rule>,
+    },
+    /// Whether do-not-disturb is on.
+    Dnd { enabled: bool },
+    /// The process id of the daemon and the sway socket it talks to.
+    Status { pid: u32, swaysock: Option<String> },
+    /// Whether do-not-disturb is on and the split chosen last, see [crate::bar].
+    Bar { dnd: bool, split: Option<Split> },
+    /// The journal is empty, see [Request::Undo].
+    NothingToUndo,
+}
+
+/// The number of focus changes kept in [DaemonState::history].
+const HISTORY_LEN: usize = 100;
+
+/// The number of directional moves kept in [DaemonState::moves].
+const MOVES_LEN: usize = 50;
+
+/// The number of changes kept in [DaemonState::journal] unless configured otherwise.
+const JOURNAL_LEN: usize = 20;
+
+/// How long the windows changed by an operation are left out of the journal, so the events the
+/// operation causes are not journaled as changes of their own.
+const QUIET_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+/// The state the daemon tracks from the window events of sway.
+pub struct DaemonState {
+    /// The id of the window focused last on each output, keyed by the output name
+    pub last_focused_on_output: BTreeMap<String, i32>,
+    /// The id of the window focused last on each workspace, keyed by the workspace name
+    pub last_focused_on_workspace: BTreeMap<String, i32>,
+    /// The latest focus changes, oldest first
+    pub history: VecDeque<FocusChange>,
+    /// The ids of the open windows which were focused, most recently focused first
+    pub mru: Vec<i32>,
+    /// Do-not-disturb: neither new nor urgent windows take the focus and windows becoming urgent
+    /// are marked with [crate::config::DaemonConfig::dnd_mark]
+    pub dnd: bool,
+    /// The latest window moves, floating changes and composite operations, oldest first, which
+    /// `haswaynav undo` rolls back
+    pub journal: VecDeque<JournalEntry>,
+    /// The unbroken chain of directional moves leading to the focused window, oldest first, which
+    /// `haswaynav focus back` retraces. Unlike [DaemonState::mru] it only holds focus changes made
+    /// by directional moves.
+    pub moves: Vec<DirectionalMove>,
+    /// The tag viewed last on each workspace, keyed by the workspace name, which new windows
+    /// opening there get as well
+    pub views: BTreeMap<String, String>,
+    /// The split chosen last by `split auto --indicate`, until the window closes
+    pub split: Option<Split>,
+    /// The position `haswaynav pointer move-to` moved the pointer to last, sway does not report
+    /// where the pointer is
+    pub pointer: Option<PointerWarp>,
+    /// The gaps chosen for each workspace with `haswaynav gaps`, keyed by the workspace name
+    pub gaps: BTreeMap<String, WorkspaceGaps>,
+    /// The index of the keyboard layout active in each window, keyed by the window id, see
+    /// [crate::config::DaemonConfig::keyboard_layout_per_window]
+    pub keyboard_layouts: BTreeMap<i32, usize>,
+    /// The tiled window being dragged with `haswaynav drag start`, which swaps places with every
+    /// tiled window the pointer enters until `haswaynav drag stop`
+    pub drag: Option<i32>,
+    /// The index of the keyboard layout active now, when known
+    #[serde(skip)]
+    keyboard_layout: Option<usize>,
+    /// The workspaces whose gaps [crate::config::GapsConfig::smart] removed for their single window
+    #[serde(skip)]
+    single: BTreeSet<String>,
+    /// The workspace every window is on, to know where a moved window came from
+    #[serde(skip)]
+    locations: BTreeMap<i32, String>,
+    /// Windows whose changes are not journaled until the instant
+    #[serde(skip)]
+    quiet: BTreeMap<i32, Instant>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+/// A change which can be rolled back, see [DaemonState::journal].
+pub struct JournalEntry {
+    /// The ids of the windows changed
+    pub ids: Vec<i32>,
+    /// The sway commands rolling the change back
+    pub commands: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+/// A focus change made by a directional move, see [DaemonState::moves].
+pub struct DirectionalMove {
+    /// The id of the node focused before the move
+    pub from: i32,
+    /// The id of the node focused by the move
+    pub to: i32,
+    pub direction: Direction,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+/// A window gaining the focus, see [DaemonState::history].
+pub struct FocusChange {
+    /// The id of the focused window
+    pub id: i32,
+    /// The name of the output the window is on
+    pub output: Option<String>,
+    /// The name of the workspace the window is on
+    pub workspace: Option<String>,
+}
+
+impl DaemonState {
+    /// Track that a window gained the focus.
+    pub(crate) fn focused(&mut self, change: FocusChange) {
+        if let Some(output) = &change.output {
+            self.last_focused_on_output
+                .insert(output.clone(), change.id);
+        }
+        if let Some(workspace) = &change.workspace {
+            self.last_focused_on_workspace
+                .insert(workspace.clone(), change.id);
+        }
+        self.mru.retain(|id| *id != change.id);
+        self.mru.insert(0, change.id);
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(change);
+    }
+
+    /// Change do-not-disturb, returning whether it is on now.
+    fn set_dnd(&mut self, mode: DndMode) -> bool {
+        self.dnd = match mode {
+            DndMode::On => true,
+            DndMode::Off => false,
+            DndMode::Toggle => !self.dnd,
+            DndMode::Status => self.dnd,
+        };
+        self.dnd
+    }
+
+    /// Track that the keyboard layout changed, in the focused window.
+    fn layout_changed(&mut self, index: usize) {
+        self.keyboard_layout = Some(index);
+        if let Some(focused) = self.mru.first() {
+            self.keyboard_layouts.insert(*focused, index);
+        }
+    }
+
+    /// The index of the keyboard layout to switch to for the window which gained the focus, if it
+    /// differs from the active one. A window focused for the first time keeps the active layout.
+    fn layout_for(&mut self, id: i32) -> Option<usize> {
+        match (self.keyboard_layouts.get(&id), self.keyboard_layout) {
+            (Some(index), active) if active != Some(*index) => Some(*index),
+            (Some(_), _) => None,
+            (None, active) => {
+                if let Some(index) = active {
+                    self.keyboard_layouts.insert(id, index);
+                }
+                None
+            }
+        }
+    }
+
+    /// Forget a window which was closed, its focus changes are kept in the history.
+    pub(crate) fn closed(&mut self, id: i32) {
+        self.last_focused_on_output.retain(|_, v| *v != id);
+        self.last_focused_on_workspace.retain(|_, v| *v != id);
+        self.mru.retain(|v| *v != id);
+        self.keyboard_layouts.remove(&id);
+        // The moves up to the window can no longer be retraced.
+        if let Some(pos) = self.moves.iter().rposition(|m| m.from == id || m.to == id) {
+            self.moves.drain(..=pos);
+        }
+        if self.split.is_some_and(|s| s.id == id) {
+            self.split = None;
+        }
+        if self.drag == Some(id) {
+            self.drag = None;
+        }
+        self.locations.remove(&id);
+        self.quiet.remove(&id);
+        for entry in &mut self.journal {
+            entry.ids.retain(|v| *v != id);
+        }
+        self.journal.retain(|e| !e.ids.is_empty());
+    }
+
+    /// The commands bringing back what reloading the sway config lost: the appearance the rules
+    /// give the windows and the gaps chosen for the workspaces or removed by smart gaps. The gaps of
+    /// a workspace are set through one of its windows, so those of empty workspaces are left out.
+    fn restore_commands(&self, tree: &TreeNode, config: &DaemonConfig) -> Vec<String> {
+        let mut commands = Vec::new();
+        let mut workspaces: BTreeMap<&str, i32> = BTreeMap::new();
+        for window in windows(tree) {
+            let profile: Vec<String> = config
+                .rules
+                .iter()
+                .filter(|r| r.matches(window.node()))
+                .flat_map(|r| r.profile_commands())
+                .collect();
+            if !profile.is_empty() {
+                commands.push(format!("[con_id={}] {}", window.id, profile.join(", ")));
+            }
+            if let Some(workspace) = window.workspace.filter(|w| *w != "__i3_scratch") {
+                workspaces.entry(workspace).or_insert(window.id);
+            }
+        }
+        for (workspace, id) in workspaces {
+            let gaps = if config.gaps.smart && self.single.contains(workspace) {
+                Some(Gaps::NONE)
+            } else {
+                self.gaps
+                    .get(workspace)
+                    .and_then(|g| g.gaps(&config.gaps.presets))
+            };
+            if let Some(gaps) = gaps {
+                commands.push(format!("[con_id={}] {}", id, gaps.commands()));
+            }
+        }
+        commands
+    }
+
+    /// The commands removing the gaps of the workspaces which came to show a single tiled window and
+    /// restoring the gaps of those which show more again, see [GapsConfig::smart]. Once smart gaps
+    /// are turned off the gaps of all workspaces they removed are restored.
+    fn smart_gaps(&mut self, tree: &TreeNode, config: &GapsConfig) -> Vec<String> {
+        // The number of tiled windows on each workspace and one of them to run the commands on.
+        let mut tiled: BTreeMap<&str, (usize, i32)> = BTreeMap::new();
+        for window in windows(tree).filter(|w| !w.floating) {
+            if let Some(workspace) = window.workspace.filter(|w| *w != "__i3_scratch") {
+                tiled.entry(workspace).or_insert((0, window.id)).0 += 1;
+            }
+        }
+        let mut commands = Vec::new();
+        for (workspace, (count, id)) in &tiled {
+            let single = config.smart && *count == 1;
+            if single == self.single.contains(*workspace) {
+                continue;
+            }
+            let gaps = if single {
+                self.single.insert(workspace.to_string());
+                Some(Gaps::NONE)
+            } else {
+                self.single.remove(*workspace);
+                let chosen = self.gaps.get(*workspace).copied().unwrap_or_default();
+                chosen.gaps(&config.presets)
+            };
+            if let Some(gaps) = gaps {
+                commands.push(format!("[con_id={}] {}", id, gaps.commands()));
+            }
+        }
+        // Empty workspaces still have no gaps, until sway destroys them.
+        let existing: BTreeSet<&str> = tree
+            .into_iter()
+            .filter(|c| c.get_node().node_type == NodeType::Workspace)
+            .filter_map(|c| c.get_node().name.as_deref())
+            .collect();
+        self.single
+            .retain(|w| config.smart && existing.contains(w.as_str()));
+        commands
+    }
+
+    /// Add a directional move, starting a new chain when it does not continue from where the latest
+    /// move went.
+    pub(crate) fn moved(&mut self, m: DirectionalMove) {
+        if self.moves.last().is_some_and(|last| last.to != m.from) {
+            self.moves.clear();
+        }
+        if self.moves.len() == MOVES_LEN {
+            self.moves.remove(0);
+        }
+        self.moves.push(m);
+    }
+
+    /// The window focused last on the output or workspace of a [Request::LastFocused] or
+    /// [Request::LastFocusedOnWorkspace] request.
+    pub(crate) fn remembered(&self, request: &Request) -> Option<i32> {
+        match request {
+            Request::LastFocused { output } => self.last_focused_on_output.get(output).copied(),
+            Request::LastFocusedOnWorkspace { workspace } => {
+                self.last_focused_on_workspace.get(workspace).copied()
+            }
+            _ => None,
+        }
+    }
+
+    /// Take the latest directional move to retrace it, `None` when there is none or the focus
+    /// changed otherwise since, which breaks the chain.
+    pub(crate) fn back(&mut self, focused: Option<i32>) -> Option<DirectionalMove> {
+        match self.moves.last() {
+            Some(last) if Some(last.to) == focused => self.moves.pop(),
+            _ => {
+                self.moves.clear();
+                None
+            }
+        }
+    }
+
+    /// Add an entry to the journal, dropping the oldest entries beyond `depth`. The windows of
+    /// the entry are quiet for a while, see [QUIET_PERIOD].
+    fn journal(&mut self, entry: JournalEntry, depth: usize) {
+        self.quiet(&entry.ids);
+        self.journal.push_back(entry);
+        while self.journal.len() > depth {
+            self.journal.pop_front();
+        }
+    }
+
+    /// Leave the changes of the windows out of the journal for a while.
+    fn quiet(&mut self, ids: &[i32]) {
+        let until = Instant::now() + QUIET_PERIOD;
+        for id in ids {
+            self.quiet.insert(*id, until);
+        }
+    }
+
+    /// Track that the window is on the workspace, returning the journal entry moving it back when
+    /// it was moved there from another workspace and is not quiet.
+    fn located(&mut self, id: i32, workspace: String) -> Option<JournalEntry> {
+        let previous = self.locations.insert(id, workspace.clone())?;
+        if previous == workspace || self.is_quiet(id) {
+            return None;
+        }
+        Some(JournalEntry {
+            ids: vec![id],
+            commands: vec![format!(
+                "[con_id={}] move container to workspace {}",
+                id,
+                quote(&previous)
+            )],
+        })
+    }
+
+    fn is_quiet(&mut self, id: i32) -> bool {
+        match self.quiet.get(&id) {
+            Some(until) if *until > Instant::now() => true,
+            Some(_) => {
+                self.quiet.remove(&id);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+/// The directory the daemon sockets are placed in, `$XDG_RUNTIME_DIR`.
+fn runtime_dir() -> Result<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| {
+            anyhow::format_err!("Environment variable 'XDG_RUNTIME_DIR' which specifies where to place the daemon socket is not defined")
+        })?;
+    Ok(PathBuf::from(runtime_dir))
+}
+
+/// The path of the sway socket the daemon and its clients talk to, from `SWAYSOCK`.
+pub(crate) fn swaysock() -> Option<String> {
+    std::env::var("SWAYSOCK").ok().filter(|s| !s.is_empty())
+}
+
+/// The file name of the daemon socket for the sway session with the socket, e.g.
+/// `haswaynav.sway-ipc.1000.1234.sock`, or the shared `haswaynav.sock` without a session.
+fn socket_name(swaysock: Option<&str>) -> String {
+    let session = swaysock
+        .and_then(|s| Path::new(s).file_name())
+        .map(|name| name.to_string_lossy());
+    match session {
+        Some(session) if session.ends_with(".sock") => format!("haswaynav.{}", session),
+        Some(session) => format!("haswaynav.{}.sock", session),
+        None => "haswaynav.sock".to_string(),
+    }
+}
+
+/// The path of the daemon socket of the sway session given by `SWAYSOCK`, see [socket_name], so
+/// the daemons of several sway sessions, e.g. a nested one for testing, each have their own.
+pub fn session_socket_path() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(socket_name(swaysock().as_deref())))
+}
+
+/// The path of the daemon socket shared by all sessions, `$XDG_RUNTIME_DIR/haswaynav.sock`, which
+/// the systemd socket unit listens on.
+pub fn shared_socket_path() -> Result<PathBuf> {
+    Ok(runtime_dir()?.join(socket_name(None)))
+}
+
+/// The path of the socket of the daemon serving this sway session: the socket of the session when
+/// a daemon answers on it, otherwise the shared socket unless the daemon there reports serving
+/// another session. Falls back to the socket of the session, which no daemon listens on yet.
+///
+/// A socket of the session left behind by a crashed daemon refuses connections and is passed
+/// over. The daemon on the shared socket is asked once per process.
+pub fn default_socket_path() -> Result<PathBuf> {
+    static SHARED_SERVES_SESSION: OnceLock<bool> = OnceLock::new();
+
+    let session = session_socket_path()?;
+    if session.exists() && UnixStream::connect(&session).is_ok() {
+        return Ok(session);
+    }
+    let shared = shared_socket_path()?;
+    if shared != session && shared.exists() {
+        let serves =
+            *SHARED_SERVES_SESSION.get_or_init(|| match request(&shared, &Request::Status) {
+                Ok(Response::Status {
+                    swaysock: Some(other),
+                    ..
+                }) if Some(&other) != swaysock().as_ref() => {
+                    tracing::debug!(
+                        other,
+                        "the daemon on the shared socket serves another session"
+                    );
+                    false
+                }
+                _ => true,
+            });
+        if serves {
+            return Ok(shared);
+        }
+    }
+    Ok(session)
+}
+
+/// Hold a lock on the sway session given by `SWAYSOCK` for as long as the returned listener is
+/// kept, so two daemons never serve the same session. The lock is an abstract socket which the
+/// kernel releases when the daemon exits, however it exits. `None` outside of a sway session.
+pub fn lock_session() -> Result<Option<UnixListener>> {
+    use std::os::linux::net::SocketAddrExt;
+
+    let Some(swaysock) = swaysock() else {
+        return Ok(None);
+    };
+    let addr =
+        std::os::unix::net::SocketAddr::from_abstract_name(format!("haswaynav/{}", swaysock))?;
+    match UnixListener::bind_addr(&addr) {
+        Ok(listener) => Ok(Some(listener)),
+        Err(err) if err.kind() == std::io::ErrorKind::AddrInUse => anyhow::bail!(
+            "Another daemon already serves the sway session at '{}'",
+            swaysock
+        ),
+        Err(err) => Err(err).context("locking the sway session"),
+    }
+}
+
+/// Take over the listening socket passed by systemd socket activation if there is one.
+///
+/// Only a single socket is supported and it is only accepted when `LISTEN_PID` refers to this
+/// process. The environment variables are removed so that they are not inherited by children.
+pub fn listen_fds() -> Result<Option<UnixListener>> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let fds = std::env::var("LISTEN_FDS").ok();
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let (pid, fds) = match (pid, fds) {
+        (Some(pid), Some(fds)) => (pid, fds),
+        _ => return Ok(None),
+    };
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        return Ok(None);
+    }
+    match fds.parse::<i32>() {
+        Ok(0) => Ok(None),
+        Ok(1) => {
+            // SAFETY: systemd guarantees that the first passed descriptor is open and owned by
+            // this process, and the environment variables were removed above so it is only
+            // taken once.
+            let listener = unsafe { UnixListener::from_raw_fd(SD_LISTEN_FDS_START) };
+            Ok(Some(listener))
+        }
+        Ok(n) => anyhow::bail!(
+            "Expected a single socket from systemd but LISTEN_FDS is {}",
+            n
+        ),
+        Err(err) => anyhow::bail!("Invalid LISTEN_FDS value '{}': {}", fds, err),
+    }
+}
+
+/// Send a status update such as `READY=1` to the service manager if `NOTIFY_SOCKET` is set,
+/// see `man sd_notify`.
+pub fn sd_notify(state: &str) -> Result<()> {
+    let path = match std::env::var_os("NOTIFY_SOCKET") {
+        None => return Ok(()),
+        Some(path) => path,
+    };
+    let socket = UnixDatagram::unbound()?;
+    let bytes = path.as_encoded_bytes();
+    match bytes.strip_prefix(b"@") {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        None => {
+            socket.send_to(state.as_bytes(), &path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Bind the daemon socket at the given path, replacing a stale socket left behind by a previous
+/// instance but refusing to run when another daemon is still answering on it.
+pub fn bind(path: &Path) -> Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            anyhow::bail!("A daemon is already listening on '{}'", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("removing stale socket '{}'", path.display()))?;
+    }
+    UnixListener::bind(path).with_context(|| format!("binding socket '{}'", path.display()))
+}
+
+/// The daemon's configuration which can be reloaded while it is running.
+pub struct SharedConfig {
+    path: Option<PathBuf>,
+    config: RwLock<Arc<Config>>,
+}
+
+impl SharedConfig {
+    /// Load the configuration from the given path or the default path, see [Config::load].
+    pub fn load(path: Option<PathBuf>) -> Result<SharedConfig> {
+        let path = path.or_else(default_config_path);
+        let config = Config::load(path.as_deref())?;
+        Ok(SharedConfig {
+            path,
+            config: RwLock::new(Arc::new(config)),
+        })
+    }
+
+    /// Get the current configuration.
+    pub fn get(&self) -> Arc<Config> {
+        self.config.read().expect("config lock poisoned").clone()
+    }
+
+    /// Load the configuration file again, keeping the current configuration if it is invalid.
+    pub fn reload(&self) -> Result<()> {
+        let config = Config::load(self.path.as_deref())?;
+        *self.config.write().expect("config lock poisoned") = Arc::new(config);
+        Ok(())
+    }
+}
+
+/// The [DaemonState] shared between the thread watching sway and the one serving clients.
+type SharedState = Arc<Mutex<DaemonState>>;
+
+/// The state kept by the daemon between requests.
+struct Daemon {
+    config: Arc<SharedConfig>,
+    sway: Option<SwayClient>,
+    /// The name and steps of the macro being recorded
+    recording: Option<(String, Vec<String>)>,
+    state: SharedState,
+}
+
+impl Daemon {
+    /// Get the connection to sway, connecting if there is not one yet.
+    fn sway(&mut self) -> Result<&mut SwayClient> {
+        if self.sway.is_none() {
+            self.sway = Some(SwayClient::connect()?);
+        }
+        Ok(self.sway.as_mut().expect("connected above"))
+    }
+
+    /// Change the gaps of the focused workspace and apply them, unless smart gaps keep them off
+    /// while it shows a single window.
+    fn change_gaps(&mut self, change: GapsChange) -> Result<()> {
+        let config = self.config.get();
+        let state = self.state.clone();
+        let socket = self.sway()?.socket();
+        let tree = get_tree_slim(socket)?;
+        let workspace = find_focused_fast(&tree)
+            .and_then(|c| c.workspace())
+            .and_then(|w| w.get_node().name.clone())
+            .context("No workspace is focused")?;
+        let gaps = {
+            let mut state = state.lock().expect("state lock poisoned");
+            let chosen = state.gaps.entry(workspace.clone()).or_default();
+            chosen.change(change, &config.daemon.gaps.presets)?;
+            let gaps = chosen.gaps(&config.daemon.gaps.presets);
+            if config.daemon.gaps.smart && state.single.contains(&workspace) {
+                return Ok(());
+            }
+            gaps
+        };
+        match gaps {
+            Some(gaps) => run_checked(socket, &gaps.commands()),
+            None => Ok(()),
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    fn handle(&mut self, request: Request) -> Response {
+        let result = match request {
+            Request::Ping => Ok(()),
+            Request::Focus { direction } => {
+                let config = self.config.get();
+                self.sway()
+                    .and_then(|s| {
+                        let direction = remap_direction(s.socket(), &config, direction)?;
+                        s.change_focus(direction, &config.focus)
+                    })
+                    .map(|moved| {
+                        if let Some(FocusMove { from, to }) = moved {
+                            let mut state = self.state.lock().expect("state lock poisoned");
+                            state.moved(DirectionalMove {
+                                from,
+                                to,
+                                direction,
+                            });
+                        }
+                    })
+            }
+            Request::Moved {
+                from,
+                to,
+                direction,
+            } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                state.moved(DirectionalMove {
+                    from,
+                    to,
+                    direction,
+                });
+                return Response::Ok;
+            }
+            Request::Back => {
+                // The focus is taken from sway, the thread watching it may not have seen the
+                // latest focus change yet.
+                let focused = match self.sway().and_then(|s| get_tree_slim(s.socket())) {
+                    Ok(tree) => find_focused_fast(&tree).map(|c| c.get_node().id),
+                    Err(err) => return local_response(Err(err)),
+                };
+                let back = self
+                    .state
+                    .lock()
+                    .expect("state lock poisoned")
+                    .back(focused);
+                match back {
+                    Some(m) => self.sway().and_then(|s| {
+                        run_checked(s.socket(), &format!("[con_id={}] focus", m.from))
+                    }),
+                    None => {
+                        return local_response(Err(anyhow::format_err!("No move to go back on")))
+                    }
+                }
+            }
+            Request::Gaps { change } => self.change_gaps(change),
+            Request::Split { id, direction } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                state.split = Some(Split { id, direction });
+                return Response::Ok;
+            }
+            Request::Pointer { warp } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                state.pointer = Some(warp);
+                return Response::Ok;
+            }
+            Request::Drag { id } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                state.drag = id;
+                return Response::Ok;
+            }
+            Request::TagView { workspace, tag } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                state.views.insert(workspace, tag);
+                return Response::Ok;
+            }
+            Request::Reload => self.config.reload().and_then(|()| {
+                let gaps = self.config.get().daemon.gaps.clone();
+                let state = self.state.clone();
+                apply_smart_gaps(self.sway()?.socket(), &state, &gaps)
+            }),
+            Request::RecordStart { name } => {
+                return local_response(macro_path(&name).map(|_| {
+                    self.recording = Some((name, Vec::new()));
+                }))
+            }
+            Request::RecordStop => {
+                return local_response(match self.recording.take() {
+                    Some((name, steps)) => save_macro(&name, &steps),
+                    None => Err(anyhow::format_err!("No macro is being recorded")),
+                })
+            }
+            Request::LastFocused { .. } | Request::LastFocusedOnWorkspace { .. } => {
+                let state = self.state.lock().expect("state lock poisoned");
+                return Response::Window {
+                    id: state.remembered(&request),
+                };
+            }
+            Request::Status => {
+                return Response::Status {
+                    pid: std::process::id(),
+                    swaysock: swaysock(),
+                }
+            }
+            Request::Bar => {
+                let state = self.state.lock().expect("state lock poisoned");
+                return Response::Bar {
+                    dnd: state.dnd,
+                    split: state.split,
+                };
+            }
+            Request::State => {
+                let state = self.state.lock().expect("state lock poisoned");
+                return Response::State {
+                    state: Box::new(state.clone()),
+                    rules: self.config.get().daemon.rules.clone(),
+                };
+            }
+            Request::Undo => {
+                let entry = self
+                    .state
+                    .lock()
+                    .expect("state lock poisoned")
+                    .journal
+                    .pop_back();
+                match entry {
+                    Some(entry) => {
+                        self.state
+                            .lock()
+                            .expect("state lock poisoned")
+                            .quiet(&entry.ids);
+                        let result = self
+                            .sway()
+                            .and_then(|s| run_checked(s.socket(), &entry.commands.join("; ")));
+                        if result.is_err() {
+                            // Keep the entry so undoing it can be tried again.
+                            self.state
+                                .lock()
+                                .expect("state lock poisoned")
+                                .journal
+                                .push_back(entry);
+                        }
+                        result
+                    }
+                    None => return Response::NothingToUndo,
+                }
+            }
+            Request::Journal { ids, commands } => {
+                let depth = self.config.get().daemon.undo_depth.unwrap_or(JOURNAL_LEN);
+                let entry = JournalEntry { ids, commands };
+                self.state
+                    .lock()
+                    .expect("state lock poisoned")
+                    .journal(entry, depth);
+                return Response::Ok;
+            }
+            Request::LeaveMode {
+                mode,
+                timeout_ms,
+                once,
+            } => {
+                std::thread::spawn(move || {
+                    let timeout = timeout_ms.map(Duration::from_millis);
+                    if let Err(err) = leave_mode_later(&mode, timeout, once) {
+                        tracing::warn!(mode, "not leaving the mode: {:#}", err);
+                    }
+                });
+                return Response::Ok;
+            }
+            Request::Dnd { mode } => {
+                let mut state = self.state.lock().expect("state lock poisoned");
+                return Response::Dnd {
+                    enabled: state.set_dnd(mode),
+                };
+            }
+            Request::Executed { line } => {
+                if let Some((_, steps)) = self.recording.as_mut() {
+                    steps.push(line);
+                }
+                return Response::Ok;
+            }
+        };
+        match result {
+            Ok(()) => Response::Ok,
+            Err(err) => {
+                // The sway connection may be left in an unknown state so start afresh next time.
+                self.sway = None;
+                Response::Error {
+                    message: format!("{:#}", err),
+                }
+            }
+        }
+    }
+}
+
+/// Serve the requests of a client until it hangs up. Only handling a request holds the daemon, so a
+/// client which is slow to send or to read does not hold up the others.
+fn serve_client(daemon: &Mutex<Daemon>, stream: UnixStream) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => daemon.lock().expect("daemon lock poisoned").handle(request),
+            Err(err) => Response::Error {
+                message: format!("Invalid request: {}", err),
+            },
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// The name of the workspace the window is on.
+fn workspace_of(tree: &TreeNode, id: i32) -> Option<String> {
+    Cursor::new(tree)
+        .iter()
+        .find(|c| c.get_node().id == id)
+        .and_then(|c| c.workspace())
+        .and_then(|w| w.get_node().name.clone())
+}
+
+/// The response to a request which does not involve sway.
+fn local_response(result: Result<()>) -> Response {
+    match result {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::Error {
+            message: format!("{:#}", err),
+        },
+    }
+}
+
+/// The commands of every matching rule for a newly created window followed by moving it to its
+/// home workspace, or only the commands applying their appearance profiles for a window which
+/// moved. Every entry is run on its own.
+fn rule_commands(rules: &[Rule], node: &TreeNode, new: bool) -> Vec<String> {
+    let mut commands: Vec<Vec<String>> = rules
+        .iter()
+        .filter(|r| r.matches(node))
+        .map(|rule| {
+            let mut all = rule.profile_commands();
+            if new {
+                all.splice(0..0, rule.commands.iter().cloned());
+            }
+            all
+        })
+        .collect();
+    if new {
+        commands.extend(home_command(rules, node).map(|c| vec![c]));
+    }
+    commands
+        .into_iter()
+        .filter(|all| !all.is_empty())
+        .map(|all| format!("[con_id={}] {}", node.id, all.join(", ")))
+        .collect()
+}
+
+/// The command restoring the inner gaps of the workspace which a window carrying gaps in its
+/// profile left: those of the profile of another window still there, otherwise the ones chosen for
+/// the workspace, see [DaemonState::gaps], or none. `None` when the window carries no gaps or no
+/// window is left to run the command through.
+fn left_gaps_command(
+    state: &DaemonState,
+    tree: &TreeNode,
+    config: &DaemonConfig,
+    node: &TreeNode,
+    left: &str,
+) -> Option<String> {
+    let profile_gaps = |node: &TreeNode| {
+        config
+            .rules
+            .iter()
+            .rev()
+            .filter(|r| r.matches(node))
+            .find_map(|r| r.gaps)
+    };
+    profile_gaps(node)?;
+    let remaining: Vec<_> = windows(tree)
+        .filter(|w| w.workspace == Some(left) && w.id != node.id)
+        .collect();
+    let through = remaining.first()?;
+    let inner = if config.gaps.smart && state.single.contains(left) {
+        0
+    } else {
+        remaining
+            .iter()
+            .find_map(|w| profile_gaps(w.node()))
+            .or_else(|| {
+                let chosen = state.gaps.get(left).copied().unwrap_or_default();
+                chosen.gaps(&config.gaps.presets).map(|g| g.inner)
+            })
+            .unwrap_or(0)
+    };
+    Some(format!(
+        "[con_id={}] gaps inner current set {}",
+        through.id, inner
+    ))
+}
+
+/// Run the commands of the matching rules, see [rule_commands].
+fn apply_rules(sock: &mut UnixStream, rules: &[Rule], node: &TreeNode, new: bool) -> Result<()> {
+    for commands in rule_commands(rules, node, new) {
+        let results = run_command(sock, &commands)?;
+        if let Err(err) = check_results(&commands, &results) {
+            tracing::warn!("rule command failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// The command setting the title shown for the node with the first of the title rules applying to
+/// it, or restoring the plain title when none does since the title may have stopped matching.
+fn title_command(rules: &[TitleRule], node: &TreeNode) -> String {
+    let format = match rules.iter().find_map(|r| r.format(node)) {
+        Some(title) => title.replace('%', "%%"),
+        None => "%title".to_string(),
+    };
+    format!("[con_id={}] title_format {}", node.id, quote(&format))
+}
+
+/// Set the title shown for the node, see [title_command]. Without any rules sway's own title format
+/// is left alone.
+fn apply_title_rules(sock: &mut UnixStream, rules: &[TitleRule], node: &TreeNode) -> Result<()> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let commands = title_command(rules, node);
+    let results = run_command(sock, &commands)?;
+    if let Err(err) = check_results(&commands, &results) {
+        tracing::warn!("title rule failed: {}", err);
+    }
+    Ok(())
+}
+
+/// Return the focus to the previously focused window when do-not-disturb is on or the policy
+/// protects it from the newly created window, marking that window urgent instead. An urgent window
+/// taking the focus, when `created` is false, only loses it again while do-not-disturb is on or to
+/// a window in global fullscreen, see [FocusPolicy::protect_global_fullscreen]. Returns whether the
+/// focus was returned.
+fn keep_focus(
+    sock: &mut UnixStream,
+    policy: &FocusPolicy,
+    dnd: bool,
+    previous: i32,
+    focused: i32,
+    created: bool,
+) -> Result<bool> {
+    let guarded = dnd
+        || if created {
+            policy.protect_fullscreen
+                || policy.protect_global_fullscreen
+                || !policy.protect.is_empty()
+        } else {
+            policy.protect_global_fullscreen
+        };
+    if !guarded {
+        return Ok(false);
+    }
+    // The slim tree leaves out the app id.
+    let tree = get_tree(sock)?;
+    let protected = Cursor::new(&tree)
+        .iter()
+        .find(|c| c.get_node().id == previous)
+        .is_some_and(|c| {
+            dnd || if created {
+                policy.protects(c.get_node())
+            } else {
+                policy.protects_global_fullscreen(c.get_node())
+            }
+        });
+    if protected {
+        tracing::debug!(previous, focused, created, "keeping the focus");
+        run_checked(
+            sock,
+            &format!(
+                "[con_id={}] focus; [con_id={}] urgent enable",
+                previous, focused
+            ),
+        )?;
+    }
+    Ok(protected)
+}
+
+/// The commands leaving the global fullscreen of containers on a workspace other than the focused
+/// one, see [FocusPolicy::unfullscreen_on_workspace_switch].
+fn unfullscreen_commands(tree: &TreeNode) -> Vec<String> {
+    let workspace = |c: &Cursor| c.workspace().map(|w| w.get_node().id);
+    let Some(focused) = find_focused_fast(tree).and_then(|c| workspace(&c)) else {
+        return Vec::new();
+    };
+    tree.into_iter()
+        .filter(|c| c.get_node().fullscreen_mode == FullScreenMode::GlobalFullScreen)
+        .filter(|c| workspace(c) != Some(focused))
+        .map(|c| format!("[con_id={}] fullscreen disable", c.get_node().id))
+        .collect()
+}
+
+/// Subscribe to window events, apply the configured rules to every new or moved window, rewrite
+/// titles and remember the window focused last on every output and workspace.
+fn watch_windows(config: Arc<SharedConfig>, state: SharedState) -> Result<()> {
+    let mut events = sway_connect()?;
+    // Layouts change without a window event, mostly from key bindings.
+    subscribe(&mut events, &["window", "binding", "workspace", "input"])?;
+    let mut commands = sway_connect()?;
+    {
+        let active = get_inputs(&mut commands)?
+            .into_iter()
+            .find_map(|i| i.xkb_active_layout_index);
+        // Know where the windows are so their first move can be journaled.
+        let tree = get_tree_slim(&mut commands)?;
+        let mut state = state.lock().expect("state lock poisoned");
+        state.keyboard_layout = active;
+        for window in Cursor::new(&tree).iter() {
+            let node = window.get_node();
+            if !matches!(node.node_type, NodeType::Con | NodeType::FloatingCon) {
+                continue;
+            }
+            if let Some(workspace) = window.workspace().and_then(|w| w.get_node().name.clone()) {
+                state.locations.insert(node.id, workspace);
+            }
+        }
+    }
+    // The window created last, until it gains the focus.
+    let mut created = None;
+    loop {
+        let event = receive_event(&mut events)?;
+        if matches!(&event, Event::Binding(e) if changes_layout(&e.binding.command))
+            && config.get().daemon.smart_title_bars
+        {
+            if let Err(err) = apply_smart_title_bars(&mut commands) {
+                tracing::warn!("changing the title bars failed: {:#}", err);
+            }
+        }
+        let unfullscreen = config
+            .get()
+            .daemon
+            .focus_policy
+            .unfullscreen_on_workspace_switch;
+        if matches!(&event, Event::Workspace(e) if e.change == "reload") {
+            if let Err(err) = restore_after_reload(&mut commands, &config.get().daemon, &state) {
+                tracing::warn!("restoring after the reload failed: {:#}", err);
+            }
+        }
+        if matches!(event, Event::Workspace(_)) && unfullscreen {
+            let tree = get_tree_slim(&mut commands)?;
+            for command in unfullscreen_commands(&tree) {
+                if let Err(err) = run_checked(&mut commands, &command) {
+                    tracing::warn!("leaving fullscreen failed: {:#}", err);
+                }
+            }
+        }
+        if let Event::Input(event) = &event {
+            if let Some(index) = event.input.xkb_active_layout_index {
+                if event.change == "xkb_layout" || event.change == "xkb_keymap" {
+                    state
+                        .lock()
+                        .expect("state lock poisoned")
+                        .layout_changed(index);
+                }
+            }
+        }
+        if let Event::Window(event) = event {
+            match event.change {
+                WindowChange::New => {
+                    created = Some(event.container.id);
+                    let config = config.get();
+                    apply_rules(&mut commands, &config.daemon.rules, &event.container, true)?;
+                    apply_title_rules(&mut commands, &config.daemon.titles, &event.container)?;
+                    let views = state.lock().expect("state lock poisoned").views.clone();
+                    let id = event.container.id;
+                    if !views.is_empty() || !config.workspace.is_empty() {
+                        if let Err(err) =
+                            settle_new_window(&mut commands, &views, &config.workspace, id)
+                        {
+                            tracing::warn!("settling the new window failed: {:#}", err);
+                            continue;
+                        }
+                    }
+                    // Know where the window ended up so its first move can be journaled.
+                    let tree = get_tree_slim(&mut commands)?;
+                    if let Some(workspace) = workspace_of(&tree, id) {
+                        let mut state = state.lock().expect("state lock poisoned");
+                        state.locations.insert(id, workspace);
+                    }
+                }
+                WindowChange::Move => {
+                    let config = config.get();
+                    apply_rules(&mut commands, &config.daemon.rules, &event.container, false)?;
+                    let id = event.container.id;
+                    let tree = get_tree_slim(&mut commands)?;
+                    if let Some(workspace) = workspace_of(&tree, id) {
+                        let mut state = state.lock().expect("state lock poisoned");
+                        let left = state
+                            .locations
+                            .get(&id)
+                            .filter(|previous| **previous != workspace)
+                            .and_then(|previous| {
+                                left_gaps_command(
+                                    &state,
+                                    &tree,
+                                    &config.daemon,
+                                    &event.container,
+                                    previous,
+                                )
+                            });
+                        if let Some(entry) = state.located(id, workspace) {
+                            tracing::debug!(?entry, "journaling a move");
+                            state.journal(entry, config.daemon.undo_depth.unwrap_or(JOURNAL_LEN));
+                        }
+                        drop(state);
+                        if let Some(command) = left {
+                            let results = run_command(&mut commands, &command)?;
+                            if let Err(err) = check_results(&command, &results) {
+                                tracing::warn!("restoring the gaps failed: {}", err);
+                            }
+                        }
+                    }
+                }
+                WindowChange::Floating => {
+                    let id = event.container.id;
+                    let mut state = state.lock().expect("state lock poisoned");
+                    if !state.is_quiet(id) {
+                        let floating = event.container.node_type == NodeType::FloatingCon;
+                        let entry = JournalEntry {
+                            ids: vec![id],
+                            commands: vec![format!(
+                                "[con_id={}] floating {}",
+                                id,
+                                if floating { "disable" } else { "enable" }
+                            )],
+                        };
+                        state.journal(entry, config.get().daemon.undo_depth.unwrap_or(JOURNAL_LEN));
+                    }
+                }
+                WindowChange::Title => {
+                    apply_title_rules(&mut commands, &config.get().daemon.titles, &event.container)?
+                }
+                WindowChange::Focus => {
+                    let id = event.container.id;
+                    let dragged = state.lock().expect("state lock poisoned").drag;
+                    if let Some(dragged) = dragged.filter(|d| *d != id) {
+                        if event.container.node_type == NodeType::Con {
+                            // The focus only passes through the entered window, it is not tracked.
+                            if let Err(err) =
+                                run_checked(&mut commands, &drag_commands(dragged, id))
+                            {
+                                tracing::warn!("dragging the window failed: {:#}", err);
+                            }
+                            continue;
+                        }
+                    }
+                    let new = created.take() == Some(id);
+                    if new || event.container.urgent {
+                        let (previous, dnd) = {
+                            let state = state.lock().expect("state lock poisoned");
+                            (state.mru.first().copied(), state.dnd)
+                        };
+                        let policy = &config.get().daemon.focus_policy;
+                        if let Some(previous) = previous.filter(|p| *p != id) {
+                            match keep_focus(&mut commands, policy, dnd, previous, id, new) {
+                                Ok(true) => continue,
+                                Ok(false) => (),
+                                Err(err) => tracing::warn!("keeping the focus failed: {:#}", err),
+                            }
+                        }
+                    }
+                    let tree = get_tree_slim(&mut commands)?;
+                    let Some(window) = Cursor::new(&tree).iter().find(|c| c.get_node().id == id)
+                    else {
+                        continue;
+                    };
+                    let name = |c: Option<Cursor>| c.and_then(|c| c.get_node().name.clone());
+                    let change = FocusChange {
+                        id,
+                        output: name(window.output()),
+                        workspace: name(window.workspace()),
+                    };
+                    tracing::trace!(?change, "focused window");
+                    let mut state = state.lock().expect("state lock poisoned");
+                    if let Some(workspace) = &change.workspace {
+                        // A window gaining the focus is not moved, only its location is tracked.
+                        state.locations.insert(id, workspace.clone());
+                    }
+                    state.focused(change);
+                    let restore = config
+                        .get()
+                        .daemon
+                        .keyboard_layout_per_window
+                        .then(|| state.layout_for(id))
+                        .flatten();
+                    drop(state);
+                    if let Some(index) = restore {
+                        let command = switch_layout_command(None, &index.to_string());
+                        if let Err(err) = run_checked(&mut commands, &command) {
+                            tracing::warn!("restoring the keyboard layout failed: {:#}", err);
+                        }
+                    }
+                }
+                WindowChange::Urgent if event.container.urgent => {
+                    let dnd = state.lock().expect("state lock poisoned").dnd;
+                    if let Some(mark) = config.get().daemon.dnd_mark.as_ref().filter(|_| dnd) {
+                        // Marks are unique, so every window gets one of its own.
+                        let id = event.container.id;
+                        let mark_command = format!(
+                            "[con_id={}] mark --add {}",
+                            id,
+                            quote(&format!("{}:{}", mark, id))
+                        );
+                        if let Err(err) = run_checked(&mut commands, &mark_command) {
+                            tracing::warn!("marking the urgent window failed: {:#}", err);
+                        }
+                    }
+                }
+                WindowChange::Close => state
+                    .lock()
+                    .expect("state lock poisoned")
+                    .closed(event.container.id),
+                _ => (),
+            }
+            let gaps = &config.get().daemon.gaps;
+            let tiling_changed = matches!(
+                event.change,
+                WindowChange::New
+                    | WindowChange::Close
+                    | WindowChange::Move
+                    | WindowChange::Floating
+            );
+            if tiling_changed && config.get().daemon.smart_title_bars {
+                apply_smart_title_bars(&mut commands)?;
+            }
+            if tiling_changed {
+                apply_smart_gaps(&mut commands, &state, gaps)?;
+            }
+        }
+    }
+}
+
+/// Remove or restore the gaps of the workspaces whose number of tiled windows changed, see
+/// [DaemonState::smart_gaps]. Does nothing when smart gaps are off and removed no gaps.
+fn apply_smart_gaps(sock: &mut UnixStream, state: &SharedState, config: &GapsConfig) -> Result<()> {
+    if !config.smart && state.lock().expect("state lock poisoned").single.is_empty() {
+        return Ok(());
+    }
+    let tree = get_tree_slim(sock)?;
+    let smart = state
+        .lock()
+        .expect("state lock poisoned")
+        .smart_gaps(&tree, config);
+    for command in smart {
+        let results = run_command(sock, &command)?;
+        if let Err(err) = check_results(&command, &results) {
+            tracing::warn!("smart gaps failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// The commands settling a new window on its workspace: it gets the tag viewed there, see
+/// [DaemonState::views], and the first tiled window of a workspace with a
+/// [crate::config::WorkspaceConfig::default_layout] sets the layout of the workspace.
+fn new_window_commands(
+    tree: &TreeNode,
+    views: &BTreeMap<String, String>,
+    workspaces: &BTreeMap<String, WorkspaceConfig>,
+    id: i32,
+) -> Result<Vec<String>> {
+    let mut commands = Vec::new();
+    let Some(window) = Cursor::new(tree).iter().find(|c| c.get_node().id == id) else {
+        return Ok(commands);
+    };
+    let Some(workspace) = window.workspace() else {
+        return Ok(commands);
+    };
+    let Some(name) = workspace.get_node().name.as_deref() else {
+        return Ok(commands);
+    };
+    if let Some(tag) = views.get(name) {
+        commands.push(format!(
+            "[con_id={}] mark --add \"{}\"",
+            id,
+            tag_mark(tag, id)?
+        ));
+    }
+    let layout = workspaces.get(name).and_then(|w| w.default_layout);
+    if let Some(layout) = layout.filter(|_| !window.is_floating()) {
+        let first = workspace.pre_order().all(|c| {
+            let node = c.get_node();
+            node.id == id
+                || c.is_floating()
+                || node.node_type != NodeType::Con
+                || !node.nodes.is_empty()
+        });
+        if first {
+            commands.push(format!("[con_id={}] layout {}", id, layout));
+        }
+    }
+    Ok(commands)
+}
+
+/// Settle the new window on its workspace, see [new_window_commands]. The window may be gone by the
+/// time the commands run, sway failing them is only worth a warning.
+fn settle_new_window(
+    sock: &mut UnixStream,
+    views: &BTreeMap<String, String>,
+    workspaces: &BTreeMap<String, WorkspaceConfig>,
+    id: i32,
+) -> Result<()> {
+    let tree = get_tree_slim(sock)?;
+    let settle = new_window_commands(&tree, views, workspaces, id)?;
+    if settle.is_empty() {
+        return Ok(());
+    }
+    tracing::debug!(?settle, "settling a new window");
+    let commands = settle.join("; ");
+    let results = run_command(sock, &commands)?;
+    if let Err(err) = check_results(&commands, &results) {
+        tracing::warn!("settling the new window failed: {}", err);
+    }
+    Ok(())
+}
+
+/// The commands hiding the title bars of tiled windows alone in a split container and showing them
+/// for windows with siblings or in a tabbed or stacked container, see
+/// [crate::config::DaemonConfig::smart_title_bars]. Windows without borders or drawing their own
+/// decorations are left alone.
+fn smart_title_commands(tree: &TreeNode) -> Vec<String> {
+    windows(tree)
+        .filter(|w| !w.floating)
+        .filter_map(|window| {
+            let parent = window.cursor().clone().ascend().ok()?;
+            let parent = parent.get_node();
+            let titled =
+                matches!(parent.layout, Layout::Tabbed | Layout::Stacked) || parent.nodes.len() > 1;
+            match window.node().border {
+                Border::Normal if !titled => Some(format!("[con_id={}] border pixel", window.id)),
+                Border::Pixel if titled => Some(format!("[con_id={}] border normal", window.id)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Bring back what reloading the sway config lost, see [DaemonState::restore_commands], along with
+/// the smart title bars and the outputs of the workspaces, logging what was restored.
+fn restore_after_reload(
+    sock: &mut UnixStream,
+    config: &DaemonConfig,
+    state: &SharedState,
+) -> Result<()> {
+    // The slim tree leaves out the app ids the rules match.
+    let tree = get_tree(sock)?;
+    let mut restore = state
+        .lock()
+        .expect("state lock poisoned")
+        .restore_commands(&tree, config);
+    if !config.workspace_outputs.is_empty() {
+        let workspaces = get_workspaces(sock)?;
+        let outputs = get_outputs(sock)?;
+        restore.extend(plan_workspace_outputs(
+            &config.workspace_outputs,
+            &workspaces,
+            &outputs,
+        ));
+    }
+    if config.smart_title_bars {
+        restore.extend(smart_title_commands(&tree));
+    }
+    tracing::info!(?restore, "restoring after the sway config was reloaded");
+    for command in restore {
+        let results = run_command(sock, &command)?;
+        if let Err(err) = check_results(&command, &results) {
+            tracing::warn!("restoring after the reload failed: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the commands of a binding may change the layout of containers, which sway reports with
+/// no window event.
+fn changes_layout(command: &str) -> bool {
+    command.contains("layout") || command.contains("split")
+}
+
+/// Show or hide the title bars of the tiled windows, see [smart_title_commands].
+fn apply_smart_title_bars(sock: &mut UnixStream) -> Result<()> {
+    // The slim tree leaves out the borders.
+    let tree = get_tree(sock)?;
+    let all = smart_title_commands(&tree);
+    if all.is_empty() {
+        return Ok(());
+    }
+    let commands = all.join("; ");
+    let results = run_command(sock, &commands)?;
+    if let Err(err) = check_results(&commands, &results) {
+        tracing::warn!("changing the title bars failed: {}", err);
+    }
+    Ok(())
+}
+
+/// The type of the output event without the event bit, see `man sway-ipc`.
+const OUTPUT_EVENT: u32 = 1;
+
+/// Subscribe to output events and move the workspaces to their preferred outputs whenever outputs
+/// are connected or disconnected, see [plan_workspace_outputs].
+fn watch_outputs(config: Arc<SharedConfig>) -> Result<()> {
+    let mut events = sway_connect()?;
+    subscribe(&mut events, &["output"])?;
+    let mut commands = sway_connect()?;
+    loop {
+        if let Event::Other(OUTPUT_EVENT) = receive_event(&mut events)? {
+            let config = config.get();
+            if config.daemon.workspace_outputs.is_empty() {
+                continue;
+            }
+            let workspaces = get_workspaces(&mut commands)?;
+            let outputs = get_outputs(&mut commands)?;
+            let moves =
+                plan_workspace_outputs(&config.daemon.workspace_outputs, &workspaces, &outputs);
+            if !moves.is_empty() {
+                tracing::debug!(?moves, "moving workspaces to their outputs");
+                if let Err(err) = run_checked(&mut commands, &moves.join("; ")) {
+                    tracing::warn!("moving workspaces failed: {:#}", err);
+                }
+            }
+        }
+    }
+}
+
+/// The closest ancestor of the directory which exists, the directory itself when it does.
+fn existing_ancestor(dir: &Path) -> PathBuf {
+    dir.ancestors()
+        .find(|ancestor| ancestor.is_dir())
+        .filter(|ancestor| !ancestor.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf()
+}
+
+/// Watch the configuration file with inotify and reload it whenever it changes. The directory is
+/// watched rather than the file itself since editors often replace the file when saving. Until the
+/// directory exists its closest existing ancestor is watched instead, following the directories
+/// down as they are created.
+fn watch_config(config: Arc<SharedConfig>, state: SharedState) -> Result<()> {
+    let path = match &config.path {
+        None => return Ok(()),
+        Some(path) => path.clone(),
+    };
+    let (dir, file_name) = match (path.parent(), path.file_name()) {
+        (Some(dir), Some(file_name)) => (dir, file_name),
+        _ => anyhow::bail!("Can not watch config file '{}'", path.display()),
+    };
+
+    let mask = WatchMask::CLOSE_WRITE
+        | WatchMask::MOVED_TO
+        | WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::DELETE_SELF
+        | WatchMask::MOVE_SELF;
+    let mut inotify = Inotify::init().context("initialising inotify")?;
+    let mut watched = existing_ancestor(dir);
+    let mut watch = inotify
+        .watches()
+        .add(&watched, mask)
+        .with_context(|| format!("watching config directory '{}'", watched.display()))?;
+
+    let mut buffer = [0; 4096];
+    loop {
+        let mut changed = false;
+        let mut moved = false;
+        for event in inotify.read_events_blocking(&mut buffer)? {
+            if watched == dir {
+                changed |= event.name == Some(file_name);
+            }
+            // Anything in an ancestor may be the next directory, and the watched directory may go.
+            moved |= watched != dir
+                || event
+                    .mask
+                    .intersects(EventMask::DELETE_SELF | EventMask::MOVE_SELF);
+        }
+        if moved {
+            let ancestor = existing_ancestor(dir);
+            if ancestor != watched {
+                // Removing fails when the directory went and the kernel dropped the watch already.
+                let _ = inotify.watches().remove(watch);
+                watch = inotify.watches().add(&ancestor, mask).with_context(|| {
+                    format!("watching config directory '{}'", ancestor.display())
+                })?;
+                tracing::debug!(dir = %ancestor.display(), "watching for the config file");
+                watched = ancestor;
+                // The file may have been written before the watch was in place.
+                changed |= watched == dir && path.exists();
+            }
+        }
+        if changed {
+            tracing::info!(path = %path.display(), "config file changed");
+            if let Err(err) = config.reload() {
+                tracing::error!("keeping the previous configuration: {:#}", err);
+                continue;
+            }
+            let gaps = &config.get().daemon.gaps;
+            if let Err(err) =
+                sway_connect().and_then(|mut s| apply_smart_gaps(&mut s, &state, gaps))
+            {
+                tracing::warn!("smart gaps failed: {:#}", err);
+            }
+        }
+    }
+}
+
+/// Accept clients on the listener and serve each on its own thread until accepting fails.
+pub fn serve(listener: UnixListener, config: SharedConfig) -> Result<()> {
+    let config = Arc::new(config);
+    let state = SharedState::default();
+    {
+        let config = config.clone();
+        let state = state.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = watch_windows(config, state) {
+                tracing::error!("stopped watching windows: {:#}", err);
+            }
+        });
+    }
+    {
+        let config = config.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = watch_outputs(config) {
+                tracing::error!("stopped watching outputs: {:#}", err);
+            }
+        });
+    }
+    {
+        let config = config.clone();
+        let state = state.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = watch_config(config, state) {
+                tracing::error!("stopped watching the config file: {:#}", err);
+            }
+        });
+    }
+
+    let daemon = Arc::new(Mutex::new(Daemon {
+        config,
+        sway: None,
+        recording: None,
+        state,
+    }));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let daemon = daemon.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = serve_client(&daemon, stream) {
+                        tracing::warn!("failed serving client: {:#}", err);
+                    }
+                });
+            }
+            Err(err) => anyhow::bail!("Failed accepting connection: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// Ask the daemon on the default socket for the window focused last on the output or workspace,
+/// see [Request::LastFocused], and check that the window is still on the container with the given
+/// type and name. Not having a daemon running is normal so failing to ask it is only logged, the
+/// state file of [crate::config::DaemonConfig::run_once] answers instead when there is one.
+pub fn remembered_window(
+    socket: &mut UnixStream,
+    request: &Request,
+    node_type: NodeType,
+    name: &str,
+) -> Result<Option<i32>> {
+    let response = default_socket_path().and_then(|path| self::request(&path, request));
+    let id = match response {
+        Ok(Response::Window { id: Some(id) }) => id,
+        Ok(Response::Window { id: None }) => return Ok(None),
+        Ok(response) => {
+            tracing::debug!(?response, "unexpected daemon response");
+            return Ok(None);
+        }
+        Err(err) => match run_once::load() {
+            Ok(Some(state)) => match state.remembered(request) {
+                Some(id) => id,
+                None => return Ok(None),
+            },
+            Ok(None) => {
+                tracing::debug!("not restoring the focus: {:#}", err);
+                return Ok(None);
+            }
+            Err(file_err) => {
+                tracing::debug!("not restoring the focus: {:#}, {:#}", err, file_err);
+                return Ok(None);
+            }
+        },
+    };
+    let tree = get_tree_slim(socket)?;
+    let window = Cursor::new(&tree)
+        .iter()
+        .find(|c| c.get_node().id == id)
+        .and_then(|c| c.closest(node_type))
+        .filter(|c| c.get_node().name.as_deref() == Some(name));
+    Ok(window.map(|_| id))
+}
+
+/// Send a single request to the daemon listening on the given socket and wait for its response.
+pub fn request(path: &Path, request: &Request) -> Result<Response> {
+    let mut stream = UnixStream::connect(path).map_err(|err| {
+        anyhow::format_err!(
+            "Failed connecting to the daemon socket '{}': {}",
+            path.display(),
+            err
+        )
+    })?;
+    serde_json::to_writer(&mut stream, request)?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut line)
+        .context("reading daemon response")?;
+    serde_json::from_str(&line).context("decoding daemon response")
+}
+
+#[test]
+fn test_rule_commands() {
+    let rules: Vec<Rule> = toml::from_str::<DaemonConfig>(
+        r#"
+        [[rules]]
+        app_id = "^kitty$"
+        commands = ["floating enable"]
+        workspace = "term"
+
+        [[rules]]
+        app_id = "kitty"
+        border = "none"
+        workspace = "other"
+        "#,
+    )
+    .unwrap()
+    .rules;
+    let node = TreeNode {
+        id: 7,
+        app_id: Some("kitty".to_string()),
+        ..Default::default()
+    };
+
+    // Only the first home workspace is moved to.
+    assert_eq!(
+        rule_commands(&rules, &node, true),
+        [
+            "[con_id=7] floating enable",
+            "[con_id=7] border none",
+            "[con_id=7] move container to workspace \"term\"",
+        ]
+    );
+    assert_eq!(
+        rule_commands(&rules, &node, false),
+        ["[con_id=7] border none"]
+    );
+}
+
+#[test]
+fn test_left_gaps_command() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let config: DaemonConfig = toml::from_str(
+        r#"
+        rules = [{ app_id = "^kitty$", gaps = 20 }]
+        "#,
+    )
+    .unwrap();
+    let kitty = TreeNode {
+        id: 7,
+        app_id: Some("kitty".to_string()),
+        ..Default::default()
+    };
+    let mut state = DaemonState::default();
+
+    // Workspace 2 still shows four windows, the gaps are set through the first.
+    assert_eq!(
+        left_gaps_command(&state, &tree, &config, &kitty, "2").as_deref(),
+        Some("[con_id=126] gaps inner current set 0")
+    );
+    let chosen = WorkspaceGaps {
+        chosen: Some(Gaps { inner: 8, outer: 0 }),
+        off: false,
+    };
+    state.gaps.insert("2".to_string(), chosen);
+    assert_eq!(
+        left_gaps_command(&state, &tree, &config, &kitty, "2").as_deref(),
+        Some("[con_id=126] gaps inner current set 8")
+    );
+
+    // Windows without gaps in their profile leave the gaps alone, as do those leaving no window
+    // behind.
+    let other = TreeNode {
+        app_id: Some("foot".to_string()),
+        ..kitty.clone()
+    };
+    assert_eq!(left_gaps_command(&state, &tree, &config, &other, "2"), None);
+    assert_eq!(
+        left_gaps_command(&state, &tree, &config, &kitty, "gone"),
+        None
+    );
+}
+
+#[test]
+fn test_changes_layout() {
+    assert!(changes_layout("layout tabbed"));
+    assert!(changes_layout("exec haswaynav split auto"));
+    assert!(!changes_layout("exec haswaynav focus left"));
+}
+
+#[test]
+fn test_existing_ancestor() {
+    let dir = std::env::temp_dir();
+    assert_eq!(existing_ancestor(&dir), dir);
+    assert_eq!(existing_ancestor(&dir.join("haswaynav-missing/sub")), dir);
+    assert_eq!(existing_ancestor(Path::new("missing")), Path::new("."));
+}
+
+#[test]
+fn test_title_command() {
+    let rules: Vec<TitleRule> = toml::from_str::<DaemonConfig>(
+        r#"
+        [[titles]]
+        title = "^(.*) - Firefox$"
+        replace = "$1"
+        "#,
+    )
+    .unwrap()
+    .titles;
+    let node = |title: &str| TreeNode {
+        id: 7,
+        name: Some(title.to_string()),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        title_command(&rules, &node("100% \"done\" - Firefox")),
+        "[con_id=7] title_format \"100%% \\\"done\\\"\""
+    );
+    assert_eq!(
+        title_command(&rules, &node("vim")),
+        "[con_id=7] title_format \"%title\""
+    );
+}
+
+#[test]
+fn test_request_round_trip() {
+    let requests = [
+        Request::Ping,
+        Request::Reload,
+        Request::Focus {
+            direction: Direction::Left,
+        },
+        Request::RecordStart {
+            name: "layout".to_string(),
+        },
+        Request::RecordStop,
+        Request::Executed {
+            line: "focus left".to_string(),
+        },
+        Request::LastFocused {
+            output: "eDP-1".to_string(),
+        },
+        Request::LastFocusedOnWorkspace {
+            workspace: "1".to_string(),
+        },
+        Request::State,
+        Request::Status,
+        Request::Bar,
+        Request::Dnd {
+            mode: DndMode::Toggle,
+        },
+        Request::Undo,
+        Request::Moved {
+            from: 1,
+            to: 2,
+            direction: Direction::Down,
+        },
+        Request::Back,
+        Request::Gaps {
+            change: GapsChange::Set(Gaps { inner: 5, outer: 0 }),
+        },
+        Request::Split {
+            id: 1,
+            direction: SplitDirection::Vertical,
+        },
+        Request::Pointer {
+            warp: PointerWarp {
+                point: crate::pointer::Point { x: 640, y: 360 },
+                focused: Some(1),
+            },
+        },
+        Request::Drag { id: Some(1) },
+        Request::Drag { id: None },
+        Request::TagView {
+            workspace: "2".to_string(),
+            tag: "work".to_string(),
+        },
+        Request::Journal {
+            ids: vec![1, 2],
+            commands: vec!["[con_id=1] move container to workspace \"1\"".to_string()],
+        },
+        Request::LeaveMode {
+            mode: "resize".to_string(),
+            timeout_ms: Some(3000),
+            once: true,
+        },
+    ];
+    for request in requests {
+        let json = serde_json::to_string(&request).unwrap();
+        let parsed: Request = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, request);
+    }
+}
+
+#[test]
+fn test_socket_name() {
+    assert_eq!(
+        socket_name(Some("/run/user/1000/sway-ipc.1000.1234.sock")),
+        "haswaynav.sway-ipc.1000.1234.sock"
+    );
+    assert_eq!(socket_name(Some("/tmp/nested")), "haswaynav.nested.sock");
+    assert_eq!(socket_name(None), "haswaynav.sock");
+}
+
+#[test]
+fn test_request_deserialize() {
+    let json = r#"{"request": "focus", "direction": "right"}"#;
+
+    let parsed: Request = serde_json::from_str(json).unwrap();
+
+    assert_eq!(
+        parsed,
+        Request::Focus {
+            direction: Direction::Right
+        }
+    );
+}
+
+#[test]
+fn test_daemon_state() {
+    let change = |id, workspace: &str| FocusChange {
+        id,
+        output: Some("eDP-1".to_string()),
+        workspace: Some(workspace.to_string()),
+    };
+    let mut state = DaemonState::default();
+
+    state.focused(change(1, "1"));
+    state.focused(change(2, "2"));
+    state.focused(change(1, "1"));
+    state.closed(2);
+
+    assert_eq!(state.mru, [1]);
+    assert_eq!(state.history.len(), 3);
+    assert_eq!(state.last_focused_on_output["eDP-1"], 1);
+    assert_eq!(state.last_focused_on_workspace.get("2"), None);
+
+    for id in 0..2 * HISTORY_LEN as i32 {
+        state.focused(change(id, "1"));
+    }
+    assert_eq!(state.history.len(), HISTORY_LEN);
+    assert_eq!(state.mru[0], 2 * HISTORY_LEN as i32 - 1);
+
+    assert_eq!(state.located(1, "1".to_string()), None);
+    assert_eq!(state.located(1, "1".to_string()), None);
+    let entry = state.located(1, "3".to_string()).unwrap();
+    assert_eq!(
+        entry.commands,
+        ["[con_id=1] move container to workspace \"1\""]
+    );
+    for _ in 0..3 {
+        state.journal(entry.clone(), 2);
+    }
+    assert_eq!(state.journal.len(), 2);
+    assert_eq!(
+        state.located(1, "4".to_string()),
+        None,
+        "quiet after journaling"
+    );
+    state.closed(1);
+    assert!(state.journal.is_empty());
+    state.located(2, "a \"b\" \\".to_string());
+    assert_eq!(
+        state.located(2, "1".to_string()).unwrap().commands,
+        [r#"[con_id=2] move container to workspace "a \"b\" \\""#]
+    );
+
+    assert!(state.set_dnd(DndMode::Toggle));
+    assert!(state.set_dnd(DndMode::Status));
+    assert!(!state.set_dnd(DndMode::Off));
+}
+
+#[test]
+fn test_undo_failed() {
+    use crate::messages::{read_frame, write_frame};
+
+    let (client, mut server) = UnixStream::pair().unwrap();
+    let sway = std::thread::spawn(move || {
+        // The window was closed in the meantime.
+        let (command_type, _) = read_frame(&mut server).unwrap();
+        let failed = r#"[{"success": false, "error": "No matching node."}]"#;
+        write_frame(&mut server, command_type, failed.as_bytes()).unwrap();
+    });
+    let entry = JournalEntry {
+        ids: vec![1],
+        commands: vec!["[con_id=1] move container to workspace \"1\"".to_string()],
+    };
+    let state = SharedState::default();
+    state.lock().unwrap().journal(entry.clone(), JOURNAL_LEN);
+    let mut daemon = Daemon {
+        config: Arc::new(SharedConfig {
+            path: None,
+            config: RwLock::new(Arc::new(Config::default())),
+        }),
+        sway: Some(SwayClient::from(client)),
+        recording: None,
+        state: state.clone(),
+    };
+
+    let response = daemon.handle(Request::Undo);
+    sway.join().unwrap();
+
+    assert!(matches!(response, Response::Error { .. }));
+    assert_eq!(state.lock().unwrap().journal, [entry]);
+}
+
+#[test]
+fn test_directional_moves() {
+    let step = |from, to| DirectionalMove {
+        from,
+        to,
+        direction: Direction::Left,
+    };
+    let mut state = DaemonState::default();
+
+    state.moved(step(1, 2));
+    state.moved(step(2, 3));
+    assert_eq!(state.back(Some(3)), Some(step(2, 3)));
+    assert_eq!(state.back(Some(2)), Some(step(1, 2)));
+    assert_eq!(state.back(Some(1)), None);
+
+    // Focusing another window some other way breaks the chain.
+    state.moved(step(1, 2));
+    assert_eq!(state.back(Some(5)), None);
+    assert!(state.moves.is_empty());
+
+    // So does a move starting elsewhere.
+    state.moved(step(1, 2));
+    state.moved(step(4, 5));
+    assert_eq!(state.moves, [step(4, 5)]);
+
+    state.moved(step(5, 6));
+    state.moved(step(6, 7));
+    state.closed(5);
+    assert_eq!(state.moves, [step(6, 7)]);
+}
+
+#[test]
+fn test_keyboard_layouts() {
+    let change = |id| FocusChange {
+        id,
+        output: None,
+        workspace: None,
+    };
+    let mut state = DaemonState {
+        keyboard_layout: Some(0),
+        ..DaemonState::default()
+    };
+
+    state.focused(change(1));
+    assert_eq!(
+        state.layout_for(1),
+        None,
+        "the first focus keeps the layout"
+    );
+    state.layout_changed(1);
+    state.focused(change(2));
+    assert_eq!(state.layout_for(2), None);
+    assert_eq!(state.keyboard_layouts[&2], 1);
+    state.layout_changed(0);
+
+    state.focused(change(1));
+    assert_eq!(state.layout_for(1), Some(1));
+    // The switch is reported as a layout change of the window regaining the focus.
+    state.layout_changed(1);
+    state.focused(change(2));
+    assert_eq!(state.layout_for(2), Some(0));
+
+    state.closed(1);
+    assert!(!state.keyboard_layouts.contains_key(&1));
+}
+
+#[test]
+fn test_new_window_commands() {
+    use crate::profile::LayoutMode;
+
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let tabbed = WorkspaceConfig {
+        default_layout: Some(LayoutMode::Tabbed),
+    };
+    let workspaces = BTreeMap::from([("1".to_string(), tabbed.clone()), ("2".to_string(), tabbed)]);
+    let views = BTreeMap::from([("2".to_string(), "work".to_string())]);
+    let commands = |id| new_window_commands(&tree, &views, &workspaces, id).unwrap();
+
+    // Window 99 is alone on workspace 1.
+    assert_eq!(commands(99), ["[con_id=99] layout tabbed"]);
+    // Workspace 2 has windows already, so only the viewed tag is added.
+    assert_eq!(commands(126), ["[con_id=126] mark --add \"_tag:work:126\""]);
+    assert!(commands(79).is_empty());
+    assert!(commands(12345).is_empty());
+}
+
+#[test]
+fn test_settle_new_window_gone() {
+    use crate::messages::{read_frame, write_frame};
+
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let sway = std::thread::spawn(move || {
+        let (tree_type, _) = read_frame(&mut server).unwrap();
+        let tree = include_str!("../tests/fixtures/trees/multi-output.json");
+        write_frame(&mut server, tree_type, tree.as_bytes()).unwrap();
+        // The window closed before its settle commands arrived.
+        let (command_type, command) = read_frame(&mut server).unwrap();
+        let failed = r#"[{"success": false, "error": "No matching node."}]"#;
+        write_frame(&mut server, command_type, failed.as_bytes()).unwrap();
+        String::from_utf8(command).unwrap()
+    });
+    let workspaces = BTreeMap::from([(
+        "1".to_string(),
+        WorkspaceConfig {
+            default_layout: Some(crate::profile::LayoutMode::Tabbed),
+        },
+    )]);
+
+    settle_new_window(&mut client, &BTreeMap::new(), &workspaces, 99).unwrap();
+
+    assert_eq!(sway.join().unwrap(), "[con_id=99] layout tabbed");
+}
+
+#[test]
+fn test_smart_gaps() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let presets = [Gaps { inner: 5, outer: 5 }];
+    let config = GapsConfig {
+        presets: presets.to_vec(),
+        smart: true,
+    };
+    let mut state = DaemonState::default();
+
+    // Workspaces 1, 10, 4, 5 and 6 show a single tiled window.
+    let commands = state.smart_gaps(&tree, &config);
+    let none = |id| format!("[con_id={}] {}", id, Gaps::NONE.commands());
+    assert_eq!(
+        commands,
+        [none(99), none(409), none(79), none(205), none(184)]
+    );
+    assert!(state.smart_gaps(&tree, &config).is_empty());
+
+    // Workspace 2 showed a single window before and gets the gaps chosen for it back now that
+    // it shows four, workspace 3 those of the first preset.
+    let chosen = Gaps { inner: 8, outer: 0 };
+    let workspace = WorkspaceGaps {
+        chosen: Some(chosen),
+        off: false,
+    };
+    state.gaps.insert("2".to_string(), workspace);
+    state.single.insert("2".to_string());
+    state.single.insert("3".to_string());
+    assert_eq!(
+        state.smart_gaps(&tree, &config),
+        [
+            format!("[con_id=126] {}", chosen.commands()),
+            format!("[con_id=206] {}", presets[0].commands())
+        ]
+    );
+    assert!(!state.single.contains("2"));
+
+    // Workspaces which no longer exist are forgotten.
+    state.single.insert("gone".to_string());
+    state.smart_gaps(&tree, &config);
+    assert!(!state.single.contains("gone"));
+
+    // Turning smart gaps off restores the gaps of every workspace they removed.
+    let off = GapsConfig {
+        smart: false,
+        ..config
+    };
+    assert_eq!(
+        state.smart_gaps(&tree, &off),
+        [99, 409, 79, 205, 184].map(|id| format!("[con_id={}] {}", id, presets[0].commands()))
+    );
+    assert!(state.single.is_empty());
+    assert!(state.smart_gaps(&tree, &off).is_empty());
+}
+
+#[test]
+fn test_restore_commands() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let config: DaemonConfig = toml::from_str(
+        r#"
+        rules = [{ title = "^~/d/kristy$", opacity = 0.9, border = "pixel 2" }]
+        gaps = { presets = [{ inner = 5, outer = 5 }], smart = true }
+        "#,
+    )
+    .unwrap();
+    let mut state = DaemonState::default();
+    assert_eq!(
+        state.restore_commands(&tree, &config),
+        [
+            "[con_id=130] border pixel 2, opacity 0.9",
+            "[con_id=139] border pixel 2, opacity 0.9"
+        ]
+    );
+
+    // Only the gaps the daemon set are restored, workspaces without windows can not be reached.
+    let chosen = Gaps { inner: 8, outer: 0 };
+    let workspace = WorkspaceGaps {
+        chosen: Some(chosen),
+        off: false,
+    };
+    state.gaps.insert("2".to_string(), workspace);
+    state.gaps.insert("8".to_string(), workspace);
+    state.single.insert("5".to_string());
+    let restore = state.restore_commands(&tree, &config);
+    assert_eq!(
+        restore[2..],
+        [
+            format!("[con_id=126] {}", chosen.commands()),
+            format!("[con_id=205] {}", Gaps::NONE.commands())
+        ]
+    );
+}
+
+#[test]
+fn test_smart_title_commands() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let pixel = |id| format!("[con_id={}] border pixel", id);
+    // The windows alone in a split container, those alone in a tabbed one keep their title bars.
+    assert_eq!(
+        smart_title_commands(&tree),
+        [pixel(99), pixel(409), pixel(205), pixel(184)]
+    );
+}
+
+#[test]
+fn test_unfullscreen_commands() {
+    let mut tree = crate::tree::fixtures::load("multi-output").unwrap();
+    assert!(unfullscreen_commands(&tree).is_empty());
+    fn fullscreen(node: &mut TreeNode, id: i32) {
+        if node.id == id {
+            node.fullscreen_mode = FullScreenMode::GlobalFullScreen;
+        }
+        node.nodes.iter_mut().for_each(|n| fullscreen(n, id));
+    }
+    // Window 126 is on the focused workspace, window 99 on workspace 1 which was left.
+    fullscreen(&mut tree, 126);
+    assert!(unfullscreen_commands(&tree).is_empty());
+    fullscreen(&mut tree, 99);
+    assert_eq!(
+        unfullscreen_commands(&tree),
+        ["[con_id=99] fullscreen disable"]
+    );
+}
@@ -0,0 +1,241 @@
+//! The `doctor` command which diagnoses common setup problems.
+
+use std::{
+    io::Write,
+    os::unix::{fs::FileTypeExt, net::UnixStream},
+};
+
+use anyhow::Result;
+
+use crate::{
+    daemon::{self, default_socket_path, Request, Response},
+    messages::{get_config, get_version},
+};
+
+/// The oldest sway version whose IPC replies are known to decode.
+pub const MIN_SWAY_VERSION: (i32, i32) = (1, 5);
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+/// The outcome of a single [Check].
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// The result of checking one aspect of the setup.
+pub struct Check {
+    pub status: Status,
+    /// What was found.
+    pub summary: String,
+    /// What to do about it if the check did not pass.
+    pub hint: Option<String>,
+}
+
+impl Check {
+    fn ok(summary: String) -> Check {
+        Check {
+            status: Status::Ok,
+            summary,
+            hint: None,
+        }
+    }
+
+    fn warning(summary: String, hint: &str) -> Check {
+        Check {
+            status: Status::Warning,
+            summary,
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    fn error(summary: String, hint: &str) -> Check {
+        Check {
+            status: Status::Error,
+            summary,
+            hint: Some(hint.to_string()),
+        }
+    }
+}
+
+/// Get the lines of the sway config which bind keys to a haswaynav command.
+pub fn haswaynav_bindings(config: &str) -> Vec<&str> {
+    config
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.starts_with("bindsym") || l.starts_with("bindcode"))
+        .filter(|l| l.contains("haswaynav"))
+        .collect()
+}
+
+/// Check the sway version against [MIN_SWAY_VERSION].
+fn check_version(major: i32, minor: i32, human_readable: &str) -> Check {
+    if (major, minor) >= MIN_SWAY_VERSION {
+        Check::ok(format!("sway version {}", human_readable))
+    } else {
+        Check::warning(
+            format!(
+                "sway version {} is older than the oldest supported version {}.{}",
+                human_readable, MIN_SWAY_VERSION.0, MIN_SWAY_VERSION.1
+            ),
+            "Upgrade sway, replies from older versions may fail to decode.",
+        )
+    }
+}
+
+/// Check the connection to sway, stopping at the first check that fails since the later ones
+/// depend on it.
+fn check_sway(checks: &mut Vec<Check>) {
+    let path = match std::env::var("SWAYSOCK").ok().filter(|s| !s.is_empty()) {
+        Some(path) => path,
+        None => {
+            let hint = match std::env::var("I3SOCK").ok().filter(|s| !s.is_empty()) {
+                Some(_) => "I3SOCK is set but haswaynav only reads SWAYSOCK, export SWAYSOCK=$I3SOCK if it points at sway.",
+                None => "Run haswaynav from within a sway session, or export SWAYSOCK with the path printed by `sway --get-socketpath`.",
+            };
+            checks.push(Check::error("SWAYSOCK is not set".to_string(), hint));
+            return;
+        }
+    };
+    checks.push(Check::ok(format!("SWAYSOCK is '{}'", path)));
+
+    match std::fs::metadata(&path) {
+        Err(err) => {
+            checks.push(Check::error(
+                format!("Can not access '{}': {}", path, err),
+                "The socket belongs to a sway session which is no longer running, restart the shell from within the current session.",
+            ));
+            return;
+        }
+        Ok(meta) if !meta.file_type().is_socket() => {
+            checks.push(Check::error(
+                format!("'{}' is not a socket", path),
+                "SWAYSOCK must point at the IPC socket of sway, see `sway --get-socketpath`.",
+            ));
+            return;
+        }
+        Ok(_) => (),
+    }
+
+    let mut socket = match UnixStream::connect(&path) {
+        Ok(socket) => socket,
+        Err(err) => {
+            let hint = match err.kind() {
+                std::io::ErrorKind::PermissionDenied => {
+                    "The socket belongs to another user, run haswaynav as the user running sway."
+                }
+                _ => "Sway is not accepting connections on the socket, check that it is still running.",
+            };
+            checks.push(Check::error(
+                format!("Failed connecting to '{}': {}", path, err),
+                hint,
+            ));
+            return;
+        }
+    };
+    checks.push(Check::ok("Connected to sway".to_string()));
+
+    match get_version(&mut socket) {
+        Ok(v) => checks.push(check_version(v.major, v.minor, &v.human_readable)),
+        Err(err) => {
+            checks.push(Check::error(
+                format!("Failed getting the sway version: {:#}", err),
+                "The socket may not belong to sway, check that SWAYSOCK is correct.",
+            ));
+            return;
+        }
+    }
+
+    match get_config(&mut socket) {
+        Ok(reply) => {
+            let bindings = haswaynav_bindings(&reply.config);
+            if bindings.is_empty() {
+                checks.push(Check::warning(
+                    "No keybindings in the sway config run haswaynav".to_string(),
+                    "Add bindings such as `bindsym $mod+h exec haswaynav focus left` to the sway config, keybindings in included files are not checked.",
+                ));
+            } else {
+                checks.push(Check::ok(format!(
+                    "{} keybindings in the sway config run haswaynav",
+                    bindings.len()
+                )));
+            }
+        }
+        Err(err) => checks.push(Check::warning(
+            format!("Failed getting the sway config: {:#}", err),
+            "Keybindings could not be checked.",
+        )),
+    }
+}
+
+/// Check whether the daemon is running, it is optional so failing to reach it is a warning.
+fn check_daemon(checks: &mut Vec<Check>) {
+    let hint = "Start `haswaynavd` or enable `haswaynavd.socket`, only needed for daemon features such as rules.";
+    let path = match default_socket_path() {
+        Ok(path) => path,
+        Err(err) => {
+            checks.push(Check::warning(format!("{:#}", err), hint));
+            return;
+        }
+    };
+    match daemon::request(&path, &Request::Ping) {
+        Ok(Response::Ok) => checks.push(Check::ok(format!(
+            "Daemon is running on '{}'",
+            path.display()
+        ))),
+        Ok(response) => checks.push(Check::warning(
+            format!("Daemon on '{}' replied with {:?}", path.display(), response),
+            "Restart the daemon, it may be a different version than the CLI.",
+        )),
+        Err(err) => checks.push(Check::warning(format!("{:#}", err), hint)),
+    }
+}
+
+/// Run all the checks.
+pub fn run_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    check_sway(&mut checks);
+    check_daemon(&mut checks);
+    checks
+}
+
+/// Print the checks with their hints.
+pub fn print_checks(checks: &[Check], out: &mut dyn Write) -> Result<()> {
+    for check in checks {
+        let label = match check.status {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Error => "error",
+        };
+        writeln!(out, "[{:^7}] {}", label, check.summary)?;
+        if let Some(hint) = &check.hint {
+            writeln!(out, "          {}", hint)?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_haswaynav_bindings() {
+    let config = "set $mod Mod4\n\
+                  bindsym $mod+h exec haswaynav focus left\n\
+                  bindsym $mod+j focus down\n\
+                  \tbindcode 44 exec haswaynav focus up\n\
+                  # bindsym $mod+l exec haswaynav focus right\n";
+
+    assert_eq!(
+        haswaynav_bindings(config),
+        [
+            "bindsym $mod+h exec haswaynav focus left",
+            "bindcode 44 exec haswaynav focus up"
+        ]
+    );
+}
+
+#[test]
+fn test_check_version() {
+    assert_eq!(check_version(1, 9, "1.9").status, Status::Ok);
+    assert_eq!(check_version(2, 0, "2.0").status, Status::Ok);
+    assert_eq!(check_version(1, 4, "1.4").status, Status::Warning);
+}
@@ -0,0 +1,46 @@
+//! Rearranging tiled windows with the pointer, which sway only offers for floating windows and with
+//! a modifier drag between containers. `haswaynav drag start` tells the daemon which window is
+//! dragged, and while the drag lasts every other tiled window the pointer enters swaps places with
+//! it. Sway does not report the pointer over IPC, so this relies on `focus_follows_mouse` turning
+//! the pointer entering a window into a focus event.
+
+use anyhow::Result;
+
+use crate::tree::{cursor::find_focused_fast, TreeNode, Window};
+
+/// The focused window to drag, which has to be tiled.
+pub fn drag_target(tree: &TreeNode) -> Result<i32> {
+    let window = find_focused_fast(tree)
+        .and_then(Window::at)
+        .ok_or_else(|| anyhow::format_err!("No window is focused to drag"))?;
+    if window.floating {
+        anyhow::bail!("Floating windows are dragged with the mouse by sway itself");
+    }
+    Ok(window.id)
+}
+
+/// The commands swapping the dragged window into the place of the window the pointer entered,
+/// keeping the focus on the dragged window which is under the pointer afterwards.
+pub fn drag_commands(dragged: i32, entered: i32) -> String {
+    format!(
+        "[con_id={}] swap container with con_id {}; [con_id={}] focus",
+        dragged, entered, dragged
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn drag_focused_window() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let focused = find_focused_fast(&tree).unwrap().get_node().id;
+        assert_eq!(drag_target(&tree).unwrap(), focused);
+        assert_eq!(
+            drag_commands(126, 11),
+            "[con_id=126] swap container with con_id 11; [con_id=126] focus"
+        );
+    }
+}
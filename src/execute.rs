@@ -0,0 +1,737 @@
+//! Executing the haswaynav subcommands, either given on the command line or as the steps of
+//! aliases and macros.
+
+use std::{io::Write, os::unix::net::UnixStream, path::PathBuf, time::Duration};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    bar::run_bar,
+    batch::run_batch,
+    bench::run_bench,
+    bindings::{bindings, write_bindings},
+    capture::replay,
+    cli::{
+        Cli, Commands, DaemonCommand, DragCommand, FocusArgs, FocusCommand, GapsCommand, GetArgs,
+        GetCommand, InputCommand, KbLayoutCommand, ModeCommand, MoveCommand, OutputCommand,
+        PointerCommand, ProfileCommand, RecordCommand, ResizeCommand, SplitCommand, StateCommand,
+        SwapCommand, TagCommand,
+    },
+    completions::{complete_values, write_completions},
+    config::{Config, Rule},
+    daemon::{self, default_socket_path, DaemonState, DirectionalMove, DndMode, Request, Response},
+    doctor::{print_checks, run_checks, Status},
+    drag::drag_target,
+    explain_focus,
+    expose::{expose, print_expose},
+    filter::Filter,
+    gaps::{Gaps, GapsChange},
+    geometry::MM_PER_INCH,
+    input::next_layout,
+    launch::{launch, Placement},
+    messages::{get_binding_state, get_config, get_inputs, get_tree, get_tree_json, get_tree_slim},
+    mode::enter_mode,
+    output::{float_size, focus_output, remap_direction, send_to_output},
+    pointer::{move_pointer, move_to_pointer, pointer_info},
+    profile::{apply_profile, load_profile},
+    query::{find_at_point, focused_info, tab_info, workspace_layout, WindowInfo},
+    recording::play_macro,
+    repl::run_repl,
+    resize::distribute,
+    run_checked, run_once,
+    snapshot::write_snapshot,
+    split::{split_auto, Split},
+    tag::{add_tag, remove_tag, toggle_tag, view_tag},
+    toggle_layer,
+    tree::cursor::find_focused_fast,
+    undo::undo,
+    wait::{wait_for, Condition},
+    workspace::{focus_workspace, isolate_app, send_home, swap_workspace_contents},
+    Direction, FocusMove, SwayClient,
+};
+
+/// Execute a haswaynav subcommand, connecting to sway with `connect` when the command needs it.
+/// When `offline`, as when running against a snapshot, neither the daemon nor the state and undo
+/// files of the live session are used.
+pub fn execute(
+    command: Commands,
+    config: &Config,
+    connect: &mut dyn FnMut() -> Result<UnixStream>,
+    offline: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    // Only the commands given directly are recorded, not the steps they run.
+    let recordable = command.recordable_line().filter(|_| STEP_DEPTH.get() == 0);
+    if let Commands::Focus(
+        args @ FocusArgs {
+            command: Some(_), ..
+        },
+    ) = &command
+    {
+        args.check_subcommand()?;
+    }
+    match command {
+        Commands::Focus(FocusArgs {
+            command: Some(FocusCommand::ToggleLayer),
+            ..
+        }) => toggle_layer(&mut connect()?)?,
+        Commands::Focus(
+            ref args @ FocusArgs {
+                command: Some(FocusCommand::Into { direction }),
+                ..
+            },
+        ) => {
+            let mut socket = connect()?;
+            let direction = remap_direction(&mut socket, config, direction)?;
+            let moved = SwayClient::from(socket.try_clone()?)
+                .reporting(keeps_moves(config, offline))
+                .focus_into(direction, &args.apply(&config.focus))?;
+            notify_moved(moved, direction, offline);
+        }
+        Commands::Focus(FocusArgs {
+            command: Some(FocusCommand::Back),
+            ..
+        }) => go_back(&mut connect()?, config, offline)?,
+        Commands::Focus(args) => {
+            let direction = args
+                .direction
+                .context("focus needs a direction or a subcommand")?;
+            let mut socket = connect()?;
+            let direction = remap_direction(&mut socket, config, direction)?;
+            let steps = mode_steps(&mut socket, config, direction)?;
+            if args.explain {
+                writeln!(out, "direction: {}", direction)?;
+                let lines = match steps {
+                    Some((mode, steps)) => std::iter::once(format!(
+                        "the binding mode '{}' runs its steps instead",
+                        mode
+                    ))
+                    .chain(steps.iter().map(|step| format!("step: {}", step)))
+                    .collect(),
+                    None => {
+                        let tree = get_tree_slim(&mut socket)?;
+                        explain_focus(&tree, direction, &args.apply(&config.focus)).1
+                    }
+                };
+                for line in lines {
+                    writeln!(out, "{}", line)?;
+                }
+                return Ok(());
+            }
+            match steps {
+                Some((mode, steps)) => {
+                    let what = format!("mode '{}'", mode);
+                    run_steps(&mut socket, config, &what, steps, offline)?
+                }
+                None => {
+                    let moved = SwayClient::from(socket.try_clone()?)
+                        .reporting(keeps_moves(config, offline))
+                        .change_focus(direction, &args.apply(&config.focus))?;
+                    notify_moved(moved, direction, offline);
+                }
+            }
+        }
+        Commands::Run { name } => run_alias(&mut connect()?, config, &name, offline)?,
+        Commands::Completions { shell } => write_completions(shell, out)?,
+        Commands::Complete { kind } => {
+            for value in complete_values(kind, config)? {
+                writeln!(out, "{}", value)?;
+            }
+        }
+        Commands::Doctor => {
+            let checks = run_checks();
+            print_checks(&checks, out)?;
+            let errors = checks.iter().filter(|c| c.status == Status::Error).count();
+            if errors > 0 {
+                anyhow::bail!("{} problems found", errors);
+            }
+        }
+        Commands::Reload => daemon_request(&Request::Reload, "reloading", offline)?,
+        Commands::Replay { file } => replay(&file, config, out)?,
+        Commands::Repl => run_repl(config)?,
+        Commands::Batch => run_batch(&mut std::io::stdin().lock(), out, config, connect, offline)?,
+        Commands::Record(RecordCommand::Start { name }) => daemon_request(
+            &Request::RecordStart { name },
+            "starting the recording",
+            offline,
+        )?,
+        Commands::Record(RecordCommand::Stop) => {
+            daemon_request(&Request::RecordStop, "stopping the recording", offline)?
+        }
+        Commands::InitBindings { modifier, style } => {
+            let existing = connect()
+                .and_then(|mut socket| get_config(&mut socket))
+                .map_err(|err| tracing::warn!("not checking for conflicts: {:#}", err))
+                .ok();
+            let conflicts = write_bindings(
+                out,
+                &bindings(&modifier, style),
+                existing.as_ref().map(|c| c.config.as_str()),
+            )?;
+            if conflicts > 0 {
+                tracing::warn!("{} bindings conflict with the sway config", conflicts);
+            }
+        }
+        Commands::Bench { iterations } => run_bench(&mut connect()?, iterations, out)?,
+        Commands::Output(OutputCommand::Focus { target, restore }) => {
+            focus_output(&mut connect()?, target, restore && !offline)?
+        }
+        Commands::Output(OutputCommand::Send { target, follow }) => {
+            send_to_output(&mut connect()?, target, follow)?
+        }
+        Commands::Play { name } => play_macro(&mut connect()?, config, &name, offline)?,
+        Commands::Workspace { number } => focus_workspace(&mut connect()?, number, !offline)?,
+        Commands::IsolateApp { workspace } => {
+            isolate_app(&mut connect()?, workspace.as_deref(), !offline)?
+        }
+        Commands::SendHome => send_home(&mut connect()?, &config.daemon.rules, !offline)?,
+        Commands::Move(MoveCommand::ToPointer) => {
+            let warp = match daemon_state(config, offline) {
+                Ok((state, _)) => state.pointer,
+                Err(err) => {
+                    tracing::debug!("no pointer position from the daemon: {:#}", err);
+                    None
+                }
+            };
+            move_to_pointer(&mut connect()?, warp)?
+        }
+        Commands::Swap(SwapCommand::WorkspaceContents { first, second }) => {
+            swap_workspace_contents(&mut connect()?, &first, second.as_deref(), !offline)?
+        }
+        Commands::Exec {
+            workspace,
+            layout,
+            float,
+            size,
+            app_id,
+            timeout,
+            command,
+        } => {
+            let placement = Placement {
+                workspace,
+                layout,
+                float,
+                size,
+            };
+            launch(
+                &mut connect()?,
+                connect()?,
+                &command,
+                app_id.as_ref(),
+                &placement,
+                Duration::from_millis(timeout),
+            )?
+        }
+        Commands::Wait {
+            app_id,
+            title,
+            workspace,
+            closed,
+            timeout,
+        } => {
+            let condition = Condition {
+                app_id,
+                title,
+                workspace,
+                closed,
+            };
+            if let Some(window) = wait_for(&mut connect()?, connect()?, &condition, timeout)? {
+                writeln!(out, "{}", serde_json::to_string(&window)?)?;
+            }
+        }
+        Commands::Bar { plain } => run_bar(&config.bar, plain, out)?,
+        Commands::Expose { json } => {
+            // The slim tree leaves out the app id.
+            let tree = get_tree(&mut connect()?)?;
+            let workspaces = expose(&tree, config.text.max_title_length);
+            print_expose(&workspaces, json, out)?
+        }
+        Commands::Daemon(DaemonCommand::Status) => {
+            let socket = daemon_socket(offline)?;
+            let (pid, swaysock) = match daemon::request(&socket, &Request::Status)? {
+                Response::Status { pid, swaysock } => (pid, swaysock),
+                response => {
+                    anyhow::bail!("Daemon replied {:?} when asked for its status", response)
+                }
+            };
+            let session = swaysock.is_some() && swaysock == std::env::var("SWAYSOCK").ok();
+            let status = serde_json::json!({
+                "socket": socket,
+                "pid": pid,
+                "swaysock": swaysock,
+                "session": session,
+            });
+            writeln!(out, "{}", status)?;
+        }
+        Commands::Daemon(DaemonCommand::RunOnce) => {
+            if offline {
+                anyhow::bail!("The focus is not recorded when running against a snapshot");
+            }
+            run_once::record(&mut connect()?)?
+        }
+        Commands::Dnd { mode } => {
+            let enabled = match daemon::request(&daemon_socket(offline)?, &Request::Dnd { mode })? {
+                Response::Dnd { enabled } => enabled,
+                Response::Error { message } => {
+                    anyhow::bail!("Daemon failed changing do-not-disturb: {}", message)
+                }
+                response => {
+                    anyhow::bail!("Daemon replied {:?} when changing do-not-disturb", response)
+                }
+            };
+            if mode == DndMode::Status {
+                writeln!(out, "{}", if enabled { "on" } else { "off" })?;
+            }
+        }
+        Commands::FloatSize { mm, inches } => {
+            let inches = inches.map(|(w, h)| (w * MM_PER_INCH, h * MM_PER_INCH));
+            let (width, height) = mm.or(inches).context("float-size needs --mm or --in")?;
+            float_size(&mut connect()?, config, width, height)?
+        }
+        Commands::Undo => {
+            if offline {
+                anyhow::bail!("Nothing is undone when running against a snapshot");
+            }
+            undo(&mut connect()?)?
+        }
+        Commands::Snapshot { dir } => {
+            let path = write_snapshot(&mut connect()?, &dir, config)?;
+            writeln!(out, "{}", path.display())?;
+        }
+        Commands::Mode(ModeCommand::Enter {
+            name,
+            timeout,
+            once,
+        }) => {
+            // Without the daemon nobody returns to the default mode.
+            let timeout = timeout.filter(|_| !offline);
+            enter_mode(&mut connect()?, &name, timeout, once && !offline)?
+        }
+        Commands::Profile(ProfileCommand::Apply { name }) => {
+            let profile = load_profile(&name)?;
+            apply_profile(&mut connect()?, connect()?, &profile, !offline)?
+        }
+        Commands::Gaps(command) => {
+            let change = match command {
+                GapsCommand::Cycle => GapsChange::Cycle,
+                GapsCommand::Set { inner, outer } => GapsChange::Set(Gaps { inner, outer }),
+                GapsCommand::Toggle => GapsChange::Toggle,
+            };
+            daemon_request(&Request::Gaps { change }, "changing the gaps", offline)?
+        }
+        Commands::Input(InputCommand::List) => {
+            for input in get_inputs(&mut connect()?)? {
+                writeln!(out, "{}", serde_json::to_string(&input)?)?;
+            }
+        }
+        Commands::Input(InputCommand::KbLayout(KbLayoutCommand::Next { device })) => {
+            next_layout(&mut connect()?, device.as_deref())?
+        }
+        Commands::Pointer(PointerCommand::Get) => {
+            let (state, _) = daemon_state(config, offline)?;
+            let warp = state.pointer.context(
+                "Sway does not report where the pointer is and haswaynav did not move it yet",
+            )?;
+            // The slim tree leaves out the app id and pid.
+            let tree = get_tree(&mut connect()?)?;
+            let mut info = pointer_info(&tree, warp.point);
+            let max = config.text.max_title_length;
+            info.window = info.window.map(|w| w.clean_title(max));
+            writeln!(out, "{}", serde_json::to_string(&info)?)?;
+        }
+        Commands::Pointer(PointerCommand::MoveTo { target }) => {
+            let warp = move_pointer(&mut connect()?, &target, &config.focus)?;
+            if let Err(err) = daemon_socket(offline)
+                .and_then(|path| daemon::request(&path, &Request::Pointer { warp }))
+            {
+                tracing::debug!("not remembering the pointer position: {:#}", err);
+            }
+        }
+        Commands::Resize(ResizeCommand::Distribute { shares }) => {
+            distribute(&mut connect()?, &shares)?
+        }
+        Commands::Drag(DragCommand::Start) => {
+            let id = drag_target(&get_tree_slim(&mut connect()?)?)?;
+            let request = Request::Drag { id: Some(id) };
+            daemon_request(&request, "starting the drag", offline)?
+        }
+        Commands::Drag(DragCommand::Stop) => {
+            daemon_request(&Request::Drag { id: None }, "stopping the drag", offline)?
+        }
+        Commands::Split(SplitCommand::Auto { indicate }) => {
+            let Split { id, direction } = split_auto(&mut connect()?)?;
+            if indicate {
+                let request = Request::Split { id, direction };
+                daemon_request(&request, "indicating the split", offline)?;
+            }
+        }
+        Commands::Tag(TagCommand::Add { tag }) => add_tag(&mut connect()?, &tag)?,
+        Commands::Tag(TagCommand::Remove { tag }) => remove_tag(&mut connect()?, &tag)?,
+        Commands::Tag(TagCommand::Toggle { tag }) => toggle_tag(&mut connect()?, &tag)?,
+        Commands::Tag(TagCommand::View { tag }) => {
+            let workspace = view_tag(&mut connect()?, &tag, !offline)?;
+            let request = Request::TagView { workspace, tag };
+            if let Err(err) =
+                daemon_socket(offline).and_then(|path| daemon::request(&path, &request))
+            {
+                tracing::debug!("not tagging new windows: {:#}", err);
+            }
+        }
+        Commands::State(view) => print_state(view, config, offline, out)?,
+        Commands::Get(GetArgs {
+            command: Some(GetCommand::TabInfo),
+            ..
+        }) => {
+            let tree = get_tree_slim(&mut connect()?)?;
+            writeln!(out, "{}", serde_json::to_string(&tab_info(&tree))?)?;
+        }
+        Commands::Get(GetArgs {
+            command: Some(GetCommand::Focused { format }),
+            ..
+        }) => {
+            // The slim tree leaves out the app id and pid.
+            let tree = get_tree(&mut connect()?)?;
+            let info = focused_info(&tree);
+            print_window(info, format.as_deref(), config, "Nothing is focused", out)?
+        }
+        Commands::Get(GetArgs {
+            command: Some(GetCommand::AtPoint { x, y, format }),
+            ..
+        }) => {
+            let tree = get_tree(&mut connect()?)?;
+            let info = find_at_point(&tree, x, y).map(|c| WindowInfo::from(&c));
+            print_window(
+                info,
+                format.as_deref(),
+                config,
+                "No window is shown there",
+                out,
+            )?
+        }
+        Commands::Get(GetArgs {
+            command: Some(GetCommand::WorkspaceLayout),
+            ..
+        }) => {
+            let tree = get_tree(&mut connect()?)?;
+            let layout = workspace_layout(&tree).context("Nothing is focused")?;
+            writeln!(out, "{}", serde_json::to_string(&layout)?)?;
+        }
+        Commands::Get(GetArgs { path, raw, .. }) => {
+            let filter = Filter::parse(path.as_deref().unwrap_or("."))?;
+            let tree = get_tree_json(&mut connect()?)?;
+            let tree: serde_json::Value =
+                serde_json::from_slice(&tree).context("Failed to decode the tree")?;
+            for value in filter.apply(&tree)? {
+                match value {
+                    serde_json::Value::String(s) if raw => writeln!(out, "{s}")?,
+                    value => writeln!(out, "{value}")?,
+                }
+            }
+        }
+    }
+
+    if let Some(line) = recordable.filter(|_| !offline) {
+        notify_executed(line);
+    }
+    Ok(())
+}
+
+/// The steps configured for a move in the direction in the active binding mode, if any. Sway is
+/// only asked for the mode when the config has steps for some mode.
+fn mode_steps<'a>(
+    socket: &mut UnixStream,
+    config: &'a Config,
+    direction: Direction,
+) -> Result<Option<(String, &'a [String])>> {
+    if config.modes.is_empty() {
+        return Ok(None);
+    }
+    let mode = get_binding_state(socket)?.name;
+    let steps = config.modes.get(&mode).map(|m| m.steps(direction));
+    Ok(steps.filter(|s| !s.is_empty()).map(|s| (mode, s)))
+}
+
+/// The path of the daemon socket, failing without looking for it when `offline`.
+fn daemon_socket(offline: bool) -> Result<PathBuf> {
+    if offline {
+        anyhow::bail!("The daemon is not asked when running against a snapshot");
+    }
+    default_socket_path()
+}
+
+/// Send the request to the daemon and turn an error response into an error.
+fn daemon_request(request: &Request, what: &str, offline: bool) -> Result<()> {
+    match daemon::request(&daemon_socket(offline)?, request)? {
+        Response::Ok => Ok(()),
+        Response::Error { message } => anyhow::bail!("Daemon failed {}: {}", what, message),
+        response => anyhow::bail!("Daemon replied {:?} when {}", response, what),
+    }
+}
+
+/// Print the window as JSON or filling in the template, failing with the message for a template
+/// when there is no window. The title is cleaned up and the template defaults as configured in
+/// [Config::text].
+fn print_window(
+    info: Option<WindowInfo>,
+    template: Option<&str>,
+    config: &Config,
+    missing: &str,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let info = info.map(|i| i.clean_title(config.text.max_title_length));
+    match (template.or(config.text.window_format.as_deref()), info) {
+        (Some(template), Some(info)) => writeln!(out, "{}", info.format(template)?)?,
+        (Some(_), None) => anyhow::bail!("{}", missing),
+        (None, info) => writeln!(out, "{}", serde_json::to_string(&info)?)?,
+    }
+    Ok(())
+}
+
+/// Get the state and the rules from the daemon.
+fn daemon_state(config: &Config, offline: bool) -> Result<(Box<DaemonState>, Vec<Rule>)> {
+    let response = daemon_socket(offline).and_then(|path| daemon::request(&path, &Request::State));
+    let response = match (response, run_once::load()) {
+        (Err(_), Ok(Some(state))) if !offline => {
+            return Ok((Box::new(state), config.daemon.rules.clone()))
+        }
+        (response, _) => response?,
+    };
+    match response {
+        Response::State { state, rules } => Ok((state, rules)),
+        Response::Error { message } => {
+            anyhow::bail!("Daemon failed getting the state: {}", message)
+        }
+        response => anyhow::bail!("Daemon replied {:?} when getting the state", response),
+    }
+}
+
+/// Get the state from the daemon and write the requested part of it as JSON.
+fn print_state(
+    view: StateCommand,
+    config: &Config,
+    offline: bool,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let (state, rules) = daemon_state(config, offline)?;
+    let json = match view {
+        StateCommand::Dump => serde_json::json!({ "state": state, "rules": rules }),
+        StateCommand::History => serde_json::to_value(&state.history)?,
+        StateCommand::Split => serde_json::to_value(state.split)?,
+        StateCommand::Mru => serde_json::to_value(&state.mru)?,
+        StateCommand::Rules => serde_json::to_value(&rules)?,
+    };
+    write_json(out, &json)
+}
+
+/// Write the value as a JSON document, indented with the `pretty` feature.
+fn write_json(out: &mut dyn Write, value: &serde_json::Value) -> Result<()> {
+    #[cfg(feature = "pretty")]
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    #[cfg(not(feature = "pretty"))]
+    serde_json::to_writer(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Tell the daemon about an executed command so it ends up in the macro being recorded. Not having
+/// a daemon running is normal so failures are only logged.
+fn notify_executed(line: String) {
+    if let Err(err) =
+        default_socket_path().and_then(|path| daemon::request(&path, &Request::Executed { line }))
+    {
+        tracing::debug!("not recording the command: {:#}", err);
+    }
+}
+
+/// Whether a directional move is kept for `focus back`, by the daemon or the state file of
+/// [crate::config::DaemonConfig::run_once].
+fn keeps_moves(config: &Config, offline: bool) -> bool {
+    !offline && (config.daemon.run_once || default_socket_path().is_ok_and(|path| path.exists()))
+}
+
+/// Tell the daemon about a directional move so `focus back` can retrace it, failures are only
+/// logged like for [notify_executed].
+fn notify_moved(moved: Option<FocusMove>, direction: Direction, offline: bool) {
+    let Some(FocusMove { from, to }) = moved.filter(|_| !offline) else {
+        return;
+    };
+    let request = Request::Moved {
+        from,
+        to,
+        direction,
+    };
+    if let Err(err) = default_socket_path().and_then(|path| daemon::request(&path, &request)) {
+        let m = DirectionalMove {
+            from,
+            to,
+            direction,
+        };
+        match run_once::update(|state| state.moved(m)) {
+            Ok(Some(())) => (),
+            Ok(None) => tracing::debug!("not keeping the move: {:#}", err),
+            Err(file_err) => tracing::debug!("not keeping the move: {:#}, {:#}", err, file_err),
+        }
+    }
+}
+
+/// Retrace the latest directional move with the daemon, or with the state file when no daemon
+/// answers and [crate::config::DaemonConfig::run_once] is configured.
+fn go_back(socket: &mut UnixStream, config: &Config, offline: bool) -> Result<()> {
+    if offline {
+        anyhow::bail!("There is no move to go back on when running against a snapshot");
+    }
+    let response = default_socket_path().and_then(|path| daemon::request(&path, &Request::Back));
+    let err = match response {
+        Ok(Response::Ok) => return Ok(()),
+        Ok(Response::Error { message }) => anyhow::bail!("Daemon failed going back: {}", message),
+        Ok(response) => anyhow::bail!("Daemon replied {:?} when going back", response),
+        Err(err) if run_once::active(config) => err,
+        Err(err) => return Err(err),
+    };
+    let focused = find_focused_fast(&get_tree_slim(socket)?).map(|c| c.get_node().id);
+    let back = run_once::update(|state| state.back(focused))?.ok_or(err)?;
+    let m = back.context("No move to go back on")?;
+    run_checked(socket, &format!("[con_id={}] focus", m.from))
+}
+
+/// A single step of an alias, see [run_alias].
+#[derive(Debug)]
+pub enum Step {
+    /// A haswaynav subcommand.
+    Haswaynav(Commands),
+    /// Anything which is not a haswaynav subcommand is passed on to sway as is.
+    Sway(String),
+}
+
+impl Step {
+    /// Parse the step as a haswaynav subcommand if possible and otherwise as a sway command.
+    pub fn parse(step: &str) -> Step {
+        let args = std::iter::once("haswaynav").chain(step.split_whitespace());
+        match Cli::try_parse_args_from(args) {
+            Ok(cli) => Step::Haswaynav(cli.command),
+            Err(_) => Step::Sway(step.to_string()),
+        }
+    }
+}
+
+/// How deeply aliases and macros may run each other before giving up on a presumed cycle.
+const MAX_STEP_DEPTH: usize = 16;
+
+thread_local! {
+    static STEP_DEPTH: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Run the steps one at a time over a single connection, stopping at the first step which fails.
+/// Steps may be sway commands or the haswaynav `focus` and `run` commands.
+pub fn run_steps(
+    socket: &mut UnixStream,
+    config: &Config,
+    what: &str,
+    steps: &[String],
+    offline: bool,
+) -> Result<()> {
+    let depth = STEP_DEPTH.get();
+    if depth >= MAX_STEP_DEPTH {
+        anyhow::bail!("{} is nested too deeply, do aliases run each other?", what);
+    }
+    STEP_DEPTH.set(depth + 1);
+    let result = steps.iter().enumerate().try_for_each(|(idx, step)| {
+        let result = match Step::parse(step) {
+            Step::Haswaynav(command @ (Commands::Focus(_) | Commands::Run { .. })) => execute(
+                command,
+                config,
+                &mut || Ok(socket.try_clone()?),
+                offline,
+                &mut std::io::stdout(),
+            ),
+            Step::Haswaynav(_) => Err(anyhow::format_err!(
+                "only focus and run commands can be used as steps"
+            )),
+            Step::Sway(commands) => run_checked(socket, &commands),
+        };
+        result.with_context(|| {
+            format!(
+                "step {} of {} of {} ('{}') failed",
+                idx + 1,
+                steps.len(),
+                what,
+                step
+            )
+        })
+    });
+    STEP_DEPTH.set(depth);
+    result
+}
+
+/// Run the named alias from the config file, see [run_steps].
+pub fn run_alias(
+    socket: &mut UnixStream,
+    config: &Config,
+    name: &str,
+    offline: bool,
+) -> Result<()> {
+    let steps = config
+        .aliases
+        .get(name)
+        .ok_or_else(|| anyhow::format_err!("No alias named '{}' in the config file", name))?;
+    run_steps(socket, config, &format!("alias '{}'", name), steps, offline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_step() {
+        assert!(matches!(
+            Step::parse("focus left --wrap=false"),
+            Step::Haswaynav(Commands::Focus(FocusArgs {
+                direction: Some(Direction::Left),
+                wrap: Some(false),
+                ..
+            }))
+        ));
+        assert!(matches!(Step::parse("focus parent"), Step::Sway(s) if s == "focus parent"));
+        assert!(matches!(Step::parse("layout tabbed"), Step::Sway(s) if s == "layout tabbed"));
+    }
+
+    #[test]
+    fn recordable_line() {
+        let line = |step| match Step::parse(step) {
+            Step::Haswaynav(command) => command.recordable_line(),
+            Step::Sway(_) => panic!("not a haswaynav command"),
+        };
+        assert_eq!(
+            line("focus up --cross-output deny --wrap").as_deref(),
+            Some("focus up --wrap=true --cross-output=deny")
+        );
+        assert_eq!(
+            line("focus --skip-tabbed=none into left").as_deref(),
+            Some("focus --skip-tabbed=none into left")
+        );
+        assert_eq!(line("run dev").as_deref(), Some("run dev"));
+        assert_eq!(
+            line("focus toggle-layer").as_deref(),
+            Some("focus toggle-layer")
+        );
+        assert_eq!(line("doctor"), None);
+    }
+
+    #[test]
+    fn parse_strictly() {
+        let parse = |args: &str| {
+            let args = std::iter::once("haswaynav").chain(args.split_whitespace());
+            Cli::try_parse_args_from(args).map(|cli| cli.command)
+        };
+        assert!(parse("focus ri").is_ok());
+        assert!(parse("output focus h").is_ok());
+        assert!(parse("--strict focus right").is_ok());
+        assert!(parse("--strict focus l").is_ok());
+        assert!(parse("--strict focus ri").is_err());
+        assert!(parse("focus into Right --strict").is_ok());
+        assert!(parse("focus into RIGHT --strict").is_err());
+        assert!(parse("--strict output send Next").is_err());
+        assert!(parse("--strict pointer move-to ri").is_err());
+        assert!(parse("--strict pointer move-to output:Ri").is_ok());
+    }
+}
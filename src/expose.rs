@@ -0,0 +1,212 @@
+//! The data for an overview of all workspaces like Mission Control, for external tools rendering
+//! the thumbnails: the windows of every workspace with their geometry relative to the workspace
+//! and hints which window the workspace focuses when entered.
+
+use std::io::Write;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    text,
+    tree::{
+        cursor::{find_focused_fast, Cursor},
+        windows, NodeType, Rect, TreeNode,
+    },
+};
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+/// A workspace of the overview, see [expose].
+pub struct ExposeWorkspace {
+    pub name: String,
+    /// The name of the output the workspace is on
+    pub output: Option<String>,
+    /// Whether the workspace holds the focus
+    pub focused: bool,
+    /// Whether the workspace is shown on its output, only those can be captured with e.g. `grim -g`
+    pub visible: bool,
+    /// The absolute geometry of the workspace
+    pub rect: Rect,
+    /// The windows of the workspace, tiled ones before floating ones in their order in the tree
+    pub windows: Vec<ExposeWindow>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+/// A window of an [ExposeWorkspace].
+pub struct ExposeWindow {
+    pub id: i32,
+    /// The app id, or the X11 class for Xwayland windows
+    pub app_id: Option<String>,
+    /// The title without control characters and cut short, see [text::clean]
+    pub title: Option<String>,
+    pub floating: bool,
+    /// Whether the window has the focus
+    pub focused: bool,
+    pub urgent: bool,
+    /// Whether the window is on screen rather than a hidden tab or on a workspace not shown
+    pub visible: bool,
+    /// The absolute geometry of the window
+    pub rect: Rect,
+    /// The geometry relative to the workspace, with the workspace spanning 0 to 1 in both
+    /// directions. Floating windows reaching beyond the workspace fall outside of that range.
+    pub normalized: NormalizedRect,
+    /// The rank of the window in the focus history of the workspace, 0 for the window the
+    /// workspace focuses when it is entered
+    pub focus_order: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy)]
+/// A rectangle in fractions of an enclosing one, see [ExposeWindow::normalized].
+pub struct NormalizedRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl NormalizedRect {
+    /// The rectangle in fractions of `outer`, all zero when `outer` is empty.
+    pub fn within(rect: &Rect, outer: &Rect) -> Self {
+        if outer.width <= 0 || outer.height <= 0 {
+            return NormalizedRect {
+                x: 0.0,
+                y: 0.0,
+                width: 0.0,
+                height: 0.0,
+            };
+        }
+        let (width, height) = (f64::from(outer.width), f64::from(outer.height));
+        NormalizedRect {
+            x: f64::from(rect.x - outer.x) / width,
+            y: f64::from(rect.y - outer.y) / height,
+            width: f64::from(rect.width) / width,
+            height: f64::from(rect.height) / height,
+        }
+    }
+}
+
+/// The ids of the windows below the cursor ordered by the focus lists, most recently focused first.
+fn focus_order(cursor: &Cursor, order: &mut Vec<i32>) {
+    let children = cursor.children_by_focus();
+    if children.is_empty() && cursor.get_node().node_type != NodeType::Workspace {
+        order.push(cursor.get_node().id);
+    }
+    for child in children {
+        focus_order(&child, order);
+    }
+}
+
+/// The workspaces of all outputs with their windows, leaving out the scratchpad. Titles are cleaned
+/// up to `max_title_length` characters.
+pub fn expose(tree: &TreeNode, max_title_length: usize) -> Vec<ExposeWorkspace> {
+    let focused_workspace = find_focused_fast(tree)
+        .and_then(|c| c.workspace())
+        .map(|w| w.get_node().id);
+    Cursor::new(tree)
+        .iter()
+        .filter(|c| {
+            let node = c.get_node();
+            node.node_type == NodeType::Workspace && node.name.as_deref() != Some("__i3_scratch")
+        })
+        .map(|workspace| {
+            let node = workspace.get_node();
+            let mut order = Vec::new();
+            focus_order(&workspace, &mut order);
+            let windows = windows(node)
+                .map(|w| ExposeWindow {
+                    id: w.id,
+                    app_id: w.app_id.map(str::to_string),
+                    title: w.title.map(|t| text::clean(t, max_title_length)),
+                    floating: w.floating,
+                    focused: w.focused,
+                    urgent: w.urgent,
+                    visible: w.visible && workspace.is_visible(),
+                    rect: w.rect,
+                    normalized: NormalizedRect::within(&w.rect, &node.rect),
+                    focus_order: order
+                        .iter()
+                        .position(|id| *id == w.id)
+                        .unwrap_or(order.len()),
+                })
+                .collect();
+            ExposeWorkspace {
+                name: node.name.clone().unwrap_or_default(),
+                output: workspace.output().and_then(|o| o.get_node().name.clone()),
+                focused: focused_workspace == Some(node.id),
+                visible: workspace.is_visible(),
+                rect: node.rect,
+                windows,
+            }
+        })
+        .collect()
+}
+
+/// Print the workspaces as JSON, one workspace per line, or as an outline for reading.
+pub fn print_expose(workspaces: &[ExposeWorkspace], json: bool, out: &mut dyn Write) -> Result<()> {
+    for workspace in workspaces {
+        if json {
+            writeln!(out, "{}", serde_json::to_string(workspace)?)?;
+            continue;
+        }
+        let output = workspace.output.as_deref().unwrap_or("?");
+        let focused = if workspace.focused { " *" } else { "" };
+        writeln!(out, "{} on {}{}", workspace.name, output, focused)?;
+        for window in &workspace.windows {
+            let focused = if window.focused { " *" } else { "" };
+            writeln!(
+                out,
+                "  {} {}: {}{}",
+                window.id,
+                window.app_id.as_deref().unwrap_or("?"),
+                window.title.as_deref().unwrap_or_default(),
+                focused
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn expose_workspaces() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let workspaces = expose(&tree, 100);
+        assert!(workspaces.iter().all(|w| w.name != "__i3_scratch"));
+        assert_eq!(workspaces.iter().filter(|w| w.focused).count(), 1);
+
+        let focused = workspaces.iter().find(|w| w.focused).unwrap();
+        assert!(focused.visible);
+        let window = focused.windows.iter().find(|w| w.focused).unwrap();
+        assert_eq!(window.focus_order, 0);
+        for window in focused.windows.iter().filter(|w| !w.floating) {
+            let n = window.normalized;
+            assert!(n.x >= 0.0 && n.y >= 0.0 && n.x + n.width <= 1.0 && n.y + n.height <= 1.0);
+        }
+
+        let rect = Rect {
+            x: 100,
+            y: 50,
+            width: 200,
+            height: 100,
+        };
+        let outer = Rect {
+            x: 0,
+            y: 0,
+            width: 400,
+            height: 200,
+        };
+        assert_eq!(
+            NormalizedRect::within(&rect, &outer),
+            NormalizedRect {
+                x: 0.25,
+                y: 0.25,
+                width: 0.5,
+                height: 0.5,
+            }
+        );
+    }
+}
@@ -0,0 +1,654 @@
+//! A small part of jq's filter language for `haswaynav get --path`, so scripts polling the tree
+//! every second need not spawn jq on the whole reply.
+//!
+//! Supported are `.`, `..`, `.name`, `."name"`, `.[]`, `.[n]`, `f?`, pipes `|`, commas `,`,
+//! `select(f)`, `length`, `not`, comparisons with `==`, `!=`, `<`, `<=`, `>`, `>=`, `and`, `or`,
+//! arrays `[f]`, objects `{name, key: f}`, parentheses and string, number, boolean and null
+//! literals, e.g. `.nodes[].nodes[] | select(.type == "workspace") | {name, focused}`.
+
+use std::cmp::Ordering;
+
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, PartialEq)]
+/// A parsed filter, see the module documentation for the syntax.
+pub enum Filter {
+    /// `.`
+    Identity,
+    /// `..`, the input and everything nested in it
+    Recurse,
+    /// `.name`
+    Field(String),
+    /// `.[n]`, counting from the end for negative indices
+    Index(i64),
+    /// `.[]`
+    Iterate,
+    /// A string, number, boolean or null
+    Literal(Value),
+    /// `f?`, the outputs of `f` or none when it fails
+    Try(Box<Filter>),
+    /// `f | g`
+    Pipe(Box<Filter>, Box<Filter>),
+    /// `f, g`
+    Comma(Box<Filter>, Box<Filter>),
+    /// `select(f)`
+    Select(Box<Filter>),
+    /// `length`
+    Length,
+    /// `not`
+    Not,
+    /// `f == g` and the other comparisons
+    Compare(Box<Filter>, Op, Box<Filter>),
+    /// `f and g`
+    And(Box<Filter>, Box<Filter>),
+    /// `f or g`
+    Or(Box<Filter>, Box<Filter>),
+    /// `[f]`
+    Array(Box<Filter>),
+    /// `{key: f, ...}`
+    Object(Vec<(String, Filter)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A comparison operator.
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Filter {
+    /// Parse the filter.
+    pub fn parse(text: &str) -> Result<Filter> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+        let filter = parser.pipe()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some(token) => anyhow::bail!("Unexpected {:?} in filter '{}'", token, text),
+        }
+    }
+
+    /// Apply the filter to the input, returning every output in order.
+    pub fn apply(&self, input: &Value) -> Result<Vec<Value>> {
+        let mut outputs = Vec::new();
+        self.eval(input, &mut |v| {
+            outputs.push(v);
+            Ok(())
+        })?;
+        Ok(outputs)
+    }
+
+    fn eval(&self, input: &Value, out: &mut dyn FnMut(Value) -> Result<()>) -> Result<()> {
+        match self {
+            Filter::Identity => out(input.clone()),
+            Filter::Recurse => recurse(input, out),
+            Filter::Field(name) => match input {
+                Value::Object(map) => out(map.get(name).cloned().unwrap_or(Value::Null)),
+                Value::Null => out(Value::Null),
+                other => anyhow::bail!("Cannot index {} with \"{}\"", type_name(other), name),
+            },
+            Filter::Index(idx) => match input {
+                Value::Array(items) => {
+                    let idx = if *idx < 0 {
+                        items.len() as i64 + idx
+                    } else {
+                        *idx
+                    };
+                    let item = usize::try_from(idx).ok().and_then(|i| items.get(i));
+                    out(item.cloned().unwrap_or(Value::Null))
+                }
+                Value::Null => out(Value::Null),
+                other => anyhow::bail!("Cannot index {} with a number", type_name(other)),
+            },
+            Filter::Iterate => match input {
+                Value::Array(items) => items.iter().try_for_each(|v| out(v.clone())),
+                Value::Object(map) => map.values().try_for_each(|v| out(v.clone())),
+                other => anyhow::bail!("Cannot iterate over {}", type_name(other)),
+            },
+            Filter::Literal(value) => out(value.clone()),
+            Filter::Try(filter) => match filter.apply(input) {
+                Ok(outputs) => outputs.into_iter().try_for_each(out),
+                Err(_) => Ok(()),
+            },
+            Filter::Pipe(first, second) => first.eval(input, &mut |v| second.eval(&v, out)),
+            Filter::Comma(first, second) => {
+                first.eval(input, out)?;
+                second.eval(input, out)
+            }
+            Filter::Select(condition) => {
+                for v in condition.apply(input)? {
+                    if truthy(&v) {
+                        out(input.clone())?;
+                    }
+                }
+                Ok(())
+            }
+            Filter::Length => out(match input {
+                Value::Array(items) => items.len().into(),
+                Value::Object(map) => map.len().into(),
+                Value::String(s) => s.chars().count().into(),
+                Value::Null => 0.into(),
+                Value::Number(n) => n.as_f64().map(f64::abs).into(),
+                Value::Bool(_) => anyhow::bail!("A boolean has no length"),
+            }),
+            Filter::Not => out(Value::Bool(!truthy(input))),
+            Filter::Compare(left, op, right) => {
+                for r in right.apply(input)? {
+                    for l in left.apply(input)? {
+                        let ordering = compare(&l, &r);
+                        let result = match op {
+                            Op::Eq => ordering == Ordering::Equal,
+                            Op::Ne => ordering != Ordering::Equal,
+                            Op::Lt => ordering == Ordering::Less,
+                            Op::Le => ordering != Ordering::Greater,
+                            Op::Gt => ordering == Ordering::Greater,
+                            Op::Ge => ordering != Ordering::Less,
+                        };
+                        out(Value::Bool(result))?;
+                    }
+                }
+                Ok(())
+            }
+            Filter::And(left, right) => {
+                for l in left.apply(input)? {
+                    if !truthy(&l) {
+                        out(Value::Bool(false))?;
+                        continue;
+                    }
+                    for r in right.apply(input)? {
+                        out(Value::Bool(truthy(&r)))?;
+                    }
+                }
+                Ok(())
+            }
+            Filter::Or(left, right) => {
+                for l in left.apply(input)? {
+                    if truthy(&l) {
+                        out(Value::Bool(true))?;
+                        continue;
+                    }
+                    for r in right.apply(input)? {
+                        out(Value::Bool(truthy(&r)))?;
+                    }
+                }
+                Ok(())
+            }
+            Filter::Array(items) => out(Value::Array(items.apply(input)?)),
+            Filter::Object(fields) => {
+                // Every combination of the outputs of the values, like jq.
+                let mut objects = vec![Map::new()];
+                for (key, value) in fields {
+                    let values = value.apply(input)?;
+                    objects = objects
+                        .into_iter()
+                        .flat_map(|o| {
+                            values.iter().map(move |v| {
+                                let mut o = o.clone();
+                                o.insert(key.clone(), v.clone());
+                                o
+                            })
+                        })
+                        .collect();
+                }
+                objects.into_iter().try_for_each(|o| out(Value::Object(o)))
+            }
+        }
+    }
+}
+
+/// Output the value and then everything nested in it, depth first.
+fn recurse(value: &Value, out: &mut dyn FnMut(Value) -> Result<()>) -> Result<()> {
+    out(value.clone())?;
+    match value {
+        Value::Array(items) => items.iter().try_for_each(|v| recurse(v, out)),
+        Value::Object(map) => map.values().try_for_each(|v| recurse(v, out)),
+        _ => Ok(()),
+    }
+}
+
+/// Only `false` and `null` are false, like in jq.
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Order values like jq: null, false, true, numbers, strings, arrays, objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let (x, y) = (x.as_f64().unwrap_or(0.0), y.as_f64().unwrap_or(0.0));
+            x.partial_cmp(&y).unwrap_or(Ordering::Equal)
+        }
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            fn keys(m: &Map<String, Value>) -> Vec<&String> {
+                let mut keys: Vec<&String> = m.keys().collect();
+                keys.sort();
+                keys
+            }
+            keys(x).cmp(&keys(y)).then_with(|| {
+                keys(x)
+                    .into_iter()
+                    .map(|k| compare(&x[k], &y[k]))
+                    .find(|o| *o != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    DotDot,
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Punct(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    const PUNCTS: [&str; 16] = [
+        "==", "!=", "<=", ">=", "<", ">", "|", ",", "(", ")", "[", "]", "{", "}", ":", "?",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if let Some(r) = rest.strip_prefix("..") {
+            tokens.push(Token::DotDot);
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('.') {
+            tokens.push(Token::Dot);
+            rest = r;
+        } else if let Some(p) = PUNCTS.iter().find(|p| rest.starts_with(**p)) {
+            tokens.push(Token::Punct(p));
+            rest = &rest[p.len()..];
+        } else if c == '"' {
+            // Let serde_json decode the string with its escapes.
+            let mut end = 1;
+            let bytes = rest.as_bytes();
+            while end < bytes.len() && bytes[end] != b'"' {
+                end += if bytes[end] == b'\\' { 2 } else { 1 };
+            }
+            let literal = rest
+                .get(..end + 1)
+                .context("Unterminated string in filter")?;
+            let s: String = serde_json::from_str(literal)
+                .with_context(|| format!("Invalid string {} in filter", literal))?;
+            tokens.push(Token::Str(s));
+            rest = &rest[end + 1..];
+        } else if c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|d: char| d.is_ascii_digit()))
+        {
+            let len = 1 + rest[1..]
+                .find(|d: char| !(d.is_ascii_digit() || d == '.'))
+                .unwrap_or(rest.len() - 1);
+            let n = rest[..len]
+                .parse()
+                .with_context(|| format!("Invalid number '{}' in filter", &rest[..len]))?;
+            tokens.push(Token::Num(n));
+            rest = &rest[len..];
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|d: char| !(d.is_alphanumeric() || d == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            rest = &rest[len..];
+        } else {
+            anyhow::bail!("Unexpected '{}' in filter", c);
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &'static str) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: &'static str) -> Result<()> {
+        if !self.eat(punct) {
+            anyhow::bail!("Expected '{}' in filter but found {:?}", punct, self.peek());
+        }
+        Ok(())
+    }
+
+    fn eat_ident(&mut self, ident: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(i)) if i == ident) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Filter> {
+        let mut filter = self.comma()?;
+        while self.eat("|") {
+            filter = Filter::Pipe(Box::new(filter), Box::new(self.comma()?));
+        }
+        Ok(filter)
+    }
+
+    fn comma(&mut self) -> Result<Filter> {
+        let mut filter = self.or()?;
+        while self.eat(",") {
+            filter = Filter::Comma(Box::new(filter), Box::new(self.or()?));
+        }
+        Ok(filter)
+    }
+
+    fn or(&mut self) -> Result<Filter> {
+        let mut filter = self.and()?;
+        while self.eat_ident("or") {
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter> {
+        let mut filter = self.comparison()?;
+        while self.eat_ident("and") {
+            filter = Filter::And(Box::new(filter), Box::new(self.comparison()?));
+        }
+        Ok(filter)
+    }
+
+    fn comparison(&mut self) -> Result<Filter> {
+        let left = self.postfix()?;
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        for (punct, op) in ops {
+            if self.eat(punct) {
+                let right = self.postfix()?;
+                return Ok(Filter::Compare(Box::new(left), op, Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    /// A term followed by any number of `.name`, `[]`, `[n]` and `?`.
+    fn postfix(&mut self) -> Result<Filter> {
+        let mut filter = self.term()?;
+        loop {
+            if self.eat("?") {
+                filter = Filter::Try(Box::new(filter));
+                continue;
+            }
+            let next = if self.peek() == Some(&Token::Dot)
+                && matches!(
+                    self.tokens.get(self.pos + 1),
+                    Some(Token::Ident(_) | Token::Str(_))
+                ) {
+                self.pos += 1;
+                self.field()?
+            } else if self.peek() == Some(&Token::Punct("[")) {
+                self.brackets()?
+            } else if self.peek() == Some(&Token::Dot)
+                && self.tokens.get(self.pos + 1) == Some(&Token::Punct("["))
+            {
+                self.pos += 1;
+                self.brackets()?
+            } else {
+                return Ok(filter);
+            };
+            filter = Filter::Pipe(Box::new(filter), Box::new(next));
+        }
+    }
+
+    fn field(&mut self) -> Result<Filter> {
+        match self.next() {
+            Some(Token::Ident(name) | Token::Str(name)) => Ok(Filter::Field(name)),
+            token => anyhow::bail!("Expected a field name in filter but found {:?}", token),
+        }
+    }
+
+    /// `[]` or `[n]`, after the `.` if there was one.
+    fn brackets(&mut self) -> Result<Filter> {
+        self.expect("[")?;
+        if self.eat("]") {
+            return Ok(Filter::Iterate);
+        }
+        let filter = match self.next() {
+            Some(Token::Num(n)) if n.fract() == 0.0 => Filter::Index(n as i64),
+            Some(Token::Str(name)) => Filter::Field(name),
+            token => anyhow::bail!("Expected an index in filter but found {:?}", token),
+        };
+        self.expect("]")?;
+        Ok(filter)
+    }
+
+    fn term(&mut self) -> Result<Filter> {
+        match self.next() {
+            Some(Token::DotDot) => Ok(Filter::Recurse),
+            Some(Token::Dot) => match self.peek() {
+                Some(Token::Ident(_) | Token::Str(_)) => self.field(),
+                Some(Token::Punct("[")) => self.brackets(),
+                _ => Ok(Filter::Identity),
+            },
+            Some(Token::Str(s)) => Ok(Filter::Literal(Value::String(s))),
+            Some(Token::Num(n)) => Ok(Filter::Literal(
+                serde_json::Number::from_f64(n)
+                    .filter(|_| n.fract() != 0.0)
+                    .map(Value::Number)
+                    .unwrap_or_else(|| (n as i64).into()),
+            )),
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "true" => Ok(Filter::Literal(Value::Bool(true))),
+                "false" => Ok(Filter::Literal(Value::Bool(false))),
+                "null" => Ok(Filter::Literal(Value::Null)),
+                "length" => Ok(Filter::Length),
+                "not" => Ok(Filter::Not),
+                "select" => {
+                    self.expect("(")?;
+                    let condition = self.pipe()?;
+                    self.expect(")")?;
+                    Ok(Filter::Select(Box::new(condition)))
+                }
+                other => anyhow::bail!("Unknown function '{}' in filter", other),
+            },
+            Some(Token::Punct("(")) => {
+                let filter = self.pipe()?;
+                self.expect(")")?;
+                Ok(filter)
+            }
+            Some(Token::Punct("[")) => {
+                if self.eat("]") {
+                    return Ok(Filter::Literal(Value::Array(Vec::new())));
+                }
+                let items = self.pipe()?;
+                self.expect("]")?;
+                Ok(Filter::Array(Box::new(items)))
+            }
+            Some(Token::Punct("{")) => self.object(),
+            token => anyhow::bail!("Unexpected {:?} in filter", token),
+        }
+    }
+
+    /// The fields of an object after the `{`, with `name` short for `name: .name`.
+    fn object(&mut self) -> Result<Filter> {
+        let mut fields = Vec::new();
+        if self.eat("}") {
+            return Ok(Filter::Object(fields));
+        }
+        loop {
+            let key = match self.next() {
+                Some(Token::Ident(key) | Token::Str(key)) => key,
+                token => anyhow::bail!("Expected a key in filter but found {:?}", token),
+            };
+            let value = if self.eat(":") {
+                self.postfix()?
+            } else {
+                Filter::Field(key.clone())
+            };
+            fields.push((key, value));
+            if self.eat("}") {
+                return Ok(Filter::Object(fields));
+            }
+            self.expect(",")?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(filter: &str, input: &Value) -> Vec<Value> {
+        Filter::parse(filter).unwrap().apply(input).unwrap()
+    }
+
+    #[test]
+    fn paths_and_iteration() {
+        let input = json!({"a": {"b": [1, 2, 3]}, "c": "x"});
+        assert_eq!(run(".", &input), vec![input.clone()]);
+        assert_eq!(run(".a.b[]", &input), [json!(1), json!(2), json!(3)]);
+        assert_eq!(run(".a.b[-1], .c", &input), [json!(3), json!("x")]);
+        assert_eq!(run(".a | .b | length", &input), [json!(3)]);
+        assert_eq!(run(".missing.deeper", &input), [Value::Null]);
+        assert_eq!(run("[.a.b[] | select(. >= 2)]", &input), [json!([2, 3])]);
+        assert_eq!(run("{c, n: .a.b[0]}", &input), [json!({"c": "x", "n": 1})]);
+        assert!(Filter::parse(".a |").is_err());
+        assert!(Filter::parse("frobnicate").is_err());
+        assert!(Filter::parse(".c[]").unwrap().apply(&input).is_err());
+    }
+
+    #[test]
+    fn paths() {
+        let input = json!({"a b": {"c": [{"d": 1}, {"d": 2}]}, "e": null});
+        assert_eq!(run(r#"."a b".c[1].d"#, &input), [json!(2)]);
+        assert_eq!(run(r#"."a b".c[5]"#, &input), [Value::Null]);
+        assert_eq!(run(".e.f", &input), [Value::Null]);
+        assert_eq!(run(".[]?", &json!(1)), Vec::<Value>::new());
+        assert_eq!(
+            run(r#"[.. | .d? | select(. != null)]"#, &input),
+            [json!([1, 2])]
+        );
+    }
+
+    #[test]
+    fn pipes_and_select() {
+        let input = json!([{"n": 1, "ok": true}, {"n": 2, "ok": false}, {"n": 3}]);
+        assert_eq!(run(".[] | select(.ok) | .n", &input), [json!(1)]);
+        assert_eq!(
+            run(".[] | select(.ok | not) | .n", &input),
+            [json!(2), json!(3)]
+        );
+        assert_eq!(
+            run(".[] | select(.n > 1 and .n < 3 or .n == 3) | .n", &input),
+            [json!(2), json!(3)]
+        );
+        assert_eq!(
+            run("([.[] | .n] | length), (.[0] | length)", &input),
+            [json!(3), json!(2)]
+        );
+        assert_eq!(run(r#""x", 1, true, null"#, &input).len(), 4);
+    }
+
+    #[test]
+    fn errors() {
+        for text in [
+            "",
+            ".[",
+            "(.a",
+            "{a:}",
+            r#""open"#,
+            ".a ==",
+            "select(.a",
+            "&",
+        ] {
+            assert!(Filter::parse(text).is_err(), "{:?} parsed", text);
+        }
+        let input = json!({"s": "text", "n": 1});
+        for text in [".s.x", ".n[]", ".s[0]", ".n | length | not | .x"] {
+            let filter = Filter::parse(text).unwrap();
+            assert!(filter.apply(&input).is_err(), "{:?} applied", text);
+        }
+        assert_eq!(run(".s.x?", &input), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn select_on_the_tree() {
+        let tree: Value =
+            serde_json::from_str(include_str!("../tests/fixtures/trees/multi-output.json"))
+                .unwrap();
+
+        assert_eq!(
+            run(
+                r#".nodes[] | select(.type == "output" and .name != "__i3") | .name"#,
+                &tree
+            ),
+            [json!("eDP-1"), json!("DP-2")]
+        );
+        assert_eq!(
+            run(".. | select(.focused? == true) | {id, app_id}", &tree),
+            [json!({"id": 126, "app_id": "Alacritty"})]
+        );
+        assert!(Filter::parse(".. | select(.focused)")
+            .unwrap()
+            .apply(&tree)
+            .is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Gaps chosen per workspace with `haswaynav gaps`, from presets in the config or set directly. The
+//! daemon keeps the gaps of every workspace so they can be cycled and toggled, and with
+//! [crate::config::GapsConfig::smart] removes them while a workspace shows a single window.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(deny_unknown_fields)]
+/// The inner and outer gaps of a workspace in pixels.
+pub struct Gaps {
+    pub inner: u32,
+    pub outer: u32,
+}
+
+impl Gaps {
+    /// No gaps at all.
+    pub const NONE: Gaps = Gaps { inner: 0, outer: 0 };
+
+    /// The sway commands giving these gaps to the workspace the commands run on.
+    pub fn commands(&self) -> String {
+        format!(
+            "gaps inner current set {}, gaps outer current set {}",
+            self.inner, self.outer
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// How `haswaynav gaps` changes the gaps of the focused workspace.
+pub enum GapsChange {
+    /// Use these gaps.
+    Set(Gaps),
+    /// Use the preset after the gaps in use, starting over after the last and starting with the first
+    /// when the gaps in use are no preset.
+    Cycle,
+    /// Remove the gaps, or bring them back when they were removed.
+    Toggle,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+/// The gaps chosen for a workspace, see [crate::daemon::DaemonState::gaps].
+pub struct WorkspaceGaps {
+    /// The gaps set or cycled to last
+    pub chosen: Option<Gaps>,
+    /// Whether `gaps toggle` removed the gaps
+    pub off: bool,
+}
+
+impl WorkspaceGaps {
+    /// The gaps of the workspace: none when toggled off, otherwise the ones chosen or else the first
+    /// preset. `None` when nothing tells, leaving the gaps to sway.
+    pub fn gaps(&self, presets: &[Gaps]) -> Option<Gaps> {
+        if self.off {
+            Some(Gaps::NONE)
+        } else {
+            self.chosen.or_else(|| presets.first().copied())
+        }
+    }
+
+    /// Apply the change, using the presets to cycle through and to toggle gaps on when none were
+    /// chosen.
+    pub fn change(&mut self, change: GapsChange, presets: &[Gaps]) -> Result<()> {
+        match change {
+            GapsChange::Set(gaps) => {
+                self.chosen = Some(gaps);
+                self.off = false;
+            }
+            GapsChange::Cycle => {
+                if presets.is_empty() {
+                    anyhow::bail!("No gap presets to cycle through in the config");
+                }
+                let next = self
+                    .gaps(presets)
+                    .and_then(|g| presets.iter().position(|p| *p == g))
+                    .map_or(0, |idx| (idx + 1) % presets.len());
+                self.chosen = Some(presets[next]);
+                self.off = false;
+            }
+            GapsChange::Toggle if self.off => self.off = false,
+            GapsChange::Toggle => match self.gaps(presets) {
+                Some(gaps) if gaps != Gaps::NONE => self.off = true,
+                _ => {
+                    let on = presets
+                        .iter()
+                        .find(|p| **p != Gaps::NONE)
+                        .context("No gaps to toggle on, set some or add presets to the config")?;
+                    self.chosen = Some(*on);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_change_gaps() {
+    let small = Gaps { inner: 5, outer: 0 };
+    let large = Gaps {
+        inner: 20,
+        outer: 10,
+    };
+    let presets = [Gaps::NONE, small, large];
+    let mut workspace = WorkspaceGaps::default();
+    assert_eq!(workspace.gaps(&[]), None);
+    assert_eq!(workspace.gaps(&presets), Some(Gaps::NONE));
+
+    let cycle = |w: &mut WorkspaceGaps| {
+        w.change(GapsChange::Cycle, &presets).unwrap();
+        w.gaps(&presets).unwrap()
+    };
+    // The workspace starts with the first preset, so cycling moves on to the second.
+    assert_eq!(cycle(&mut workspace), small);
+    assert_eq!(cycle(&mut workspace), large);
+    assert_eq!(cycle(&mut workspace), Gaps::NONE);
+    assert_eq!(cycle(&mut workspace), small);
+    assert_eq!(cycle(&mut workspace), large);
+    assert_eq!(cycle(&mut workspace), Gaps::NONE);
+
+    // Toggling on without gaps picks the first preset with gaps.
+    workspace.change(GapsChange::Toggle, &presets).unwrap();
+    assert_eq!(workspace.gaps(&presets), Some(small));
+    workspace.change(GapsChange::Set(large), &presets).unwrap();
+    workspace.change(GapsChange::Toggle, &presets).unwrap();
+    assert_eq!(workspace.gaps(&presets), Some(Gaps::NONE));
+    workspace.change(GapsChange::Toggle, &presets).unwrap();
+    assert_eq!(workspace.gaps(&presets), Some(large));
+
+    // Cycling from gaps toggled off moves on from no gaps.
+    workspace.change(GapsChange::Toggle, &presets).unwrap();
+    assert_eq!(cycle(&mut workspace), small);
+
+    let mut workspace = WorkspaceGaps::default();
+    assert!(workspace.change(GapsChange::Cycle, &[]).is_err());
+    assert!(workspace.change(GapsChange::Toggle, &[Gaps::NONE]).is_err());
+    assert_eq!(
+        large.commands(),
+        "gaps inner current set 20, gaps outer current set 10"
+    );
+}
@@ -0,0 +1,221 @@
+//! Converting between sway's layout coordinates and the pixels of the outputs' framebuffers.
+//!
+//! Sway reports the rects of the tree in layout coordinates, a single logical space spanning all
+//! outputs in which their scale and transform are already applied. Geometric focus and warping the
+//! pointer therefore work on the rects as they are and only pixels of an output, e.g. of a
+//! screenshot, need converting.
+
+use crate::tree::{OutputReply, Rect, Transform};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+/// Where an output is in the layout and how its framebuffer is shown there.
+pub struct OutputGeometry {
+    /// The output in layout coordinates
+    pub rect: Rect,
+    /// The number of framebuffer pixels per layout coordinate
+    pub scale: f64,
+    /// How the framebuffer is flipped and then turned clockwise to be shown
+    pub transform: Transform,
+}
+
+impl From<&OutputReply> for OutputGeometry {
+    fn from(output: &OutputReply) -> Self {
+        OutputGeometry {
+            rect: output.rect,
+            scale: output.scale.filter(|s| *s > 0.0).unwrap_or(1.0),
+            transform: output.transform,
+        }
+    }
+}
+
+impl OutputGeometry {
+    /// The size of the framebuffer in pixels.
+    pub fn physical_size(&self) -> (i32, i32) {
+        let (width, height) = (
+            scaled(self.rect.width, self.scale),
+            scaled(self.rect.height, self.scale),
+        );
+        match self.transform.flip_and_quarter_turns() {
+            (_, 1 | 3) => (height, width),
+            _ => (width, height),
+        }
+    }
+
+    /// The framebuffer pixel shown at the point in layout coordinates.
+    pub fn to_physical(&self, x: i32, y: i32) -> (i32, i32) {
+        let (flipped, turns) = self.transform.flip_and_quarter_turns();
+        let mut size = (
+            scaled(self.rect.width, self.scale),
+            scaled(self.rect.height, self.scale),
+        );
+        let mut point = (
+            scaled(x - self.rect.x, self.scale),
+            scaled(y - self.rect.y, self.scale),
+        );
+        // Undo the turns by turning counter-clockwise, then undo the flip.
+        for _ in 0..turns {
+            point = (point.1, size.0 - point.0);
+            size = (size.1, size.0);
+        }
+        if flipped {
+            point.0 = size.0 - point.0;
+        }
+        point
+    }
+
+    /// The point in layout coordinates showing the framebuffer pixel, the inverse of
+    /// [OutputGeometry::to_physical].
+    pub fn to_logical(&self, x: i32, y: i32) -> (i32, i32) {
+        let (flipped, turns) = self.transform.flip_and_quarter_turns();
+        let mut size = self.physical_size();
+        let mut point = (x, y);
+        if flipped {
+            point.0 = size.0 - point.0;
+        }
+        for _ in 0..turns {
+            point = (size.1 - point.1, point.0);
+            size = (size.1, size.0);
+        }
+        (
+            self.rect.x + unscaled(point.0, self.scale),
+            self.rect.y + unscaled(point.1, self.scale),
+        )
+    }
+
+    /// The framebuffer pixels covered by the rect in layout coordinates.
+    pub fn rect_to_physical(&self, rect: &Rect) -> Rect {
+        let a = self.to_physical(rect.x, rect.y);
+        let b = self.to_physical(rect.x + rect.width, rect.y + rect.height);
+        Rect {
+            x: a.0.min(b.0),
+            y: a.1.min(b.1),
+            width: (a.0 - b.0).abs(),
+            height: (a.1 - b.1).abs(),
+        }
+    }
+
+    /// The size in layout coordinates of something measuring `width` by `height` millimetres when
+    /// shown on the output, whose display measures `display_mm` before its transform is applied.
+    pub fn mm_to_logical(&self, width: f64, height: f64, display_mm: (u32, u32)) -> (i32, i32) {
+        let (display_width, display_height) = match self.transform.flip_and_quarter_turns() {
+            (_, 1 | 3) => (display_mm.1, display_mm.0),
+            _ => display_mm,
+        };
+        (
+            (width * self.rect.width as f64 / display_width as f64).round() as i32,
+            (height * self.rect.height as f64 / display_height as f64).round() as i32,
+        )
+    }
+}
+
+/// Millimetres per inch.
+pub const MM_PER_INCH: f64 = 25.4;
+
+fn scaled(value: i32, scale: f64) -> i32 {
+    (value as f64 * scale).round() as i32
+}
+
+fn unscaled(value: i32, scale: f64) -> i32 {
+    (value as f64 / scale).round() as i32
+}
+
+/// Parse a size written as `<width>x<height>` with decimal numbers, e.g. `400x300` or `12.5x8`.
+pub fn parse_dimensions(s: &str) -> anyhow::Result<(f64, f64)> {
+    let invalid = || anyhow::format_err!("invalid size '{}', expected <width>x<height>", s);
+    let (width, height) = s.split_once('x').ok_or_else(invalid)?;
+    let number = |n: &str| {
+        n.parse::<f64>()
+            .ok()
+            .filter(|n| *n > 0.0)
+            .ok_or_else(invalid)
+    };
+    Ok((number(width)?, number(height)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(width: i32, height: i32, scale: f64, transform: Transform) -> OutputGeometry {
+        OutputGeometry {
+            rect: Rect {
+                x: 1000,
+                y: 0,
+                width,
+                height,
+            },
+            scale,
+            transform,
+        }
+    }
+
+    #[test]
+    fn scaled_output() {
+        let output = geometry(1920, 1080, 2.0, Transform::Normal);
+
+        assert_eq!(output.physical_size(), (3840, 2160));
+        assert_eq!(output.to_physical(1010, 20), (20, 40));
+        assert_eq!(output.to_logical(20, 40), (1010, 20));
+        assert_eq!(
+            output.rect_to_physical(&Rect {
+                x: 1100,
+                y: 100,
+                width: 50,
+                height: 10
+            }),
+            Rect {
+                x: 200,
+                y: 200,
+                width: 100,
+                height: 20
+            }
+        );
+    }
+
+    #[test]
+    fn physical_sizes() {
+        // A 27" 4K monitor at scale 2 and turned into portrait.
+        let display_mm = (597, 336);
+        let landscape = geometry(1920, 1080, 2.0, Transform::Normal);
+        let portrait = geometry(1080, 1920, 2.0, Transform::Rotate270);
+
+        assert_eq!(
+            landscape.mm_to_logical(597.0, 168.0, display_mm),
+            (1920, 540)
+        );
+        assert_eq!(
+            portrait.mm_to_logical(168.0, 597.0, display_mm),
+            (540, 1920)
+        );
+    }
+
+    #[test]
+    fn dimensions() {
+        assert_eq!(parse_dimensions("400x300").unwrap(), (400.0, 300.0));
+        assert_eq!(parse_dimensions("12.5x8").unwrap(), (12.5, 8.0));
+        assert!(parse_dimensions("12").is_err());
+        assert!(parse_dimensions("0x8").is_err());
+    }
+
+    #[test]
+    fn rotated_output() {
+        // A 1920x1080 monitor turned into portrait.
+        let output = geometry(1080, 1920, 1.0, Transform::Rotate90);
+
+        assert_eq!(output.physical_size(), (1920, 1080));
+        assert_eq!(output.to_physical(1000, 0), (0, 1080));
+        assert_eq!(output.to_physical(1000 + 1080, 1920), (1920, 0));
+        for transform in [
+            Transform::Rotate90,
+            Transform::Rotate180,
+            Transform::Rotate270,
+            Transform::Flipped,
+            Transform::Flipped90,
+            Transform::Flipped270,
+        ] {
+            let output = geometry(1080, 1920, 1.5, transform);
+            let (x, y) = output.to_physical(1300, 700);
+            assert_eq!(output.to_logical(x, y), (1300, 700), "{:?}", transform);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+//! Listing the input devices and switching the keyboard layouts with `xkb_switch_layout`. The
+//! daemon restores the layout of every window when it regains the focus, see
+//! [crate::config::DaemonConfig::keyboard_layout_per_window].
+
+use std::os::unix::net::UnixStream;
+
+use anyhow::Result;
+
+use crate::{messages::get_inputs, run_checked, tree::InputReply};
+
+/// The command switching the layout of the keyboard with the identifier, or of all keyboards
+/// without one, to `layout`: `next`, `prev` or the index of a layout.
+pub fn switch_layout_command(device: Option<&str>, layout: &str) -> String {
+    match device {
+        Some(identifier) => format!("input \"{}\" xkb_switch_layout {}", identifier, layout),
+        None => format!("input type:keyboard xkb_switch_layout {}", layout),
+    }
+}
+
+/// Check that the device is a keyboard with more than one layout to switch between.
+fn check_keyboard(inputs: &[InputReply], identifier: &str) -> Result<()> {
+    let Some(input) = inputs.iter().find(|i| i.identifier == identifier) else {
+        anyhow::bail!(
+            "No input device '{}', `haswaynav input list` shows the identifiers",
+            identifier
+        );
+    };
+    if input.input_type != "keyboard" {
+        anyhow::bail!("The input device '{}' is not a keyboard", identifier);
+    }
+    if input.xkb_layout_names.len() < 2 {
+        anyhow::bail!(
+            "The keyboard '{}' has a single layout, there is none to switch to",
+            identifier
+        );
+    }
+    Ok(())
+}
+
+/// Switch the keyboard with the identifier, or all keyboards without one, to the next layout.
+#[tracing::instrument(skip(socket))]
+pub fn next_layout(socket: &mut UnixStream, device: Option<&str>) -> Result<()> {
+    if let Some(identifier) = device {
+        check_keyboard(&get_inputs(socket)?, identifier)?;
+    }
+    run_checked(socket, &switch_layout_command(device, "next"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switch_layout_of_keyboards() {
+        let inputs: Vec<InputReply> = serde_json::from_str(
+            r#"[
+                {"identifier": "1:1:AT_Translated_Set_2_keyboard", "name": "AT Translated Set 2 keyboard",
+                 "type": "keyboard", "xkb_layout_names": ["English (US)", "German"],
+                 "xkb_active_layout_index": 1, "xkb_active_layout_name": "German"},
+                {"identifier": "1267:12377:ELAN1300:00_04F3:3059_Touchpad",
+                 "name": "ELAN1300:00 04F3:3059 Touchpad", "type": "touchpad"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(inputs[0].xkb_active_layout_index, Some(1));
+        assert!(check_keyboard(&inputs, "1:1:AT_Translated_Set_2_keyboard").is_ok());
+        assert!(check_keyboard(&inputs, "1267:12377:ELAN1300:00_04F3:3059_Touchpad").is_err());
+        assert!(check_keyboard(&inputs, "0:0:missing").is_err());
+        assert_eq!(
+            switch_layout_command(Some("1:1:AT_Translated_Set_2_keyboard"), "next"),
+            "input \"1:1:AT_Translated_Set_2_keyboard\" xkb_switch_layout next"
+        );
+        assert_eq!(
+            switch_layout_command(None, "0"),
+            "input type:keyboard xkb_switch_layout 0"
+        );
+    }
+}
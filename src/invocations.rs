@@ -0,0 +1,86 @@
+//! The invocation log, a black box recorder appending one JSON line per invocation of haswaynav
+//! so that what a key binding did can be looked at afterwards.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The size in bytes beyond which the log is rotated, see [append_invocation].
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+/// A line of the invocation log.
+pub struct Invocation {
+    /// Seconds since the unix epoch when haswaynav started
+    pub time: f64,
+    /// The command line arguments
+    pub args: Vec<String>,
+    /// The commands sent to sway
+    pub commands: Vec<String>,
+    /// The error the invocation failed with, none if it succeeded
+    pub error: Option<String>,
+    /// How long the invocation took in milliseconds
+    pub duration_ms: f64,
+}
+
+/// The default path of the invocation log, `$XDG_STATE_HOME/haswaynav/invocations.log` falling
+/// back to `~/.local/state/haswaynav/invocations.log`.
+pub fn default_log_path() -> Option<PathBuf> {
+    let non_empty = |name| std::env::var_os(name).filter(|s| !s.is_empty());
+    let state_home = non_empty("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state_home.join("haswaynav").join("invocations.log"))
+}
+
+/// Append the invocation to the log at the given path. A log grown beyond [MAX_LOG_SIZE] is first
+/// moved aside to the same path with `.1` appended, replacing the previous one.
+pub fn append_invocation(path: &Path, invocation: &Invocation) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating log directory '{}'", dir.display()))?;
+    }
+    if std::fs::metadata(path).is_ok_and(|m| m.len() > MAX_LOG_SIZE) {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(".1");
+        std::fs::rename(path, &rotated)
+            .with_context(|| format!("rotating log file '{}'", path.display()))?;
+    }
+    let mut line = serde_json::to_vec(invocation)?;
+    line.push(b'\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(&line))
+        .with_context(|| format!("writing log file '{}'", path.display()))
+}
+
+#[test]
+fn test_append_invocation() {
+    let dir = std::env::temp_dir().join(format!("haswaynav-invocations-{}", std::process::id()));
+    let path = dir.join("invocations.log");
+    let invocation = Invocation {
+        time: 1.5,
+        args: vec!["haswaynav".to_string(), "focus".to_string()],
+        commands: vec!["focus left".to_string()],
+        error: None,
+        duration_ms: 2.0,
+    };
+
+    append_invocation(&path, &invocation).unwrap();
+    append_invocation(&path, &invocation).unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<Invocation> = contents
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(lines, [invocation.clone(), invocation]);
+}
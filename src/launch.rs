@@ -0,0 +1,174 @@
+//! Launching a command and placing its first window, replacing `exec ...; sleep 1; move ...`
+//! patterns in sway configs.
+
+use std::{
+    fmt,
+    os::unix::{net::UnixStream, process::CommandExt},
+    process::{Command, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::Pattern,
+    messages::{receive_new_window, subscribe},
+    profile::LayoutMode,
+    run_checked,
+};
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+/// A length given to sway's `resize set`, either in pixels or in percent of the workspace.
+pub enum Length {
+    Px(u32),
+    Ppt(u32),
+}
+
+impl FromStr for Length {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (number, ppt) = match s.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (s.strip_suffix("px").unwrap_or(s), false),
+        };
+        let number = number
+            .parse()
+            .with_context(|| format!("invalid length '{}'", s))?;
+        Ok(if ppt {
+            Length::Ppt(number)
+        } else {
+            Length::Px(number)
+        })
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Length::Px(px) => write!(f, "{} px", px),
+            Length::Ppt(ppt) => write!(f, "{} ppt", ppt),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+/// The size of a window written as `<width>x<height>`, e.g. `800x600` or `50%x50%`.
+pub struct Size {
+    pub width: Length,
+    pub height: Length,
+}
+
+impl FromStr for Size {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (width, height) = s
+            .split_once('x')
+            .with_context(|| format!("invalid size '{}', expected <width>x<height>", s))?;
+        Ok(Size {
+            width: width.parse()?,
+            height: height.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+/// Where and how to place a window.
+pub struct Placement {
+    /// The workspace the window is moved to
+    pub workspace: Option<String>,
+    /// The layout of the container holding the window
+    pub layout: Option<LayoutMode>,
+    /// Make the window floating
+    pub float: bool,
+    /// The size the window is resized to
+    pub size: Option<Size>,
+}
+
+impl Placement {
+    /// The commands placing the window with the id, run together so the window is placed in one
+    /// go. `None` when there is nothing to do.
+    pub fn commands(&self, id: i32) -> Option<String> {
+        let mut commands = Vec::new();
+        if self.float {
+            commands.push("floating enable".to_string());
+        }
+        if let Some(workspace) = &self.workspace {
+            commands.push(format!("move container to workspace \"{}\"", workspace));
+        }
+        if let Some(layout) = self.layout {
+            commands.push(format!("layout {}", layout));
+        }
+        if let Some(size) = self.size {
+            commands.push(format!(
+                "resize set width {} height {}",
+                size.width, size.height
+            ));
+        }
+        (!commands.is_empty()).then(|| format!("[con_id={}] {}", id, commands.join(", ")))
+    }
+}
+
+/// Run the command and place its first window, recognised by the pid of the command or by the
+/// `app_id` pattern for apps which open their windows from another process. Gives up when no such
+/// window appears before the timeout.
+#[tracing::instrument(skip(socket, events))]
+pub fn launch(
+    socket: &mut UnixStream,
+    mut events: UnixStream,
+    command: &[String],
+    app_id: Option<&Pattern>,
+    placement: &Placement,
+    timeout: Duration,
+) -> Result<()> {
+    let (program, args) = command.split_first().context("No command to run")?;
+    // Subscribe before starting the command so its window is not missed.
+    subscribe(&mut events, &["window"])?;
+    // The command outlives us in its own process group, away from the signals of our terminal.
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("running '{}'", program))?;
+    let pid = child.id() as i32;
+    // Reap it should it exit while we wait for its window, apps started through a launcher script
+    // do; after we exit init does.
+    std::thread::spawn(move || child.wait());
+
+    let deadline = Instant::now() + timeout;
+    let window = loop {
+        let window = receive_new_window(&mut events, deadline)?
+            .with_context(|| format!("No window of '{}' appeared in time", program))?;
+        if window.pid == Some(pid) || app_id.is_some_and(|p| p.matches(window.app_id.as_deref())) {
+            break window;
+        }
+    };
+    if let Some(commands) = placement.commands(window.id) {
+        run_checked(socket, &commands)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_placement_commands() {
+    let placement = Placement {
+        workspace: Some("3".to_string()),
+        layout: Some(LayoutMode::Tabbed),
+        float: true,
+        size: Some("50%x600".parse().unwrap()),
+    };
+
+    assert_eq!(
+        placement.commands(7).as_deref(),
+        Some(
+            "[con_id=7] floating enable, move container to workspace \"3\", layout tabbed, \
+             resize set width 50 ppt height 600 px"
+        )
+    );
+    assert_eq!(Placement::default().commands(7), None);
+    assert!("50%".parse::<Size>().is_err());
+    assert!("ax600".parse::<Size>().is_err());
+}
@@ -6,19 +6,211 @@
 //! in the specified direction. This little utility allows you to select the next container in the
 //! physical direction.
 
-use std::os::unix::net::UnixStream;
+use std::{
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
+pub mod bar;
+#[cfg(feature = "cli")]
+pub(crate) mod batch;
+#[cfg(feature = "cli")]
+pub(crate) mod bench;
+#[cfg(feature = "cli")]
+pub(crate) mod bindings;
+pub mod capture;
+#[cfg(feature = "cli")]
 pub mod cli;
+#[cfg(feature = "cli")]
+pub(crate) mod completions;
+pub mod config;
+pub mod daemon;
+#[cfg(feature = "cli")]
+pub(crate) mod doctor;
+pub mod drag;
+#[cfg(feature = "cli")]
+mod execute;
+pub mod expose;
+pub mod filter;
+pub mod gaps;
+pub mod geometry;
+pub mod input;
+#[cfg(feature = "cli")]
+pub mod invocations;
+pub mod launch;
+#[cfg(feature = "cli")]
+pub mod logging;
 pub mod messages;
+pub mod mode;
+pub mod notify;
+pub mod output;
+pub mod pointer;
+pub mod profile;
+pub mod query;
+pub mod recording;
+#[cfg(feature = "cli")]
+pub(crate) mod repl;
+pub mod resize;
+pub mod run_once;
+#[cfg(feature = "cli")]
+pub mod snapshot;
+pub mod split;
+pub mod tag;
+pub mod text;
 pub mod tree;
+pub mod undo;
+pub mod wait;
+pub mod workspace;
 
-use anyhow::Result;
-use cli::Direction;
-use messages::{get_tree, run_command};
-use tree::{cursor::find_focused, Layout};
+use anyhow::{Context, Result};
+use config::{Boundary, CrossOutput, FocusConfig, OnEdge};
+#[cfg(feature = "cli")]
+pub use execute::{execute, run_alias, run_steps, Step};
+use messages::{check_results, get_tree_slim, run_command, CommandError};
+use serde::{Deserialize, Serialize};
+use tree::{cursor::find_focused_fast, FullScreenMode, Layout, NodeType, Rect};
+pub use tree::{cursor::Cursor, Event, TreeNode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// The enumeration of directions used with focus to change focus in a specified direction.
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    /// All directions, in the order the help lists them.
+    pub const ALL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ];
+
+    /// The name of the direction, e.g. `left`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Left => "left",
+            Direction::Right => "right",
+            Direction::Up => "up",
+            Direction::Down => "down",
+        }
+    }
+
+    /// The direction after turning a quarter clockwise, e.g. up becomes right.
+    pub fn clockwise(self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    /// The direction mirrored horizontally, swapping left and right.
+    pub fn mirrored(self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            other => other,
+        }
+    }
+}
+
+/// The names of the directions with their synonyms: the vim keys and the names of the arrow keys as
+/// xkb keysyms and as Linux input event codes, so the key of a binding can be passed on as is.
+const DIRECTION_NAMES: &[(&str, Direction)] = &[
+    ("left", Direction::Left),
+    ("right", Direction::Right),
+    ("up", Direction::Up),
+    ("down", Direction::Down),
+    ("h", Direction::Left),
+    ("l", Direction::Right),
+    ("k", Direction::Up),
+    ("j", Direction::Down),
+    ("Left", Direction::Left),
+    ("Right", Direction::Right),
+    ("Up", Direction::Up),
+    ("Down", Direction::Down),
+    ("KP_Left", Direction::Left),
+    ("KP_Right", Direction::Right),
+    ("KP_Up", Direction::Up),
+    ("KP_Down", Direction::Down),
+    ("KEY_LEFT", Direction::Left),
+    ("KEY_RIGHT", Direction::Right),
+    ("KEY_UP", Direction::Up),
+    ("KEY_DOWN", Direction::Down),
+];
+
+impl Direction {
+    /// Parse a direction from its name or one of its synonyms, see [DIRECTION_NAMES]. Unless
+    /// `strict` the case does not matter and the names may be cut short as long as two letters
+    /// remain, e.g. `ri`. Strictly those are rejected as ambiguous, naming the direction meant.
+    /// Mistyped directions are rejected suggesting the closest name.
+    pub fn parse(value: &str, strict: bool) -> Result<Direction> {
+        if let Some((_, direction)) = DIRECTION_NAMES.iter().find(|(name, _)| *name == value) {
+            return Ok(*direction);
+        }
+        let lower = value.to_lowercase();
+        let loose = DIRECTION_NAMES
+            .iter()
+            .find(|(name, _)| name.to_lowercase() == lower)
+            .map(|(_, direction)| *direction)
+            .or_else(|| {
+                let mut prefixed = Direction::ALL
+                    .into_iter()
+                    .filter(|d| lower.len() >= 2 && d.name().starts_with(&lower));
+                prefixed.next().filter(|_| prefixed.next().is_none())
+            });
+        match loose {
+            Some(direction) if !strict => Ok(direction),
+            Some(direction) => {
+                anyhow::bail!(
+                    "ambiguous direction '{}', did you mean '{}'?",
+                    value,
+                    direction
+                )
+            }
+            None => match Direction::ALL
+                .into_iter()
+                .map(|d| (config::edit_distance(&lower, d.name()), d))
+                .filter(|(distance, _)| *distance <= 2)
+                .min_by_key(|(distance, _)| *distance)
+            {
+                Some((_, name)) => {
+                    anyhow::bail!("invalid direction '{}', did you mean '{}'?", value, name)
+                }
+                None => anyhow::bail!(
+                    "invalid direction '{}', expected left, right, up or down",
+                    value
+                ),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = anyhow::Error;
+
+    /// Parse the direction leniently, see [Direction::parse].
+    fn from_str(s: &str) -> Result<Self> {
+        Direction::parse(s, false)
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
 
 /// Read the path to the sway domain socket from the `SWAYSOCK` environment variable and connect to it
 /// returning a descriptive error message if any error occurs.
+#[tracing::instrument]
 pub fn sway_connect() -> Result<UnixStream> {
     let swayswock = {
         let errmsg = || {
@@ -38,48 +230,1810 @@ pub fn sway_connect() -> Result<UnixStream> {
     })
 }
 
+/// The longest pause between two attempts of [wait_for_sway].
+const MAX_WAIT_DELAY: Duration = Duration::from_secs(1);
+
+/// The newest sway socket in `XDG_RUNTIME_DIR`, named `sway-ipc.<uid>.<pid>.sock` by sway.
+fn find_sway_socket() -> Option<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").filter(|s| !s.is_empty())?;
+    std::fs::read_dir(runtime_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("sway-ipc.") && name.ends_with(".sock")
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path())
+}
+
+/// Wait up to `timeout` for sway to accept connections, for starting haswaynav from autostart
+/// scripts which may run before sway is up. Without `SWAYSOCK` the newest sway socket in
+/// `XDG_RUNTIME_DIR` is taken and exported to the processes started later, looking again on every
+/// attempt since a stale socket of an earlier session may be found before sway is up. The attempts
+/// are 50ms apart at first, doubling up to [MAX_WAIT_DELAY].
+pub fn wait_for_sway(timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut delay = Duration::from_millis(50);
+    let guess = std::env::var_os("SWAYSOCK")
+        .filter(|s| !s.is_empty())
+        .is_none();
+    loop {
+        if guess {
+            if let Some(path) = find_sway_socket() {
+                std::env::set_var("SWAYSOCK", path);
+            }
+        }
+        let err = match sway_connect() {
+            Ok(_) => {
+                if guess {
+                    tracing::info!(swaysock = ?std::env::var_os("SWAYSOCK"), "connected to sway");
+                }
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(err.context(format!(
+                "Sway did not accept connections within {:?}",
+                timeout
+            )));
+        }
+        tracing::debug!("waiting for sway: {:#}", err);
+        std::thread::sleep(delay.min(deadline - now));
+        delay = (delay * 2).min(MAX_WAIT_DELAY);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Assembles the commands sent to sway as a single `RUN_COMMAND` payload, separated by `;`. The
+/// buffer keeps its capacity when cleared, so joining the commands again does not allocate once it
+/// is large enough. Fetching the tree and planning the commands still allocate.
+pub struct CommandBuffer {
+    buffer: String,
+}
+
+impl CommandBuffer {
+    /// Append a command.
+    pub fn push(&mut self, command: impl std::fmt::Display) {
+        use std::fmt::Write;
+        if !self.buffer.is_empty() {
+            self.buffer.push_str("; ");
+        }
+        write!(self.buffer, "{}", command).expect("writing to a string cannot fail");
+    }
+
+    /// Remove all commands keeping the allocated capacity.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Are there no commands.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// The commands as sent to sway.
+    pub fn as_str(&self) -> &str {
+        &self.buffer
+    }
+}
+
+/// A connection to sway which runs the commands planned from a single fetch of the tree.
+pub struct SwayClient {
+    socket: UnixStream,
+    /// Reused for the commands of every transaction
+    commands: CommandBuffer,
+    /// Whether the focus changes are reported as a [FocusMove], see [SwayClient::reporting]
+    report: bool,
+}
+
+impl From<UnixStream> for SwayClient {
+    fn from(socket: UnixStream) -> Self {
+        SwayClient {
+            socket,
+            commands: CommandBuffer::default(),
+            report: true,
+        }
+    }
+}
+
+impl SwayClient {
+    /// Connect to sway, see [sway_connect].
+    pub fn connect() -> Result<Self> {
+        Ok(sway_connect()?.into())
+    }
+
+    /// Whether to report the focus changes as a [FocusMove]. When sway chose the window to focus
+    /// that takes fetching the tree again, which is skipped when nobody keeps the moves.
+    pub fn reporting(mut self, report: bool) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// The underlying connection, for messages not covered by the client.
+    pub fn socket(&mut self) -> &mut UnixStream {
+        &mut self.socket
+    }
+
+    /// Fetch the tree once and let `plan` compute all the commands to run from it. The commands
+    /// are sent to sway as a single `RUN_COMMAND` message and fail the transaction if any of them
+    /// fails. Nothing is sent when `plan` returns no commands.
+    pub fn transaction(
+        &mut self,
+        plan: impl FnOnce(&TreeNode) -> Result<Vec<String>>,
+    ) -> Result<()> {
+        let tree = get_tree_slim(&mut self.socket)?;
+        let commands = plan(&tree)?;
+        if commands.is_empty() {
+            return Ok(());
+        }
+        self.commands.clear();
+        for command in &commands {
+            self.commands.push(command);
+        }
+        run_checked(&mut self.socket, self.commands.as_str())
+    }
+
+    /// Change the focus in the direction, see [change_focus].
+    pub fn change_focus(
+        &mut self,
+        dir: Direction,
+        options: &FocusConfig,
+    ) -> Result<Option<FocusMove>> {
+        self.change_focus_with(dir, options, plan_focus)
+    }
+
+    /// Change the focus in the direction into the aligned window, see [focus_into].
+    pub fn focus_into(
+        &mut self,
+        dir: Direction,
+        options: &FocusConfig,
+    ) -> Result<Option<FocusMove>> {
+        self.change_focus_with(dir, options, plan_focus_into)
+    }
+
+    fn change_focus_with(
+        &mut self,
+        dir: Direction,
+        options: &FocusConfig,
+        planner: fn(&TreeNode, Direction, &FocusConfig) -> FocusPlan,
+    ) -> Result<Option<FocusMove>> {
+        let attempts = if options.verify { 2 } else { 1 };
+        let mut moved = false;
+        let mut focus_move = None;
+        for attempt in 1..=attempts {
+            let mut from = None;
+            let mut target = None;
+            let result = self.transaction(|tree| {
+                from = find_focused_fast(tree).map(|c| c.get_node().id);
+                let plan = planner(tree, dir, options);
+                moved = !plan.commands.is_empty();
+                target = plan.target;
+                Ok(plan.commands)
+            });
+            if let Err(err) = result {
+                if err.downcast_ref::<CommandError>().is_none() {
+                    return Err(err);
+                }
+                target = self
+                    .fall_back(from, dir, options, planner)
+                    .map_err(|_| err.context("The simpler plans failed as well"))?;
+            }
+            if !moved {
+                tracing::info!(?dir, "no window in the direction");
+                notify::notify(&format!("No window to focus {}", dir));
+                break;
+            }
+            if !options.verify {
+                // Where sway moved to is only known from a fresh tree when it was left to sway.
+                let to = match target {
+                    Some(target) => Some(target),
+                    None if self.report => find_focused_fast(&get_tree_slim(&mut self.socket)?)
+                        .map(|c| c.get_node().id),
+                    None => None,
+                };
+                focus_move = FocusMove::new(from, to);
+                break;
+            }
+            let tree = get_tree_slim(&mut self.socket)?;
+            let to = find_focused_fast(&tree).map(|c| c.get_node().id);
+            focus_move = FocusMove::new(from, to);
+            let intended = match target {
+                Some(target) => to == Some(target),
+                None => to != from,
+            };
+            if intended {
+                tracing::debug!(?from, ?to, "verified the focus changed");
+                break;
+            }
+            if attempt == attempts {
+                anyhow::bail!(
+                    "The focus moved from {:?} to {:?} instead of {:?} after retrying",
+                    from,
+                    to,
+                    target
+                );
+            }
+            tracing::info!(
+                ?from,
+                ?to,
+                ?target,
+                "the focus did not change as intended, retrying"
+            );
+        }
+        if moved && options.warp_cursor {
+            warp_cursor_to_focused(&mut self.socket)?;
+        }
+        Ok(focus_move)
+    }
+
+    /// After sway rejected a command of a plan part way, focus the window the move started from
+    /// again, plan again from a fresh tree and try the simpler plans of [FocusPlan::fallbacks] in
+    /// turn. Returns the target of the plan which ran.
+    fn fall_back(
+        &mut self,
+        from: Option<i32>,
+        dir: Direction,
+        options: &FocusConfig,
+        planner: fn(&TreeNode, Direction, &FocusConfig) -> FocusPlan,
+    ) -> Result<Option<i32>> {
+        if let Some(from) = from {
+            run_checked(&mut self.socket, &format!("[con_id={}] focus", from))
+                .context("restoring the focus before falling back")?;
+        }
+        let tree = get_tree_slim(&mut self.socket)?;
+        let plan = planner(&tree, dir, options);
+        for fallback in plan.fallbacks(dir) {
+            let commands = fallback.commands.join("; ");
+            match run_checked(&mut self.socket, &commands) {
+                Ok(()) => {
+                    tracing::info!(commands, "fell back to a simpler plan");
+                    return Ok(fallback.target);
+                }
+                Err(err) => tracing::debug!(commands, "fallback failed: {:#}", err),
+            }
+        }
+        anyhow::bail!("No simpler plan succeeded")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// The focus change made by a directional move, which the daemon keeps to retrace the moves with
+/// `haswaynav focus back`.
+pub struct FocusMove {
+    /// The node focused before the move
+    pub from: i32,
+    /// The node focused after the move
+    pub to: i32,
+}
+
+impl FocusMove {
+    fn new(from: Option<i32>, to: Option<i32>) -> Option<FocusMove> {
+        match (from, to) {
+            (Some(from), Some(to)) if from != to => Some(FocusMove { from, to }),
+            _ => None,
+        }
+    }
+}
+
 /// Change the focus to the next visible window in the specified direction. This will ignore the
-/// other siblings in a tabbed or stacked container.
-pub fn change_focus(socket: &mut UnixStream, dir: Direction) -> Result<()> {
-    let tree = get_tree(socket)?;
+/// other siblings in a tabbed or stacked container for the moves configured with
+/// [FocusConfig::skip_tabbed] and [FocusConfig::skip_stacked].
+#[tracing::instrument(skip(socket))]
+pub fn change_focus(
+    socket: &mut UnixStream,
+    dir: Direction,
+    options: &FocusConfig,
+) -> Result<Option<FocusMove>> {
+    SwayClient::from(socket.try_clone()?).change_focus(dir, options)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+/// The commands changing the focus in a direction, see [plan_focus].
+pub struct FocusPlan {
+    /// The commands to run, none when the focus should stay where it is
+    pub commands: Vec<String>,
+    /// The id of the container which will be focused when it could be resolved from the tree
+    pub target: Option<i32>,
+}
+
+impl FocusPlan {
+    /// The simpler plans tried in turn when sway rejects a command of this plan part way, e.g.
+    /// because the tree changed before the commands ran: focusing the target by id, then a plain
+    /// `focus <direction>`. Plans the same as this one are left out. Run against a fresh tree the
+    /// plan should be computed from that tree.
+    pub fn fallbacks(&self, dir: Direction) -> Vec<FocusPlan> {
+        if self.commands.is_empty() {
+            return Vec::new();
+        }
+        let by_id = self.target.map(|id| FocusPlan {
+            commands: vec![format!("[con_id={}] focus", id)],
+            target: Some(id),
+        });
+        let plain = FocusPlan {
+            commands: vec![format!("focus {}", dir)],
+            target: None,
+        };
+        by_id
+            .into_iter()
+            .chain([plain])
+            .filter(|p| p.commands != self.commands)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The steps of a decision in words, only recorded when asked for so planning does not pay for
+/// formatting them otherwise.
+pub struct Trace(Option<Vec<String>>);
+
+impl Trace {
+    /// A trace recording the steps.
+    pub fn recording() -> Self {
+        Trace(Some(Vec::new()))
+    }
+
+    /// Record a step, the line is only formatted when recording.
+    fn note(&mut self, line: impl FnOnce() -> String) {
+        if let Some(lines) = &mut self.0 {
+            lines.push(line());
+        }
+    }
+
+    /// The recorded steps in order.
+    pub fn lines(&self) -> &[String] {
+        self.0.as_deref().unwrap_or_default()
+    }
+}
+
+/// Describe the node under the cursor for a [Trace], e.g. `#12 Con "Firefox" (SplitH, 3 children)`.
+fn describe(c: &Cursor) -> String {
+    let node = c.get_node();
+    format!(
+        "#{} {:?} {:?} ({:?}, {} children)",
+        node.id,
+        node.node_type,
+        node.name.as_deref().unwrap_or_default(),
+        node.layout,
+        node.nodes.len()
+    )
+}
+
+/// Compute the commands which change the focus in the specified direction. When the container
+/// to focus can be resolved from the tree it is focused by id, so that the commands do not act on
+/// another container if the focus changes before they run. Otherwise the relative focus commands
+/// are left to sway, e.g. when moving to another output.
+pub fn plan_focus(tree: &TreeNode, dir: Direction, options: &FocusConfig) -> FocusPlan {
+    plan_focus_traced(tree, dir, options, &mut Trace::default())
+}
+
+/// Change the focus in the specified direction like [change_focus], but when the move enters a
+/// container focus the window in it aligned with the one focused, see [plan_focus_into].
+#[tracing::instrument(skip(socket))]
+pub fn focus_into(
+    socket: &mut UnixStream,
+    dir: Direction,
+    options: &FocusConfig,
+) -> Result<Option<FocusMove>> {
+    SwayClient::from(socket.try_clone()?).focus_into(dir, options)
+}
+
+/// Compute the commands moving the focus in the direction like [plan_focus], but descending into
+/// the container entered to the window which overlaps the focused one the most across the
+/// direction, e.g. the one at the same height when moving to the left. Where the container is
+/// split along the direction the window at the edge entered is chosen, and in tabbed and stacked
+/// containers the visible one.
+pub fn plan_focus_into(tree: &TreeNode, dir: Direction, options: &FocusConfig) -> FocusPlan {
+    let plan = plan_focus(tree, dir, options);
+    let (Some(focused), Some(target)) = (find_focused_fast(tree), plan.target) else {
+        return plan;
+    };
+    let Some(target) = tree.into_iter().find(|c| c.get_node().id == target) else {
+        return plan;
+    };
+    // The container entered is the child of the innermost container holding both windows.
+    let common: Vec<i32> = focused
+        .ancestors()
+        .iter()
+        .map(|a| a.get_node().id)
+        .collect();
+    let Some(entered) = std::iter::once(target.clone())
+        .chain(target.ancestors())
+        .take_while(|c| !common.contains(&c.get_node().id))
+        .last()
+        .filter(|c| c.get_node().node_type == NodeType::Con)
+    else {
+        return plan;
+    };
+    let aligned = aligned_descendant(entered, &focused.get_node().rect, dir);
+    let id = aligned.get_node().id;
+    tracing::debug!(id, "aligned window in the container entered");
+    FocusPlan {
+        commands: vec![format!("[con_id={}] focus", id)],
+        target: Some(id),
+    }
+}
+
+/// Descend from the container to the window aligned with `from`, see [plan_focus_into].
+fn aligned_descendant<'a>(mut c: Cursor<'a>, from: &Rect, dir: Direction) -> Cursor<'a> {
+    loop {
+        let node = c.get_node();
+        if node.nodes.is_empty() {
+            return c;
+        }
+        let along = match dir {
+            Direction::Left | Direction::Right => Layout::SplitH,
+            Direction::Up | Direction::Down => Layout::SplitV,
+        };
+        let idx = match node.layout {
+            Layout::Tabbed | Layout::Stacked => None,
+            layout if layout == along => Some(match dir {
+                Direction::Left | Direction::Up => node.nodes.len() - 1,
+                Direction::Right | Direction::Down => 0,
+            }),
+            _ => node
+                .nodes
+                .iter()
+                .enumerate()
+                .max_by_key(|(idx, n)| {
+                    let recent = node.focus.iter().position(|id| *id == n.id);
+                    (
+                        overlap_across(from, &n.rect, dir),
+                        std::cmp::Reverse(recent.unwrap_or(usize::MAX)),
+                        std::cmp::Reverse(*idx),
+                    )
+                })
+                .map(|(idx, _)| idx),
+        };
+        let next = match idx {
+            Some(idx) => c.clone().child(idx).ok(),
+            None => c.visible_child(),
+        };
+        match next {
+            Some(next) => c = next,
+            None => return c,
+        }
+    }
+}
+
+/// Plan the focus change like [plan_focus] and describe why it chose its commands, for
+/// `haswaynav focus <direction> --explain`.
+pub fn explain_focus(
+    tree: &TreeNode,
+    dir: Direction,
+    options: &FocusConfig,
+) -> (FocusPlan, Vec<String>) {
+    let mut trace = Trace::recording();
+    let plan = plan_focus_traced(tree, dir, options, &mut trace);
+    trace.note(|| match plan.target {
+        Some(id) => format!("predicted target: #{}", id),
+        None if plan.commands.is_empty() => "the focus stays where it is".to_string(),
+        None => "predicted target: left to sway".to_string(),
+    });
+    trace.note(|| format!("commands: {}", plan.commands.join("; ")));
+    for fallback in plan.fallbacks(dir) {
+        trace.note(|| format!("if sway rejects them: {}", fallback.commands.join("; ")));
+    }
+    (plan, trace.0.unwrap_or_default())
+}
+
+fn plan_focus_traced(
+    tree: &TreeNode,
+    dir: Direction,
+    options: &FocusConfig,
+    trace: &mut Trace,
+) -> FocusPlan {
+    if options.fullscreen_guard && in_global_fullscreen(tree) {
+        trace.note(|| {
+            "the focused window is fullscreen on all outputs, --force moves anyway".into()
+        });
+        tracing::debug!("fullscreen guard keeps the focus");
+        return FocusPlan::default();
+    }
+    let plan = plan_tiled_focus(tree, dir, options, trace);
+    if !options.enter_floating {
+        return plan;
+    }
+    let c = match find_focused_fast(tree) {
+        Some(c) if !in_floating_layer(&c) => c,
+        _ => return plan,
+    };
+    let floating = match floating_in_direction(&c, dir) {
+        None => return plan,
+        Some(f) => f,
+    };
+    let r = c.get_node().rect;
+    let tiled = plan
+        .target
+        .and_then(|id| tree.into_iter().find(|x| x.get_node().id == id));
+    // A floating window on the workspace is nearer than anything past its edge.
+    let nearer = tiled.is_none_or(|t| {
+        floating.get_node().rect.distance_squared(&r) < t.get_node().rect.distance_squared(&r)
+    });
+    trace.note(|| {
+        format!(
+            "floating window {} is nearer: {}",
+            describe(&floating),
+            nearer
+        )
+    });
+    if !nearer {
+        return plan;
+    }
+    let id = visible_descendant(floating).get_node().id;
+    tracing::debug!(id, "entering the floating window");
+    FocusPlan {
+        commands: vec![format!("[con_id={}] focus", id)],
+        target: Some(id),
+    }
+}
+
+/// Is the focused node or a container holding it fullscreen on all outputs.
+fn in_global_fullscreen(tree: &TreeNode) -> bool {
+    find_focused_fast(tree).is_some_and(|c| {
+        std::iter::once(c.clone())
+            .chain(c.ancestors())
+            .any(|a| a.get_node().fullscreen_mode == FullScreenMode::GlobalFullScreen)
+    })
+}
+
+/// Plan the focus change between tiled containers, see [plan_focus].
+fn plan_tiled_focus(
+    tree: &TreeNode,
+    dir: Direction,
+    options: &FocusConfig,
+    trace: &mut Trace,
+) -> FocusPlan {
     let focus_dir = match dir {
         Direction::Left => "focus left",
         Direction::Right => "focus right",
         Direction::Up => "focus up",
         Direction::Down => "focus down",
     };
-    match find_focused(&tree) {
-        None => println!("no focused node"),
-        Some(c) => {
-            let nav = c
-                .ancestors()
-                .into_iter()
-                .map_while(|x| {
-                    if x.get_node().layout == Layout::SplitH
-                        || x.get_node().layout == Layout::SplitV
-                        || x.get_node().layout == Layout::Output
-                    {
-                        None
-                    } else {
-                        Some("focus parent")
-                    }
-                })
-                .chain([focus_dir])
-                .collect::<Vec<_>>()
-                .join("; ");
-
-            match run_command(socket, &nav) {
-                Err(err) => anyhow::bail!("Failed running navigation command: {}", err),
-                Ok(xs) => {
-                    for x in xs {
-                        if !x.success {
-                            anyhow::bail!("Failure reported by sway: {:?}", x.error)
-                        }
-                    }
+    let c = match find_focused_fast(tree) {
+        None => {
+            tracing::warn!("no focused node");
+            trace.note(|| "nothing is focused".to_string());
+            return FocusPlan::default();
+        }
+        Some(c) => c,
+    };
+    tracing::debug!(id = c.get_node().id, name = ?c.get_node().name, "focused node");
+    trace.note(|| format!("focused: {}", describe(&c)));
+    if c.get_node().node_type == NodeType::Workspace {
+        return plan_empty_workspace_focus(tree, &c, dir, options, trace);
+    }
+    let (c, prefix) = if options.skip_floating && c.is_floating() {
+        match c.workspace() {
+            Some(ws) => (visible_descendant(ws), Some("focus mode_toggle")),
+            None => (c, None),
+        }
+    } else {
+        (c, None)
+    };
+    if prefix.is_some() {
+        trace.note(|| format!("skipping the floating layer from {}", describe(&c)));
+    }
+
+    // The climb stays inside the workspace, only when nothing in it takes the move does the move
+    // reach the boundary.
+    let climbed = climb(&c, dir, options, trace);
+    let from = climbed.last().unwrap_or(&c);
+    let target = target_in_direction(from, dir);
+    if target.is_none() && at_workspace_edge(&c, dir) {
+        if let Some(on_edge) = options
+            .on_edge
+            .filter(|_| options.boundary != Boundary::Workspace)
+        {
+            trace.note(|| format!("at the {} edge of the workspace: {:?}", dir, on_edge));
+            return plan_on_edge(tree, &c, dir, on_edge);
+        }
+        let crosses_output = c
+            .output()
+            .is_some_and(|o| output_in_direction(tree, o.get_node(), dir));
+        let allowed = match options.boundary {
+            Boundary::Workspace => false,
+            Boundary::Output => !crosses_output && options.wrap,
+            Boundary::None if crosses_output => options.cross_output != CrossOutput::Deny,
+            Boundary::None => options.wrap,
+        };
+        tracing::debug!(crosses_output, allowed, "at the edge of the workspace");
+        trace.note(|| {
+            format!(
+                "at the {} edge of the workspace, crossing outputs: {}, allowed by {:?}: {}",
+                dir, crosses_output, options.boundary, allowed
+            )
+        });
+        if !allowed {
+            return FocusPlan::default();
+        }
+        if crosses_output && options.cross_output == CrossOutput::Geometric {
+            return plan_geometric_focus(tree, &c, dir, trace);
+        }
+    }
+
+    if let Some(target) = target {
+        let id = target.get_node().id;
+        tracing::debug!(
+            id,
+            visible = target.is_visible(),
+            "resolved the container to focus"
+        );
+        trace.note(|| {
+            format!(
+                "resolved the target from {}: {}",
+                describe(from),
+                describe(&target)
+            )
+        });
+        return FocusPlan {
+            commands: vec![format!("[con_id={}] focus", id)],
+            target: Some(id),
+        };
+    }
+
+    trace.note(|| {
+        format!(
+            "no target {} of {} in the tree, leaving the move to sway after {} focus parent",
+            dir,
+            describe(from),
+            climbed.len()
+        )
+    });
+    FocusPlan {
+        commands: prefix
+            .into_iter()
+            .chain(climbed.iter().map(|_| "focus parent"))
+            .chain([focus_dir])
+            .map(str::to_string)
+            .collect(),
+        target: None,
+    }
+}
+
+/// The prefix of the hidden mark remembering the tiled window of a workspace to return to, followed
+/// by the id of the workspace.
+const TILED_MARK_PREFIX: &str = "_haswaynav_tiled_";
+
+/// Switch between the tiling and floating layers of the focused workspace, see [plan_toggle_layer].
+#[tracing::instrument(skip(socket))]
+pub fn toggle_layer(socket: &mut UnixStream) -> Result<()> {
+    SwayClient::from(socket.try_clone()?).transaction(|tree| Ok(plan_toggle_layer(tree).commands))
+}
+
+/// Compute the commands switching between the tiling and floating layers of the focused workspace.
+/// From a tiled window the floating window nearest to it is focused and the tiled window is
+/// remembered with a hidden mark. From a floating window the remembered tiled window is focused, or
+/// the visible one if the remembered window is gone.
+pub fn plan_toggle_layer(tree: &TreeNode) -> FocusPlan {
+    let (c, ws) = match find_focused_fast(tree).and_then(|c| Some((c.clone(), c.workspace()?))) {
+        None => return FocusPlan::default(),
+        Some(x) => x,
+    };
+    let mark = format!("{}{}", TILED_MARK_PREFIX, ws.get_node().id);
+    let focus = |target: Cursor, mut commands: Vec<String>| {
+        let id = target.get_node().id;
+        commands.push(format!("[con_id={}] focus", id));
+        FocusPlan {
+            commands,
+            target: Some(id),
+        }
+    };
+
+    if in_floating_layer(&c) {
+        let remembered = tree.into_iter().find(|x| {
+            x.get_node().marks.contains(&mark)
+                && !in_floating_layer(x)
+                && x.workspace().map(|w| w.get_node().id) == Some(ws.get_node().id)
+        });
+        let target = remembered.unwrap_or_else(|| visible_descendant(ws.clone()));
+        if target.get_node().id == ws.get_node().id {
+            tracing::debug!("no tiled window to return to");
+            return FocusPlan::default();
+        }
+        focus(target, Vec::new())
+    } else {
+        let tiling = ws.get_node().nodes.len();
+        let rect = c.get_node().rect;
+        let nearest = (0..ws.get_node().floating_nodes.len())
+            .filter_map(|idx| ws.clone().child(tiling + idx).ok())
+            .min_by_key(|f| f.get_node().rect.distance_squared(&rect));
+        match nearest {
+            None => {
+                tracing::debug!("no floating window to switch to");
+                FocusPlan::default()
+            }
+            Some(f) => focus(
+                visible_descendant(f),
+                vec![format!("[con_id={}] mark --add {}", c.get_node().id, mark)],
+            ),
+        }
+    }
+}
+
+/// Is the node under the cursor a floating container or inside one.
+pub(crate) fn in_floating_layer(c: &Cursor) -> bool {
+    c.is_floating() || c.ancestors().iter().any(Cursor::is_floating)
+}
+
+/// Run the commands and turn any failure reported by sway into an error, with a
+/// [messages::CommandError] naming the command which failed.
+pub(crate) fn run_checked(socket: &mut UnixStream, commands: &str) -> Result<()> {
+    tracing::debug!(commands, "running sway commands");
+    match run_command(socket, commands) {
+        Err(err) => anyhow::bail!("Failed running navigation command: {}", err),
+        Ok(results) => check_results(commands, &results)
+            .map_err(|err| anyhow::Error::new(err).context("Failure reported by sway")),
+    }
+}
+
+/// Follow the visible children down from the container to the view which is on screen, this is
+/// also the view sway focuses when moving into the container or with `focus mode_toggle`.
+fn visible_descendant(mut c: Cursor<'_>) -> Cursor<'_> {
+    while let Some(child) = c.visible_child() {
+        c = child;
+    }
+    c
+}
+
+/// The ancestors to focus with `focus parent` before moving in the given direction so that the
+/// move skips over the siblings of tabbed and stacked containers. The climb never leaves the
+/// workspace, or the floating container, and passes through containers with a single child
+/// without stopping. Such containers are not climbed on their own though since there are no
+/// siblings to skip.
+fn climb<'a>(
+    c: &Cursor<'a>,
+    dir: Direction,
+    options: &FocusConfig,
+    trace: &mut Trace,
+) -> Vec<Cursor<'a>> {
+    let mut climbed = Vec::new();
+    let mut needed = 0;
+    for x in c.ancestors() {
+        let node = x.get_node();
+        if node.node_type != NodeType::Con {
+            trace.note(|| format!("climb stops at {}: not a container", describe(&x)));
+            break;
+        }
+        let single_child = node.nodes.len() <= 1;
+        let skip = match node.layout {
+            Layout::Tabbed => options.skip_tabbed.contains(dir),
+            Layout::Stacked => options.skip_stacked.contains(dir),
+            Layout::SplitH | Layout::SplitV | Layout::Output => false,
+            Layout::None => true,
+        };
+        if !skip && !single_child {
+            trace.note(|| {
+                format!(
+                    "climb stops at {}: its siblings are not skipped",
+                    describe(&x)
+                )
+            });
+            break;
+        }
+        trace.note(|| {
+            let why = if single_child {
+                "single child"
+            } else {
+                "skipped"
+            };
+            format!("climbing {}: {}", describe(&x), why)
+        });
+        climbed.push(x);
+        if skip && !single_child {
+            needed = climbed.len();
+        }
+    }
+    climbed.truncate(needed);
+    trace.note(|| format!("climbed {} levels", climbed.len()));
+    climbed
+}
+
+/// Find the container sway focuses when moving from the tiled container in the given direction
+/// without leaving the workspace. Like sway, tabbed containers count as split horizontally and
+/// stacked ones as split vertically. The focus moves into the view of the sibling which is on
+/// screen, rather than e.g. the tabbed container itself when none of its tabs was focused yet.
+fn target_in_direction<'a>(c: &Cursor<'a>, dir: Direction) -> Option<Cursor<'a>> {
+    let mut child = c.clone();
+    while let Ok(parent) = child.clone().ascend() {
+        if child.is_floating() {
+            return None;
+        }
+        let layout = parent.get_node().layout;
+        let parallel = match dir {
+            Direction::Left | Direction::Right => {
+                layout == Layout::SplitH || layout == Layout::Tabbed
+            }
+            Direction::Up | Direction::Down => {
+                layout == Layout::SplitV || layout == Layout::Stacked
+            }
+        };
+        if parallel {
+            let sibling = match dir {
+                Direction::Left | Direction::Up => child.clone().prev_sibling().ok(),
+                Direction::Right | Direction::Down => child
+                    .clone()
+                    .next_sibling()
+                    .ok()
+                    .filter(|s| !s.is_floating()),
+            };
+            if let Some(sibling) = sibling {
+                return Some(visible_descendant(sibling));
+            }
+        }
+        if parent.get_node().node_type == NodeType::Workspace {
+            return None;
+        }
+        child = parent;
+    }
+    None
+}
+
+/// Is the tiled container at the edge of its workspace in the given direction, i.e. there is no
+/// sibling in that direction in any of its ancestors split along the same axis.
+fn at_workspace_edge(c: &Cursor, dir: Direction) -> bool {
+    let layout = match dir {
+        Direction::Left | Direction::Right => Layout::SplitH,
+        Direction::Up | Direction::Down => Layout::SplitV,
+    };
+    let mut child = c.clone();
+    while let Ok(parent) = child.clone().ascend() {
+        if child.is_floating() {
+            return false;
+        }
+        if parent.get_node().layout == layout {
+            let has_sibling = match dir {
+                Direction::Left | Direction::Up => child.clone().prev_sibling().is_ok(),
+                Direction::Right | Direction::Down => {
+                    child.clone().next_sibling().is_ok_and(|s| !s.is_floating())
                 }
+            };
+            if has_sibling {
+                return false;
+            }
+        }
+        if parent.get_node().node_type == NodeType::Workspace {
+            break;
+        }
+        child = parent;
+    }
+    true
+}
+
+/// Plan the move from the container at the edge of its workspace, see [FocusConfig::on_edge].
+fn plan_on_edge(tree: &TreeNode, c: &Cursor, dir: Direction, on_edge: OnEdge) -> FocusPlan {
+    let command = match on_edge {
+        OnEdge::Nothing => return FocusPlan::default(),
+        OnEdge::SwitchWorkspace => switch_workspace_command(dir).to_string(),
+        OnEdge::FocusOutput => {
+            let crosses_output = c
+                .output()
+                .is_some_and(|o| output_in_direction(tree, o.get_node(), dir));
+            if !crosses_output {
+                return FocusPlan::default();
+            }
+            format!("focus output {}", dir)
+        }
+        OnEdge::Wrap => {
+            let Some(ws) = c.workspace() else {
+                return FocusPlan::default();
+            };
+            // Entering the workspace from the opposite edge lands on the window to wrap to.
+            let target = aligned_descendant(ws, &c.get_node().rect, dir);
+            let id = target.get_node().id;
+            if id == c.get_node().id || target.get_node().node_type != NodeType::Con {
+                return FocusPlan::default();
+            }
+            return FocusPlan {
+                commands: vec![format!("[con_id={}] focus", id)],
+                target: Some(id),
+            };
+        }
+    };
+    FocusPlan {
+        commands: vec![command],
+        target: None,
+    }
+}
+
+/// The command switching to the previous workspace on the output for moves to the left and up, and
+/// to the next one for moves to the right and down.
+fn switch_workspace_command(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Left | Direction::Up => "workspace prev_on_output",
+        Direction::Right | Direction::Down => "workspace next_on_output",
+    }
+}
+
+/// Plan the move from a focused workspace, which sway focuses when the workspace has no tiled
+/// windows, see [FocusConfig::empty_workspace].
+fn plan_empty_workspace_focus(
+    tree: &TreeNode,
+    ws: &Cursor,
+    dir: Direction,
+    options: &FocusConfig,
+    trace: &mut Trace,
+) -> FocusPlan {
+    trace.note(|| format!("the workspace is empty: {:?}", options.empty_workspace));
+    if options.boundary == Boundary::Workspace {
+        return FocusPlan::default();
+    }
+    let command = match options.empty_workspace {
+        OnEdge::Nothing | OnEdge::Wrap => return FocusPlan::default(),
+        OnEdge::SwitchWorkspace => switch_workspace_command(dir),
+        OnEdge::FocusOutput => {
+            let crosses_output = ws
+                .output()
+                .is_some_and(|o| output_in_direction(tree, o.get_node(), dir));
+            trace.note(|| format!("crossing outputs: {}", crosses_output));
+            if !crosses_output || options.boundary == Boundary::Output {
+                return FocusPlan::default();
+            }
+            match options.cross_output {
+                CrossOutput::Deny => return FocusPlan::default(),
+                CrossOutput::Geometric => return plan_geometric_focus(tree, ws, dir, trace),
+                CrossOutput::Allow => match dir {
+                    Direction::Left => "focus output left",
+                    Direction::Right => "focus output right",
+                    Direction::Up => "focus output up",
+                    Direction::Down => "focus output down",
+                },
             }
         }
     };
+    FocusPlan {
+        commands: vec![command.to_string()],
+        target: None,
+    }
+}
+
+/// Is there another output adjacent to the given one in the specified direction.
+fn output_in_direction(tree: &TreeNode, output: &TreeNode, dir: Direction) -> bool {
+    let r = output.rect;
+    tree.nodes
+        .iter()
+        .filter(|o| o.node_type == NodeType::Output && o.id != output.id)
+        .map(|o| o.rect)
+        .filter(|o| o.width > 0 && o.height > 0)
+        .any(|o| match dir {
+            Direction::Left => o.x + o.width <= r.x && r.overlaps_y(&o),
+            Direction::Right => o.x >= r.x + r.width && r.overlaps_y(&o),
+            Direction::Up => o.y + o.height <= r.y && r.overlaps_x(&o),
+            Direction::Down => o.y >= r.y + r.height && r.overlaps_x(&o),
+        })
+}
+
+/// Is `other` entirely past the edge of `r` in the direction.
+fn beyond(r: &Rect, other: &Rect, dir: Direction) -> bool {
+    gap(r, other, dir) >= 0
+}
+
+/// The distance from the edge of `r` in the direction to the near edge of `other`.
+fn gap(r: &Rect, other: &Rect, dir: Direction) -> i32 {
+    match dir {
+        Direction::Left => r.x - (other.x + other.width),
+        Direction::Right => other.x - (r.x + r.width),
+        Direction::Up => r.y - (other.y + other.height),
+        Direction::Down => other.y - (r.y + r.height),
+    }
+}
+
+/// How far the rects overlap across the direction, e.g. vertically for left and right, negative
+/// when they do not overlap.
+fn overlap_across(a: &Rect, b: &Rect, dir: Direction) -> i32 {
+    match dir {
+        Direction::Left | Direction::Right => (a.y + a.height).min(b.y + b.height) - a.y.max(b.y),
+        Direction::Up | Direction::Down => (a.x + a.width).min(b.x + b.width) - a.x.max(b.x),
+    }
+}
+
+/// Plan moving from the container at the edge of its output to the adjacent output in the
+/// direction, see [CrossOutput::Geometric]. Focuses the output when the workspace shown on it has
+/// no tiled windows.
+fn plan_geometric_focus(
+    tree: &TreeNode,
+    c: &Cursor,
+    dir: Direction,
+    trace: &mut Trace,
+) -> FocusPlan {
+    let Some(from) = c.output().map(|o| o.get_node().rect) else {
+        return FocusPlan::default();
+    };
+    let r = c.get_node().rect;
+    let Some(output) = Cursor::new(tree)
+        .pre_order()
+        .prune(|o| o.get_node().node_type == NodeType::Output)
+        .filter(|o| {
+            let rect = o.get_node().rect;
+            o.get_node().node_type == NodeType::Output
+                && rect.width > 0
+                && rect.height > 0
+                && beyond(&from, &rect, dir)
+                && overlap_across(&from, &rect, dir) > 0
+        })
+        .max_by_key(|o| {
+            let rect = o.get_node().rect;
+            (overlap_across(&r, &rect, dir), -gap(&from, &rect, dir))
+        })
+    else {
+        trace.note(|| format!("no output {} of the focused one", dir));
+        return FocusPlan::default();
+    };
+    trace.note(|| format!("geometric target output: {}", describe(&output)));
 
+    let window = output.visible_child().and_then(|ws| {
+        ws.pre_order()
+            .filter(|w| {
+                let node = w.get_node();
+                node.node_type == NodeType::Con
+                    && node.nodes.is_empty()
+                    && !in_floating_layer(w)
+                    && w.is_visible()
+            })
+            .max_by_key(|w| {
+                let rect = w.get_node().rect;
+                (-gap(&r, &rect, dir), overlap_across(&r, &rect, dir))
+            })
+    });
+    match window {
+        Some(w) => {
+            trace.note(|| format!("nearest window to the edge entered: {}", describe(&w)));
+            let id = w.get_node().id;
+            FocusPlan {
+                commands: vec![format!("[con_id={}] focus", id)],
+                target: Some(id),
+            }
+        }
+        None => FocusPlan {
+            commands: vec![format!(
+                "focus output \"{}\"",
+                output.get_node().name.as_deref().unwrap_or_default()
+            )],
+            target: None,
+        },
+    }
+}
+
+/// Find the floating window of the workspace nearest to the tiled container in the given direction.
+/// Floating windows count when they extend past the container's edge in the direction, overlap it
+/// across the direction and have their center beyond the container's center.
+fn floating_in_direction<'a>(c: &Cursor<'a>, dir: Direction) -> Option<Cursor<'a>> {
+    let ws = c.workspace()?;
+    let r = c.get_node().rect;
+    let (cx, cy) = r.center();
+    let tiling = ws.get_node().nodes.len();
+    (0..ws.get_node().floating_nodes.len())
+        .filter_map(|idx| ws.clone().child(tiling + idx).ok())
+        .filter(|f| {
+            let o = f.get_node().rect;
+            let (ox, oy) = o.center();
+            match dir {
+                Direction::Left => o.x < r.x && ox < cx && r.overlaps_y(&o),
+                Direction::Right => o.x + o.width > r.x + r.width && ox > cx && r.overlaps_y(&o),
+                Direction::Up => o.y < r.y && oy < cy && r.overlaps_x(&o),
+                Direction::Down => o.y + o.height > r.y + r.height && oy > cy && r.overlaps_x(&o),
+            }
+        })
+        .min_by_key(|f| f.get_node().rect.distance_squared(&r))
+}
+
+/// Warp the mouse pointer to the center of the currently focused container.
+fn warp_cursor_to_focused(socket: &mut UnixStream) -> Result<()> {
+    let tree = get_tree_slim(socket)?;
+    if let Some(c) = find_focused_fast(&tree) {
+        let (x, y) = c.get_node().rect.center();
+        run_checked(socket, &format!("seat - cursor set {} {}", x, y))?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Axes,
+        tree::{cursor::find_focused, Rect},
+    };
+
+    fn workspace(layout: Layout, nodes: Vec<TreeNode>) -> TreeNode {
+        TreeNode {
+            node_type: NodeType::Workspace,
+            layout,
+            nodes,
+            ..Default::default()
+        }
+    }
+
+    fn view(id: i32) -> TreeNode {
+        TreeNode {
+            id,
+            node_type: NodeType::Con,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plan_focus_climbs_out_of_tabs() {
+        let tree = workspace(
+            Layout::SplitH,
+            vec![
+                view(1),
+                TreeNode {
+                    layout: Layout::Tabbed,
+                    nodes: vec![
+                        TreeNode {
+                            focused: true,
+                            ..view(2)
+                        },
+                        view(3),
+                    ],
+                    ..view(4)
+                },
+            ],
+        );
+
+        assert_eq!(
+            plan_focus(&tree, Direction::Left, &FocusConfig::default()),
+            FocusPlan {
+                commands: vec!["[con_id=1] focus".to_string()],
+                target: Some(1),
+            }
+        );
+        // Wrapping around the workspace is left to sway.
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &FocusConfig::default()),
+            FocusPlan {
+                commands: vec!["focus parent".to_string(), "focus right".to_string()],
+                target: None,
+            }
+        );
+        assert_eq!(
+            plan_focus(&view(5), Direction::Left, &FocusConfig::default()),
+            FocusPlan::default()
+        );
+
+        let (plan, lines) = explain_focus(&tree, Direction::Left, &FocusConfig::default());
+        assert_eq!(plan.target, Some(1));
+        assert_eq!(
+            lines,
+            [
+                "focused: #2 Con \"\" (None, 0 children)",
+                "climbing #4 Con \"\" (Tabbed, 2 children): skipped",
+                "climb stops at #0 Workspace \"\" (SplitH, 2 children): not a container",
+                "climbed 1 levels",
+                "resolved the target from #4 Con \"\" (Tabbed, 2 children): \
+                 #1 Con \"\" (None, 0 children)",
+                "predicted target: #1",
+                "commands: [con_id=1] focus",
+                "if sway rejects them: focus left",
+            ]
+        );
+    }
+
+    #[test]
+    fn focus_plan_fallbacks() {
+        let plan = |commands: &[&str], target| FocusPlan {
+            commands: commands.iter().map(|c| c.to_string()).collect(),
+            target,
+        };
+        assert_eq!(
+            plan(&["focus parent", "[con_id=5] focus"], Some(5)).fallbacks(Direction::Left),
+            [
+                plan(&["[con_id=5] focus"], Some(5)),
+                plan(&["focus left"], None)
+            ]
+        );
+        assert_eq!(
+            plan(&["[con_id=5] focus"], Some(5)).fallbacks(Direction::Up),
+            [plan(&["focus up"], None)]
+        );
+        assert!(plan(&["focus right"], None)
+            .fallbacks(Direction::Right)
+            .is_empty());
+        assert!(plan(&[], None).fallbacks(Direction::Right).is_empty());
+    }
+
+    #[test]
+    fn plan_focus_fullscreen_guard() {
+        let fullscreen = |mode| {
+            workspace(
+                Layout::SplitH,
+                vec![
+                    view(1),
+                    TreeNode {
+                        focused: true,
+                        fullscreen_mode: mode,
+                        ..view(2)
+                    },
+                ],
+            )
+        };
+        let guard = FocusConfig {
+            fullscreen_guard: true,
+            ..Default::default()
+        };
+        let tree = fullscreen(FullScreenMode::GlobalFullScreen);
+        assert_eq!(
+            plan_focus(&tree, Direction::Left, &guard),
+            FocusPlan::default()
+        );
+        let (_, lines) = explain_focus(&tree, Direction::Left, &guard);
+        assert_eq!(
+            lines,
+            [
+                "the focused window is fullscreen on all outputs, --force moves anyway",
+                "the focus stays where it is",
+                "commands: ",
+            ]
+        );
+        assert_eq!(
+            plan_focus(&tree, Direction::Left, &FocusConfig::default()).target,
+            Some(1)
+        );
+        // Fullscreen on the workspace only is not guarded.
+        let tree = fullscreen(FullScreenMode::FullWorkspace);
+        assert_eq!(plan_focus(&tree, Direction::Left, &guard).target, Some(1));
+    }
+
+    #[test]
+    fn plan_focus_skip_policy() {
+        let tree = workspace(
+            Layout::SplitH,
+            vec![
+                view(1),
+                TreeNode {
+                    layout: Layout::Stacked,
+                    nodes: vec![
+                        TreeNode {
+                            focused: true,
+                            ..view(2)
+                        },
+                        view(3),
+                    ],
+                    ..view(4)
+                },
+            ],
+        );
+        let skip_stacked = |axes| FocusConfig {
+            skip_stacked: axes,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            plan_focus(&tree, Direction::Down, &skip_stacked(Axes::Vertical)).commands,
+            ["focus parent", "focus down"]
+        );
+        assert_eq!(
+            plan_focus(&tree, Direction::Down, &skip_stacked(Axes::None)).commands,
+            ["[con_id=3] focus"]
+        );
+    }
+
+    #[test]
+    fn plan_focus_geometric_cross_output() {
+        let rect = |x, y, width, height| Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+        let output = |id, name: &str, r, nodes| TreeNode {
+            id,
+            name: Some(name.to_string()),
+            node_type: NodeType::Output,
+            rect: r,
+            nodes,
+            ..Default::default()
+        };
+        let window = |id, r, focused| TreeNode {
+            rect: r,
+            focused,
+            ..view(id)
+        };
+        // A large output on the left, two stacked on the right of which the lower one is empty.
+        let tree = |focused_y| TreeNode {
+            node_type: NodeType::Root,
+            nodes: vec![
+                output(
+                    1,
+                    "DP-1",
+                    rect(0, 0, 2560, 1440),
+                    vec![workspace(
+                        Layout::SplitV,
+                        vec![
+                            window(10, rect(0, 0, 2560, focused_y), false),
+                            window(11, rect(0, focused_y, 2560, 1440 - focused_y), true),
+                        ],
+                    )],
+                ),
+                output(
+                    2,
+                    "DP-2",
+                    rect(2560, 0, 1920, 1080),
+                    vec![workspace(
+                        Layout::SplitV,
+                        vec![
+                            window(20, rect(2560, 0, 1920, 540), false),
+                            window(21, rect(2560, 540, 1920, 540), false),
+                        ],
+                    )],
+                ),
+                output(
+                    3,
+                    "DP-3",
+                    rect(2560, 1080, 1920, 1080),
+                    vec![workspace(Layout::SplitH, Vec::new())],
+                ),
+            ],
+            ..Default::default()
+        };
+        let geometric = FocusConfig {
+            cross_output: CrossOutput::Geometric,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            plan_focus(&tree(700), Direction::Right, &geometric),
+            FocusPlan {
+                commands: vec!["[con_id=21] focus".to_string()],
+                target: Some(21),
+            }
+        );
+        assert_eq!(
+            plan_focus(&tree(900), Direction::Right, &geometric).commands,
+            ["focus output \"DP-3\""]
+        );
+        assert_eq!(
+            plan_focus(&tree(700), Direction::Right, &FocusConfig::default()).commands,
+            ["focus right"]
+        );
+    }
+
+    #[test]
+    fn plan_focus_into_aligned_window() {
+        let at = |id, x, y, focused| TreeNode {
+            focused,
+            rect: Rect {
+                x,
+                y,
+                width: 100,
+                height: 100,
+            },
+            ..view(id)
+        };
+        let column = |id, x, focus, nodes| TreeNode {
+            layout: Layout::SplitV,
+            focus,
+            rect: Rect {
+                x,
+                y: 0,
+                width: 100,
+                height: 200,
+            },
+            nodes,
+            ..view(id)
+        };
+        let tree = workspace(
+            Layout::SplitH,
+            vec![
+                column(
+                    5,
+                    0,
+                    vec![6, 1],
+                    vec![at(1, 0, 0, false), at(6, 0, 100, true)],
+                ),
+                column(
+                    4,
+                    100,
+                    vec![2, 3],
+                    vec![at(2, 100, 0, false), at(3, 100, 100, false)],
+                ),
+            ],
+        );
+        let options = FocusConfig::default();
+
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &options).target,
+            Some(2)
+        );
+        assert_eq!(
+            plan_focus_into(&tree, Direction::Right, &options),
+            FocusPlan {
+                commands: vec!["[con_id=3] focus".to_string()],
+                target: Some(3),
+            }
+        );
+        // Moves within a column are not affected.
+        assert_eq!(
+            plan_focus_into(&tree, Direction::Up, &options).target,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn plan_focus_on_edge() {
+        let at = |id, x, focused| TreeNode {
+            focused,
+            rect: Rect {
+                x,
+                y: 0,
+                width: 100,
+                height: 100,
+            },
+            ..view(id)
+        };
+        let tree = workspace(
+            Layout::SplitH,
+            vec![at(1, 0, false), at(2, 100, false), at(3, 200, true)],
+        );
+        let on_edge = |on_edge| FocusConfig {
+            on_edge: Some(on_edge),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &on_edge(OnEdge::Wrap)).target,
+            Some(1)
+        );
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &on_edge(OnEdge::SwitchWorkspace)).commands,
+            ["workspace next_on_output"]
+        );
+        // There is no output in any direction.
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &on_edge(OnEdge::FocusOutput)),
+            FocusPlan::default()
+        );
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &on_edge(OnEdge::Nothing)),
+            FocusPlan::default()
+        );
+        // Away from the edge the policy does not matter.
+        assert_eq!(
+            plan_focus(&tree, Direction::Left, &on_edge(OnEdge::Nothing)).target,
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn plan_focus_into_visible_tab() {
+        let tabbed = |id, focus, nodes| TreeNode {
+            layout: Layout::Tabbed,
+            focus,
+            nodes,
+            ..view(id)
+        };
+        let focused = TreeNode {
+            focused: true,
+            ..view(5)
+        };
+        let target = |focus| {
+            let tree = workspace(
+                Layout::SplitH,
+                vec![
+                    tabbed(1, focus, vec![view(2), view(3)]),
+                    tabbed(4, vec![5], vec![focused.clone(), view(6)]),
+                ],
+            );
+            plan_focus(&tree, Direction::Left, &FocusConfig::default()).target
+        };
+
+        assert_eq!(target(vec![3, 2]), Some(3));
+        assert_eq!(target(Vec::new()), Some(2));
+    }
+
+    #[test]
+    fn plan_toggle_layer_nearest_and_back() {
+        let at = |id, x, focused| TreeNode {
+            focused,
+            rect: Rect {
+                x,
+                y: 0,
+                width: 10,
+                height: 10,
+            },
+            ..view(id)
+        };
+        let floating = |id, x, focused| TreeNode {
+            node_type: NodeType::FloatingCon,
+            ..at(id, x, focused)
+        };
+        let tree = |tiled_focused: bool, marks: Vec<String>| TreeNode {
+            id: 9,
+            floating_nodes: vec![floating(2, 500, false), floating(3, 50, !tiled_focused)],
+            focus: vec![4],
+            ..workspace(
+                Layout::SplitH,
+                vec![
+                    at(4, 100, false),
+                    TreeNode {
+                        marks,
+                        ..at(1, 0, tiled_focused)
+                    },
+                ],
+            )
+        };
+
+        assert_eq!(
+            plan_toggle_layer(&tree(true, Vec::new())).commands,
+            [
+                "[con_id=1] mark --add _haswaynav_tiled_9",
+                "[con_id=3] focus"
+            ]
+        );
+        let back = vec!["_haswaynav_tiled_9".to_string()];
+        assert_eq!(plan_toggle_layer(&tree(false, back)).target, Some(1));
+        assert_eq!(plan_toggle_layer(&tree(false, Vec::new())).target, Some(4));
+    }
+
+    #[test]
+    fn plan_focus_enters_floating() {
+        let at = |id, x| TreeNode {
+            rect: Rect {
+                x,
+                y: 0,
+                width: 100,
+                height: 100,
+            },
+            ..view(id)
+        };
+        let tree = |floating_x| TreeNode {
+            floating_nodes: vec![TreeNode {
+                node_type: NodeType::FloatingCon,
+                ..at(3, floating_x)
+            }],
+            ..workspace(
+                Layout::SplitH,
+                vec![
+                    TreeNode {
+                        focused: true,
+                        ..at(1, 0)
+                    },
+                    at(4, 200),
+                ],
+            )
+        };
+        let enter = FocusConfig {
+            enter_floating: true,
+            ..Default::default()
+        };
+
+        let target =
+            |x, options: &FocusConfig| plan_focus(&tree(x), Direction::Right, options).target;
+        assert_eq!(target(60, &enter), Some(3));
+        assert_eq!(target(60, &FocusConfig::default()), Some(4));
+        assert_eq!(target(400, &enter), Some(4));
+        assert_eq!(plan_focus(&tree(60), Direction::Left, &enter).target, None);
+    }
+
+    #[test]
+    fn climb_edge_cases() {
+        let focused = |id| TreeNode {
+            focused: true,
+            ..view(id)
+        };
+        let container = |layout, nodes| TreeNode {
+            layout,
+            nodes,
+            ..view(10)
+        };
+        let climbed = |tree: &TreeNode| {
+            let c = find_focused(tree).unwrap();
+            climb(
+                &c,
+                Direction::Left,
+                &FocusConfig::default(),
+                &mut Trace::default(),
+            )
+            .len()
+        };
+
+        // A tabbed container with a single tab has no siblings to skip.
+        let tree = workspace(
+            Layout::SplitH,
+            vec![view(1), container(Layout::Tabbed, vec![focused(2)])],
+        );
+        assert_eq!(climbed(&tree), 0);
+
+        // A split container with a single child does not stop the climb out of the tabs around it.
+        let tree = workspace(
+            Layout::SplitH,
+            vec![
+                view(1),
+                container(
+                    Layout::Tabbed,
+                    vec![container(Layout::SplitV, vec![focused(2)]), view(3)],
+                ),
+            ],
+        );
+        assert_eq!(climbed(&tree), 2);
+
+        // The tabs of a tabbed workspace are moved between.
+        let tree = workspace(Layout::Tabbed, vec![focused(2), view(3)]);
+        assert_eq!(climbed(&tree), 0);
+        assert_eq!(
+            plan_focus(&tree, Direction::Right, &FocusConfig::default()).commands,
+            ["[con_id=3] focus"]
+        );
+    }
+
+    #[test]
+    fn workspace_edge() {
+        let tree = workspace(
+            Layout::SplitH,
+            vec![
+                view(1),
+                TreeNode {
+                    layout: Layout::SplitV,
+                    nodes: vec![view(2), view(3)],
+                    ..view(4)
+                },
+            ],
+        );
+        let find = |id| tree.into_iter().find(|c| c.get_node().id == id).unwrap();
+
+        assert!(at_workspace_edge(&find(1), Direction::Left));
+        assert!(!at_workspace_edge(&find(1), Direction::Right));
+        assert!(at_workspace_edge(&find(2), Direction::Right));
+        assert!(at_workspace_edge(&find(2), Direction::Up));
+        assert!(!at_workspace_edge(&find(2), Direction::Down));
+        assert!(!at_workspace_edge(&find(3), Direction::Left));
+    }
+
+    #[test]
+    fn adjacent_output() {
+        let output = |id, x| TreeNode {
+            id,
+            node_type: NodeType::Output,
+            rect: Rect {
+                x,
+                y: 0,
+                width: 100,
+                height: 100,
+            },
+            ..Default::default()
+        };
+        let tree = TreeNode {
+            nodes: vec![output(1, 0), output(2, 100)],
+            ..Default::default()
+        };
+
+        assert!(output_in_direction(&tree, &tree.nodes[0], Direction::Right));
+        assert!(!output_in_direction(&tree, &tree.nodes[0], Direction::Left));
+        assert!(output_in_direction(&tree, &tree.nodes[1], Direction::Left));
+        assert!(!output_in_direction(&tree, &tree.nodes[1], Direction::Down));
+    }
+
+    #[test]
+    fn boundary() {
+        let output = |id, x, nodes| TreeNode {
+            id,
+            node_type: NodeType::Output,
+            layout: Layout::Output,
+            rect: Rect {
+                x,
+                y: 0,
+                width: 100,
+                height: 100,
+            },
+            nodes,
+            ..Default::default()
+        };
+        let focused = TreeNode {
+            focused: true,
+            ..view(1)
+        };
+        let tree = TreeNode {
+            nodes: vec![
+                output(2, 0, vec![workspace(Layout::SplitH, vec![focused])]),
+                output(3, 100, Vec::new()),
+            ],
+            ..Default::default()
+        };
+        let moves = |dir, boundary| {
+            let options = FocusConfig {
+                boundary,
+                ..Default::default()
+            };
+            !plan_focus(&tree, dir, &options).commands.is_empty()
+        };
+
+        assert!(moves(Direction::Right, Boundary::None));
+        assert!(!moves(Direction::Right, Boundary::Output));
+        assert!(moves(Direction::Left, Boundary::Output));
+        assert!(!moves(Direction::Left, Boundary::Workspace));
+
+        // Moving between the entries of a stack at the bottom of the workspace does not reach the
+        // boundary.
+        let tree = workspace(
+            Layout::SplitV,
+            vec![
+                view(1),
+                TreeNode {
+                    layout: Layout::Stacked,
+                    nodes: vec![
+                        TreeNode {
+                            focused: true,
+                            ..view(2)
+                        },
+                        view(3),
+                    ],
+                    ..view(4)
+                },
+            ],
+        );
+        let options = FocusConfig {
+            boundary: Boundary::Workspace,
+            skip_stacked: Axes::None,
+            ..Default::default()
+        };
+        assert_eq!(plan_focus(&tree, Direction::Down, &options).target, Some(3));
+        assert_eq!(plan_focus(&tree, Direction::Up, &options).target, Some(1));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn plans_for_generated_trees(
+            tree in crate::tree::strategy::tree(),
+            dir in proptest::sample::select(
+                vec![Direction::Left, Direction::Right, Direction::Up, Direction::Down]
+            ),
+            skip_floating: bool,
+            enter_floating: bool,
+            wrap: bool,
+        ) {
+            let options = FocusConfig {
+                skip_floating,
+                enter_floating,
+                wrap,
+                ..Default::default()
+            };
+            let focused = find_focused(&tree).map(|c| c.get_node().id);
+            let is_window = |id| {
+                tree.into_iter()
+                    .any(|c| c.get_node().id == id && c.get_node().nodes.is_empty())
+            };
+
+            let plan = plan_focus(&tree, dir, &options);
+            if let Some(target) = plan.target {
+                assert!(is_window(target) && Some(target) != focused);
+                assert!(!plan.commands.is_empty());
+            }
+            if let Some(target) = plan_focus_into(&tree, dir, &options).target {
+                assert!(is_window(target) && Some(target) != focused);
+            }
+
+            let plan = plan_toggle_layer(&tree);
+            if let Some(target) = plan.target {
+                assert!(is_window(target) && Some(target) != focused);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_directions() {
+        let parse = |value| Direction::parse(value, false).map_err(|e| e.to_string());
+        let strict = |value| Direction::parse(value, true).map_err(|e| e.to_string());
+        for value in ["right", "l", "Right", "KP_Right", "KEY_RIGHT"] {
+            assert_eq!(strict(value), Ok(Direction::Right));
+        }
+        assert_eq!(parse("j"), Ok(Direction::Down));
+        assert_eq!(parse("RIGHT"), Ok(Direction::Right));
+        assert_eq!(parse("do"), Ok(Direction::Down));
+        assert_eq!(
+            strict("do"),
+            Err("ambiguous direction 'do', did you mean 'down'?".to_string())
+        );
+        assert_eq!(
+            parse("rigth"),
+            Err("invalid direction 'rigth', did you mean 'right'?".to_string())
+        );
+        assert_eq!(
+            parse("sideways"),
+            Err("invalid direction 'sideways', expected left, right, up or down".to_string())
+        );
+    }
+}
@@ -1,83 +1,290 @@
 //! All the currently supported messages which can be sent to sway over its domain socket.
 
-use std::os::unix::net::UnixStream;
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
 
-use crate::tree::{CommandResult, TreeNode};
+use crate::capture::{self, FrameDirection};
+use crate::tree::{
+    BindingStateReply, CommandResult, ConfigReply, Event, InputReply, OutputReply, SubscribeResult,
+    TreeNode, TreeNodeSlim, VersionReply, WindowChange, WorkspaceReply,
+};
 use anyhow::{Context, Result};
 use serde::de::DeserializeOwned;
-use std::io::Read;
-use std::io::Write;
 
 const MAGIC_BYTES: [u8; 6] = *(b"i3-ipc");
 
+/// The length of the header preceding the payload of every frame: the magic bytes, the payload
+/// length and the payload type.
+const HEADER_LEN: usize = 14;
+
+/// The largest payload accepted, so that the length announced by a buggy or hostile peer cannot
+/// make us allocate gigabytes. The trees of busy sessions stay well below a megabyte.
+pub const MAX_PAYLOAD_LEN: usize = 64 * 1024 * 1024;
+
 #[derive(Copy, Clone)]
 /// The identifier for the sway message being sent via IPC
-enum MessageType {
+pub(crate) enum MessageType {
     RunCommand = 0,
+    GetWorkspaces = 1,
+    Subscribe = 2,
+    GetOutputs = 3,
     GetTree = 4,
+    GetMarks = 5,
+    GetVersion = 7,
+    GetConfig = 9,
+    GetBindingState = 12,
+    GetInputs = 100,
+    /// Only asked for by [crate::snapshot]
+    #[cfg(feature = "cli")]
+    GetSeats = 101,
 }
 
-/// Send a message over the IPC socket to sway
-fn send_message(sock: &mut UnixStream, message_type: MessageType, payload: &[u8]) -> Result<()> {
-    sock.write_all(&MAGIC_BYTES)?;
+/// The bit set in the type of a message to mark it as an event rather than a reply.
+const EVENT_BIT: u32 = 0x80000000;
 
-    let payload_length: i32 = payload.len().try_into()?;
-    sock.write_all(&(payload_length).to_ne_bytes())?;
+#[derive(Debug)]
+/// Reading a frame from sway failed part way through, see [read_frame].
+pub struct ProtocolError {
+    /// The part of the frame being read, e.g. "frame header"
+    pub what: &'static str,
+    /// The bytes of the frame read before failing
+    pub read: Vec<u8>,
+    /// The error of the failed read
+    pub source: std::io::Error,
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "failed reading the {} after {} bytes of the frame: {}",
+            self.what,
+            self.read.len(),
+            self.source
+        )
+    }
+}
+
+impl std::error::Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Should the IO be retried after the error: reads and writes interrupted by a signal are, as are
+/// the ones which would block on a non-blocking socket. Those switch the socket to blocking for the
+/// rest of the transfer rather than spinning on it, `blocked` records that so it can be switched
+/// back. With a timeout set on the socket blocking means the timeout passed, which is an error.
+fn retry(
+    sock: &UnixStream,
+    err: &std::io::Error,
+    timeout: Option<Duration>,
+    blocked: &mut bool,
+) -> bool {
+    match err.kind() {
+        ErrorKind::Interrupted => true,
+        ErrorKind::WouldBlock if timeout.is_none() && !*blocked => {
+            *blocked = sock.set_nonblocking(false).is_ok();
+            *blocked
+        }
+        _ => false,
+    }
+}
+
+/// Fill the buffer from the socket however many reads it takes, on failure returning the number of
+/// bytes read along with the error.
+fn read_full(sock: &mut UnixStream, buf: &mut [u8]) -> Result<(), (usize, std::io::Error)> {
+    let timeout = sock.read_timeout().map_err(|err| (0, err))?;
+    let mut blocked = false;
+    let mut read = 0;
+    let result = loop {
+        if read == buf.len() {
+            break Ok(());
+        }
+        match sock.read(&mut buf[read..]) {
+            Ok(0) => break Err((read, ErrorKind::UnexpectedEof.into())),
+            Ok(n) => read += n,
+            Err(err) if retry(sock, &err, timeout, &mut blocked) => (),
+            Err(err) => break Err((read, err)),
+        }
+    };
+    if blocked {
+        sock.set_nonblocking(true).map_err(|err| (read, err))?;
+    }
+    result
+}
 
-    sock.write_all(&(message_type as i32).to_ne_bytes())?;
+/// Write all of the buffer to the socket however many writes it takes.
+fn write_full(sock: &mut UnixStream, buf: &[u8]) -> std::io::Result<()> {
+    let timeout = sock.write_timeout()?;
+    let mut blocked = false;
+    let mut written = 0;
+    let result = loop {
+        if written == buf.len() {
+            break Ok(());
+        }
+        match sock.write(&buf[written..]) {
+            Ok(0) => break Err(ErrorKind::WriteZero.into()),
+            Ok(n) => written += n,
+            Err(err) if retry(sock, &err, timeout, &mut blocked) => (),
+            Err(err) => break Err(err),
+        }
+    };
+    if blocked {
+        sock.set_nonblocking(true)?;
+    }
+    result
+}
 
-    sock.write_all(payload)?;
+/// Write a single frame, the header followed by the payload, to the IPC socket.
+pub(crate) fn write_frame(sock: &mut UnixStream, payload_type: u32, payload: &[u8]) -> Result<()> {
+    let payload_length: i32 = payload.len().try_into()?;
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(&MAGIC_BYTES);
+    frame.extend_from_slice(&payload_length.to_ne_bytes());
+    frame.extend_from_slice(&payload_type.to_ne_bytes());
+    frame.extend_from_slice(payload);
 
-    sock.flush()?;
+    write_full(sock, &frame).context("writing frame")?;
 
     Ok(())
 }
 
-/// Receive a response over the IPC socket from sway after sending a message
-fn receive_message<T: DeserializeOwned>(
-    sock: &mut UnixStream,
-    message_type: MessageType,
-) -> Result<T> {
-    let mut magic_bytes: [u8; 6] = *(b"000000");
-    sock.read_exact(&mut magic_bytes)
-        .context("reading magic bytes")?;
+/// Parse the header of a frame returning the payload type and length.
+fn parse_header(header: &[u8; HEADER_LEN]) -> Result<(u32, usize)> {
+    let (magic_bytes, rest) = header.split_at(MAGIC_BYTES.len());
     if magic_bytes != MAGIC_BYTES {
         anyhow::bail!(
             "expected {:?} as magic bytes but got {:?}",
             &MAGIC_BYTES,
-            &magic_bytes
+            magic_bytes
         );
     }
+    let (length, payload_type) = rest.split_at(4);
+    let length = i32::from_ne_bytes(length.try_into().expect("4 bytes of length"));
+    let length = usize::try_from(length)
+        .map_err(|_| anyhow::format_err!("negative payload length {}", length))?;
+    if length > MAX_PAYLOAD_LEN {
+        anyhow::bail!(
+            "payload length {} exceeds the limit of {} bytes",
+            length,
+            MAX_PAYLOAD_LEN
+        );
+    }
+    let payload_type = u32::from_ne_bytes(payload_type.try_into().expect("4 bytes of type"));
+    Ok((payload_type, length))
+}
 
-    let payload_length = {
-        let mut payload_length_bytes = 0_i32.to_ne_bytes();
-        sock.read_exact(&mut payload_length_bytes)
-            .context("reading payload length")?;
-        i32::from_ne_bytes(payload_length_bytes)
-    };
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// A frame parsed from a buffer by [parse_frame].
+pub struct ParsedFrame<'a> {
+    /// The message type, with the high bit set for events
+    pub payload_type: u32,
+    /// The payload, JSON when sent by sway
+    pub payload: &'a [u8],
+    /// The number of bytes of the buffer taken up by the frame
+    pub len: usize,
+}
 
-    let payload_type = {
-        let mut bytes = 0_i32.to_ne_bytes();
-        sock.read_exact(&mut bytes).context("payload type")?;
-        i32::from_ne_bytes(bytes)
+/// Parse the frame at the start of the bytes without doing any IO, `None` when the bytes end before
+/// the frame does. Fails on anything which is not the start of a frame sway could send.
+pub fn parse_frame(bytes: &[u8]) -> Result<Option<ParsedFrame<'_>>> {
+    let Some(header) = bytes.first_chunk::<HEADER_LEN>() else {
+        // Waiting for more bytes only makes sense when what is there could still start a frame.
+        let prefix = &bytes[..bytes.len().min(MAGIC_BYTES.len())];
+        if !MAGIC_BYTES.starts_with(prefix) {
+            anyhow::bail!(
+                "expected {:?} as magic bytes but got {:?}",
+                &MAGIC_BYTES,
+                prefix
+            );
+        }
+        return Ok(None);
     };
-    if payload_type != message_type as i32 {
+    let (payload_type, length) = parse_header(header)?;
+    Ok(bytes
+        .get(HEADER_LEN..HEADER_LEN + length)
+        .map(|payload| ParsedFrame {
+            payload_type,
+            payload,
+            len: HEADER_LEN + length,
+        }))
+}
+
+/// Read a single frame from the IPC socket returning its type and raw payload. When the socket
+/// fails part way through the frame the error is a [ProtocolError].
+pub(crate) fn read_frame(sock: &mut UnixStream) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0; HEADER_LEN];
+    read_full(sock, &mut header).map_err(|(read, source)| ProtocolError {
+        what: "frame header",
+        read: header[..read].to_vec(),
+        source,
+    })?;
+    let (payload_type, payload_length) = parse_header(&header)?;
+
+    let mut payload = vec![0; payload_length];
+    read_full(sock, &mut payload).map_err(|(read, source)| ProtocolError {
+        what: "payload",
+        read: [&header[..], &payload[..read]].concat(),
+        source,
+    })?;
+
+    Ok((payload_type, payload))
+}
+
+/// Send a message over the IPC socket to sway
+fn send_message(sock: &mut UnixStream, message_type: MessageType, payload: &[u8]) -> Result<()> {
+    write_frame(sock, message_type as u32, payload)?;
+    capture::record(FrameDirection::Sent, message_type as u32, payload);
+    Ok(())
+}
+
+/// Receive a single message over the IPC socket from sway returning its type and raw payload.
+fn receive_frame(sock: &mut UnixStream) -> Result<(u32, Vec<u8>)> {
+    let (payload_type, payload) = read_frame(sock)?;
+    capture::record(FrameDirection::Received, payload_type, &payload);
+    Ok((payload_type, payload))
+}
+
+/// Receive the raw payload of the response over the IPC socket from sway after sending a message
+fn receive_payload(sock: &mut UnixStream, message_type: MessageType) -> Result<Vec<u8>> {
+    let (payload_type, payload_json) = receive_frame(sock)?;
+    if payload_type != message_type as u32 {
         anyhow::bail!(
             "Wrong payload type specifier, expected {} but got {}",
-            message_type as i32,
+            message_type as u32,
             payload_type
         );
     };
+    Ok(payload_json)
+}
 
-    let payload_json: Vec<u8> = {
-        let mut payload = vec![0; payload_length as usize];
-        sock.read_exact(&mut payload).context("reading payload")?;
-        payload
-    };
+/// Receive a response over the IPC socket from sway after sending a message
+fn receive_message<T: DeserializeOwned>(
+    sock: &mut UnixStream,
+    message_type: MessageType,
+) -> Result<T> {
+    let payload_json = receive_payload(sock, message_type)?;
 
-    let payload = serde_json::from_slice(&payload_json).context("decoding payload")?;
+    decode(&payload_json).context("decoding payload")
+}
 
-    Ok(payload)
+/// Decode a payload from sway. Sway passes on titles as the applications set them, which need not
+/// be valid UTF-8, so invalid sequences are replaced rather than failing the whole payload.
+fn decode<T: DeserializeOwned>(payload_json: &[u8]) -> Result<T> {
+    match serde_json::from_slice(payload_json) {
+        Ok(payload) => Ok(payload),
+        Err(err) if std::str::from_utf8(payload_json).is_err() => {
+            tracing::debug!("replacing invalid UTF-8 after: {}", err);
+            Ok(serde_json::from_str(&String::from_utf8_lossy(
+                payload_json,
+            ))?)
+        }
+        Err(err) => Err(err.into()),
+    }
 }
 
 /// Send a message to sway over the IPC socket and then receive its response to the message.
@@ -91,12 +298,374 @@ fn message<T: DeserializeOwned>(
 }
 
 /// Get the node layout tree by sending a `GET_TREE` message to sway over the IPC socket.
+#[tracing::instrument(skip_all)]
 pub fn get_tree(sock: &mut UnixStream) -> Result<TreeNode> {
     message(sock, MessageType::GetTree, &[])
 }
 
+/// Get the node layout tree like [get_tree] but only decode the fields needed for navigation, see
+/// [TreeNodeSlim].
+#[tracing::instrument(skip_all)]
+pub fn get_tree_slim(sock: &mut UnixStream) -> Result<TreeNode> {
+    let slim: TreeNodeSlim = message(sock, MessageType::GetTree, &[])?;
+    Ok(slim.into())
+}
+
+/// Get the node layout tree as the raw JSON sent by sway, leaving decoding to the caller.
+pub fn get_tree_json(sock: &mut UnixStream) -> Result<Vec<u8>> {
+    get_json(sock, MessageType::GetTree)
+}
+
+/// Send a message without a payload and return the raw JSON of the reply.
+pub(crate) fn get_json(sock: &mut UnixStream, message_type: MessageType) -> Result<Vec<u8>> {
+    send_message(sock, message_type, &[])?;
+    receive_payload(sock, message_type)
+}
+
+/// Send all the messages before reading any of the replies, which sway sends in the same order.
+/// Saves a round trip to sway for every message but the first compared to sending them one by one.
+fn pipeline(sock: &mut UnixStream, messages: &[(MessageType, &[u8])]) -> Result<Vec<Vec<u8>>> {
+    for (message_type, payload) in messages {
+        send_message(sock, *message_type, payload)?;
+    }
+    messages
+        .iter()
+        .map(|(message_type, _)| receive_payload(sock, *message_type))
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+/// The tree, the workspaces and the outputs fetched together, see [get_snapshot].
+pub struct Snapshot {
+    pub tree: TreeNode,
+    pub workspaces: Vec<WorkspaceReply>,
+    pub outputs: Vec<OutputReply>,
+}
+
+/// Get the tree, the workspaces and the outputs sending the `GET_TREE`, `GET_WORKSPACES` and
+/// `GET_OUTPUTS` messages at once rather than waiting for each reply before sending the next.
+#[tracing::instrument(skip_all)]
+pub fn get_snapshot(sock: &mut UnixStream) -> Result<Snapshot> {
+    let replies = pipeline(
+        sock,
+        &[
+            (MessageType::GetTree, &[]),
+            (MessageType::GetWorkspaces, &[]),
+            (MessageType::GetOutputs, &[]),
+        ],
+    )?;
+    let [tree, workspaces, outputs] = <[Vec<u8>; 3]>::try_from(replies).expect("three replies");
+    Ok(Snapshot {
+        tree: serde_json::from_slice(&tree).context("decoding tree")?,
+        workspaces: serde_json::from_slice(&workspaces).context("decoding workspaces")?,
+        outputs: serde_json::from_slice(&outputs).context("decoding outputs")?,
+    })
+}
+
+/// Get the list of workspaces by sending a `GET_WORKSPACES` message to sway over the IPC socket.
+pub fn get_workspaces(sock: &mut UnixStream) -> Result<Vec<WorkspaceReply>> {
+    message(sock, MessageType::GetWorkspaces, &[])
+}
+
+/// Get the list of outputs by sending a `GET_OUTPUTS` message to sway over the IPC socket.
+pub fn get_outputs(sock: &mut UnixStream) -> Result<Vec<OutputReply>> {
+    message(sock, MessageType::GetOutputs, &[])
+}
+
+/// Get the input devices by sending a `GET_INPUTS` message to sway over the IPC socket.
+pub fn get_inputs(sock: &mut UnixStream) -> Result<Vec<InputReply>> {
+    message(sock, MessageType::GetInputs, &[])
+}
+
+/// Get the names of all marks currently set by sending a `GET_MARKS` message to sway over the IPC
+/// socket.
+pub fn get_marks(sock: &mut UnixStream) -> Result<Vec<String>> {
+    message(sock, MessageType::GetMarks, &[])
+}
+
+/// Get the version of sway by sending a `GET_VERSION` message to sway over the IPC socket.
+pub fn get_version(sock: &mut UnixStream) -> Result<VersionReply> {
+    message(sock, MessageType::GetVersion, &[])
+}
+
+/// Get the contents of the last loaded config file by sending a `GET_CONFIG` message to sway over
+/// the IPC socket.
+pub fn get_config(sock: &mut UnixStream) -> Result<ConfigReply> {
+    message(sock, MessageType::GetConfig, &[])
+}
+
+/// Get the active binding mode by sending a `GET_BINDING_STATE` message to sway over the IPC
+/// socket.
+pub fn get_binding_state(sock: &mut UnixStream) -> Result<BindingStateReply> {
+    message(sock, MessageType::GetBindingState, &[])
+}
+
 /// Run the supplied string as sway commands by sending the `RUN_COMMAND` message to sway over the
 /// IPC socket.
+#[tracing::instrument(skip(sock))]
 pub fn run_command(sock: &mut UnixStream, commands: &str) -> Result<Vec<CommandResult>> {
     message(sock, MessageType::RunCommand, commands.as_bytes())
 }
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// A sway command which failed, found by matching the [CommandResult]s to the commands sent, see
+/// [check_results]. Sway reports which of the commands joined with `;` or `,` failed only by the
+/// position of its result.
+pub struct CommandError {
+    /// The position of the command among the ones sent, counting from 0
+    pub index: usize,
+    /// The text of the command, without the criteria it shares with the commands before it
+    pub command: String,
+    /// Whether sway could not parse the command, rather than failing to run it
+    pub parse_error: bool,
+    /// The error sway gave
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = if self.parse_error {
+            "could not be parsed"
+        } else {
+            "failed"
+        };
+        write!(f, "command {} '{}' {}", self.index + 1, self.command, what)?;
+        match &self.error {
+            Some(error) => write!(f, ": {}", error),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Split the commands sent in one `RUN_COMMAND` message the way sway does, at the `;` and `,`
+/// outside of quotes and criteria, so they line up with the results.
+pub fn split_commands(commands: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut criteria = false;
+    let mut escaped = false;
+    for (idx, c) in commands.char_indices() {
+        match (c, quote) {
+            _ if escaped => escaped = false,
+            ('\\', _) => escaped = true,
+            (c, Some(q)) if c == q => quote = None,
+            (_, Some(_)) => (),
+            ('"' | '\'', None) => quote = Some(c),
+            ('[', None) => criteria = true,
+            (']', None) => criteria = false,
+            (';' | ',', None) if !criteria => {
+                parts.push(&commands[start..idx]);
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    parts.push(&commands[start..]);
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Check the results of running the commands, failing with a [CommandError] naming the first
+/// command which failed.
+pub fn check_results(commands: &str, results: &[CommandResult]) -> Result<(), CommandError> {
+    let Some(index) = results.iter().position(|r| !r.success) else {
+        return Ok(());
+    };
+    let result = &results[index];
+    let command = split_commands(commands)
+        .get(index)
+        .map_or_else(|| commands.to_string(), |c| c.to_string());
+    Err(CommandError {
+        index,
+        command,
+        parse_error: result.parse_error.unwrap_or_default(),
+        error: result.error.clone(),
+    })
+}
+
+/// Subscribe to the given events, e.g. `["window"]`, by sending the `SUBSCRIBE` message to sway over
+/// the IPC socket. Afterwards the events can be received with [receive_event].
+pub fn subscribe(sock: &mut UnixStream, events: &[&str]) -> Result<()> {
+    let payload = serde_json::to_vec(events)?;
+    let reply: SubscribeResult = message(sock, MessageType::Subscribe, &payload)?;
+    if !reply.success {
+        anyhow::bail!("Sway refused the subscription to {:?}", events);
+    }
+    Ok(())
+}
+
+/// Wait for the next event on a socket which was subscribed to events with [subscribe].
+pub fn receive_event(sock: &mut UnixStream) -> Result<Event> {
+    let (payload_type, payload_json) = receive_frame(sock)?;
+    if payload_type & EVENT_BIT == 0 {
+        anyhow::bail!("Expected an event but got a reply of type {}", payload_type);
+    }
+    let event = match payload_type & !EVENT_BIT {
+        0 => Event::Workspace(decode(&payload_json).context("decoding workspace event")?),
+        3 => Event::Window(decode(&payload_json).context("decoding window event")?),
+        5 => Event::Binding(decode(&payload_json).context("decoding binding event")?),
+        21 => Event::Input(decode(&payload_json).context("decoding input event")?),
+        other => Event::Other(other),
+    };
+    Ok(event)
+}
+
+/// Wait until the deadline for the next window to be created, on a socket subscribed to window
+/// events. Returns `None` once the deadline passed.
+pub fn receive_new_window(sock: &mut UnixStream, deadline: Instant) -> Result<Option<TreeNode>> {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        sock.set_read_timeout(Some(remaining))?;
+        match receive_event(sock) {
+            Ok(Event::Window(event)) if event.change == WindowChange::New => {
+                return Ok(Some(event.container))
+            }
+            Ok(_) => (),
+            Err(_) if Instant::now() >= deadline => return Ok(None),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[test]
+fn test_decode_invalid_utf8() {
+    let payload = b"{\"name\": \"caf\xe9\"}";
+    let decoded: serde_json::Value = decode(payload).unwrap();
+    assert_eq!(decoded["name"], "caf\u{fffd}");
+    assert!(decode::<serde_json::Value>(b"{").is_err());
+}
+
+#[test]
+fn test_parse_frame() {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&MAGIC_BYTES);
+    bytes.extend_from_slice(&2_i32.to_ne_bytes());
+    bytes.extend_from_slice(&4_u32.to_ne_bytes());
+    bytes.extend_from_slice(b"{}trailing");
+
+    assert_eq!(
+        parse_frame(&bytes).unwrap(),
+        Some(ParsedFrame {
+            payload_type: 4,
+            payload: b"{}",
+            len: HEADER_LEN + 2,
+        })
+    );
+    assert_eq!(parse_frame(&bytes[..HEADER_LEN + 1]).unwrap(), None);
+    assert_eq!(parse_frame(&bytes[..3]).unwrap(), None);
+
+    bytes[6..10].copy_from_slice(&(-1_i32).to_ne_bytes());
+    assert!(parse_frame(&bytes).is_err());
+    bytes[6..10].copy_from_slice(&i32::MAX.to_ne_bytes());
+    assert!(parse_frame(&bytes).is_err());
+    bytes[0] = b'x';
+    assert!(parse_frame(&bytes).is_err());
+    assert!(parse_frame(&bytes[..3]).is_err());
+    assert!(parse_frame(&bytes[..HEADER_LEN - 1]).is_err());
+}
+
+#[test]
+fn test_read_frame_in_pieces() {
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let writer = std::thread::spawn(move || {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&MAGIC_BYTES);
+        frame.extend_from_slice(&4_i32.to_ne_bytes());
+        frame.extend_from_slice(&7_u32.to_ne_bytes());
+        frame.extend_from_slice(b"{}");
+        for piece in frame.chunks(5) {
+            server.write_all(piece).unwrap();
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        // Hang up two bytes short of the announced payload.
+    });
+
+    let err = read_frame(&mut client).unwrap_err();
+    writer.join().unwrap();
+
+    let err = err.downcast::<ProtocolError>().unwrap();
+    assert_eq!(err.what, "payload");
+    assert_eq!(err.read.len(), HEADER_LEN + 2);
+    assert_eq!(err.source.kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_frame_non_blocking() {
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    client.set_nonblocking(true).unwrap();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        write_frame(&mut server, 7, b"{}").unwrap();
+        server
+    });
+
+    let (payload_type, payload) = read_frame(&mut client).unwrap();
+    let _server = writer.join().unwrap();
+
+    assert_eq!(payload_type, 7);
+    assert_eq!(payload, b"{}");
+    // The socket is handed back non-blocking.
+    let err = client.read(&mut [0; 1]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::WouldBlock);
+}
+
+#[test]
+fn test_get_snapshot_pipelines() {
+    let (mut client, mut server) = UnixStream::pair().unwrap();
+    let sway = std::thread::spawn(move || {
+        // Only reply once all the messages arrived, which deadlocks unless they are pipelined.
+        let types: Vec<u32> = (0..3).map(|_| read_frame(&mut server).unwrap().0).collect();
+        for message_type in &types {
+            let reply = match *message_type {
+                4 => include_str!("../tests/fixtures/trees/multi-output.json"),
+                _ => "[]",
+            };
+            write_frame(&mut server, *message_type, reply.as_bytes()).unwrap();
+        }
+        types
+    });
+
+    let snapshot = get_snapshot(&mut client).unwrap();
+
+    assert_eq!(sway.join().unwrap(), [4, 1, 3]);
+    assert_eq!(snapshot.tree.id, 1);
+    assert!(snapshot.workspaces.is_empty() && snapshot.outputs.is_empty());
+}
+
+#[test]
+fn test_check_results() {
+    let commands = r#"[app_id="a;b"] focus, resize set width 50 ppt; bogus "x,y"; nop"#;
+    assert_eq!(
+        split_commands(commands),
+        [
+            r#"[app_id="a;b"] focus"#,
+            "resize set width 50 ppt",
+            r#"bogus "x,y""#,
+            "nop"
+        ]
+    );
+    // Sway stops at the command it cannot parse.
+    let results: Vec<CommandResult> = serde_json::from_str(
+        r#"[{"success": true}, {"success": true},
+            {"success": false, "parse_error": true, "error": "Unknown/invalid command 'bogus'"}]"#,
+    )
+    .unwrap();
+    let err = check_results(commands, &results).unwrap_err();
+    assert_eq!(err.index, 2);
+    assert_eq!(err.command, r#"bogus "x,y""#);
+    assert!(err.parse_error);
+    assert_eq!(
+        err.to_string(),
+        r#"command 3 'bogus "x,y"' could not be parsed: Unknown/invalid command 'bogus'"#
+    );
+    assert!(check_results(commands, &results[..2]).is_ok());
+}
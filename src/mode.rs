@@ -0,0 +1,113 @@
+//! Entering sway binding modes which are left again on their own, so a forgotten `Escape` does not
+//! leave the keyboard stuck in e.g. the resize mode.
+
+use std::{
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    daemon::{default_socket_path, request, Request, Response},
+    messages::{get_binding_state, receive_event, subscribe},
+    run_checked, sway_connect,
+    tree::Event,
+};
+
+/// The type of the mode event without the event bit, see `man sway-ipc`.
+const MODE_EVENT: u32 = 2;
+
+/// Parse a duration written as a number with the unit `ms`, `s` or `m`, e.g. `3s` or `500ms`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow::format_err!("invalid duration '{}'", s))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => number
+            .checked_mul(60)
+            .map(Duration::from_secs)
+            .ok_or_else(|| anyhow::format_err!("duration '{}' is too long", s)),
+        _ => anyhow::bail!("invalid duration '{}', expected e.g. 3s or 500ms", s),
+    }
+}
+
+/// Switch to the binding mode and ask the daemon to return to the default mode after the timeout
+/// or after one binding ran in the mode, see [leave_mode_later].
+#[tracing::instrument(skip(socket))]
+pub fn enter_mode(
+    socket: &mut UnixStream,
+    mode: &str,
+    timeout: Option<Duration>,
+    once: bool,
+) -> Result<()> {
+    run_checked(socket, &format!("mode \"{}\"", mode))?;
+    if timeout.is_none() && !once {
+        return Ok(());
+    }
+    let leave = Request::LeaveMode {
+        mode: mode.to_string(),
+        timeout_ms: timeout.map(|t| t.as_millis() as u64),
+        once,
+    };
+    match request(&default_socket_path()?, &leave)? {
+        Response::Ok => Ok(()),
+        response => {
+            // Staying in the mode is worse than not entering it.
+            run_checked(socket, "mode default")?;
+            anyhow::bail!("Daemon replied {:?} when asked to leave the mode", response)
+        }
+    }
+}
+
+/// Return to the default mode once the timeout passed or, with `once`, after the first binding ran
+/// in the mode. Gives up watching as soon as another mode becomes active.
+pub fn leave_mode_later(mode: &str, timeout: Option<Duration>, once: bool) -> Result<()> {
+    let mut events = sway_connect()?;
+    subscribe(&mut events, &["mode", "binding"])?;
+    let mut commands = sway_connect()?;
+    if get_binding_state(&mut commands)?.name != mode {
+        return Ok(());
+    }
+    let deadline = timeout.map(|t| Instant::now() + t);
+    loop {
+        if let Some(deadline) = deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            events.set_read_timeout(Some(remaining))?;
+        }
+        match receive_event(&mut events) {
+            Ok(Event::Other(MODE_EVENT)) => {
+                if get_binding_state(&mut commands)?.name != mode {
+                    return Ok(());
+                }
+            }
+            Ok(Event::Binding(_)) if once => break,
+            Ok(_) => (),
+            Err(_) if deadline.is_some_and(|d| Instant::now() >= d) => break,
+            Err(err) => return Err(err),
+        }
+    }
+    if get_binding_state(&mut commands)?.name == mode {
+        tracing::debug!(mode, "leaving the mode");
+        run_checked(&mut commands, "mode default")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("3s").unwrap(), Duration::from_secs(3));
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+    assert!(parse_duration("3").is_err());
+    assert!(parse_duration("s").is_err());
+    assert!(parse_duration("3h").is_err());
+    assert!(parse_duration(&format!("{}m", u64::MAX)).is_err());
+}
@@ -0,0 +1,33 @@
+//! Desktop notifications for failures and moves that hit an edge, which would otherwise go
+//! unnoticed when haswaynav runs from a key binding without a terminal to show stderr.
+
+use std::{
+    process::{Command, Stdio},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether [notify] shows notifications, see [enable_notifications].
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Show notifications for the rest of the process.
+pub fn enable_notifications() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Show a desktop notification with `notify-send` if notifications are enabled. Failing to show it
+/// is only logged.
+pub fn notify(body: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let status = Command::new("notify-send")
+        .args(["--app-name=haswaynav", "haswaynav", body])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .status();
+    match status {
+        Ok(status) if status.success() => (),
+        Ok(status) => tracing::warn!("notify-send failed with {}", status),
+        Err(err) => tracing::warn!("failed running notify-send: {}", err),
+    }
+}
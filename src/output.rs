@@ -0,0 +1,240 @@
+//! Focusing outputs and sending windows to them by their position rather than their name, so that
+//! key bindings keep working when monitors are renamed or plugged into another port.
+
+use std::os::unix::net::UnixStream;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    config::Config,
+    daemon::{remembered_window, Request},
+    geometry::OutputGeometry,
+    messages::get_outputs,
+    notify::notify,
+    run_checked,
+    tree::{NodeType, OutputReply, Rect},
+    Direction,
+};
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "snake_case")]
+/// How to pick an output relative to the focused one, written as a direction, `next` or `prev`.
+pub enum OutputTarget {
+    /// The nearest output left of the focused one
+    Left,
+    /// The nearest output right of the focused one
+    Right,
+    /// The nearest output above the focused one
+    Up,
+    /// The nearest output below the focused one
+    Down,
+    /// The next output ordered from left to right and top to bottom, wrapping around
+    Next,
+    /// The previous output ordered from left to right and top to bottom, wrapping around
+    Prev,
+}
+
+impl OutputTarget {
+    /// The names of the targets.
+    pub const NAMES: [&'static str; 6] = ["left", "right", "up", "down", "next", "prev"];
+
+    /// Parse the target, the directions with their synonyms like [Direction::parse] does.
+    pub fn parse(value: &str, strict: bool) -> Result<OutputTarget> {
+        let ordered = [("next", OutputTarget::Next), ("prev", OutputTarget::Prev)];
+        if let Some((name, target)) = ordered.iter().find(|(n, _)| n.eq_ignore_ascii_case(value)) {
+            if strict && *name != value {
+                anyhow::bail!("ambiguous output '{}', did you mean '{}'?", value, name);
+            }
+            return Ok(*target);
+        }
+        let target = match Direction::parse(value, strict)? {
+            Direction::Left => OutputTarget::Left,
+            Direction::Right => OutputTarget::Right,
+            Direction::Up => OutputTarget::Up,
+            Direction::Down => OutputTarget::Down,
+        };
+        Ok(target)
+    }
+}
+
+impl std::str::FromStr for OutputTarget {
+    type Err = anyhow::Error;
+
+    /// Parse the target leniently, see [OutputTarget::parse].
+    fn from_str(s: &str) -> Result<Self> {
+        OutputTarget::parse(s, false)
+    }
+}
+
+/// The direction to move in on the focused output, see [crate::config::Remap]. Sway is only asked
+/// for the outputs when the config remaps directions.
+pub fn remap_direction(
+    socket: &mut UnixStream,
+    config: &Config,
+    direction: Direction,
+) -> Result<Direction> {
+    if config.remap.is_empty() {
+        return Ok(direction);
+    }
+    let outputs = get_outputs(socket)?;
+    Ok(outputs
+        .iter()
+        .find(|o| o.focused)
+        .and_then(|o| {
+            config
+                .remap
+                .get(&o.name)
+                .map(|r| r.apply(direction, o.transform))
+        })
+        .unwrap_or(direction))
+}
+
+/// Make the focused window floating and resize it to measure `width` by `height` millimetres on
+/// the focused output, using the physical size sway reports or the one from the config.
+#[tracing::instrument(skip(socket, config))]
+pub fn float_size(socket: &mut UnixStream, config: &Config, width: f64, height: f64) -> Result<()> {
+    let outputs = get_outputs(socket)?;
+    let output = outputs
+        .iter()
+        .find(|o| o.focused)
+        .context("No output is focused")?;
+    let reported = output.physical_width.zip(output.physical_height);
+    let display_mm = reported
+        .filter(|(w, h)| *w > 0 && *h > 0)
+        .or_else(|| config.outputs.get(&output.name).and_then(|o| o.size_mm))
+        .with_context(|| {
+            format!(
+                "The physical size of output '{}' is unknown, set outputs.\"{}\".size_mm",
+                output.name, output.name
+            )
+        })?;
+    let (width, height) = OutputGeometry::from(output).mm_to_logical(width, height, display_mm);
+    run_checked(
+        socket,
+        &format!(
+            "floating enable, resize set width {} px height {} px",
+            width, height
+        ),
+    )
+}
+
+/// Resolve the target relative to the focused output among the active outputs.
+pub fn resolve_output(outputs: &[OutputReply], target: OutputTarget) -> Option<&OutputReply> {
+    let mut active: Vec<&OutputReply> = outputs.iter().filter(|o| o.active).collect();
+    active.sort_by_key(|o| (o.rect.x, o.rect.y));
+    let current = active.iter().position(|o| o.focused)?;
+    let r = active[current].rect;
+    let beyond = |o: &Rect| match target {
+        OutputTarget::Left => o.x + o.width <= r.x && r.overlaps_y(o),
+        OutputTarget::Right => o.x >= r.x + r.width && r.overlaps_y(o),
+        OutputTarget::Up => o.y + o.height <= r.y && r.overlaps_x(o),
+        OutputTarget::Down => o.y >= r.y + r.height && r.overlaps_x(o),
+        OutputTarget::Next | OutputTarget::Prev => false,
+    };
+    match target {
+        OutputTarget::Next if active.len() > 1 => Some(active[(current + 1) % active.len()]),
+        OutputTarget::Prev if active.len() > 1 => {
+            Some(active[(current + active.len() - 1) % active.len()])
+        }
+        OutputTarget::Next | OutputTarget::Prev => None,
+        _ => active
+            .iter()
+            .filter(|o| beyond(&o.rect))
+            .min_by_key(|o| o.rect.distance_squared(&r))
+            .copied(),
+    }
+}
+
+/// Resolve the output from the outputs sway reports, warning when there is none.
+fn target_output(socket: &mut UnixStream, target: OutputTarget) -> Result<Option<String>> {
+    let outputs = get_outputs(socket)?;
+    let output = resolve_output(&outputs, target).map(|o| o.name.clone());
+    if output.is_none() {
+        tracing::warn!(?target, "no output to move to");
+        notify(&format!("No output to move to ({:?})", target));
+    }
+    Ok(output)
+}
+
+/// Focus the output picked by the target. With `restore` the window focused last on the output is
+/// focused if the daemon remembers one which is still on that output, otherwise sway picks the
+/// window like for a plain `focus output`.
+#[tracing::instrument(skip(socket))]
+pub fn focus_output(socket: &mut UnixStream, target: OutputTarget, restore: bool) -> Result<()> {
+    let Some(name) = target_output(socket, target)? else {
+        return Ok(());
+    };
+    let window = if restore {
+        let request = Request::LastFocused {
+            output: name.clone(),
+        };
+        remembered_window(socket, &request, NodeType::Output, &name)?
+    } else {
+        None
+    };
+    match window {
+        Some(id) => run_checked(socket, &format!("[con_id={}] focus", id))?,
+        None => run_checked(socket, &format!("focus output \"{}\"", name))?,
+    }
+    Ok(())
+}
+
+/// Move the focused container to the output picked by the target, focusing that output as well
+/// when `follow` is set.
+#[tracing::instrument(skip(socket))]
+pub fn send_to_output(socket: &mut UnixStream, target: OutputTarget, follow: bool) -> Result<()> {
+    if let Some(name) = target_output(socket, target)? {
+        let mut commands = format!("move container to output \"{}\"", name);
+        if follow {
+            commands.push_str(&format!("; focus output \"{}\"", name));
+        }
+        run_checked(socket, &commands)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_resolve_output() {
+    let output = |name: &str, x, y, focused| OutputReply {
+        name: name.to_string(),
+        active: true,
+        focused,
+        rect: Rect {
+            x,
+            y,
+            width: 100,
+            height: 100,
+        },
+        ..Default::default()
+    };
+    let outputs = [
+        output("right", 100, 0, false),
+        output("left", 0, 0, true),
+        output("below", 0, 100, false),
+        OutputReply {
+            active: false,
+            ..output("off", -100, 0, false)
+        },
+    ];
+    let name = |target| resolve_output(&outputs, target).map(|o| o.name.as_str());
+
+    assert_eq!(name(OutputTarget::Right), Some("right"));
+    assert_eq!(name(OutputTarget::Down), Some("below"));
+    assert_eq!(name(OutputTarget::Left), None);
+    assert_eq!(name(OutputTarget::Next), Some("below"));
+    assert_eq!(name(OutputTarget::Prev), Some("right"));
+}
+
+#[test]
+fn test_parse_output_target() {
+    assert_eq!("h".parse::<OutputTarget>().unwrap(), OutputTarget::Left);
+    assert_eq!("Next".parse::<OutputTarget>().unwrap(), OutputTarget::Next);
+    assert_eq!(
+        OutputTarget::parse("ri", false).unwrap(),
+        OutputTarget::Right
+    );
+    assert!(OutputTarget::parse("ri", true).is_err());
+    assert!(OutputTarget::parse("Next", true).is_err());
+    assert!(OutputTarget::parse("nxt", false).is_err());
+}
@@ -0,0 +1,285 @@
+//! Moving the mouse pointer to windows and outputs with `seat - cursor set`, for navigating with
+//! devices which show no focus. Sway does not report where the pointer is, so the daemon keeps the
+//! position haswaynav moved it to last, see [crate::daemon::DaemonState::pointer].
+
+use std::{fmt, os::unix::net::UnixStream, str::FromStr};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::FocusConfig,
+    messages::get_tree_slim,
+    plan_focus,
+    query::{find_at_point, WindowInfo},
+    run_checked,
+    tree::{cursor::find_focused_fast, NodeType, TreeNode},
+    Direction,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+/// A position in the layout coordinates spanning all outputs.
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+/// Where haswaynav moved the pointer to last, see [crate::daemon::DaemonState::pointer].
+pub struct PointerWarp {
+    pub point: Point,
+    /// The window focused right after the pointer moved, while it stays focused the pointer most
+    /// likely did not move either
+    pub focused: Option<i32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+/// Where `pointer move-to` moves the pointer, written as a direction, `focused`, `output:NAME`,
+/// `mark:NAME` or `id:ID`.
+pub enum PointerTarget {
+    /// The window `focus` would move to in the direction
+    Direction(Direction),
+    /// The focused window
+    Focused,
+    /// The output with the name
+    Output(String),
+    /// The window with the mark
+    Mark(String),
+    /// The container with the id
+    Id(i32),
+}
+
+impl PointerTarget {
+    /// Parse the target, directions like [Direction::parse] does.
+    pub fn parse(s: &str, strict: bool) -> Result<PointerTarget> {
+        let target = match s.split_once(':') {
+            None if s == "focused" => PointerTarget::Focused,
+            None => match Direction::parse(s, strict) {
+                Ok(direction) => PointerTarget::Direction(direction),
+                Err(err) => anyhow::bail!("{}: {}", unknown_target(s), err),
+            },
+            Some(("output", name)) => PointerTarget::Output(name.to_string()),
+            Some(("mark", mark)) => PointerTarget::Mark(mark.to_string()),
+            Some(("id", id)) => {
+                PointerTarget::Id(id.parse().with_context(|| format!("invalid id '{}'", id))?)
+            }
+            Some(_) => anyhow::bail!("{}", unknown_target(s)),
+        };
+        Ok(target)
+    }
+}
+
+impl FromStr for PointerTarget {
+    type Err = anyhow::Error;
+
+    /// Parse the target leniently, see [PointerTarget::parse].
+    fn from_str(s: &str) -> Result<Self> {
+        PointerTarget::parse(s, false)
+    }
+}
+
+fn unknown_target(s: &str) -> String {
+    format!(
+        "unknown pointer target '{}', expected a direction, focused, output:NAME, mark:NAME or id:ID",
+        s
+    )
+}
+
+impl fmt::Display for PointerTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerTarget::Direction(dir) => write!(f, "{}", dir),
+            PointerTarget::Focused => f.write_str("focused"),
+            PointerTarget::Output(name) => write!(f, "output:{}", name),
+            PointerTarget::Mark(mark) => write!(f, "mark:{}", mark),
+            PointerTarget::Id(id) => write!(f, "id:{}", id),
+        }
+    }
+}
+
+/// The center of the target, directions are planned with the focus options like `focus` does.
+pub fn plan_move_to(
+    tree: &TreeNode,
+    target: &PointerTarget,
+    options: &FocusConfig,
+) -> Result<Point> {
+    let find = |id: i32| tree.into_iter().find(|c| c.get_node().id == id);
+    let cursor = match target {
+        PointerTarget::Direction(dir) => {
+            let id = plan_focus(tree, *dir, options)
+                .target
+                .with_context(|| format!("No window to the {} known from the tree", dir))?;
+            find(id)
+        }
+        PointerTarget::Focused => find_focused_fast(tree),
+        PointerTarget::Output(name) => tree.into_iter().find(|c| {
+            let node = c.get_node();
+            node.node_type == NodeType::Output && node.name.as_deref() == Some(name.as_str())
+        }),
+        PointerTarget::Mark(mark) => tree
+            .into_iter()
+            .find(|c| c.get_node().marks.iter().any(|m| m == mark)),
+        PointerTarget::Id(id) => find(*id),
+    }
+    .with_context(|| format!("Nothing to move the pointer to at {}", target))?;
+    let (x, y) = cursor.get_node().rect.center();
+    Ok(Point { x, y })
+}
+
+/// Warp the pointer to the center of the target, see [plan_move_to]. Returns where it went and the
+/// window focused afterwards, which differs with `focus_follows_mouse`.
+#[tracing::instrument(skip(socket))]
+pub fn move_pointer(
+    socket: &mut UnixStream,
+    target: &PointerTarget,
+    options: &FocusConfig,
+) -> Result<PointerWarp> {
+    let tree = get_tree_slim(socket)?;
+    let point = plan_move_to(&tree, target, options)?;
+    run_checked(
+        socket,
+        &format!("seat - cursor set {} {}", point.x, point.y),
+    )?;
+    let tree = get_tree_slim(socket)?;
+    Ok(PointerWarp {
+        point,
+        focused: find_focused_fast(&tree).map(|c| c.get_node().id),
+    })
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// What is under the pointer, see [pointer_info].
+pub struct PointerInfo {
+    pub x: i32,
+    pub y: i32,
+    /// The name of the output under the pointer
+    pub output: Option<String>,
+    /// The window shown under the pointer
+    pub window: Option<WindowInfo>,
+}
+
+/// The output and the window at the position of the pointer.
+pub fn pointer_info(tree: &TreeNode, point: Point) -> PointerInfo {
+    let output = tree.into_iter().find(|c| {
+        let node = c.get_node();
+        node.node_type == NodeType::Output && node.rect.contains_point(point.x, point.y)
+    });
+    PointerInfo {
+        x: point.x,
+        y: point.y,
+        output: output.and_then(|c| c.get_node().name.clone()),
+        window: find_at_point(tree, point.x, point.y).map(|c| WindowInfo::from(&c)),
+    }
+}
+
+/// The command moving the focused window to the pointer. A floating window is moved to be centered
+/// on the pointer, a tiled one swaps places with the tiled window under the pointer, or is moved to
+/// the output under it when it shows no window there. Tiled moves need the position of the pointer,
+/// see [crate::daemon::DaemonState::pointer], and are refused once the focus moved on from the
+/// window focused after the pointer moved, since the mouse may have moved the pointer since.
+pub fn plan_move_to_pointer(tree: &TreeNode, warp: Option<PointerWarp>) -> Result<String> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    let id = focused.get_node().id;
+    if focused.is_floating() {
+        // Sway knows where the pointer is even when haswaynav does not.
+        return Ok(format!("[con_id={}] move position cursor", id));
+    }
+    let warp = warp.context(
+        "Sway does not report where the pointer is and haswaynav did not move it yet, see \
+         `haswaynav pointer move-to`",
+    )?;
+    if warp.focused != Some(id) {
+        anyhow::bail!(
+            "The focus changed since haswaynav moved the pointer, which may have moved as well, \
+             see `haswaynav pointer move-to`"
+        );
+    }
+    let point = warp.point;
+    let info = pointer_info(tree, point);
+    match info.window {
+        Some(window) if window.id == id => {
+            anyhow::bail!("The focused window is already under the pointer")
+        }
+        Some(window) if window.floating => anyhow::bail!(
+            "The window under the pointer is floating, only tiled windows swap places"
+        ),
+        Some(window) => Ok(format!(
+            "[con_id={}] swap container with con_id {}",
+            id, window.id
+        )),
+        None => {
+            let output = info
+                .output
+                .with_context(|| format!("No output at {},{}", point.x, point.y))?;
+            Ok(format!(
+                "[con_id={}] move container to output \"{}\"",
+                id, output
+            ))
+        }
+    }
+}
+
+/// Move the focused window to the pointer haswaynav moved, see [plan_move_to_pointer].
+#[tracing::instrument(skip(socket))]
+pub fn move_to_pointer(socket: &mut UnixStream, warp: Option<PointerWarp>) -> Result<()> {
+    let tree = get_tree_slim(socket)?;
+    run_checked(socket, &plan_move_to_pointer(&tree, warp)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn parse_pointer_target() {
+        let parse = |s: &str| s.parse::<PointerTarget>();
+        assert_eq!(
+            parse("left").unwrap(),
+            PointerTarget::Direction(Direction::Left)
+        );
+        assert_eq!(
+            parse("output:DP-1").unwrap(),
+            PointerTarget::Output("DP-1".into())
+        );
+        assert_eq!(parse("id:12").unwrap(), PointerTarget::Id(12));
+        assert!(parse("id:twelve").is_err());
+        assert!(parse("middle").is_err());
+        assert!(parse("window:12").is_err());
+        assert_eq!(parse("mark:a:b").unwrap().to_string(), "mark:a:b");
+    }
+
+    #[test]
+    fn move_to_targets() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let options = FocusConfig::default();
+        let focused = plan_move_to(&tree, &PointerTarget::Focused, &options).unwrap();
+        let info = pointer_info(&tree, focused);
+        assert_eq!(info.window.map(|w| w.id), Some(126));
+        assert!(info.output.is_some());
+
+        let output = info.output.unwrap();
+        let center = plan_move_to(&tree, &PointerTarget::Output(output.clone()), &options).unwrap();
+        assert_eq!(pointer_info(&tree, center).output, Some(output));
+        assert!(plan_move_to(&tree, &PointerTarget::Id(-1), &options).is_err());
+    }
+
+    #[test]
+    fn move_to_pointer_targets() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let warp = |x, y, focused| PointerWarp {
+            point: Point { x, y },
+            focused: Some(focused),
+        };
+        let plan = |x, y| plan_move_to_pointer(&tree, Some(warp(x, y, 126)));
+        assert_eq!(
+            plan(1500, 300).unwrap(),
+            "[con_id=126] swap container with con_id 11"
+        );
+        assert!(plan(600, 300).is_err(), "already under the pointer");
+        assert!(plan(-10, -10).is_err());
+        assert!(plan_move_to_pointer(&tree, None).is_err());
+        // The focus moved on from the window focused when the pointer was moved.
+        assert!(plan_move_to_pointer(&tree, Some(warp(1500, 300, 11))).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Profiles declaring workspaces, their layouts and the apps on them, applied with
+//! `haswaynav profile apply <name>`.
+//!
+//! Profiles are TOML files under `$XDG_CONFIG_HOME/haswaynav/profiles`. Applying one launches the
+//! apps which have no window yet through sway's `exec`, waits for their windows to appear and then
+//! moves every window to its workspace and sets the layouts.
+
+use std::{
+    fmt,
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{
+    config::{default_config_dir, from_toml, Criteria, Pattern},
+    messages::{get_tree, receive_new_window, subscribe},
+    run_checked,
+    tree::{NodeType, TreeNode},
+    undo::{move_back, run_plan, Plan},
+};
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// A declarative setup of workspaces and the apps on them.
+pub struct Profile {
+    /// How long to wait for the windows of launched apps in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// The workspaces set up by the profile
+    #[serde(rename = "workspace", default)]
+    pub workspaces: Vec<ProfileWorkspace>,
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// A workspace of a [Profile].
+pub struct ProfileWorkspace {
+    /// The name of the workspace
+    pub name: String,
+    /// The layout of the windows on the workspace
+    pub layout: Option<LayoutMode>,
+    /// The output the workspace is moved to
+    pub output: Option<String>,
+    /// The apps placed on the workspace, in order
+    #[serde(rename = "app", default)]
+    pub apps: Vec<ProfileApp>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+/// The layouts accepted by sway's `layout` command.
+pub enum LayoutMode {
+    #[cfg_attr(feature = "cli", value(name = "splith"))]
+    SplitH,
+    #[cfg_attr(feature = "cli", value(name = "splitv"))]
+    SplitV,
+    Stacking,
+    Tabbed,
+}
+
+impl fmt::Display for LayoutMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LayoutMode::SplitH => "splith",
+            LayoutMode::SplitV => "splitv",
+            LayoutMode::Stacking => "stacking",
+            LayoutMode::Tabbed => "tabbed",
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+/// An app of a [ProfileWorkspace], matched against the windows like the daemon rules.
+pub struct ProfileApp {
+    /// Pattern matched against [TreeNode::app_id]
+    pub app_id: Option<Pattern>,
+    /// Pattern matched against [TreeNode::name], the window title
+    pub title: Option<Pattern>,
+    /// Command launching the app when no window matches, run with sway's `exec`
+    pub exec: Option<String>,
+}
+
+impl ProfileApp {
+    /// Does the app's window match the node.
+    pub fn matches(&self, node: &TreeNode) -> bool {
+        Criteria::match_patterns(self.app_id.as_ref(), self.title.as_ref(), node)
+    }
+}
+
+impl Profile {
+    /// Parse a profile from the contents of a profile file.
+    pub fn parse(contents: &str) -> Result<Profile> {
+        from_toml(contents)
+    }
+
+    /// The apps of all workspaces in order.
+    fn apps(&self) -> impl Iterator<Item = &ProfileApp> {
+        self.workspaces.iter().flat_map(|w| &w.apps)
+    }
+
+    /// Pick a window for every app from the windows in the tree, in the order of [Profile::apps].
+    /// Every window is picked for at most one app and windows in the scratchpad are left alone.
+    pub fn claim_windows(&self, tree: &TreeNode) -> Vec<Option<i32>> {
+        let windows: Vec<&TreeNode> = tree
+            .into_iter()
+            .filter(|c| {
+                let node = c.get_node();
+                matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+                    && node.nodes.is_empty()
+                    && c.workspace()
+                        .is_some_and(|w| w.get_node().name.as_deref() != Some("__i3_scratch"))
+            })
+            .map(|c| c.get_node())
+            .collect();
+        let mut claimed: Vec<Option<i32>> = Vec::new();
+        for app in self.apps() {
+            let window = windows
+                .iter()
+                .find(|w| app.matches(w) && !claimed.contains(&Some(w.id)))
+                .map(|w| w.id);
+            claimed.push(window);
+        }
+        claimed
+    }
+
+    /// Plan the commands moving the claimed windows to their workspaces, moving the workspaces to
+    /// their outputs and setting their layouts. Workspaces without any window are skipped since
+    /// sway removes empty workspaces.
+    pub fn plan_arrangement(&self, claimed: &[Option<i32>]) -> Vec<String> {
+        let mut claimed = claimed.iter();
+        let mut commands = Vec::new();
+        for workspace in &self.workspaces {
+            let ids: Vec<i32> = claimed
+                .by_ref()
+                .take(workspace.apps.len())
+                .flatten()
+                .copied()
+                .collect();
+            let Some(first) = ids.first() else {
+                continue;
+            };
+            for id in &ids {
+                commands.push(format!(
+                    "[con_id={}] move container to workspace \"{}\"",
+                    id, workspace.name
+                ));
+            }
+            if let Some(output) = &workspace.output {
+                commands.push(format!(
+                    "[con_id={}] move workspace to output \"{}\"",
+                    first, output
+                ));
+            }
+            if let Some(layout) = workspace.layout {
+                commands.push(format!("[con_id={}] layout {}", first, layout));
+            }
+        }
+        commands
+    }
+}
+
+/// The path of the file holding the named profile.
+pub fn profile_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        anyhow::bail!("Invalid profile name '{}'", name);
+    }
+    let dir = default_config_dir().ok_or_else(|| {
+        anyhow::format_err!(
+            "Neither XDG_CONFIG_HOME nor HOME is set to locate the profile directory"
+        )
+    })?;
+    Ok(dir.join("profiles").join(format!("{}.toml", name)))
+}
+
+/// Load the named profile.
+pub fn load_profile(name: &str) -> Result<Profile> {
+    let path = profile_path(name)?;
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("reading profile file '{}'", path.display()))?;
+    Profile::parse(&contents).with_context(|| format!("parsing profile file '{}'", path.display()))
+}
+
+/// Apply the profile, launching the apps without a window and waiting for their windows on the
+/// `events` connection before arranging all of them, see [Profile::plan_arrangement]. Apps whose
+/// windows do not appear within the timeout are left out. The inverse is saved for `undo` with
+/// `journal`, see [run_plan].
+#[tracing::instrument(skip_all)]
+pub fn apply_profile(
+    socket: &mut UnixStream,
+    mut events: UnixStream,
+    profile: &Profile,
+    journal: bool,
+) -> Result<()> {
+    // Subscribe before launching anything so no window is missed.
+    subscribe(&mut events, &["window"])?;
+    let tree = get_tree(socket)?;
+    let mut claimed = profile.claim_windows(&tree);
+    let apps: Vec<&ProfileApp> = profile.apps().collect();
+
+    let mut pending: Vec<usize> = Vec::new();
+    for (idx, app) in apps.iter().enumerate() {
+        if let (None, Some(exec)) = (claimed[idx], &app.exec) {
+            run_checked(socket, &format!("exec {}", exec))?;
+            pending.push(idx);
+        }
+    }
+
+    let deadline = Instant::now() + Duration::from_millis(profile.timeout_ms);
+    while !pending.is_empty() {
+        let Some(window) = receive_new_window(&mut events, deadline)? else {
+            tracing::warn!(
+                missing = pending.len(),
+                "gave up waiting for the windows of launched apps"
+            );
+            break;
+        };
+        if claimed.contains(&Some(window.id)) {
+            continue;
+        }
+        if let Some(pos) = pending.iter().position(|&idx| apps[idx].matches(&window)) {
+            claimed[pending.remove(pos)] = Some(window.id);
+        }
+    }
+
+    let ids: Vec<i32> = claimed.iter().flatten().copied().collect();
+    let plan = Plan {
+        forward: profile.plan_arrangement(&claimed),
+        // Launched windows are not in the tree and stay where the profile put them.
+        inverse: move_back(&tree, &ids),
+        ids,
+    };
+    run_plan(socket, &plan, journal)
+}
+
+#[test]
+fn test_profile_arrangement() {
+    let profile = Profile::parse(
+        r#"
+        [[workspace]]
+        name = "web"
+        layout = "tabbed"
+        output = "DP-1"
+        [[workspace.app]]
+        app_id = "^firefox$"
+        [[workspace.app]]
+        app_id = "^firefox$"
+        [[workspace.app]]
+        app_id = "^nope$"
+        exec = "nope"
+
+        [[workspace]]
+        name = "empty"
+        [[workspace.app]]
+        app_id = "^nope$"
+
+        [[workspace]]
+        name = "term"
+        layout = "splitv"
+        [[workspace.app]]
+        app_id = "^Alacritty$"
+        title = "get_tree"
+        "#,
+    )
+    .unwrap();
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+
+    let claimed = profile.claim_windows(&tree);
+    assert_eq!(claimed, [Some(99), Some(11), None, None, Some(126)]);
+    assert_eq!(
+        profile.plan_arrangement(&claimed),
+        [
+            "[con_id=99] move container to workspace \"web\"",
+            "[con_id=11] move container to workspace \"web\"",
+            "[con_id=99] move workspace to output \"DP-1\"",
+            "[con_id=99] layout tabbed",
+            "[con_id=126] move container to workspace \"term\"",
+            "[con_id=126] layout splitv",
+        ]
+    );
+    assert!(Profile::parse("[[workspace]]\nname = \"x\"\nlayout = \"stacked\"").is_err());
+}
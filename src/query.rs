@@ -0,0 +1,383 @@
+//! Answers to questions about the tree for scripts and status bars, see the `get` command.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    in_floating_layer, text,
+    tree::{
+        cursor::{find_focused_fast, Cursor},
+        Layout, NodeType, TreeNode,
+    },
+};
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// Where the focused window is among the tabs or stack entries of its nearest tabbed or stacked
+/// ancestor, see [tab_info].
+pub struct TabInfo {
+    /// The position of the tab holding the focused window, counting from 1
+    pub index: usize,
+    /// The number of tabs
+    pub count: usize,
+    /// Either [Layout::Tabbed] or [Layout::Stacked]
+    pub layout: Layout,
+}
+
+/// The position of the focused window within the nearest tabbed or stacked container on its
+/// workspace, `None` if it is not in one.
+pub fn tab_info(tree: &TreeNode) -> Option<TabInfo> {
+    let mut child = find_focused_fast(tree)?;
+    while let Ok(parent) = child.clone().ascend() {
+        let node = parent.get_node();
+        if node.node_type == NodeType::Workspace || child.is_floating() {
+            break;
+        }
+        if matches!(node.layout, Layout::Tabbed | Layout::Stacked) {
+            return Some(TabInfo {
+                index: child.index_in_parent() + 1,
+                count: node.nodes.len(),
+                layout: node.layout,
+            });
+        }
+        child = parent;
+    }
+    None
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// The properties of a window and where it is, see [focused_info] and [find_at_point]. The focused
+/// node can also be a container, e.g. after `focus parent`, which has children and a layout of its
+/// own but no app id.
+pub struct WindowInfo {
+    pub id: i32,
+    pub app_id: Option<String>,
+    /// The window title
+    pub title: Option<String>,
+    pub pid: Option<i32>,
+    pub marks: Vec<String>,
+    pub floating: bool,
+    /// The layout of the container holding the window
+    pub layout: Option<Layout>,
+    /// The name of the workspace
+    pub workspace: Option<String>,
+    /// The name of the output
+    pub output: Option<String>,
+    /// The number of tiled and floating children, 0 for a window
+    pub children: usize,
+    /// The layout of the node itself when it is a container
+    pub container_layout: Option<Layout>,
+}
+
+impl WindowInfo {
+    /// The names of the fields which can be used in templates, see [WindowInfo::format].
+    pub const FIELDS: &'static [&'static str] = &[
+        "id",
+        "app_id",
+        "title",
+        "pid",
+        "marks",
+        "floating",
+        "layout",
+        "workspace",
+        "output",
+        "children",
+        "container_layout",
+    ];
+
+    /// The field with the name as text, missing values are empty and marks are separated by
+    /// commas.
+    pub fn field(&self, name: &str) -> Option<String> {
+        let text = |value: &Option<String>| value.clone().unwrap_or_default();
+        let layout = |value: Option<Layout>| {
+            value
+                .and_then(|l| serde_json::to_value(l).ok())
+                .and_then(|l| l.as_str().map(str::to_string))
+                .unwrap_or_default()
+        };
+        Some(match name {
+            "id" => self.id.to_string(),
+            "app_id" => text(&self.app_id),
+            "title" => text(&self.title),
+            "pid" => self.pid.map(|p| p.to_string()).unwrap_or_default(),
+            "marks" => self.marks.join(","),
+            "floating" => self.floating.to_string(),
+            "layout" => layout(self.layout),
+            "workspace" => text(&self.workspace),
+            "output" => text(&self.output),
+            "children" => self.children.to_string(),
+            "container_layout" => layout(self.container_layout),
+            _ => return None,
+        })
+    }
+
+    /// The info with the title made safe to print, see [crate::text::clean].
+    pub fn clean_title(mut self, max: usize) -> Self {
+        self.title = self.title.map(|t| text::clean(&t, max));
+        self
+    }
+
+    /// Fill in the template replacing every `{field}` by the value of the field, see
+    /// [WindowInfo::FIELDS] and [text::render_template] for the modifiers.
+    pub fn format(&self, template: &str) -> Result<String> {
+        text::render_template(template, |name| self.field(name))
+    }
+}
+
+impl From<&Cursor<'_>> for WindowInfo {
+    fn from(c: &Cursor) -> Self {
+        let node = c.get_node();
+        let name = |c: Option<Cursor>| c.and_then(|c| c.get_node().name.clone());
+        WindowInfo {
+            id: node.id,
+            app_id: node.app_id.clone(),
+            title: node.name.clone(),
+            pid: node.pid,
+            marks: node.marks.clone(),
+            floating: c.is_floating(),
+            layout: c.clone().ascend().ok().map(|p| p.get_node().layout),
+            workspace: name(c.workspace()),
+            output: name(c.output()),
+            children: node.nodes.len() + node.floating_nodes.len(),
+            container_layout: (!node.nodes.is_empty()).then_some(node.layout),
+        }
+    }
+}
+
+/// The focused window along with its workspace and output, `None` if nothing is focused.
+pub fn focused_info(tree: &TreeNode) -> Option<WindowInfo> {
+    find_focused_fast(tree).map(|c| WindowInfo::from(&c))
+}
+
+/// The window shown at the position in the layout coordinates spanning all outputs. Floating
+/// windows are above tiled ones and hidden tabs and workspaces are never found.
+pub fn find_at_point(tree: &TreeNode, x: i32, y: i32) -> Option<Cursor<'_>> {
+    tree.into_iter()
+        .filter(|c| {
+            let node = c.get_node();
+            matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+                && node.nodes.is_empty()
+                && node.rect.contains_point(x, y)
+                && c.is_visible()
+        })
+        // Of several floating windows the one sway lists last is on top.
+        .max_by_key(in_floating_layer)
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// A normalized summary of a workspace for scripts, see [workspace_layout].
+pub struct WorkspaceLayout {
+    /// The name of the workspace
+    pub workspace: Option<String>,
+    /// The name of the output
+    pub output: Option<String>,
+    /// The nesting of the tiled containers as an S-expression of their layouts and the indices of
+    /// the windows, e.g. `(splith 1 (tabbed *2 3))`. The branch of a tabbed or stacked container
+    /// which is shown is marked with `*`.
+    pub tree: String,
+    /// The windows of the workspace, the tiled ones in order followed by the floating ones
+    pub windows: Vec<LayoutWindow>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq, Clone)]
+/// A window of a [WorkspaceLayout].
+pub struct LayoutWindow {
+    /// The position of the window among the windows of the workspace, counting from 1
+    pub index: usize,
+    pub id: i32,
+    pub app_id: Option<String>,
+    /// The window title
+    pub title: Option<String>,
+    pub floating: bool,
+    /// Is the window on screen rather than a hidden tab or stack entry
+    pub visible: bool,
+    pub focused: bool,
+}
+
+/// Summarize the workspace holding the focus, `None` if nothing is focused.
+pub fn workspace_layout(tree: &TreeNode) -> Option<WorkspaceLayout> {
+    let ws = find_focused_fast(tree)?.workspace()?;
+    let mut windows = Vec::new();
+    let mut push = |c: &Cursor| {
+        let node = c.get_node();
+        windows.push(LayoutWindow {
+            index: windows.len() + 1,
+            id: node.id,
+            app_id: node.app_id.clone(),
+            title: node.name.clone(),
+            floating: in_floating_layer(c),
+            visible: c.is_visible(),
+            focused: node.focused,
+        });
+        windows.len()
+    };
+
+    let tiling = ws.get_node().nodes.len();
+    let mut sexp = String::new();
+    sexp_of(&ws, &mut push, &mut sexp);
+    for idx in 0..ws.get_node().floating_nodes.len() {
+        if let Ok(floating) = ws.clone().child(tiling + idx) {
+            for c in floating.pre_order() {
+                if c.get_node().nodes.is_empty() && c.get_node().floating_nodes.is_empty() {
+                    push(&c);
+                }
+            }
+        }
+    }
+    let name = |c: Option<Cursor>| c.and_then(|c| c.get_node().name.clone());
+    Some(WorkspaceLayout {
+        workspace: name(Some(ws.clone())),
+        output: name(ws.output()),
+        tree: sexp,
+        windows,
+    })
+}
+
+/// Write the tiled containers under the cursor as an S-expression, numbering the windows with
+/// `push`, see [WorkspaceLayout::tree].
+fn sexp_of(c: &Cursor, push: &mut dyn FnMut(&Cursor) -> usize, out: &mut String) {
+    let node = c.get_node();
+    if node.nodes.is_empty() && node.node_type != NodeType::Workspace {
+        out.push_str(&push(c).to_string());
+        return;
+    }
+    let layout = serde_json::to_value(node.layout)
+        .ok()
+        .and_then(|l| l.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let shows_one = matches!(node.layout, Layout::Tabbed | Layout::Stacked);
+    let visible = c.visible_child().map(|v| v.index_in_parent());
+    out.push('(');
+    out.push_str(&layout);
+    for idx in 0..node.nodes.len() {
+        let Ok(child) = c.clone().child(idx) else {
+            continue;
+        };
+        out.push(' ');
+        if shows_one && visible == Some(idx) {
+            out.push('*');
+        }
+        sexp_of(&child, push, out);
+    }
+    out.push(')');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    /// Move the focus of the tree to the node with the id.
+    fn focus(node: &mut TreeNode, id: i32) {
+        node.focused = node.id == id;
+        for child in node.nodes.iter_mut().chain(&mut node.floating_nodes) {
+            focus(child, id);
+        }
+    }
+
+    #[test]
+    fn tab_info_of_focused() {
+        let mut tree = fixtures::load("multi-output").unwrap();
+        assert_eq!(tab_info(&tree), None);
+
+        focus(&mut tree, 355);
+        assert_eq!(
+            tab_info(&tree),
+            Some(TabInfo {
+                index: 2,
+                count: 4,
+                layout: Layout::Tabbed
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&tab_info(&tree)).unwrap(),
+            r#"{"index":2,"count":4,"layout":"tabbed"}"#
+        );
+    }
+
+    #[test]
+    fn focused_info_format() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let info = focused_info(&tree).unwrap();
+
+        assert_eq!(
+            info.format("{{{id}}} {title} [{workspace} on {output}, {layout}]")
+                .unwrap(),
+            "{126} swaymsg -t get_tree  ~/d/sway-nav [2 on DP-2, splith]"
+        );
+        assert!(info.format("{nope}").is_err());
+        assert!(info.format("{title").is_err());
+        assert!(info.format("title}").is_err());
+        for field in WindowInfo::FIELDS {
+            assert!(info.field(field).is_some());
+        }
+    }
+
+    #[test]
+    fn focused_info_of_container() {
+        let tree = fixtures::load("focused-container").unwrap();
+        let info = focused_info(&tree).unwrap();
+
+        assert_eq!(
+            info.format("{id} {children} {container_layout} in {layout} on {workspace}")
+                .unwrap(),
+            "20 2 splitv in splith on 2"
+        );
+        assert_eq!(
+            focused_info(&fixtures::load("multi-output").unwrap())
+                .unwrap()
+                .container_layout,
+            None
+        );
+    }
+
+    #[test]
+    fn workspace_layout_of_focused() {
+        let mut tree = fixtures::load("multi-output").unwrap();
+        let layout = workspace_layout(&tree).unwrap();
+        assert_eq!(
+            (layout.workspace.as_deref(), layout.output.as_deref()),
+            (Some("2"), Some("DP-2"))
+        );
+        assert_eq!(layout.tree, "(splith (splith 1 (splitv 2 (splith 3 4))))");
+        assert!(layout.windows.iter().all(|w| w.visible && !w.floating));
+        assert_eq!(
+            layout.windows.iter().map(|w| w.id).collect::<Vec<_>>(),
+            [126, 11, 130, 139]
+        );
+
+        // Workspace 3 is not shown on its output, so neither are its tabs.
+        focus(&mut tree, 355);
+        let layout = workspace_layout(&tree).unwrap();
+        assert_eq!(layout.workspace.as_deref(), Some("3"));
+        assert_eq!(layout.tree, "(splith (tabbed *1) (tabbed *2 3 4 5))");
+        assert!(layout.windows.iter().all(|w| !w.visible));
+        let focused = layout.windows.iter().find(|w| w.focused).unwrap();
+        assert_eq!((focused.index, focused.id), (3, 355));
+    }
+
+    #[test]
+    fn find_at_point_visible() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let at = |x, y| find_at_point(&tree, x, y).map(|c| c.get_node().id);
+
+        // Workspace 2 is shown on DP-2 rather than the tabs of workspace 3, likewise 10 on eDP-1.
+        assert_eq!(at(1300, 100), Some(11));
+        assert_eq!(at(1300, 1000), Some(130));
+        assert_eq!(at(100, 100), Some(126));
+        assert_eq!(at(3000, 100), Some(409));
+        assert_eq!(at(5000, 100), None);
+
+        let tree = fixtures::load("scratchpad").unwrap();
+        let at = |x, y| find_at_point(&tree, x, y).map(|c| c.get_node().id);
+        assert_eq!(at(700, 300), Some(13));
+        assert_eq!(at(100, 100), Some(10));
+
+        // The hidden scratchpad window over the tiled one is not shown.
+        let mut tree = fixtures::load("scratchpad").unwrap();
+        tree.nodes[1].nodes[0].floating_nodes.clear();
+        assert_eq!(
+            find_at_point(&tree, 700, 300).map(|c| c.get_node().id),
+            Some(10)
+        );
+    }
+}
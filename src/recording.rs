@@ -0,0 +1,89 @@
+//! Macros recorded from the haswaynav commands executed while recording and played back later.
+//!
+//! Recording is done by the daemon which is told about every top level `focus` and `run` command
+//! executed, see [crate::daemon::Request::Executed]. Macros are stored as plain text files with one
+//! step per line under `$XDG_CONFIG_HOME/haswaynav/macros`, so they can also be written by hand.
+
+#[cfg(feature = "cli")]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::config::default_config_dir;
+#[cfg(feature = "cli")]
+use crate::{config::Config, run_steps};
+
+/// The path of the file holding the named macro.
+pub fn macro_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        anyhow::bail!("Invalid macro name '{}'", name);
+    }
+    let dir = default_config_dir().ok_or_else(|| {
+        anyhow::format_err!("Neither XDG_CONFIG_HOME nor HOME is set to locate the macro directory")
+    })?;
+    Ok(dir.join("macros").join(name))
+}
+
+/// Parse the steps of a macro file, skipping blank lines and `#` comments.
+pub fn parse_macro(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Save the steps as the named macro, replacing any existing macro of that name.
+pub fn save_macro(name: &str, steps: &[String]) -> Result<()> {
+    let path = macro_path(name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating macro directory '{}'", dir.display()))?;
+    }
+    let mut contents = steps.join("\n");
+    contents.push('\n');
+    std::fs::write(&path, contents)
+        .with_context(|| format!("writing macro file '{}'", path.display()))
+}
+
+/// Load the steps of the named macro.
+pub fn load_macro(name: &str) -> Result<Vec<String>> {
+    let path = macro_path(name)?;
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("reading macro file '{}'", path.display()))?;
+    Ok(parse_macro(&contents))
+}
+
+#[cfg(feature = "cli")]
+/// Play the named macro over a single connection, see [run_steps].
+pub fn play_macro(
+    socket: &mut UnixStream,
+    config: &Config,
+    name: &str,
+    offline: bool,
+) -> Result<()> {
+    let steps = load_macro(name)?;
+    run_steps(
+        socket,
+        config,
+        &format!("macro '{}'", name),
+        &steps,
+        offline,
+    )
+}
+
+#[test]
+fn test_parse_macro() {
+    let contents = "# layout\nfocus left\n\n  run dev  \n";
+
+    assert_eq!(parse_macro(contents), ["focus left", "run dev"]);
+}
+
+#[test]
+fn test_macro_path_rejects_paths() {
+    assert!(macro_path("../config.toml").is_err());
+    assert!(macro_path("a/b").is_err());
+    assert!(macro_path("").is_err());
+}
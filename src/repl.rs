@@ -0,0 +1,164 @@
+//! An interactive prompt for trying out haswaynav and sway commands.
+//!
+//! Every line is run like a step of an alias, see [Step], and the commands sent to sway together
+//! with sway's replies are printed after each line.
+
+use std::os::unix::net::UnixStream;
+
+use anyhow::Result;
+use clap::CommandFactory;
+use rustyline::{
+    completion::Completer, error::ReadlineError, history::DefaultHistory, Editor, Helper,
+    Highlighter, Hinter, Validator,
+};
+
+use crate::{
+    capture::{collect_frames, take_frames, FrameDirection},
+    cli::Cli,
+    config::Config,
+    execute, run_checked, Step,
+};
+
+#[derive(Helper, Hinter, Highlighter, Validator)]
+/// Completes subcommand names, the possible values of their arguments and their flags.
+struct ReplHelper {
+    command: clap::Command,
+}
+
+impl ReplHelper {
+    /// The candidates for the word at the given index of the line.
+    fn candidates(&self, words: &[&str], idx: usize) -> Vec<String> {
+        if idx == 0 {
+            return self
+                .command
+                .get_subcommands()
+                .filter(|c| !c.is_hide_set())
+                .map(|c| c.get_name().to_string())
+                .chain(["exit".to_string()])
+                .collect();
+        }
+        let subcommand = match self.command.find_subcommand(words[0]) {
+            None => return Vec::new(),
+            Some(subcommand) => subcommand,
+        };
+        let positional = words[1..idx].iter().filter(|w| !w.starts_with('-')).count();
+        subcommand
+            .get_positionals()
+            .nth(positional)
+            .into_iter()
+            .flat_map(|a| a.get_possible_values())
+            .map(|v| v.get_name().to_string())
+            .chain(
+                subcommand
+                    .get_subcommands()
+                    .filter(|_| idx == 1)
+                    .map(|c| c.get_name().to_string()),
+            )
+            .chain(
+                subcommand
+                    .get_arguments()
+                    .filter_map(|a| a.get_long())
+                    .map(|l| format!("--{}", l)),
+            )
+            .collect()
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let words: Vec<&str> = line[..start].split_whitespace().collect();
+        let prefix = &line[start..];
+        let candidates = self
+            .candidates(&words, words.len())
+            .into_iter()
+            .filter(|c| c.starts_with(prefix))
+            .collect();
+        Ok((start, candidates))
+    }
+}
+
+/// Run a single line of input using the shared connection.
+fn run_line(line: &str, config: &Config, socket: &mut Option<UnixStream>) -> Result<()> {
+    let mut shared = || -> Result<UnixStream> {
+        if socket.is_none() {
+            *socket = Some(crate::sway_connect()?);
+        }
+        Ok(socket.as_ref().expect("connected above").try_clone()?)
+    };
+    match Step::parse(line) {
+        Step::Haswaynav(command) => {
+            execute(command, config, &mut shared, false, &mut std::io::stdout())
+        }
+        Step::Sway(commands) => run_checked(&mut shared()?, &commands),
+    }
+}
+
+/// Run the interactive prompt until end of input or `exit`.
+pub fn run_repl(config: &Config) -> Result<()> {
+    let mut editor: Editor<ReplHelper, DefaultHistory> = Editor::new()?;
+    editor.set_helper(Some(ReplHelper {
+        command: Cli::command(),
+    }));
+    collect_frames();
+
+    let mut socket = None;
+    loop {
+        let line = match editor.readline("haswaynav> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+        if line == "exit" || line == "quit" {
+            return Ok(());
+        }
+
+        let result = run_line(line, config, &mut socket);
+        for frame in take_frames() {
+            if frame.message_type != crate::messages::MessageType::RunCommand as u32 {
+                continue;
+            }
+            match frame.direction {
+                FrameDirection::Sent => println!("sway <- {}", frame.payload),
+                FrameDirection::Received => println!("sway -> {}", frame.payload),
+            }
+        }
+        if let Err(err) = result {
+            println!("error: {:#}", err);
+            // The connection may be in an unknown state after a failure.
+            socket = None;
+        }
+    }
+}
+
+#[test]
+fn test_candidates() {
+    let helper = ReplHelper {
+        command: Cli::command(),
+    };
+
+    let subcommands = helper.candidates(&[], 0);
+    assert!(subcommands.contains(&"focus".to_string()));
+    assert!(!subcommands.contains(&"complete".to_string()));
+
+    let directions = helper.candidates(&["focus"], 1);
+    assert!(directions.contains(&"left".to_string()));
+    assert!(directions.contains(&"--wrap".to_string()));
+    assert!(directions.contains(&"toggle-layer".to_string()));
+
+    assert!(helper.candidates(&["bogus"], 1).is_empty());
+}
@@ -0,0 +1,302 @@
+//! Resizing the windows of a container to given shares of it, e.g. 50/25/25, in one go. The
+//! commands are computed from the `percent` sway reports for every sibling and set the sizes with
+//! `resize set ... ppt`, rather than repeating grow and shrink steps which drift away from the
+//! sizes asked for.
+
+use std::os::unix::net::UnixStream;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    messages::get_tree,
+    run_checked,
+    tree::{cursor::find_focused_fast, Layout, NodeType, TreeNode},
+};
+
+/// The most legs [plan_shares] splits a change into.
+const MAX_LEGS: usize = 8;
+
+/// Compute the `resize set` steps giving the children of a split container the target shares,
+/// both given as fractions summing up to 1. Returns the index of the child and its size in percent
+/// of the container, to be run in order.
+///
+/// Sway gives a tiled window the size set by taking the difference from its neighbours, half
+/// from each or all of it from the only one at the edge. The changes of the children are therefore
+/// solved for as a whole, one child taking up the rest without a step of its own. Every child is
+/// tried as that one and the steps are ordered so that the windows shrink the least on the way.
+/// Sway refuses to shrink a window to nothing, so when a window would still shrink below half the
+/// smallest share before or after, the change is made in several legs with smaller swings.
+pub fn plan_shares(current: &[f64], target: &[f64]) -> Vec<(usize, u32)> {
+    let n = current.len();
+    if n < 2 || target.len() != n {
+        return Vec::new();
+    }
+    let floor = smallest(current).min(smallest(target)) / 2.0;
+    let mut steps = Vec::new();
+    for legs in 1..=MAX_LEGS {
+        let mut shares = current.to_vec();
+        let mut lowest = f64::INFINITY;
+        steps.clear();
+        for leg in 1..=legs {
+            let fraction = leg as f64 / legs as f64;
+            let waypoint: Vec<f64> = current
+                .iter()
+                .zip(target)
+                .map(|(c, t)| c + (t - c) * fraction)
+                .collect();
+            let Some((leg_steps, low)) = (0..n)
+                .filter_map(|rest| solve_deltas(&shares, &waypoint, rest))
+                .map(|deltas| order_steps(&shares, &deltas))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+            else {
+                break;
+            };
+            for (k, ppt) in &leg_steps {
+                shares = set_share(&shares, *k, *ppt);
+            }
+            lowest = lowest.min(low);
+            steps.extend(leg_steps);
+        }
+        if lowest >= floor {
+            break;
+        }
+    }
+    steps
+}
+
+/// How the shares change when the size of child `k` changes by `delta`.
+fn resized(shares: &mut [f64], k: usize, delta: f64) {
+    let n = shares.len();
+    shares[k] += delta;
+    match k {
+        0 => shares[1] -= delta,
+        k if k == n - 1 => shares[k - 1] -= delta,
+        k => {
+            shares[k - 1] -= delta / 2.0;
+            shares[k + 1] -= delta / 2.0;
+        }
+    }
+}
+
+/// The change of every child reaching the target with child `rest` left without a step, `None`
+/// when there is no such change.
+fn solve_deltas(current: &[f64], target: &[f64], rest: usize) -> Option<Vec<f64>> {
+    let n = current.len();
+    let unknowns: Vec<usize> = (0..n).filter(|k| *k != rest).collect();
+    // The rows of the equations, the changes being conserved makes the one of `rest` redundant.
+    let mut rows: Vec<Vec<f64>> = unknowns
+        .iter()
+        .map(|j| {
+            let mut row: Vec<f64> = unknowns
+                .iter()
+                .map(|k| {
+                    let mut effect = vec![0.0; n];
+                    resized(&mut effect, *k, 1.0);
+                    effect[*j]
+                })
+                .collect();
+            row.push(target[*j] - current[*j]);
+            row
+        })
+        .collect();
+    let m = unknowns.len();
+    for col in 0..m {
+        let pivot = (col..m).max_by(|a, b| rows[*a][col].abs().total_cmp(&rows[*b][col].abs()))?;
+        if rows[pivot][col].abs() < 1e-9 {
+            return None;
+        }
+        rows.swap(col, pivot);
+        let pivot_row = rows[col].clone();
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i != col {
+                let factor = row[col] / pivot_row[col];
+                for (x, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    let mut deltas = vec![0.0; n];
+    for (i, k) in unknowns.iter().enumerate() {
+        deltas[*k] = rows[i][m] / rows[i][i];
+    }
+    Some(deltas)
+}
+
+/// Order the steps making the changes, taking the one leaving the smallest window the largest
+/// first. Returns the steps and the smallest share a window had on the way.
+fn order_steps(current: &[f64], deltas: &[f64]) -> (Vec<(usize, u32)>, f64) {
+    let mut shares = current.to_vec();
+    let mut lowest = smallest(&shares);
+    let mut pending: Vec<usize> = (0..deltas.len())
+        .filter(|k| deltas[*k].abs() >= 0.005)
+        .collect();
+    let mut steps = Vec::new();
+    while !pending.is_empty() {
+        let (pos, low) = pending
+            .iter()
+            .enumerate()
+            .map(|(pos, k)| {
+                let mut after = shares.clone();
+                resized(&mut after, *k, deltas[*k]);
+                (pos, smallest(&after))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("steps are pending");
+        let k = pending.remove(pos);
+        let ppt = ((shares[k] + deltas[k]) * 100.0).round().clamp(1.0, 100.0) as u32;
+        shares = set_share(&shares, k, ppt);
+        lowest = lowest.min(low);
+        steps.push((k, ppt));
+    }
+    (steps, lowest)
+}
+
+/// The shares after sway set the size of child `k` to `ppt` percent, see [plan_shares].
+fn set_share(shares: &[f64], k: usize, ppt: u32) -> Vec<f64> {
+    let delta = f64::from(ppt) / 100.0 - shares[k];
+    let mut shares = shares.to_vec();
+    resized(&mut shares, k, delta);
+    shares
+}
+
+fn smallest(shares: &[f64]) -> f64 {
+    shares.iter().copied().fold(f64::INFINITY, f64::min)
+}
+
+/// Plan resizing the children of the split container holding the focused window to the shares
+/// given by the weights, e.g. `[50, 25, 25]` or `[2, 1, 1]`, or to equal sizes without weights.
+/// The container is the nearest one holding the focused window which is split horizontally or
+/// vertically and has more than one child.
+pub fn plan_distribute(tree: &TreeNode, weights: &[f64]) -> Result<Vec<String>> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    if focused.is_floating() {
+        anyhow::bail!("The focused window is floating, only tiled windows share a container");
+    }
+    let container = focused
+        .ancestors()
+        .into_iter()
+        .take_while(|a| matches!(a.get_node().node_type, NodeType::Con | NodeType::Workspace))
+        .find(|a| {
+            let node = a.get_node();
+            matches!(node.layout, Layout::SplitH | Layout::SplitV) && node.nodes.len() > 1
+        })
+        .context("The focused window shares no split container with other windows")?;
+    let node = container.get_node();
+    let n = node.nodes.len();
+    let weights = match weights {
+        [] => vec![1.0; n],
+        w if w.len() == n => w.to_vec(),
+        w => anyhow::bail!(
+            "The container holds {} windows but {} shares were given",
+            n,
+            w.len()
+        ),
+    };
+    if weights.iter().any(|w| !w.is_finite() || *w <= 0.0) {
+        anyhow::bail!("The shares have to be positive numbers");
+    }
+    let horizontal = node.layout == Layout::SplitH;
+    // The percent sway reports, or else the share of the geometry.
+    let current: Vec<f64> = node
+        .nodes
+        .iter()
+        .map(|c| match c.percent {
+            Some(percent) => f64::from(percent),
+            None if horizontal => f64::from(c.rect.width),
+            None => f64::from(c.rect.height),
+        })
+        .collect();
+    let normalize = |xs: &[f64]| -> Vec<f64> {
+        let sum: f64 = xs.iter().sum();
+        xs.iter().map(|x| x / sum).collect()
+    };
+    let axis = if horizontal { "width" } else { "height" };
+    Ok(plan_shares(&normalize(&current), &normalize(&weights))
+        .into_iter()
+        .map(|(k, ppt)| {
+            format!(
+                "[con_id={}] resize set {} {} ppt",
+                node.nodes[k].id, axis, ppt
+            )
+        })
+        .collect())
+}
+
+/// Resize the windows of the container holding the focused window to the shares, see
+/// [plan_distribute].
+#[tracing::instrument(skip(socket))]
+pub fn distribute(socket: &mut UnixStream, shares: &[f64]) -> Result<()> {
+    // The slim tree leaves out the percent.
+    let tree = get_tree(socket)?;
+    for command in plan_distribute(&tree, shares)? {
+        run_checked(socket, &command)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::{collection::vec, prelude::*};
+
+    fn apply(current: &[f64], steps: &[(usize, u32)]) -> Vec<f64> {
+        steps.iter().fold(current.to_vec(), |shares, (k, ppt)| {
+            set_share(&shares, *k, *ppt)
+        })
+    }
+
+    #[test]
+    fn plan_shares_of_three() {
+        let third = 1.0 / 3.0;
+        let steps = plan_shares(&[third, third, third], &[0.5, 0.25, 0.25]);
+        assert_eq!(steps, [(1, 50), (0, 50)]);
+        let reached = apply(&[third, third, third], &steps);
+        assert!(reached
+            .iter()
+            .zip([0.5, 0.25, 0.25])
+            .all(|(r, t)| (r - t).abs() < 1e-9));
+        // The last child only changes through its neighbour.
+        assert_eq!(plan_shares(&[0.5, 0.5], &[0.7, 0.3]), [(0, 70)]);
+        assert!(plan_shares(&[0.5, 0.5], &[0.5, 0.5]).is_empty());
+    }
+
+    #[test]
+    fn plan_distribute_focused_container() {
+        let tree = crate::tree::fixtures::load("multi-output").unwrap();
+        // The focused window shares its workspace with one container, equally.
+        assert!(plan_distribute(&tree, &[]).unwrap().is_empty());
+        assert_eq!(
+            plan_distribute(&tree, &[2.0, 1.0]).unwrap(),
+            ["[con_id=126] resize set width 67 ppt"]
+        );
+        assert!(plan_distribute(&tree, &[1.0; 9]).is_err());
+        assert!(plan_distribute(&tree, &[0.0, 1.0]).is_err());
+    }
+
+    proptest! {
+        // Windows take a few percent of the container at least, like the 100 px sway keeps them
+        // from shrinking below.
+        #[test]
+        fn plan_shares_reaches_the_target(
+            (current, target) in (2..7usize)
+                .prop_flat_map(|n| (vec(20..100u32, n), vec(20..100u32, n)))
+        ) {
+            let normalize = |xs: Vec<u32>| -> Vec<f64> {
+                let sum: u32 = xs.iter().sum();
+                xs.iter().map(|x| f64::from(*x) / f64::from(sum)).collect()
+            };
+            let (current, target) = (normalize(current), normalize(target));
+            let steps = plan_shares(&current, &target);
+            prop_assert!(steps.len() < current.len() * MAX_LEGS);
+            let mut reached = current.clone();
+            for (k, ppt) in &steps {
+                reached = set_share(&reached, *k, *ppt);
+                prop_assert!(smallest(&reached) > 0.0, "{:?} shrinks a window to nothing", steps);
+            }
+            for (r, t) in reached.iter().zip(&target) {
+                prop_assert!((r - t).abs() <= 0.02, "reached {:?} for {:?}", reached, target);
+            }
+        }
+    }
+}
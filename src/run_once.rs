@@ -0,0 +1,176 @@
+//! Keeping the state of the daemon without a resident daemon, see
+//! [crate::config::DaemonConfig::run_once]. Every invocation records the focused window before and
+//! after it runs in a file next to the daemon socket, which `focus back`, restoring the focus of
+//! outputs and workspaces and the state commands read when no daemon answers. Focus changes made
+//! without haswaynav between two invocations are only seen as a single one.
+//!
+//! The file is locked while it is read or written. Sway reports no timestamps in the tree, so the
+//! state of another sway session is dropped and windows missing from the tree are forgotten
+//! whenever the focus is recorded.
+
+use std::{
+    collections::BTreeSet,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::Config,
+    daemon::{default_socket_path, session_socket_path, swaysock, DaemonState, FocusChange},
+    messages::get_tree_slim,
+    tree::{
+        cursor::{find_focused_fast, Cursor},
+        windows, TreeNode,
+    },
+};
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+/// The content of the state file.
+struct StateFile {
+    /// The sway socket of the session the state was recorded in
+    swaysock: Option<String>,
+    state: DaemonState,
+}
+
+/// The path of the state file of this sway session, next to its daemon socket, e.g.
+/// `$XDG_RUNTIME_DIR/haswaynav.sway-ipc.1000.1234.json`.
+pub fn state_path() -> Result<PathBuf> {
+    Ok(session_socket_path()?.with_extension("json"))
+}
+
+/// Whether the state is kept in the file by this invocation: configured and no daemon listens.
+pub fn active(config: &Config) -> bool {
+    config.daemon.run_once && !default_socket_path().is_ok_and(|path| path.exists())
+}
+
+/// Read the state from the locked file, an empty state when it is empty or from another session.
+fn read(file: &mut File) -> Result<DaemonState> {
+    let mut json = String::new();
+    file.read_to_string(&mut json)?;
+    if json.trim().is_empty() {
+        return Ok(DaemonState::default());
+    }
+    let saved: StateFile = serde_json::from_str(&json).context("Failed to parse the state file")?;
+    if saved.swaysock != swaysock() {
+        tracing::debug!(swaysock = ?saved.swaysock, "dropping the state of another sway session");
+        return Ok(DaemonState::default());
+    }
+    Ok(saved.state)
+}
+
+/// Open the state file, `None` when it does not exist and is not to be created.
+fn open(create: bool) -> Result<Option<File>> {
+    let path = state_path()?;
+    match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(create)
+        .truncate(false)
+        .open(&path)
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to open {}", path.display())),
+    }
+}
+
+/// Change the state in the file while holding an exclusive lock on it.
+fn modify<T>(mut file: File, f: impl FnOnce(&mut DaemonState) -> T) -> Result<T> {
+    file.lock().context("Failed to lock the state file")?;
+    let mut state = read(&mut file)?;
+    let result = f(&mut state);
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    let saved = StateFile {
+        swaysock: swaysock(),
+        state,
+    };
+    serde_json::to_writer(&file, &saved)?;
+    Ok(result)
+}
+
+/// The state from the file, `None` when there is none.
+pub fn load() -> Result<Option<DaemonState>> {
+    let Some(mut file) = open(false)? else {
+        return Ok(None);
+    };
+    file.lock_shared()
+        .context("Failed to lock the state file")?;
+    read(&mut file).map(Some)
+}
+
+/// Change the state in the file, `None` without changing anything when there is none.
+pub fn update<T>(f: impl FnOnce(&mut DaemonState) -> T) -> Result<Option<T>> {
+    match open(false)? {
+        Some(file) => modify(file, f).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Forget the windows which are gone from the tree and record the focused window, unless it is
+/// focused already.
+pub fn observe(state: &mut DaemonState, tree: &TreeNode) {
+    let open: BTreeSet<i32> = windows(tree).map(|w| w.id).collect();
+    let known: BTreeSet<i32> = state
+        .mru
+        .iter()
+        .chain(state.last_focused_on_output.values())
+        .chain(state.last_focused_on_workspace.values())
+        .copied()
+        .collect();
+    for id in known.difference(&open) {
+        state.closed(*id);
+    }
+    let Some(focused) = find_focused_fast(tree).filter(|c| open.contains(&c.get_node().id)) else {
+        return;
+    };
+    let id = focused.get_node().id;
+    if state.mru.first() == Some(&id) {
+        return;
+    }
+    let name = |c: Option<Cursor>| c.and_then(|c| c.get_node().name.clone());
+    state.focused(FocusChange {
+        id,
+        output: name(focused.output()),
+        workspace: name(focused.workspace()),
+    });
+}
+
+/// Record the focus in the state file, creating it.
+pub fn record(socket: &mut UnixStream) -> Result<()> {
+    let tree = get_tree_slim(socket)?;
+    let file = open(true)?.context("Failed to create the state file")?;
+    modify(file, |state| observe(state, &tree))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn observe_the_focus() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let focused = find_focused_fast(&tree).unwrap().get_node().id;
+        let mut state = DaemonState::default();
+        state.mru.push(-1);
+        state
+            .last_focused_on_workspace
+            .insert("gone".to_string(), -1);
+
+        observe(&mut state, &tree);
+        assert_eq!(state.mru, [focused]);
+        assert!(!state.last_focused_on_workspace.contains_key("gone"));
+        assert_eq!(state.history.len(), 1);
+        assert!(state.history[0].workspace.is_some());
+
+        // The focus recorded already is not recorded again.
+        observe(&mut state, &tree);
+        assert_eq!(state.history.len(), 1);
+    }
+}
@@ -0,0 +1,236 @@
+//! Reproduction bundles capturing everything haswaynav reads from sway, written with
+//! `haswaynav snapshot <dir>` and used offline with `--snapshot <file>`, or with `--tree-file
+//! <file>` for a bundle of nothing but a saved tree.
+//!
+//! A bundle is a tar file holding the raw replies to the messages haswaynav sends, the versions of
+//! sway and haswaynav and the configuration file. Unlike a capture (see [crate::capture]) it does
+//! not depend on the command being reproduced: a fake sway answers every message with the reply
+//! in the bundle, however often it is asked, and reports every command as successful.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Read,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::Config,
+    messages::{get_json, read_frame, write_frame, MessageType},
+    tree::TreeNode,
+};
+
+/// The replies kept in a bundle and the names of their files.
+const REPLIES: [(&str, MessageType); 7] = [
+    ("tree.json", MessageType::GetTree),
+    ("workspaces.json", MessageType::GetWorkspaces),
+    ("outputs.json", MessageType::GetOutputs),
+    ("seats.json", MessageType::GetSeats),
+    ("marks.json", MessageType::GetMarks),
+    ("binding_state.json", MessageType::GetBindingState),
+    ("version.json", MessageType::GetVersion),
+];
+
+/// The file holding the version of haswaynav which wrote the bundle.
+const HASWAYNAV_VERSION: &str = "haswaynav-version";
+
+/// The file holding the configuration file of haswaynav, if there was one.
+const CONFIG: &str = "config.toml";
+
+/// Write a bundle of the current state of sway to the directory, named after the time, returning
+/// its path.
+#[tracing::instrument(skip(socket, config))]
+pub fn write_snapshot(socket: &mut UnixStream, dir: &Path, config: &Config) -> Result<PathBuf> {
+    let mut files = Vec::new();
+    for (name, message_type) in REPLIES {
+        let reply = get_json(socket, message_type).with_context(|| format!("fetching {}", name))?;
+        files.push((name, reply));
+    }
+    files.push((
+        HASWAYNAV_VERSION,
+        format!("{}\n", env!("CARGO_PKG_VERSION")).into_bytes(),
+    ));
+    if let Some(path) = &config.path {
+        let contents = std::fs::read(path)
+            .with_context(|| format!("reading config file '{}'", path.display()))?;
+        files.push((CONFIG, contents));
+    }
+
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("creating directory '{}'", dir.display()))?;
+    let path = dir.join(format!("haswaynav-snapshot-{}.tar", time));
+    write_bundle(&path, &files, time)?;
+    Ok(path)
+}
+
+/// Write the named files to a tar file.
+fn write_bundle(path: &Path, files: &[(&str, Vec<u8>)], mtime: u64) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("creating '{}'", path.display()))?;
+    let mut tar = tar::Builder::new(file);
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        tar.append_data(&mut header, name, contents.as_slice())
+            .with_context(|| format!("writing '{}'", path.display()))?;
+    }
+    tar.into_inner()
+        .with_context(|| format!("writing '{}'", path.display()))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+/// The contents of a bundle written by [write_snapshot], keyed by file name.
+pub struct Bundle {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl Bundle {
+    /// Read a bundle file.
+    pub fn load(path: &Path) -> Result<Bundle> {
+        let file =
+            File::open(path).with_context(|| format!("opening snapshot '{}'", path.display()))?;
+        let mut files = BTreeMap::new();
+        let mut archive = tar::Archive::new(file);
+        for entry in archive
+            .entries()
+            .with_context(|| format!("reading snapshot '{}'", path.display()))?
+        {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            let mut contents = Vec::new();
+            entry
+                .read_to_end(&mut contents)
+                .with_context(|| format!("reading '{}' of the snapshot", name))?;
+            files.insert(name, contents);
+        }
+        if !files.contains_key("tree.json") {
+            anyhow::bail!("'{}' is not a haswaynav snapshot", path.display());
+        }
+        Ok(Bundle { files })
+    }
+
+    /// A bundle of only the tree saved as JSON, e.g. with `swaymsg -t get_tree`.
+    pub fn from_tree(path: &Path) -> Result<Bundle> {
+        let tree = std::fs::read(path)
+            .with_context(|| format!("reading tree file '{}'", path.display()))?;
+        serde_json::from_slice::<TreeNode>(&tree)
+            .with_context(|| format!("decoding tree file '{}'", path.display()))?;
+        let files = BTreeMap::from([("tree.json".to_string(), tree)]);
+        Ok(Bundle { files })
+    }
+
+    /// The configuration bundled, `None` when there was no configuration file.
+    pub fn config(&self) -> Result<Option<Config>> {
+        let Some(contents) = self.files.get(CONFIG) else {
+            return Ok(None);
+        };
+        let contents = std::str::from_utf8(contents).context("decoding the bundled config")?;
+        Config::parse(contents)
+            .context("parsing the bundled config")
+            .map(Some)
+    }
+
+    /// The version of haswaynav which wrote the bundle.
+    pub fn haswaynav_version(&self) -> Option<&str> {
+        self.files
+            .get(HASWAYNAV_VERSION)
+            .and_then(|v| std::str::from_utf8(v).ok())
+            .map(str::trim)
+    }
+
+    /// The bundled reply to messages of the type.
+    fn reply(&self, message_type: u32) -> Option<&[u8]> {
+        let (name, _) = REPLIES.iter().find(|(_, t)| *t as u32 == message_type)?;
+        self.files.get(*name).map(Vec::as_slice)
+    }
+
+    /// Connect to a fake sway answering from the bundle, the commands it is asked to run are added
+    /// to `sent` instead.
+    pub fn connect(&self, sent: Arc<Mutex<Vec<String>>>) -> Result<UnixStream> {
+        let (client, server) = UnixStream::pair()?;
+        let bundle = self.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = bundle.serve(server, sent) {
+                tracing::warn!("answering from the snapshot: {:#}", err);
+            }
+        });
+        Ok(client)
+    }
+
+    fn serve(&self, mut sock: UnixStream, sent: Arc<Mutex<Vec<String>>>) -> Result<()> {
+        loop {
+            let (message_type, payload) = match read_frame(&mut sock) {
+                Ok(frame) => frame,
+                // The client hung up.
+                Err(_) => return Ok(()),
+            };
+            let reply = if message_type == MessageType::RunCommand as u32 {
+                let commands = String::from_utf8_lossy(&payload).into_owned();
+                let count = commands.split([';', ',']).count();
+                sent.lock().expect("sent lock poisoned").push(commands);
+                serde_json::to_vec(&vec![serde_json::json!({"success": true}); count])?
+            } else if message_type == MessageType::Subscribe as u32 {
+                // There are no events to wait for in a snapshot.
+                br#"{"success": false}"#.to_vec()
+            } else {
+                self.reply(message_type)
+                    .with_context(|| {
+                        format!("no reply to messages of type {} was saved", message_type)
+                    })?
+                    .to_vec()
+            };
+            write_frame(&mut sock, message_type, &reply)?;
+        }
+    }
+}
+
+#[test]
+fn test_bundle_round_trip() {
+    use crate::messages::{get_tree, run_command};
+
+    let dir = std::env::temp_dir().join(format!("haswaynav-snapshot-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("bundle.tar");
+    let tree = std::fs::read("tests/fixtures/trees/multi-output.json").unwrap();
+    let files = [
+        ("tree.json", tree.clone()),
+        (CONFIG, b"[focus]\nwrap = false\n".to_vec()),
+    ];
+    write_bundle(&path, &files, 0).unwrap();
+
+    let bundle = Bundle::load(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(bundle.files["tree.json"], tree);
+    assert!(!bundle.config().unwrap().unwrap().focus.wrap);
+    assert_eq!(bundle.haswaynav_version(), None);
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let mut socket = bundle.connect(sent.clone()).unwrap();
+    assert!(!get_tree(&mut socket).unwrap().nodes.is_empty());
+    assert!(!get_tree(&mut socket).unwrap().nodes.is_empty());
+    run_command(&mut socket, "focus left; focus right").unwrap();
+    assert_eq!(*sent.lock().unwrap(), ["focus left; focus right"]);
+}
+
+#[test]
+fn test_bundle_from_tree() {
+    use crate::messages::{get_outputs, get_tree};
+
+    let bundle = Bundle::from_tree(Path::new("tests/fixtures/trees/multi-output.json")).unwrap();
+    let mut socket = bundle.connect(Default::default()).unwrap();
+    assert!(!get_tree(&mut socket).unwrap().nodes.is_empty());
+    assert!(get_outputs(&mut socket).is_err());
+    assert!(Bundle::from_tree(Path::new("Cargo.toml")).is_err());
+}
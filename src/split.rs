@@ -0,0 +1,107 @@
+//! Choosing the split of the focused window from its shape on demand, like autotiling does for
+//! every new window, so the next window opens along the longer side.
+
+use std::{fmt, os::unix::net::UnixStream};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    messages::get_tree_slim,
+    run_checked,
+    tree::{cursor::find_focused_fast, NodeType, Rect, TreeNode},
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+/// The direction the focused window is split in, where the next window opens beside it.
+pub enum SplitDirection {
+    /// Side by side, sway's `splith`.
+    Horizontal,
+    /// Above each other, sway's `splitv`.
+    Vertical,
+}
+
+impl SplitDirection {
+    /// Split along the longer side of the rectangle, horizontally when it is square.
+    pub fn for_rect(rect: &Rect) -> Self {
+        if rect.width >= rect.height {
+            SplitDirection::Horizontal
+        } else {
+            SplitDirection::Vertical
+        }
+    }
+}
+
+impl fmt::Display for SplitDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SplitDirection::Horizontal => "splith",
+            SplitDirection::Vertical => "splitv",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+/// The split chosen for a window, see [crate::daemon::DaemonState::split].
+pub struct Split {
+    /// The id of the window which was split
+    pub id: i32,
+    pub direction: SplitDirection,
+}
+
+/// Choose the split of the focused window or container from its shape.
+pub fn plan_split_auto(tree: &TreeNode) -> Result<Split> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    let node = focused.get_node();
+    if !matches!(node.node_type, NodeType::Con | NodeType::FloatingCon) {
+        anyhow::bail!("The focused workspace has no windows to split");
+    }
+    Ok(Split {
+        id: node.id,
+        direction: SplitDirection::for_rect(&node.rect),
+    })
+}
+
+/// Split the focused window along its longer side, see [plan_split_auto].
+#[tracing::instrument(skip(socket))]
+pub fn split_auto(socket: &mut UnixStream) -> Result<Split> {
+    let tree = get_tree_slim(socket)?;
+    let split = plan_split_auto(&tree)?;
+    run_checked(
+        socket,
+        &format!("[con_id={}] {}", split.id, split.direction),
+    )?;
+    Ok(split)
+}
+
+#[test]
+fn test_plan_split_auto() {
+    let rect = |width, height| Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    };
+    assert_eq!(
+        SplitDirection::for_rect(&rect(1920, 1080)),
+        SplitDirection::Horizontal
+    );
+    assert_eq!(
+        SplitDirection::for_rect(&rect(800, 800)),
+        SplitDirection::Horizontal
+    );
+    assert_eq!(
+        SplitDirection::for_rect(&rect(960, 1080)),
+        SplitDirection::Vertical
+    );
+
+    // The focused window is 1280x1388.
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let split = plan_split_auto(&tree).unwrap();
+    assert_eq!(split.id, 126);
+    assert_eq!(split.direction, SplitDirection::Vertical);
+
+    let tree = crate::tree::fixtures::load("empty-workspace").unwrap();
+    assert!(plan_split_auto(&tree).is_err());
+}
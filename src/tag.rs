@@ -0,0 +1,233 @@
+//! Tags grouping windows independently of workspaces, like the tags of dwm. A window carries a tag
+//! as a hidden mark, so tags survive restarts of the daemon and of haswaynav, and viewing a tag
+//! gathers its windows on the focused workspace while the others wait on a workspace of their own.
+
+use std::os::unix::net::UnixStream;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    messages::get_tree_slim,
+    run_checked,
+    tree::{cursor::find_focused_fast, NodeType, TreeNode},
+    undo::{move_back, run_plan, Plan},
+};
+
+/// The start of the marks holding the tags. Sway does not show marks starting with `_` in the title
+/// bars.
+pub const TAG_MARK_PREFIX: &str = "_tag:";
+
+/// The workspace holding the windows which a [plan_view] moved away from the viewed workspace.
+pub const TAG_WORKSPACE: &str = "haswaynav-tags";
+
+/// The mark putting the tag on the window with the id, `_tag:<tag>:<id>`. Marks are unique in sway,
+/// so every window carries a mark of its own for the tag.
+pub fn tag_mark(tag: &str, id: i32) -> Result<String> {
+    check_tag(tag)?;
+    Ok(format!("{}{}:{}", TAG_MARK_PREFIX, tag, id))
+}
+
+/// Fail for tags which cannot be put in a mark.
+fn check_tag(tag: &str) -> Result<()> {
+    if tag.is_empty() || tag.contains('"') {
+        anyhow::bail!(
+            "Invalid tag '{}', tags are not empty and have no quotes",
+            tag
+        );
+    }
+    Ok(())
+}
+
+/// The mark of the node holding the tag, see [tag_mark].
+fn tag_mark_of<'a>(node: &'a TreeNode, tag: &str) -> Option<&'a String> {
+    node.marks.iter().find(|mark| {
+        mark.strip_prefix(TAG_MARK_PREFIX)
+            .and_then(|rest| rest.strip_prefix(tag))
+            .and_then(|rest| rest.strip_prefix(':'))
+            .is_some_and(|id| id.parse::<i32>().is_ok())
+    })
+}
+
+/// Whether the node has the tag.
+pub fn has_tag(node: &TreeNode, tag: &str) -> bool {
+    tag_mark_of(node, tag).is_some()
+}
+
+/// The focused node of the tree.
+fn focused_node(socket: &mut UnixStream) -> Result<TreeNode> {
+    let tree = get_tree_slim(socket)?;
+    let focused = find_focused_fast(&tree).context("Nothing is focused")?;
+    Ok(focused.get_node().clone())
+}
+
+/// Add the tag to the focused window.
+pub fn add_tag(socket: &mut UnixStream, tag: &str) -> Result<()> {
+    check_tag(tag)?;
+    let node = focused_node(socket)?;
+    if has_tag(&node, tag) {
+        return Ok(());
+    }
+    let command = format!(
+        "[con_id={}] mark --add \"{}\"",
+        node.id,
+        tag_mark(tag, node.id)?
+    );
+    run_checked(socket, &command)
+}
+
+/// The command removing the tag from the node, `None` when it does not have it.
+fn unmark_command(node: &TreeNode, tag: &str) -> Option<String> {
+    tag_mark_of(node, tag).map(|mark| format!("[con_id={}] unmark \"{}\"", node.id, mark))
+}
+
+/// Remove the tag from the focused window.
+pub fn remove_tag(socket: &mut UnixStream, tag: &str) -> Result<()> {
+    check_tag(tag)?;
+    match unmark_command(&focused_node(socket)?, tag) {
+        Some(command) => run_checked(socket, &command),
+        None => Ok(()),
+    }
+}
+
+/// Add the tag to the focused window or remove it when the window has it already.
+pub fn toggle_tag(socket: &mut UnixStream, tag: &str) -> Result<()> {
+    check_tag(tag)?;
+    let node = focused_node(socket)?;
+    let command = match unmark_command(&node, tag) {
+        Some(command) => command,
+        None => format!(
+            "[con_id={}] mark --add \"{}\"",
+            node.id,
+            tag_mark(tag, node.id)?
+        ),
+    };
+    run_checked(socket, &command)
+}
+
+/// Plan showing exactly the windows with the tag on the focused workspace: the windows there
+/// without the tag move to [TAG_WORKSPACE] and the tagged windows on other workspaces move to the
+/// focused one, keeping whether they float. Windows in the scratchpad are left alone. The focus
+/// stays on the focused window when it has the tag and moves to a tagged window otherwise. The
+/// inverse moves the windows back.
+pub fn plan_view(tree: &TreeNode, tag: &str) -> Result<Plan> {
+    check_tag(tag)?;
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    let workspace = focused
+        .workspace()
+        .and_then(|w| w.get_node().name.clone())
+        .context("The focused node is not on a workspace")?;
+    if workspace == TAG_WORKSPACE {
+        anyhow::bail!("The windows hidden by tags are on this workspace, focus another one");
+    }
+
+    let mut ids = Vec::new();
+    let mut forward = Vec::new();
+    let mut shown = None;
+    for window in tree.into_iter() {
+        let node = window.get_node();
+        if !matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+            || !node.nodes.is_empty()
+        {
+            continue;
+        }
+        let Some(on) = window.workspace().and_then(|w| w.get_node().name.clone()) else {
+            continue;
+        };
+        let tagged = has_tag(node, tag);
+        let target = match (on == workspace, tagged) {
+            (true, false) => TAG_WORKSPACE,
+            (false, true) if on != "__i3_scratch" => &workspace,
+            _ => continue,
+        };
+        if tagged && shown.is_none() {
+            shown = Some(node.id);
+        }
+        ids.push(node.id);
+        forward.push(format!(
+            "[con_id={}] move container to workspace \"{}\"",
+            node.id, target
+        ));
+    }
+    let node = focused.get_node();
+    if !has_tag(node, tag) {
+        if let Some(id) = shown {
+            forward.push(format!("[con_id={}] focus", id));
+        }
+    }
+    Ok(Plan {
+        inverse: move_back(tree, &ids),
+        ids,
+        forward,
+    })
+}
+
+/// Show the windows with the tag on the focused workspace, see [plan_view]. Returns the name of
+/// the workspace. The inverse is saved for `undo` with `journal`, see [run_plan].
+#[tracing::instrument(skip(socket))]
+pub fn view_tag(socket: &mut UnixStream, tag: &str, journal: bool) -> Result<String> {
+    let tree = get_tree_slim(socket)?;
+    let plan = plan_view(&tree, tag)?;
+    run_plan(socket, &plan, journal)?;
+    let workspace = find_focused_fast(&tree)
+        .and_then(|c| c.workspace())
+        .and_then(|w| w.get_node().name.clone())
+        .unwrap_or_default();
+    Ok(workspace)
+}
+
+#[test]
+fn test_plan_view() {
+    let mut tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let mark = |tree: &mut TreeNode, id: i32| {
+        fn find(node: &mut TreeNode, id: i32) -> Option<&mut TreeNode> {
+            if node.id == id {
+                return Some(node);
+            }
+            node.nodes
+                .iter_mut()
+                .chain(node.floating_nodes.iter_mut())
+                .find_map(|n| find(n, id))
+        }
+        let mark = tag_mark("work", id).unwrap();
+        find(tree, id).unwrap().marks.push(mark);
+    };
+    let moved =
+        |id: i32, to: &str| format!("[con_id={}] move container to workspace \"{}\"", id, to);
+
+    // Only window 259 on workspace 3 has the tag, so the focus follows it to workspace 2.
+    mark(&mut tree, 259);
+    let plan = plan_view(&tree, "work").unwrap();
+    let mut expected: Vec<_> = [126, 11, 130, 139]
+        .into_iter()
+        .map(|id| moved(id, TAG_WORKSPACE))
+        .collect();
+    expected.push(moved(259, "2"));
+    expected.push("[con_id=259] focus".to_string());
+    assert_eq!(plan.forward.len(), expected.len());
+    assert!(expected.iter().all(|c| plan.forward.contains(c)));
+    assert_eq!(plan.forward.last(), expected.last());
+    assert_eq!(plan.inverse, move_back(&tree, &plan.ids));
+
+    // The focused window keeps the focus when it has the tag.
+    mark(&mut tree, 126);
+    let plan = plan_view(&tree, "work").unwrap();
+    assert!(!plan.forward.contains(&moved(126, TAG_WORKSPACE)));
+    assert!(!plan.forward.iter().any(|c| c.ends_with("focus")));
+
+    assert!(plan_view(&tree, "a\"b").is_err());
+
+    // Both windows keep their mark, and tags are told apart from tags they start with.
+    let node = |id: i32| {
+        crate::Cursor::new(&tree)
+            .iter()
+            .find(|c| c.get_node().id == id)
+            .unwrap()
+            .get_node()
+    };
+    assert!(has_tag(node(259), "work") && has_tag(node(126), "work"));
+    assert!(!has_tag(node(126), "wor"));
+    assert_eq!(
+        unmark_command(node(126), "work").as_deref(),
+        Some("[con_id=126] unmark \"_tag:work:126\"")
+    );
+}
@@ -0,0 +1,134 @@
+//! Making window titles and other text from sway safe to print: control characters are removed and
+//! enormous titles are cut short, so a title cannot break the lines of a bar or fill the screen.
+//! Also the templates users format the output with, see [render_template].
+
+use anyhow::Result;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Appended to text which was cut short by [truncate].
+pub const ELLIPSIS: &str = "…";
+
+/// Replace line breaks and tabs by spaces and drop the other control characters.
+pub fn strip_control(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Cut the text short at `max` graphemes, the last of which becomes [ELLIPSIS]. Graphemes are
+/// what is seen as one character, so accents and emoji are never split.
+pub fn truncate(text: &str, max: usize) -> String {
+    let mut graphemes = text.grapheme_indices(true);
+    match graphemes.nth(max) {
+        None => text.to_string(),
+        Some(_) if max == 0 => String::new(),
+        Some(_) => {
+            let (end, _) = text
+                .grapheme_indices(true)
+                .nth(max - 1)
+                .expect("the text is longer than max");
+            format!("{}{}", &text[..end], ELLIPSIS)
+        }
+    }
+}
+
+/// The text as a double quoted argument of a sway command, escaping backslashes and quotes.
+pub fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Strip the control characters and truncate the text, see [strip_control] and [truncate].
+pub fn clean(text: &str, max: usize) -> String {
+    truncate(&strip_control(text), max)
+}
+
+/// Replace every `{name}` in the template by the value `lookup` returns for the name, failing for
+/// names it has no value for. Literal braces are written as `{{` and `}}`.
+///
+/// Modifiers follow the name after colons and apply in order: a number cuts the value short at that
+/// many characters, see [truncate], `lower` and `upper` change the case and `clean` strips control
+/// characters, e.g. `{title:clean:30}` or `{app_id:lower}`.
+pub fn render_template(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| anyhow::format_err!("Unclosed '{{' in '{}'", template))?;
+                let mut parts = rest[..end].split(':');
+                let name = parts.next().unwrap_or_default();
+                let mut value = lookup(name).ok_or_else(|| {
+                    anyhow::format_err!("Unknown field '{}' in '{}'", name, template)
+                })?;
+                for modifier in parts {
+                    value = apply_modifier(&value, modifier).ok_or_else(|| {
+                        anyhow::format_err!(
+                            "Unknown modifier '{}' in '{}', expected a length, lower, upper or clean",
+                            modifier,
+                            template
+                        )
+                    })?;
+                }
+                out.push_str(&value);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => anyhow::bail!("Unmatched '}}' in '{}', write '}}}}' for a brace", template),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn apply_modifier(value: &str, modifier: &str) -> Option<String> {
+    Some(match modifier {
+        "lower" => value.to_lowercase(),
+        "upper" => value.to_uppercase(),
+        "clean" => strip_control(value),
+        length => truncate(value, length.parse().ok()?),
+    })
+}
+
+#[test]
+fn test_render_template() {
+    let lookup = |name: &str| match name {
+        "title" => Some("Inbox\n— Mozilla Firefox".to_string()),
+        "app_id" => Some("Firefox".to_string()),
+        _ => None,
+    };
+    let render = |template| render_template(template, lookup);
+    assert_eq!(
+        render("{app_id:lower}: {title:clean:8}").unwrap(),
+        "firefox: Inbox —…"
+    );
+    assert_eq!(render("{{{app_id:upper}}}").unwrap(), "{FIREFOX}");
+    assert!(render("{app_id:shout}").is_err());
+    assert!(render("{app_id:-3}").is_err());
+    assert!(render("{pid}").is_err());
+}
+
+#[test]
+fn test_clean() {
+    assert_eq!(strip_control("a\nb\tc\u{7}d\u{1b}[0m"), "a b cd[0m");
+    assert_eq!(truncate("hello", 5), "hello");
+    assert_eq!(truncate("hello world", 5), "hell…");
+    assert_eq!(truncate("hello", 0), "");
+    // A flag is two code points but one grapheme, an accent combines with its letter.
+    assert_eq!(truncate("🇳🇱🇳🇱🇳🇱", 2), "🇳🇱…");
+    assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+    assert_eq!(clean("line\nbreak and more", 10), "line brea…");
+}
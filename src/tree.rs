@@ -1,10 +1,21 @@
 //! Data types for representing sways layout as a tree.
 
-use serde::Deserialize;
+use std::collections::HashSet;
 
+use serde::{Deserialize, Serialize};
+
+pub mod arena;
 pub mod cursor;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+#[cfg(test)]
+pub(crate) mod strategy;
+pub mod window;
+
+pub use window::{windows, Window};
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[non_exhaustive]
 #[serde(rename_all = "snake_case")]
 /// See [TreeNode::node_type]
 pub enum NodeType {
@@ -31,6 +42,7 @@ fn test_node_type_deserialize() {
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[non_exhaustive]
 #[serde(rename_all = "snake_case")]
 /// See [TreeNode::border]
 pub enum Border {
@@ -55,7 +67,8 @@ fn test_border_deserialize() {
     assert_eq!(parsed.as_ref(), expected);
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[non_exhaustive]
 #[serde(rename_all = "lowercase")]
 /// See [TreeNode::layout]
 pub enum Layout {
@@ -83,6 +96,7 @@ fn test_layout_deserialize() {
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[non_exhaustive]
 #[serde(rename_all = "lowercase")]
 /// See [TreeNode::orientation]
 pub enum Orientation {
@@ -106,7 +120,7 @@ fn test_orientation_deserialize() {
     assert_eq!(parsed.as_ref(), expected);
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
 /// The definition of a rectangle returned from sway to describe geometries
 pub struct Rect {
     pub x: i32,
@@ -115,6 +129,44 @@ pub struct Rect {
     pub height: i32,
 }
 
+impl Rect {
+    /// The center of the rectangle.
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    /// Do the rectangles share part of their horizontal extent.
+    pub fn overlaps_x(&self, other: &Rect) -> bool {
+        other.x < self.x + self.width && self.x < other.x + other.width
+    }
+
+    /// Do the rectangles share part of their vertical extent.
+    pub fn overlaps_y(&self, other: &Rect) -> bool {
+        other.y < self.y + self.height && self.y < other.y + other.height
+    }
+
+    /// Does the point lie within the rectangle.
+    pub fn contains_point(&self, x: i32, y: i32) -> bool {
+        self.x <= x && x < self.x + self.width && self.y <= y && y < self.y + self.height
+    }
+
+    /// Does the rectangle lie within this one.
+    pub fn contains(&self, other: &Rect) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// The squared distance between the centers of the rectangles.
+    pub fn distance_squared(&self, other: &Rect) -> i64 {
+        let (x1, y1) = self.center();
+        let (x2, y2) = other.center();
+        let (dx, dy) = ((x1 - x2) as i64, (y1 - y2) as i64);
+        dx * dx + dy * dy
+    }
+}
+
 #[test]
 fn test_rect_deserialize() {
     let json = r#"{"x": 0, "y": 1, "height": 2, "width": 3}"#;
@@ -132,6 +184,7 @@ fn test_rect_deserialize() {
 }
 
 #[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[non_exhaustive]
 #[serde(try_from = "i32")]
 /// See [TreeNode::fullscreen_mode]
 pub enum FullScreenMode {
@@ -173,6 +226,7 @@ fn test_full_screen_mode_deserialize() {
 }
 
 #[derive(Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
 #[serde(rename_all = "lowercase")]
 /// See [TreeNode::idle_inhibitors]
 pub enum ApplicationInhibitor {
@@ -181,6 +235,7 @@ pub enum ApplicationInhibitor {
 }
 
 #[derive(Deserialize, PartialEq, Eq, Debug, Copy, Clone)]
+#[non_exhaustive]
 #[serde(rename_all = "lowercase")]
 /// See [TreeNode::idle_inhibitors]
 pub enum UserInhibitor {
@@ -191,7 +246,22 @@ pub enum UserInhibitor {
     Visible,
 }
 
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+#[non_exhaustive]
+/// See [TreeNode::window_properties]
+pub struct WindowProperties {
+    /// The X11 window title
+    pub title: Option<String>,
+    /// The X11 class, the counterpart of [TreeNode::app_id]
+    pub class: Option<String>,
+    /// The X11 instance
+    pub instance: Option<String>,
+    /// The X11 window role
+    pub window_role: Option<String>,
+}
+
 #[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
 /// See [TreeNode::idle_inhibitors]
 pub struct InhibitorState {
     pub application: ApplicationInhibitor,
@@ -199,6 +269,7 @@ pub struct InhibitorState {
 }
 
 #[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[non_exhaustive]
 #[allow(dead_code)]
 /// The structure returned by the sway IPC `GET_TREE` message, see `man sway-ipc`.
 pub struct TreeNode {
@@ -253,12 +324,68 @@ pub struct TreeNode {
     pub visible: Option<bool>,
     /// (Only views) The shell of the view, such as xdg_shell or xwayland
     pub shell: Option<String>,
+    /// (Only xwayland views) An object containing the title, class, instance, window_role, window_type, and transient_for for the view
+    pub window_properties: Option<WindowProperties>,
     /// (Only views) Whether the view is inhibiting the idle state
     pub inhibit_idle: Option<bool>,
     /// (Only views) An object containing the state of the application and user idle inhibitors. application can be enabled or none. user can be focus, fullscreen, open, visible or none.
     pub idle_inhibitors: Option<InhibitorState>,
 }
 
+impl TreeNode {
+    /// Check the invariants sway keeps for its tree: the ids are unique, the focus arrays only
+    /// reference children, at most one node is focused and the tiled children of a container lie
+    /// within it. Useful to detect captures and fixtures edited into trees sway never reports.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut ids = HashSet::new();
+        let mut focused = Vec::new();
+        let mut problems = Vec::new();
+        self.validate_node(&mut ids, &mut focused, &mut problems);
+        if focused.len() > 1 {
+            problems.push(format!("the nodes {:?} are all focused", focused));
+        }
+        if !problems.is_empty() {
+            anyhow::bail!("Invalid tree: {}", problems.join("; "));
+        }
+        Ok(())
+    }
+
+    fn validate_node(
+        &self,
+        ids: &mut HashSet<i32>,
+        focused: &mut Vec<i32>,
+        problems: &mut Vec<String>,
+    ) {
+        if !ids.insert(self.id) {
+            problems.push(format!("the id {} is used more than once", self.id));
+        }
+        if self.focused {
+            focused.push(self.id);
+        }
+        let children = || self.nodes.iter().chain(&self.floating_nodes);
+        for id in &self.focus {
+            if !children().any(|c| c.id == *id) {
+                problems.push(format!(
+                    "the focus of node {} references {} which is not one of its children",
+                    self.id, id
+                ));
+            }
+        }
+        for child in &self.nodes {
+            // Fullscreen windows cover the workspace or all outputs rather than their parent.
+            if child.fullscreen_mode == FullScreenMode::None && !self.rect.contains(&child.rect) {
+                problems.push(format!(
+                    "node {} lies outside its parent {}",
+                    child.id, self.id
+                ));
+            }
+        }
+        for child in children() {
+            child.validate_node(ids, focused, problems);
+        }
+    }
+}
+
 impl<'a> IntoIterator for &'a TreeNode {
     type Item = cursor::Cursor<'a>;
     type IntoIter = cursor::CursorIterator<'a>;
@@ -267,19 +394,142 @@ impl<'a> IntoIterator for &'a TreeNode {
     }
 }
 
+#[derive(Deserialize, Debug, PartialEq, Clone, Default)]
+#[non_exhaustive]
+/// A lean decoding of the `GET_TREE` reply keeping only the fields needed for navigation, every
+/// other field is skipped without being decoded. Convert into a [TreeNode] to navigate it, the
+/// skipped fields then have their default values.
+pub struct TreeNodeSlim {
+    pub id: i32,
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    pub layout: Layout,
+    pub rect: Rect,
+    pub focused: bool,
+    pub focus: Vec<i32>,
+    pub marks: Vec<String>,
+    pub fullscreen_mode: FullScreenMode,
+    pub nodes: Vec<TreeNodeSlim>,
+    pub floating_nodes: Vec<TreeNodeSlim>,
+}
+
+impl From<TreeNodeSlim> for TreeNode {
+    fn from(slim: TreeNodeSlim) -> TreeNode {
+        TreeNode {
+            id: slim.id,
+            name: slim.name,
+            node_type: slim.node_type,
+            layout: slim.layout,
+            rect: slim.rect,
+            focused: slim.focused,
+            focus: slim.focus,
+            marks: slim.marks,
+            fullscreen_mode: slim.fullscreen_mode,
+            nodes: slim.nodes.into_iter().map(TreeNode::from).collect(),
+            floating_nodes: slim
+                .floating_nodes
+                .into_iter()
+                .map(TreeNode::from)
+                .collect(),
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn test_tree_node_slim_deserialize() {
+    let example = include_str!("../tests/fixtures/trees/multi-output.json");
+    let full: TreeNode = serde_json::from_str(example).unwrap();
+
+    let slim: TreeNode = serde_json::from_str::<TreeNodeSlim>(example)
+        .unwrap()
+        .into();
+
+    let summary = |tree: &TreeNode| {
+        tree.into_iter()
+            .map(|c| {
+                let n = c.get_node();
+                (
+                    n.id,
+                    n.layout,
+                    n.focused,
+                    n.rect,
+                    n.focus.clone(),
+                    n.fullscreen_mode,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(summary(&slim), summary(&full));
+}
+
 #[test]
 fn test_tree_node_deserialize() {
-    let example = include_str!("tree/sway-tree.json");
+    let example = include_str!("../tests/fixtures/trees/multi-output.json");
 
     let parsed: Result<TreeNode, serde_json::Error> = serde_json::from_str(example);
 
     match parsed {
-        Ok(_) => (),
+        Ok(tree) => tree.validate().unwrap(),
         Err(err) => panic!("error decoding example json: {:?}", err),
     }
 }
 
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_generated_trees_validate(tree in strategy::tree()) {
+        tree.validate().unwrap();
+    }
+}
+
+#[test]
+fn test_tree_node_validate() {
+    let node = |id, rect, nodes| TreeNode {
+        id,
+        rect,
+        nodes,
+        ..Default::default()
+    };
+    let rect = |x, width| Rect {
+        x,
+        y: 0,
+        width,
+        height: 100,
+    };
+    let valid = TreeNode {
+        focus: vec![2, 1],
+        ..node(
+            0,
+            rect(0, 200),
+            vec![
+                node(1, rect(0, 100), vec![]),
+                node(2, rect(100, 100), vec![]),
+            ],
+        )
+    };
+    assert!(valid.validate().is_ok());
+
+    let mut invalid = valid.clone();
+    invalid.focus.push(3);
+    invalid.nodes[1].id = 1;
+    invalid.nodes[1].rect.width = 200;
+    invalid.nodes[0].focused = true;
+    invalid.nodes[1].focused = true;
+    let message = invalid.validate().unwrap_err().to_string();
+    for problem in [
+        "references 3",
+        "id 1 is used more than once",
+        "node 1 lies outside its parent 0",
+        "[1, 1] are all focused",
+    ] {
+        assert!(message.contains(problem), "{} in {}", problem, message);
+    }
+}
+
 #[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
 /// The reply received when sending the `RUN_COMMAND` sway IPC message, see `man sway-ipc`.
 pub struct CommandResult {
     /// A boolean indacting whether the command was successful
@@ -289,3 +539,293 @@ pub struct CommandResult {
     /// A human readable error message in case of failure
     pub error: Option<String>,
 }
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone, Default)]
+#[non_exhaustive]
+/// An element of the reply received when sending the `GET_WORKSPACES` sway IPC message, see
+/// `man sway-ipc`.
+pub struct WorkspaceReply {
+    /// The workspace number or -1 for workspaces that do not start with a number
+    pub num: i32,
+    /// The name of the workspace
+    pub name: String,
+    /// Whether the workspace is currently visible on any output
+    pub visible: bool,
+    /// Whether the workspace is currently focused by the default seat (seat0)
+    pub focused: bool,
+    /// Whether a view on the workspace has the urgent flag set
+    pub urgent: bool,
+    /// The bounds of the workspace. It consists of x, y, width, and height
+    pub rect: Rect,
+    /// The name of the output that the workspace is on
+    pub output: String,
+}
+
+#[derive(Deserialize, PartialEq, Debug, Clone, Default)]
+#[non_exhaustive]
+/// An element of the reply received when sending the `GET_OUTPUTS` sway IPC message, see
+/// `man sway-ipc`.
+pub struct OutputReply {
+    /// The name of the output
+    pub name: String,
+    /// The make of the output
+    #[serde(default)]
+    pub make: String,
+    /// The model of the output
+    #[serde(default)]
+    pub model: String,
+    /// Whether the output is currently active
+    pub active: bool,
+    /// Whether the output is currently focused by the default seat (seat0)
+    #[serde(default)]
+    pub focused: bool,
+    /// The name of the workspace currently visible on the output or null for disabled outputs
+    pub current_workspace: Option<String>,
+    /// The bounds of the output
+    pub rect: Rect,
+    /// The scale of the output, missing for disabled outputs
+    #[serde(default)]
+    pub scale: Option<f64>,
+    /// The width of the display in millimetres, when sway reports it
+    #[serde(default)]
+    pub physical_width: Option<u32>,
+    /// The height of the display in millimetres, when sway reports it
+    #[serde(default)]
+    pub physical_height: Option<u32>,
+    /// How the output is rotated and flipped
+    #[serde(default)]
+    pub transform: Transform,
+}
+
+// Sway never reports a scale which is not a number, so the comparison is an equivalence.
+impl Eq for OutputReply {}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone, Default)]
+/// See [OutputReply::transform]
+pub enum Transform {
+    #[default]
+    #[serde(rename = "normal")]
+    Normal,
+    #[serde(rename = "90")]
+    Rotate90,
+    #[serde(rename = "180")]
+    Rotate180,
+    #[serde(rename = "270")]
+    Rotate270,
+    #[serde(rename = "flipped")]
+    Flipped,
+    #[serde(rename = "flipped-90")]
+    Flipped90,
+    #[serde(rename = "flipped-180")]
+    Flipped180,
+    #[serde(rename = "flipped-270")]
+    Flipped270,
+}
+
+impl Transform {
+    /// Whether the output is flipped and by how many quarter turns it is rotated clockwise.
+    pub fn flip_and_quarter_turns(&self) -> (bool, u8) {
+        match self {
+            Transform::Normal => (false, 0),
+            Transform::Rotate90 => (false, 1),
+            Transform::Rotate180 => (false, 2),
+            Transform::Rotate270 => (false, 3),
+            Transform::Flipped => (true, 0),
+            Transform::Flipped90 => (true, 1),
+            Transform::Flipped180 => (true, 2),
+            Transform::Flipped270 => (true, 3),
+        }
+    }
+}
+
+#[test]
+fn test_output_reply_deserialize() {
+    let json = r#"{"name": "HDMI-A-1", "make": "Unknown", "model": "NS-19E310A13", "active": true,
+        "dpms": true, "primary": false, "scale": 1.0, "subpixel_hinting": "rgb",
+        "transform": "normal", "current_workspace": "1", "focused": true,
+        "rect": {"x": 0, "y": 0, "width": 1680, "height": 1050}}"#;
+
+    let parsed: OutputReply = serde_json::from_str(json).unwrap();
+
+    assert_eq!(parsed.name, "HDMI-A-1");
+    assert!(parsed.focused);
+    assert_eq!(parsed.transform, Transform::Normal);
+    assert_eq!(parsed.current_workspace.as_deref(), Some("1"));
+}
+
+#[test]
+fn test_workspace_reply_deserialize() {
+    let json = r#"{"num": 1, "name": "1: web", "visible": true, "focused": false, "urgent": false,
+        "rect": {"x": 0, "y": 23, "width": 1920, "height": 1057}, "output": "eDP-1"}"#;
+
+    let parsed: WorkspaceReply = serde_json::from_str(json).unwrap();
+
+    assert_eq!(parsed.name, "1: web");
+    assert_eq!(parsed.output, "eDP-1");
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+/// The reply received when sending the `GET_VERSION` sway IPC message, see `man sway-ipc`.
+pub struct VersionReply {
+    /// The major version of the sway process
+    pub major: i32,
+    /// The minor version of the sway process
+    pub minor: i32,
+    /// The patch version of the sway process
+    pub patch: i32,
+    /// A human readable version string that will likely contain more useful information such as
+    /// the git commit short hash and git branch
+    pub human_readable: String,
+    /// The path to the loaded config file
+    pub loaded_config_file_name: String,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+/// The reply received when sending the `GET_BINDING_STATE` sway IPC message, see `man sway-ipc`.
+pub struct BindingStateReply {
+    /// The name of the active binding mode
+    pub name: String,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+/// The reply received when sending the `GET_CONFIG` sway IPC message, see `man sway-ipc`.
+pub struct ConfigReply {
+    /// The contents of the last loaded config file
+    pub config: String,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug, Clone)]
+#[non_exhaustive]
+/// The reply received when sending the `SUBSCRIBE` sway IPC message, see `man sway-ipc`.
+pub struct SubscribeResult {
+    /// Whether the subscription was successful
+    pub success: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[non_exhaustive]
+#[serde(rename_all = "snake_case")]
+/// See [WindowEvent::change]
+pub enum WindowChange {
+    New,
+    Close,
+    Focus,
+    Title,
+    FullscreenMode,
+    Move,
+    Floating,
+    Urgent,
+    Mark,
+    #[serde(other)]
+    Unknown,
+}
+
+#[test]
+fn test_window_change_deserialize() {
+    let json = r#"["new", "close", "focus", "title", "fullscreen_mode", "move", "floating", "urgent", "mark", "resize"]"#;
+
+    let expected = {
+        use WindowChange::*;
+        [
+            New,
+            Close,
+            Focus,
+            Title,
+            FullscreenMode,
+            Move,
+            Floating,
+            Urgent,
+            Mark,
+            Unknown,
+        ]
+    };
+
+    let parsed: Vec<WindowChange> = serde_json::from_str(json).unwrap();
+
+    assert_eq!(parsed.as_ref(), expected);
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[non_exhaustive]
+/// The payload of the `window` event sent by sway to subscribers, see `man sway-ipc`.
+pub struct WindowEvent {
+    /// What happened to the window
+    pub change: WindowChange,
+    /// The container of the window
+    pub container: TreeNode,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Default)]
+#[non_exhaustive]
+/// An element of the reply received when sending the `GET_INPUTS` sway IPC message, see
+/// `man sway-ipc`.
+pub struct InputReply {
+    /// The identifier of the device, used to configure it with `input <identifier>`
+    pub identifier: String,
+    /// The name of the device
+    pub name: String,
+    /// The kind of device, e.g. `keyboard` or `pointer`
+    #[serde(rename = "type")]
+    pub input_type: String,
+    /// The names of the keyboard layouts of a keyboard
+    #[serde(default)]
+    pub xkb_layout_names: Vec<String>,
+    /// The index of the active keyboard layout of a keyboard in [InputReply::xkb_layout_names]
+    #[serde(default)]
+    pub xkb_active_layout_index: Option<usize>,
+    /// The name of the active keyboard layout of a keyboard
+    #[serde(default)]
+    pub xkb_active_layout_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[non_exhaustive]
+/// The payload of the `input` event sent by sway to subscribers, see `man sway-ipc`.
+pub struct InputEvent {
+    /// What happened to the device, e.g. `xkb_layout` when the active keyboard layout changed
+    pub change: String,
+    /// The device which changed
+    pub input: InputReply,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+/// The payload of the `workspace` event sent by sway to subscribers, see `man sway-ipc`. The
+/// workspaces involved are left out.
+pub struct WorkspaceEvent {
+    /// What happened, e.g. `focus`, `init` or `reload` after the sway config was reloaded
+    pub change: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+/// The payload of the `binding` event sent by sway to subscribers, see `man sway-ipc`.
+pub struct BindingEvent {
+    /// What happened, always `run`
+    pub change: String,
+    /// The binding which ran
+    pub binding: BindingInfo,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
+/// The binding of a [BindingEvent], leaving out the keys and buttons.
+pub struct BindingInfo {
+    /// The sway commands the binding runs
+    pub command: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[non_exhaustive]
+/// An event received from sway after subscribing to it.
+pub enum Event {
+    Workspace(WorkspaceEvent),
+    Window(Box<WindowEvent>),
+    Input(Box<InputEvent>),
+    Binding(Box<BindingEvent>),
+    /// An event of a type which is not decoded, identified by its type without the event bit.
+    Other(u32),
+}
@@ -0,0 +1,186 @@
+//! A flat index of the tree with the parent of every node, an alternative to [Cursor]s for code
+//! which walks the whole tree and asks for the ancestors of many nodes.
+//!
+//! Building the index visits every node once, after which moving to the parent is an index lookup
+//! rather than following an `Rc` chain, and cursors into the index are `Copy`. Walking a large tree
+//! and looking up the workspace of every node is about twice as fast as with [Cursor]s,
+//! see `benches/cursor.rs`, while a single lookup does not pay for building the index.
+
+use crate::tree::{cursor::Cursor, NodeType, TreeNode};
+
+#[derive(Debug, Clone, Copy)]
+struct Entry<'a> {
+    node: &'a TreeNode,
+    parent: Option<usize>,
+    idx_in_parent: usize,
+}
+
+#[derive(Debug, Clone)]
+/// Every node of a tree with its parent, in the order [Cursor::iter] visits them: depth first,
+/// children before their parent.
+pub struct Arena<'a> {
+    entries: Vec<Entry<'a>>,
+}
+
+impl<'a> Arena<'a> {
+    /// Index the tree.
+    pub fn new(root: &'a TreeNode) -> Self {
+        let mut entries: Vec<Entry<'a>> = Vec::new();
+        // The nodes waiting for their children to be indexed, with the next child to visit.
+        let mut stack: Vec<(&'a TreeNode, usize)> = vec![(root, 0)];
+        // Where the parent of every node on the stack will be put, known once it is indexed.
+        let mut pending: Vec<Vec<usize>> = vec![Vec::new()];
+        while let Some((node, next)) = stack.last_mut() {
+            let node: &'a TreeNode = node;
+            let child = node
+                .nodes
+                .get(*next)
+                .or_else(|| node.floating_nodes.get(*next - node.nodes.len()));
+            match child {
+                Some(child) => {
+                    *next += 1;
+                    stack.push((child, 0));
+                    pending.push(Vec::new());
+                }
+                None => {
+                    let idx = entries.len();
+                    let children = pending.pop().expect("pending matches the stack");
+                    for child in children {
+                        entries[child].parent = Some(idx);
+                    }
+                    let (node, _) = stack.pop().expect("the stack is not empty");
+                    let idx_in_parent = stack.last().map_or(0, |(_, next)| next - 1);
+                    entries.push(Entry {
+                        node,
+                        parent: None,
+                        idx_in_parent,
+                    });
+                    if let Some(siblings) = pending.last_mut() {
+                        siblings.push(idx);
+                    }
+                }
+            }
+        }
+        Arena { entries }
+    }
+
+    /// The number of nodes in the tree.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Is the tree empty, which it never is since there is at least the root.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The root of the tree.
+    pub fn root(&self) -> ArenaCursor<'_, 'a> {
+        self.cursor(self.entries.len() - 1)
+    }
+
+    fn cursor(&self, idx: usize) -> ArenaCursor<'_, 'a> {
+        ArenaCursor { arena: self, idx }
+    }
+
+    /// All the nodes in the order [Cursor::iter] visits them.
+    pub fn iter(&self) -> impl Iterator<Item = ArenaCursor<'_, 'a>> + '_ {
+        (0..self.entries.len()).map(|idx| self.cursor(idx))
+    }
+
+    /// The focused node.
+    pub fn find_focused(&self) -> Option<ArenaCursor<'_, 'a>> {
+        self.iter().find(|c| c.get_node().focused)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A node of an [Arena], which can move to its ancestors.
+pub struct ArenaCursor<'t, 'a> {
+    arena: &'t Arena<'a>,
+    idx: usize,
+}
+
+impl<'t, 'a> ArenaCursor<'t, 'a> {
+    fn entry(&self) -> &Entry<'a> {
+        &self.arena.entries[self.idx]
+    }
+
+    /// The node under the cursor.
+    pub fn get_node(&self) -> &'a TreeNode {
+        self.entry().node
+    }
+
+    /// The parent of the node, `None` for the root.
+    pub fn parent(&self) -> Option<Self> {
+        self.entry().parent.map(|idx| self.arena.cursor(idx))
+    }
+
+    /// The ancestors of the node with the immediate parent first.
+    pub fn ancestors(&self) -> impl Iterator<Item = Self> {
+        std::iter::successors(self.parent(), |c| c.parent())
+    }
+
+    /// Is the node a floating child of its parent.
+    pub fn is_floating(&self) -> bool {
+        self.parent()
+            .is_some_and(|p| self.entry().idx_in_parent >= p.get_node().nodes.len())
+    }
+
+    /// The closest node of the given type, starting with the node itself.
+    pub fn closest(&self, node_type: NodeType) -> Option<Self> {
+        std::iter::once(*self)
+            .chain(self.ancestors())
+            .find(|c| c.get_node().node_type == node_type)
+    }
+
+    /// The workspace containing the node.
+    pub fn workspace(&self) -> Option<Self> {
+        self.closest(NodeType::Workspace)
+    }
+
+    /// The output containing the node.
+    pub fn output(&self) -> Option<Self> {
+        self.closest(NodeType::Output)
+    }
+
+    /// A [Cursor] at the same node, for the navigation only cursors offer.
+    pub fn to_cursor(&self) -> Cursor<'a> {
+        let mut path: Vec<usize> = std::iter::once(*self)
+            .chain(self.ancestors())
+            .filter(|c| c.parent().is_some())
+            .map(|c| c.entry().idx_in_parent)
+            .collect();
+        path.reverse();
+        let root = self.arena.root().get_node();
+        path.into_iter().fold(Cursor::new(root), |c, idx| {
+            c.child(idx).expect("the path leads through the tree")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest::proptest! {
+        #[test]
+        fn same_as_cursors(tree in crate::tree::strategy::tree()) {
+            let arena = Arena::new(&tree);
+            let cursors: Vec<Cursor> = tree.into_iter().collect();
+            assert_eq!(arena.len(), cursors.len());
+            for (a, c) in arena.iter().zip(&cursors) {
+                assert_eq!(a.get_node().id, c.get_node().id);
+                assert_eq!(a.is_floating(), c.is_floating());
+                let ancestors: Vec<i32> = a.ancestors().map(|x| x.get_node().id).collect();
+                let expected: Vec<i32> = c.ancestors().iter().map(|x| x.get_node().id).collect();
+                assert_eq!(ancestors, expected);
+                assert_eq!(a.to_cursor().get_node().id, c.get_node().id);
+            }
+            assert_eq!(
+                arena.find_focused().map(|c| c.get_node().id),
+                crate::tree::cursor::find_focused(&tree).map(|c| c.get_node().id)
+            );
+        }
+    }
+}
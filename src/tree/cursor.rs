@@ -2,13 +2,29 @@
 
 use std::{default::Default, rc::Rc};
 
-use crate::tree::TreeNode;
+use crate::tree::{Layout, NodeType, TreeNode};
 
 /// Find the currently focused node in the sway tree layout.
-pub fn find_focused(root: &TreeNode) -> Option<Cursor> {
+pub fn find_focused(root: &TreeNode) -> Option<Cursor<'_>> {
     root.into_iter().find(|c| c.node.focused)
 }
 
+/// Find the currently focused node by following the [TreeNode::focus] lists down from the root,
+/// visiting only the nodes on the way rather than the whole tree like [find_focused]. Falls back
+/// to [find_focused] when the lists do not lead to a focused node.
+pub fn find_focused_fast(root: &TreeNode) -> Option<Cursor<'_>> {
+    let mut c = Cursor::new(root);
+    loop {
+        if c.node.focused {
+            return Some(c);
+        }
+        c = match c.node.focus.first().and_then(|id| c.child_by_id(*id)) {
+            Some(child) => child,
+            None => return find_focused(root),
+        };
+    }
+}
+
 #[derive(Debug, Clone)]
 /// A cursor into the sway tree layout which keeps track of where it is in the tree.
 ///
@@ -30,20 +46,17 @@ impl<'a> Cursor<'a> {
         }
     }
 
-    fn left_most_descendant(mut self) -> Self {
-        loop {
-            self = match self.descend() {
-                Result::Err(c) => return c,
-                Result::Ok(c) => c,
-            }
-        }
-    }
-
     /// Get the node associated with the cursor
     pub fn get_node(&self) -> &'a TreeNode {
         self.node
     }
 
+    /// The index of the node among the children of its parent, floating children counting after
+    /// the tiling ones.
+    pub fn index_in_parent(&self) -> usize {
+        self.idx_in_parent
+    }
+
     /// Is the node associated with the cursor a floating node
     pub fn is_floating(&self) -> bool {
         match &self.parent {
@@ -64,14 +77,113 @@ impl<'a> Cursor<'a> {
         vec
     }
 
+    /// Get the closest node of the given type, starting with the node under the cursor followed by
+    /// its ancestors.
+    pub fn closest(&self, node_type: NodeType) -> Option<Self> {
+        if self.node.node_type == node_type {
+            Some(self.clone())
+        } else {
+            self.ancestors()
+                .into_iter()
+                .find(|c| c.node.node_type == node_type)
+        }
+    }
+
+    /// Get the workspace containing the node under the cursor.
+    pub fn workspace(&self) -> Option<Self> {
+        self.closest(NodeType::Workspace)
+    }
+
+    /// Get the output containing the node under the cursor.
+    pub fn output(&self) -> Option<Self> {
+        self.closest(NodeType::Output)
+    }
+
+    /// The tiling child which is shown when the node is tabbed or stacked, the most recently
+    /// focused one or the first if none was focused yet. For split nodes this is the child sway
+    /// focuses when moving into the node.
+    pub fn visible_child(&self) -> Option<Self> {
+        self.children_by_focus()
+            .into_iter()
+            .find(|c| !c.is_floating())
+    }
+
+    /// The index of the child with the id, counting tiling children before floating ones like
+    /// [Cursor::child].
+    pub fn child_index(&self, id: i32) -> Option<usize> {
+        self.node
+            .nodes
+            .iter()
+            .chain(&self.node.floating_nodes)
+            .position(|n| n.id == id)
+    }
+
+    /// Descend into the child with the id, tiling or floating.
+    pub fn child_by_id(&self, id: i32) -> Option<Self> {
+        let idx = self.child_index(id)?;
+        self.clone().child(idx).ok()
+    }
+
+    /// The children ordered by the [TreeNode::focus] list, most recently focused first, followed
+    /// by the children which were never focused in their order in the tree. Ids in the list which
+    /// are not children are skipped.
+    pub fn children_by_focus(&self) -> Vec<Self> {
+        let mut indexes: Vec<usize> = self
+            .node
+            .focus
+            .iter()
+            .filter_map(|id| self.child_index(*id))
+            .collect();
+        let count = self.node.nodes.len() + self.node.floating_nodes.len();
+        for idx in 0..count {
+            if !indexes.contains(&idx) {
+                indexes.push(idx);
+            }
+        }
+        indexes
+            .into_iter()
+            .filter_map(|idx| self.clone().child(idx).ok())
+            .collect()
+    }
+
+    /// Is the node under the cursor actually on screen, i.e. not a hidden tab or stack entry and on
+    /// a workspace which is shown on its output. Nothing in the scratchpad is, sway moves windows
+    /// shown from the scratchpad to a workspace.
+    pub fn is_visible(&self) -> bool {
+        let mut child = self.clone();
+        while let Ok(parent) = child.clone().ascend() {
+            let shows_one = matches!(parent.node.layout, Layout::Tabbed | Layout::Stacked)
+                || parent.node.node_type == NodeType::Output;
+            if shows_one
+                && !child.is_floating()
+                && parent
+                    .visible_child()
+                    .is_some_and(|v| v.idx_in_parent != child.idx_in_parent)
+            {
+                return false;
+            }
+            if parent.node.node_type == NodeType::Output {
+                return parent.node.name.as_deref() != Some("__i3");
+            }
+            child = parent;
+        }
+        true
+    }
+
     /// Descend into the first child node if possible or return self on failure.
-    pub fn descend(mut self) -> Result<Self, Self> {
-        match self.deref_child(0) {
+    pub fn descend(self) -> Result<Self, Self> {
+        self.child(0)
+    }
+
+    /// Descend into the child at the given index, counting tiling children before floating ones,
+    /// or return self on failure.
+    pub fn child(mut self, idx: usize) -> Result<Self, Self> {
+        match self.deref_child(idx) {
             None => Err(self),
             Some(child) => {
                 self.parent = Some(Rc::new(self.clone()));
                 self.node = child;
-                self.idx_in_parent = 0;
+                self.idx_in_parent = idx;
                 Result::Ok(self)
             }
         }
@@ -140,6 +252,16 @@ impl<'a> Cursor<'a> {
     pub fn iter(self) -> CursorIterator<'a> {
         CursorIterator::new(self)
     }
+
+    /// Return an iterator over the subtree of the node iterating depth first left to right, every
+    /// node before its children. Subtrees can be skipped with [PreOrderIterator::prune].
+    pub fn pre_order(self) -> PreOrderIterator<'a, fn(&Cursor<'a>) -> bool> {
+        PreOrderIterator {
+            next: Some(self),
+            stack: Vec::new(),
+            prune: |_| false,
+        }
+    }
 }
 
 impl<'a> IntoIterator for Cursor<'a> {
@@ -150,13 +272,69 @@ impl<'a> IntoIterator for Cursor<'a> {
     }
 }
 
+#[derive(Debug)]
+/// A node on the path of a [CursorIterator] from the root to the node it visits next.
+struct Frame<'a> {
+    node: &'a TreeNode,
+    idx_in_parent: usize,
+    /// The index of the next child to visit
+    next_child: usize,
+    /// The cursor at the node, shared as the parent of its children. Only made once the first child
+    /// is visited, so leaves never need one.
+    cursor: Option<Rc<Cursor<'a>>>,
+}
+
 #[derive(Debug)]
 /// A depth first left to right iterator over a sway tree hierarchy based on [Cursor]s.
-pub struct CursorIterator<'a>(Result<Cursor<'a>, Cursor<'a>>);
+///
+/// The iterator keeps the path to the node it visits next as a stack of nodes and child indices
+/// and only makes a [Cursor] when yielding a node, sharing the parents of the cursors it yields.
+pub struct CursorIterator<'a> {
+    stack: Vec<Frame<'a>>,
+}
 
 impl<'a> CursorIterator<'a> {
     pub fn new(c: Cursor<'a>) -> Self {
-        CursorIterator(Result::Ok(Cursor::left_most_descendant(c)))
+        // Continue after the cursor with its next siblings and then its ancestors.
+        let mut stack = Vec::new();
+        let mut next = c.parent.clone();
+        let mut idx_in_parent = c.idx_in_parent;
+        while let Some(parent) = next {
+            stack.push(Frame {
+                node: parent.node,
+                idx_in_parent: parent.idx_in_parent,
+                next_child: idx_in_parent + 1,
+                cursor: Some(parent.clone()),
+            });
+            idx_in_parent = parent.idx_in_parent;
+            next = parent.parent.clone();
+        }
+        stack.reverse();
+        stack.push(Frame {
+            node: c.node,
+            idx_in_parent: c.idx_in_parent,
+            next_child: 0,
+            cursor: None,
+        });
+        CursorIterator { stack }
+    }
+
+    /// The parent shared by the children of the node on top of the stack.
+    fn top_cursor(&mut self) -> Rc<Cursor<'a>> {
+        let len = self.stack.len();
+        let parent = len
+            .checked_sub(2)
+            .and_then(|idx| self.stack[idx].cursor.clone());
+        let top = &mut self.stack[len - 1];
+        top.cursor
+            .get_or_insert_with(|| {
+                Rc::new(Cursor {
+                    parent,
+                    node: top.node,
+                    idx_in_parent: top.idx_in_parent,
+                })
+            })
+            .clone()
     }
 }
 
@@ -164,23 +342,104 @@ impl<'a> std::iter::Iterator for CursorIterator<'a> {
     type Item = Cursor<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut c = match self {
-            CursorIterator(Result::Err(_)) => return None,
-            CursorIterator(Result::Ok(c)) => match c.clone().next_sibling() {
-                Result::Err(c) => c.ascend(),
-                Result::Ok(c) => Result::Ok(Cursor::left_most_descendant(c)),
-            },
-        };
+        loop {
+            let top = self.stack.last_mut()?;
+            let idx = top.next_child;
+            let child = if idx < top.node.nodes.len() {
+                top.node.nodes.get(idx)
+            } else {
+                top.node.floating_nodes.get(idx - top.node.nodes.len())
+            };
+            if let Some(child) = child {
+                top.next_child += 1;
+                self.top_cursor();
+                self.stack.push(Frame {
+                    node: child,
+                    idx_in_parent: idx,
+                    next_child: 0,
+                    cursor: None,
+                });
+                continue;
+            }
+
+            let frame = self.stack.pop()?;
+            return Some(match frame.cursor {
+                Some(cursor) => Rc::try_unwrap(cursor).unwrap_or_else(|c| c.as_ref().clone()),
+                None => Cursor {
+                    parent: self.stack.last().and_then(|p| p.cursor.clone()),
+                    node: frame.node,
+                    idx_in_parent: frame.idx_in_parent,
+                },
+            });
+        }
+    }
+}
 
-        std::mem::swap(&mut self.0, &mut c);
+/// A depth first left to right iterator over the subtree of a [Cursor], visiting every node before
+/// its children, see [Cursor::pre_order].
+pub struct PreOrderIterator<'a, P> {
+    /// The node to yield next, before moving on to its children
+    next: Option<Cursor<'a>>,
+    /// The nodes whose children are being visited with the index of the next child to visit
+    stack: Vec<(Rc<Cursor<'a>>, usize)>,
+    prune: P,
+}
 
-        match c {
-            Result::Err(_) => None,
-            Result::Ok(c) => Some(c),
+impl<'a, P: FnMut(&Cursor<'a>) -> bool> PreOrderIterator<'a, P> {
+    /// Skip the descendants of the nodes for which `prune` returns true, the nodes themselves are
+    /// still yielded. E.g. to look at the windows of one workspace only:
+    ///
+    /// ```
+    /// # use haswaynav::{Cursor, TreeNode, tree::NodeType};
+    /// # fn windows(tree: &TreeNode) -> usize {
+    /// Cursor::new(tree)
+    ///     .pre_order()
+    ///     .prune(|c| {
+    ///         let node = c.get_node();
+    ///         node.node_type == NodeType::Workspace && node.name.as_deref() != Some("3")
+    ///     })
+    ///     .filter(|c| c.get_node().node_type == NodeType::Con)
+    ///     .count()
+    /// # }
+    /// ```
+    pub fn prune<Q: FnMut(&Cursor<'a>) -> bool>(self, prune: Q) -> PreOrderIterator<'a, Q> {
+        PreOrderIterator {
+            next: self.next,
+            stack: self.stack,
+            prune,
         }
     }
 }
 
+impl<'a, P: FnMut(&Cursor<'a>) -> bool> std::iter::Iterator for PreOrderIterator<'a, P> {
+    type Item = Cursor<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.next.is_none() {
+            let (parent, idx) = self.stack.last_mut()?;
+            match parent.deref_child(*idx) {
+                Some(node) => {
+                    self.next = Some(Cursor {
+                        parent: Some(parent.clone()),
+                        node,
+                        idx_in_parent: *idx,
+                    });
+                    *idx += 1;
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        let c = self.next.take()?;
+        let has_children = !c.node.nodes.is_empty() || !c.node.floating_nodes.is_empty();
+        if has_children && !(self.prune)(&c) {
+            self.stack.push((Rc::new(c.clone()), 0));
+        }
+        Some(c)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +536,17 @@ mod tests {
             );
         }
 
+        #[test]
+        fn navigation_child() {
+            let tree = build_tree();
+            let res: Result<Cursor, Cursor> = (|| Cursor::new(&tree).descend()?.child(2))();
+            assert_eq!(
+                "i",
+                &res.unwrap().node.name.clone().unwrap_or("".to_string())
+            );
+            assert!(Cursor::new(&tree).child(1).is_err());
+        }
+
         #[test]
         fn is_floating() {
             let tree = build_tree();
@@ -296,7 +566,7 @@ mod tests {
                     .clone()
                     .unwrap_or("".to_string())
             );
-            assert_eq!(false, res.unwrap().is_floating());
+            assert!(!res.unwrap().is_floating());
         }
 
         #[test]
@@ -319,7 +589,43 @@ mod tests {
                     .clone()
                     .unwrap_or("".to_string())
             );
-            assert_eq!(true, res.unwrap().is_floating());
+            assert!(res.unwrap().is_floating());
+        }
+
+        #[test]
+        fn children_by_focus() {
+            let tree = crate::tree::fixtures::load("multi-output").unwrap();
+            let ids = |c: &Cursor| {
+                c.children_by_focus()
+                    .iter()
+                    .map(|c| c.node.id)
+                    .collect::<Vec<_>>()
+            };
+            let tabs = Cursor::new(&tree)
+                .into_iter()
+                .find(|c| c.node.id == 261)
+                .unwrap();
+            let by_focus = ids(&tabs);
+            assert_eq!(by_focus.len(), 4);
+            assert_eq!(by_focus[..tabs.node.focus.len()], tabs.node.focus[..]);
+            assert_eq!(
+                tabs.visible_child().map(|c| c.node.id),
+                by_focus.first().copied()
+            );
+            let child = tabs.child_by_id(355).unwrap();
+            assert_eq!(child.index_in_parent(), 1);
+            assert_eq!(child.ascend().unwrap().node.id, 261);
+            assert!(tabs.child_by_id(126).is_none());
+
+            // Children never focused come in the order of the tree.
+            let tree = build_tree();
+            let b = Cursor::new(&tree).descend().unwrap();
+            let names: String = b
+                .children_by_focus()
+                .iter()
+                .filter_map(|c| c.node.name.clone())
+                .collect();
+            assert_eq!(names, "cdi");
         }
 
         #[test]
@@ -350,6 +656,46 @@ mod tests {
             assert_eq!(names.join(""), "cfehgdjiba".to_string());
         }
 
+        #[test]
+        fn pre_order() {
+            let tree = build_tree();
+            let names = |i: &mut dyn Iterator<Item = Cursor>| {
+                i.map(|c| c.node.name.clone().unwrap_or("".to_string()))
+                    .collect::<String>()
+            };
+
+            assert_eq!(names(&mut Cursor::new(&tree).pre_order()), "abcdefghij");
+            assert_eq!(
+                names(
+                    &mut Cursor::new(&tree)
+                        .pre_order()
+                        .prune(|c| c.node.name.as_deref() == Some("d"))
+                ),
+                "abcdij"
+            );
+            let d = Cursor::new(&tree).descend().unwrap().child(1).unwrap();
+            assert_eq!(names(&mut d.pre_order()), "defgh");
+            let g = Cursor::new(&tree)
+                .pre_order()
+                .find(|c| c.node.name.as_deref() == Some("g"))
+                .unwrap();
+            assert!(g.is_floating());
+            assert_eq!(g.ancestors().len(), 3);
+        }
+
+        #[test]
+        fn traversal_from_inner_node() {
+            let tree = build_tree();
+            let d = Cursor::new(&tree).descend().unwrap().child(1).unwrap();
+
+            let names = d
+                .iter()
+                .map(|c| c.node.name.clone().unwrap_or("".to_string()))
+                .collect::<Vec<String>>();
+
+            assert_eq!(names.join(""), "fehgdjiba".to_string());
+        }
+
         #[test]
         fn into_iter() {
             let tree = build_tree();
@@ -364,6 +710,40 @@ mod tests {
             );
         }
 
+        #[test]
+        fn visibility() {
+            let view = |id| TreeNode {
+                id,
+                ..Default::default()
+            };
+            let tree = TreeNode {
+                node_type: NodeType::Output,
+                nodes: vec![TreeNode {
+                    node_type: NodeType::Workspace,
+                    layout: Layout::Tabbed,
+                    focus: vec![3, 2],
+                    nodes: vec![view(2), view(3)],
+                    ..view(1)
+                }],
+                ..view(0)
+            };
+            let visible = |id| {
+                tree.into_iter()
+                    .find(|c| c.node.id == id)
+                    .unwrap()
+                    .is_visible()
+            };
+
+            assert!(!visible(2));
+            assert!(visible(3));
+            assert_eq!(
+                Cursor::new(&tree.nodes[0])
+                    .visible_child()
+                    .map(|c| c.node.id),
+                Some(3)
+            );
+        }
+
         #[test]
         fn find() {
             let tree = build_tree();
@@ -376,4 +756,54 @@ mod tests {
             assert_eq!(&trace, "fedba");
         }
     }
+
+    fn count(node: &TreeNode) -> usize {
+        1 + node
+            .nodes
+            .iter()
+            .chain(&node.floating_nodes)
+            .map(count)
+            .sum::<usize>()
+    }
+
+    #[test]
+    fn find_focused_fast_matches() {
+        for (name, _) in crate::tree::fixtures::FIXTURES {
+            let tree = crate::tree::fixtures::load(name).unwrap();
+            assert_eq!(
+                find_focused_fast(&tree).map(|c| c.node.id),
+                find_focused(&tree).map(|c| c.node.id),
+                "{}",
+                name
+            );
+        }
+        // Without focus lists the whole tree is searched.
+        let tree = build_tree();
+        assert_eq!(
+            find_focused_fast(&tree).map(|c| c.node.name.clone()),
+            Some(Some("f".to_string()))
+        );
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn traversal_of_generated_trees(tree in crate::tree::strategy::tree()) {
+            let ids: std::collections::HashSet<i32> = tree.into_iter().map(|c| c.node.id).collect();
+            assert_eq!(tree.into_iter().count(), count(&tree));
+            assert_eq!(ids.len(), count(&tree));
+
+            for c in &tree {
+                let ancestors = c.ancestors();
+                assert_eq!(ancestors.last().map_or(c.node.id, |a| a.node.id), tree.id);
+                if matches!(c.node.node_type, NodeType::Con | NodeType::FloatingCon) {
+                    assert!(c.workspace().is_some() && c.output().is_some());
+                }
+            }
+            if let Some(focused) = find_focused(&tree) {
+                // A window, or a container after `focus parent`.
+                assert!(matches!(focused.node.node_type, NodeType::Con | NodeType::FloatingCon));
+                assert!(focused.is_visible());
+            }
+        }
+    }
 }
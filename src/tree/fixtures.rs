@@ -0,0 +1,163 @@
+//! A corpus of `GET_TREE` replies for testing code navigating sway's tree, enabled with the
+//! `fixtures` feature. The trees live in `tests/fixtures/trees` of the repository.
+//!
+//! | Name | Scenario |
+//! |------|----------|
+//! | `multi-output` | Captured from sway, two outputs with split and tabbed containers |
+//! | `scratchpad` | A hidden and a shown scratchpad window next to two tiled windows |
+//! | `fullscreen` | A focused fullscreen window next to a tabbed container |
+//! | `vertical-outputs` | A laptop output below an external one |
+//! | `empty-workspace` | Like `vertical-outputs` with the focused workspace on the laptop empty |
+//! | `focused-container` | Like `vertical-outputs` with a focused split container left of a window |
+
+use anyhow::{Context, Result};
+
+use crate::tree::TreeNode;
+
+/// The names and JSON of every tree in the corpus.
+pub const FIXTURES: &[(&str, &str)] = &[
+    (
+        "multi-output",
+        include_str!("../../tests/fixtures/trees/multi-output.json"),
+    ),
+    (
+        "scratchpad",
+        include_str!("../../tests/fixtures/trees/scratchpad.json"),
+    ),
+    (
+        "fullscreen",
+        include_str!("../../tests/fixtures/trees/fullscreen.json"),
+    ),
+    (
+        "vertical-outputs",
+        include_str!("../../tests/fixtures/trees/vertical-outputs.json"),
+    ),
+    (
+        "empty-workspace",
+        include_str!("../../tests/fixtures/trees/empty-workspace.json"),
+    ),
+    (
+        "focused-container",
+        include_str!("../../tests/fixtures/trees/focused-container.json"),
+    ),
+];
+
+/// Decode the named tree of the corpus.
+pub fn load(name: &str) -> Result<TreeNode> {
+    let (_, json) = FIXTURES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .ok_or_else(|| anyhow::format_err!("No fixture named '{}'", name))?;
+    serde_json::from_str(json).with_context(|| format!("decoding fixture '{}'", name))
+}
+
+/// Decode every tree of the corpus along with its name.
+pub fn all() -> Result<Vec<(&'static str, TreeNode)>> {
+    FIXTURES
+        .iter()
+        .map(|(name, _)| Ok((*name, load(name)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{CrossOutput, FocusConfig, OnEdge},
+        plan_focus, plan_toggle_layer, Direction,
+    };
+
+    #[test]
+    fn fixtures_decode_and_validate() {
+        for (name, tree) in all().unwrap() {
+            tree.validate()
+                .with_context(|| format!("fixture '{}'", name))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn golden_focus_commands() {
+        // Moves towards an edge are left to sway, which wraps or crosses to the adjacent output.
+        let golden = [
+            ("multi-output", Direction::Left, true, "focus left"),
+            ("multi-output", Direction::Right, true, "[con_id=11] focus"),
+            ("multi-output", Direction::Up, false, ""),
+            ("scratchpad", Direction::Left, true, "[con_id=10] focus"),
+            ("scratchpad", Direction::Right, true, "focus right"),
+            ("scratchpad", Direction::Right, false, ""),
+            ("fullscreen", Direction::Right, true, "[con_id=13] focus"),
+            ("vertical-outputs", Direction::Up, false, "focus up"),
+            ("vertical-outputs", Direction::Down, false, ""),
+            (
+                "focused-container",
+                Direction::Right,
+                false,
+                "[con_id=23] focus",
+            ),
+            ("focused-container", Direction::Down, false, "focus down"),
+            ("focused-container", Direction::Left, false, ""),
+        ];
+        for (name, dir, wrap, expected) in golden {
+            let tree = load(name).unwrap();
+            let options = FocusConfig {
+                wrap,
+                ..Default::default()
+            };
+            let plan = plan_focus(&tree, dir, &options);
+            assert_eq!(plan.commands.join("; "), expected, "{} {:?}", name, dir);
+        }
+    }
+
+    #[test]
+    fn golden_empty_workspace_commands() {
+        let tree = load("empty-workspace").unwrap();
+        let golden = [
+            (OnEdge::FocusOutput, Direction::Up, "focus output up"),
+            (OnEdge::FocusOutput, Direction::Down, ""),
+            (
+                OnEdge::SwitchWorkspace,
+                Direction::Right,
+                "workspace next_on_output",
+            ),
+            (
+                OnEdge::SwitchWorkspace,
+                Direction::Up,
+                "workspace prev_on_output",
+            ),
+            (OnEdge::Nothing, Direction::Up, ""),
+            (OnEdge::Wrap, Direction::Up, ""),
+        ];
+        for (empty_workspace, dir, expected) in golden {
+            let options = FocusConfig {
+                empty_workspace,
+                ..Default::default()
+            };
+            let plan = plan_focus(&tree, dir, &options);
+            assert_eq!(
+                plan.commands.join("; "),
+                expected,
+                "{:?} {:?}",
+                empty_workspace,
+                dir
+            );
+        }
+        let geometric = FocusConfig {
+            cross_output: CrossOutput::Geometric,
+            ..Default::default()
+        };
+        assert_eq!(
+            plan_focus(&tree, Direction::Up, &geometric).target,
+            Some(22)
+        );
+    }
+
+    #[test]
+    fn golden_toggle_layer_commands() {
+        let tree = load("scratchpad").unwrap();
+        assert_eq!(
+            plan_toggle_layer(&tree).commands.join("; "),
+            "[con_id=11] mark --add _haswaynav_tiled_4; [con_id=13] focus"
+        );
+    }
+}
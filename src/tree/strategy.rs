@@ -0,0 +1,183 @@
+//! Proptest strategies generating random trees like the ones sway reports, see [tree].
+
+use proptest::{collection::vec, prelude::*, sample::Index};
+
+use crate::tree::{Layout, NodeType, Rect, TreeNode};
+
+#[derive(Debug, Clone)]
+/// The shape of a tiled container before ids and rects are assigned.
+enum Shape {
+    Window,
+    Container(Layout, Vec<Shape>),
+}
+
+fn shape() -> impl Strategy<Value = Shape> {
+    let layout = prop_oneof![
+        Just(Layout::SplitH),
+        Just(Layout::SplitV),
+        Just(Layout::Tabbed),
+        Just(Layout::Stacked),
+    ];
+    Just(Shape::Window).prop_recursive(4, 32, 4, move |inner| {
+        (layout.clone(), vec(inner, 1..4)).prop_map(|(l, c)| Shape::Container(l, c))
+    })
+}
+
+#[derive(Default)]
+/// Hands out the ids while building a tree.
+struct Ids {
+    next: i32,
+    windows: Vec<i32>,
+    /// The tiled containers with children, which sway focuses after `focus parent`
+    containers: Vec<i32>,
+}
+
+impl Ids {
+    fn node(&mut self, node_type: NodeType, layout: Layout, rect: Rect) -> TreeNode {
+        self.next += 1;
+        TreeNode {
+            id: self.next,
+            name: Some(self.next.to_string()),
+            node_type,
+            layout,
+            rect,
+            ..Default::default()
+        }
+    }
+}
+
+/// Build the tiled container dividing its rect among the children like sway does.
+fn build(shape: &Shape, rect: Rect, ids: &mut Ids) -> TreeNode {
+    match shape {
+        Shape::Window => {
+            let node = ids.node(NodeType::Con, Layout::None, rect);
+            ids.windows.push(node.id);
+            node
+        }
+        Shape::Container(layout, children) => {
+            let mut node = ids.node(NodeType::Con, *layout, rect);
+            ids.containers.push(node.id);
+            node.nodes = divide(*layout, rect, children.len())
+                .into_iter()
+                .zip(children)
+                .map(|(rect, child)| build(child, rect, ids))
+                .collect();
+            set_focus_order(&mut node);
+            node
+        }
+    }
+}
+
+/// The rects of the children of a container with the layout.
+fn divide(layout: Layout, rect: Rect, count: usize) -> Vec<Rect> {
+    let count = count as i32;
+    (0..count)
+        .map(|i| match layout {
+            Layout::SplitH => Rect {
+                x: rect.x + i * rect.width / count,
+                width: (i + 1) * rect.width / count - i * rect.width / count,
+                ..rect
+            },
+            Layout::SplitV => Rect {
+                y: rect.y + i * rect.height / count,
+                height: (i + 1) * rect.height / count - i * rect.height / count,
+                ..rect
+            },
+            _ => rect,
+        })
+        .collect()
+}
+
+/// Give the children some focus order other than their order in the tree.
+fn set_focus_order(node: &mut TreeNode) {
+    let mut focus: Vec<i32> = node
+        .nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .map(|n| n.id)
+        .collect();
+    if !focus.is_empty() {
+        let len = focus.len();
+        focus.rotate_left(node.id as usize % len);
+    }
+    node.focus = focus;
+}
+
+/// Focus the node with the id, moving the children leading to it to the front of the focus arrays.
+fn focus(node: &mut TreeNode, id: i32) -> bool {
+    if node.id == id {
+        node.focused = true;
+        return true;
+    }
+    let on_path = node
+        .nodes
+        .iter_mut()
+        .chain(&mut node.floating_nodes)
+        .find_map(|c| focus(c, id).then_some(c.id));
+    if let Some(child) = on_path {
+        node.focus.retain(|id| *id != child);
+        node.focus.insert(0, child);
+    }
+    on_path.is_some()
+}
+
+/// A tree with one to three outputs side by side, each with one to three workspaces holding random
+/// tiled layouts and a few floating windows, with one of the windows focused if there are any, or
+/// now and then one of the containers.
+pub(crate) fn tree() -> impl Strategy<Value = TreeNode> {
+    let workspace = (vec(shape(), 0..4), 0..3usize);
+    let choice = (any::<Index>(), proptest::bool::weighted(0.2));
+    (vec(vec(workspace, 1..4), 1..4), choice).prop_map(|(outputs, (focused, container))| {
+        let mut ids = Ids::default();
+        let size = |x| Rect {
+            x,
+            y: 0,
+            width: 1000,
+            height: 800,
+        };
+        let mut root = ids.node(
+            NodeType::Root,
+            Layout::SplitH,
+            Rect {
+                width: 1000 * outputs.len() as i32,
+                ..size(0)
+            },
+        );
+        for (idx, workspaces) in outputs.iter().enumerate() {
+            let rect = size(1000 * idx as i32);
+            let mut output = ids.node(NodeType::Output, Layout::Output, rect);
+            for (tiled, floating) in workspaces {
+                let mut workspace = ids.node(NodeType::Workspace, Layout::SplitH, rect);
+                workspace.nodes = divide(Layout::SplitH, rect, tiled.len())
+                    .into_iter()
+                    .zip(tiled)
+                    .map(|(rect, shape)| build(shape, rect, &mut ids))
+                    .collect();
+                workspace.floating_nodes = (0..*floating as i32)
+                    .map(|i| {
+                        let rect = Rect {
+                            x: rect.x + 100 + 300 * i,
+                            y: 100 + 200 * i,
+                            width: 200,
+                            height: 150,
+                        };
+                        let node = ids.node(NodeType::FloatingCon, Layout::None, rect);
+                        ids.windows.push(node.id);
+                        node
+                    })
+                    .collect();
+                set_focus_order(&mut workspace);
+                output.nodes.push(workspace);
+            }
+            set_focus_order(&mut output);
+            root.nodes.push(output);
+        }
+        set_focus_order(&mut root);
+        if container && !ids.containers.is_empty() {
+            focus(&mut root, *focused.get(&ids.containers));
+        } else if !ids.windows.is_empty() {
+            focus(&mut root, *focused.get(&ids.windows));
+        }
+        root
+    })
+}
@@ -0,0 +1,117 @@
+//! A view of the windows in the tree, the leaf containers showing an application, with the
+//! properties most users of the tree are after rather than every field of [TreeNode].
+
+use crate::tree::{cursor::Cursor, NodeType, Rect, TreeNode};
+
+#[derive(Debug, Clone)]
+/// A window in the tree, see [windows].
+pub struct Window<'a> {
+    /// The id of the container showing the window
+    pub id: i32,
+    /// The app id, or the X11 class for Xwayland windows
+    pub app_id: Option<&'a str>,
+    /// The title
+    pub title: Option<&'a str>,
+    /// The PID of the application owning the window
+    pub pid: Option<i32>,
+    /// The absolute geometry of the window
+    pub rect: Rect,
+    /// The name of the workspace the window is on, `__i3_scratch` in the scratchpad
+    pub workspace: Option<&'a str>,
+    /// Whether the window is floating
+    pub floating: bool,
+    /// Whether the window is shown, not hidden behind a tab or on a workspace which is not shown
+    pub visible: bool,
+    /// Whether the window has the urgent hint set
+    pub urgent: bool,
+    /// Whether the window has the focus
+    pub focused: bool,
+    /// The marks of the window
+    pub marks: &'a [String],
+    cursor: Cursor<'a>,
+}
+
+impl<'a> Window<'a> {
+    /// The window at the cursor, `None` when the node is not a window.
+    pub fn at(cursor: Cursor<'a>) -> Option<Self> {
+        let node = cursor.get_node();
+        let leaf = matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+            && node.nodes.is_empty()
+            && node.floating_nodes.is_empty();
+        if !leaf {
+            return None;
+        }
+        let class = || node.window_properties.as_ref()?.class.as_deref();
+        let floating = node.node_type == NodeType::FloatingCon
+            || cursor
+                .ancestors()
+                .iter()
+                .any(|a| a.get_node().node_type == NodeType::FloatingCon);
+        Some(Window {
+            id: node.id,
+            app_id: node.app_id.as_deref().or_else(class),
+            title: node.name.as_deref(),
+            pid: node.pid,
+            rect: node.rect,
+            workspace: cursor
+                .workspace()
+                .and_then(|w| w.get_node().name.as_deref()),
+            floating,
+            // The slim tree leaves out the visibility sway reports.
+            visible: node.visible.unwrap_or_else(|| cursor.is_visible()),
+            urgent: node.urgent,
+            focused: node.focused,
+            marks: &node.marks,
+            cursor,
+        })
+    }
+
+    /// The node of the window with all the fields sway reports.
+    pub fn node(&self) -> &'a TreeNode {
+        self.cursor.get_node()
+    }
+
+    /// A cursor at the window to navigate the tree from there.
+    pub fn cursor(&self) -> &Cursor<'a> {
+        &self.cursor
+    }
+}
+
+/// The windows in the tree, depth first with the tiled windows of a container before its floating
+/// ones, including the windows in the scratchpad.
+pub fn windows(tree: &TreeNode) -> impl Iterator<Item = Window<'_>> {
+    tree.into_iter().filter_map(Window::at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn windows_of_the_tree() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let ids: Vec<i32> = windows(&tree).map(|w| w.id).collect();
+        assert_eq!(
+            ids,
+            [
+                99, 409, 126, 11, 130, 139, 206, 259, 355, 363, 334, 79, 205, 184, 395, 396, 255,
+                304
+            ]
+        );
+
+        let focused = windows(&tree).find(|w| w.focused).unwrap();
+        assert_eq!(focused.id, 126);
+        assert_eq!(focused.app_id, Some("Alacritty"));
+        assert_eq!(focused.workspace, Some("2"));
+        assert!(focused.visible && !focused.floating);
+        assert_eq!(focused.node().id, 126);
+        // Workspace 1 is not shown on eDP-1.
+        assert!(!windows(&tree).find(|w| w.id == 99).unwrap().visible);
+
+        let tree = fixtures::load("scratchpad").unwrap();
+        let keepass = windows(&tree).find(|w| w.id == 12).unwrap();
+        assert_eq!(keepass.workspace, Some("__i3_scratch"));
+        assert!(keepass.floating);
+    }
+}
@@ -0,0 +1,157 @@
+//! Rolling back the last composite operation, such as `isolate-app` or `profile apply`, with
+//! `haswaynav undo`.
+//!
+//! Composite operations are planned together with their inverse, which is saved before the
+//! operation runs so it can be rolled back even when the operation fails halfway. The inverse goes
+//! to the daemon's journal, which also records windows moved to another workspace or (un)floated,
+//! and only to the undo file when no daemon is running.
+
+use std::{os::unix::net::UnixStream, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    daemon::{default_socket_path, request, Request, Response},
+    run_checked,
+    text::quote,
+    tree::TreeNode,
+    Cursor,
+};
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+/// The commands of a composite operation and the commands rolling it back.
+pub struct Plan {
+    /// The windows changed by the operation
+    pub ids: Vec<i32>,
+    /// The commands carrying out the operation
+    pub forward: Vec<String>,
+    /// The commands restoring what the operation changed
+    pub inverse: Vec<String>,
+}
+
+/// The path of the file holding the inverse of the last composite operation,
+/// `$XDG_RUNTIME_DIR/haswaynav.undo`.
+pub fn undo_path() -> Result<PathBuf> {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|s| !s.is_empty())
+        .context("Environment variable 'XDG_RUNTIME_DIR' which specifies where to keep the undo commands is not defined")?;
+    Ok(PathBuf::from(runtime_dir).join("haswaynav.undo"))
+}
+
+/// The commands moving the windows back to the workspaces they are on in the tree, restoring
+/// floating windows as well, and returning the focus to the window focused in the tree.
+pub fn move_back(tree: &TreeNode, ids: &[i32]) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut focused = None;
+    for window in Cursor::new(tree).iter() {
+        let node = window.get_node();
+        if node.focused {
+            focused = Some(node.id);
+        }
+        if !ids.contains(&node.id) {
+            continue;
+        }
+        let Some(workspace) = window.workspace().and_then(|w| w.get_node().name.clone()) else {
+            continue;
+        };
+        let floating = if window.is_floating() {
+            ", floating enable"
+        } else {
+            ""
+        };
+        commands.push(format!(
+            "[con_id={}] move container to workspace {}{}",
+            node.id,
+            quote(&workspace),
+            floating
+        ));
+    }
+    if let Some(id) = focused {
+        commands.push(format!("[con_id={}] focus", id));
+    }
+    commands
+}
+
+/// Save the inverse of the plan and then run it. The inverse is journaled by the daemon, or saved
+/// to the undo file when there is no daemon. Failing to save the inverse is only logged since it
+/// should not prevent the operation. Without `journal`, as when running against a snapshot, the
+/// inverse is not saved at all.
+pub fn run_plan(socket: &mut UnixStream, plan: &Plan, journal: bool) -> Result<()> {
+    // An empty plan changes nothing, so it must not replace what can be undone.
+    if plan.forward.is_empty() {
+        return Ok(());
+    }
+    if journal {
+        save_inverse(plan);
+    }
+    run_checked(socket, &plan.forward.join("; "))
+}
+
+/// Journal the inverse of the plan in the daemon, or write it to the undo file.
+fn save_inverse(plan: &Plan) {
+    let journal = Request::Journal {
+        ids: plan.ids.clone(),
+        commands: plan.inverse.clone(),
+    };
+    match default_socket_path().and_then(|path| request(&path, &journal)) {
+        Ok(Response::Ok) => (),
+        result => {
+            tracing::debug!(?result, "journaling in the daemon failed");
+            if let Err(err) = write_undo_file(&plan.inverse) {
+                tracing::warn!("the operation can not be undone: {:#}", err);
+            }
+        }
+    }
+}
+
+fn write_undo_file(commands: &[String]) -> Result<()> {
+    let path = undo_path()?;
+    let mut contents = commands.join("\n");
+    contents.push('\n');
+    std::fs::write(&path, contents)
+        .with_context(|| format!("writing undo file '{}'", path.display()))
+}
+
+/// Roll back the latest change journaled by the daemon or, when the daemon is not running or has
+/// nothing to undo, the last composite operation saved in the undo file.
+#[tracing::instrument(skip(socket))]
+pub fn undo(socket: &mut UnixStream) -> Result<()> {
+    match default_socket_path().and_then(|path| request(&path, &Request::Undo)) {
+        Ok(Response::Ok) => return Ok(()),
+        Ok(Response::NothingToUndo) => (),
+        Ok(Response::Error { message }) => anyhow::bail!("{}", message),
+        Ok(response) => anyhow::bail!("Daemon replied {:?} when asked to undo", response),
+        Err(err) => tracing::debug!("undoing in the daemon failed: {:#}", err),
+    }
+    let path = undo_path()?;
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("Nothing to undo")
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("reading undo file '{}'", path.display()))
+        }
+    };
+    std::fs::remove_file(&path)
+        .with_context(|| format!("removing undo file '{}'", path.display()))?;
+    let commands: Vec<&str> = contents.lines().filter(|l| !l.is_empty()).collect();
+    if commands.is_empty() {
+        return Ok(());
+    }
+    run_checked(socket, &commands.join("; "))
+}
+
+#[test]
+fn test_move_back() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+
+    assert_eq!(
+        move_back(&tree, &[99, 126]),
+        [
+            "[con_id=99] move container to workspace \"1\"",
+            "[con_id=126] move container to workspace \"2\"",
+            "[con_id=126] focus",
+        ]
+    );
+}
@@ -0,0 +1,126 @@
+//! Waiting for windows to appear or to close, so scripts can launch an app, wait for its window and
+//! then act on it without polling the tree in a loop.
+
+use std::{
+    os::unix::net::UnixStream,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use crate::{
+    config::Pattern,
+    messages::{get_tree, receive_event, subscribe},
+    query::WindowInfo,
+    tree::{windows, Event, TreeNode, Window},
+};
+
+#[derive(Debug, Clone, Default)]
+/// The window `haswaynav wait` waits for, every given property has to match.
+pub struct Condition {
+    /// Pattern matched against the app id, or the X11 class for Xwayland windows.
+    pub app_id: Option<Pattern>,
+    /// Pattern matched against the title.
+    pub title: Option<Pattern>,
+    /// The name of the workspace the window has to be on.
+    pub workspace: Option<String>,
+    /// Wait until no window matches rather than until one does.
+    pub closed: bool,
+}
+
+impl Condition {
+    /// The first window in the tree matching the condition.
+    pub fn find<'a>(&self, tree: &'a TreeNode) -> Option<Window<'a>> {
+        windows(tree).find(|w| {
+            self.app_id.as_ref().is_none_or(|p| p.matches(w.app_id))
+                && self.title.as_ref().is_none_or(|p| p.matches(w.title))
+                && self
+                    .workspace
+                    .as_ref()
+                    .is_none_or(|name| w.workspace == Some(name.as_str()))
+        })
+    }
+}
+
+/// Wait until a window matching the condition exists, or with `closed` until none does, checking
+/// the tree again after every window event. Returns the window found, or `None` once they closed.
+/// Fails when the condition is not met before the timeout.
+#[tracing::instrument(skip(socket, events))]
+pub fn wait_for(
+    socket: &mut UnixStream,
+    mut events: UnixStream,
+    condition: &Condition,
+    timeout: Option<Duration>,
+) -> Result<Option<WindowInfo>> {
+    // Subscribe before looking at the tree so a window appearing in between is not missed.
+    subscribe(&mut events, &["window"])?;
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let timed_out = || {
+        let what = if condition.closed { "close" } else { "appear" };
+        anyhow::format_err!("The window did not {} in time", what)
+    };
+    loop {
+        let tree = get_tree(socket)?;
+        match (condition.find(&tree), condition.closed) {
+            (Some(window), false) => return Ok(Some(WindowInfo::from(window.cursor()))),
+            (None, true) => return Ok(None),
+            _ => (),
+        }
+        loop {
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(timed_out());
+                }
+                events.set_read_timeout(Some(remaining))?;
+            }
+            match receive_event(&mut events) {
+                Ok(Event::Window(_)) => break,
+                Ok(_) => (),
+                Err(_) if deadline.is_some_and(|d| Instant::now() >= d) => return Err(timed_out()),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::fixtures;
+
+    #[test]
+    fn find_matching_window() {
+        let tree = fixtures::load("multi-output").unwrap();
+        let find = |condition: Condition| condition.find(&tree).map(|w| w.id);
+        let pattern = |s: &str| Some(s.parse::<Pattern>().unwrap());
+
+        assert!(find(Condition {
+            app_id: pattern("^firefox$"),
+            ..Condition::default()
+        })
+        .is_some());
+        assert_eq!(
+            find(Condition {
+                app_id: pattern("Alacritty"),
+                workspace: Some("2".to_string()),
+                ..Condition::default()
+            }),
+            Some(126)
+        );
+        assert_eq!(
+            find(Condition {
+                title: pattern("Budget"),
+                workspace: Some("2".to_string()),
+                ..Condition::default()
+            }),
+            None
+        );
+        assert!(find(Condition {
+            app_id: pattern("evince"),
+            title: pattern("Payslip"),
+            ..Condition::default()
+        })
+        .is_some());
+    }
+}
@@ -0,0 +1,445 @@
+//! Switching workspaces by number, returning to the window focused last on the workspace,
+//! gathering windows on a workspace of their own and swapping the contents of workspaces.
+
+use std::{collections::BTreeMap, os::unix::net::UnixStream};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    config::{home_workspace, Rule},
+    daemon::{remembered_window, Request},
+    messages::{get_tree, get_tree_slim, get_workspaces},
+    run_checked,
+    text::quote,
+    tree::{cursor::find_focused_fast, NodeType, OutputReply, TreeNode, WorkspaceReply},
+    undo::{move_back, run_plan, Plan},
+};
+
+/// Switch to the workspace with the given number like `workspace number` does. When the daemon
+/// remembers the window focused last on the workspace and it is still there that window is focused,
+/// rather than relying on sway which may pick another one after containers were moved around.
+/// Without `restore` the daemon is not asked.
+#[tracing::instrument(skip(socket))]
+pub fn focus_workspace(socket: &mut UnixStream, number: i32, restore: bool) -> Result<()> {
+    let workspaces = get_workspaces(socket)?;
+    let window = match workspaces.iter().find(|w| w.num == number) {
+        Some(workspace) if restore => {
+            let request = Request::LastFocusedOnWorkspace {
+                workspace: workspace.name.clone(),
+            };
+            remembered_window(socket, &request, NodeType::Workspace, &workspace.name)?
+        }
+        _ => None,
+    };
+    match window {
+        Some(id) => run_checked(socket, &format!("[con_id={}] focus", id)),
+        None => run_checked(socket, &format!("workspace number {}", number)),
+    }
+}
+
+/// Plan the commands moving every workspace to the first active output of its preferred outputs,
+/// see [crate::config::DaemonConfig::workspace_outputs]. The windows on a workspace select it, so
+/// the focus stays where it is.
+pub fn plan_workspace_outputs(
+    preferred: &BTreeMap<String, Vec<String>>,
+    workspaces: &[WorkspaceReply],
+    outputs: &[OutputReply],
+) -> Vec<String> {
+    let active = |name: &String| outputs.iter().any(|o| o.active && &o.name == name);
+    workspaces
+        .iter()
+        .filter_map(|workspace| {
+            let target = preferred.get(&workspace.name)?.iter().find(|o| active(o))?;
+            (*target != workspace.output).then(|| {
+                format!(
+                    "[workspace=\"^{}$\"] move workspace to output \"{}\"",
+                    regex::escape(&workspace.name),
+                    target
+                )
+            })
+        })
+        .collect()
+}
+
+/// The number sway gives a workspace from the digits its name starts with.
+fn workspace_number(name: &str) -> Option<i32> {
+    let digits = name.len() - name.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    name[..digits].parse().ok()
+}
+
+/// Plan the commands moving every window sharing the focused window's app id to the named
+/// workspace, or to the lowest numbered workspace not in use, arranging them as tabs and keeping
+/// the focused window focused. Windows in the scratchpad are left alone. The inverse moves the
+/// windows back to their workspaces.
+pub fn plan_isolate_app(tree: &TreeNode, workspace: Option<&str>) -> Result<Plan> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    if focused.get_node().node_type == NodeType::Workspace {
+        anyhow::bail!("The focused workspace has no windows");
+    }
+    if !focused.get_node().nodes.is_empty() {
+        anyhow::bail!("The focused container holds several windows, focus one of them");
+    }
+    let app_id = focused
+        .get_node()
+        .app_id
+        .as_deref()
+        .context("The focused window has no app id")?;
+    let target = match workspace {
+        Some(name) => quote(name),
+        None => {
+            let used: Vec<i32> = tree
+                .into_iter()
+                .filter(|c| c.get_node().node_type == NodeType::Workspace)
+                .filter_map(|c| c.get_node().name.as_deref().and_then(workspace_number))
+                .collect();
+            let free = (1..).find(|n| !used.contains(n)).expect("a free number");
+            format!("number {}", free)
+        }
+    };
+
+    let mut commands = Vec::new();
+    let mut ids = Vec::new();
+    for window in tree.into_iter().filter(|c| {
+        let node = c.get_node();
+        matches!(node.node_type, NodeType::Con | NodeType::FloatingCon)
+            && node.nodes.is_empty()
+            && node.app_id.as_deref() == Some(app_id)
+            && c.workspace()
+                .is_some_and(|w| w.get_node().name.as_deref() != Some("__i3_scratch"))
+    }) {
+        let node = window.get_node();
+        ids.push(node.id);
+        let floating = if window.is_floating() {
+            "floating disable, "
+        } else {
+            ""
+        };
+        commands.push(format!(
+            "[con_id={}] {}move container to workspace {}",
+            node.id, floating, target
+        ));
+    }
+    let id = focused.get_node().id;
+    commands.push(format!("[con_id={}] layout tabbed", id));
+    commands.push(format!("[con_id={}] focus", id));
+    Ok(Plan {
+        forward: commands,
+        inverse: move_back(tree, &ids),
+        ids,
+    })
+}
+
+/// Move every window of the focused app to a workspace of its own as tabs and follow them there,
+/// see [plan_isolate_app]. The inverse is saved for `undo` with `journal`, see [run_plan].
+#[tracing::instrument(skip(socket))]
+pub fn isolate_app(socket: &mut UnixStream, workspace: Option<&str>, journal: bool) -> Result<()> {
+    // The slim tree leaves out the app id.
+    let tree = get_tree(socket)?;
+    run_plan(socket, &plan_isolate_app(&tree, workspace)?, journal)
+}
+
+/// Plan moving the focused window to its home workspace from the rules, see
+/// [crate::config::Rule::workspace]. Sway creates the workspace if needed and the focus stays
+/// where it is. Nothing is moved when the window is home already.
+pub fn plan_send_home(tree: &TreeNode, rules: &[Rule]) -> Result<Plan> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    let node = focused.get_node();
+    if !matches!(node.node_type, NodeType::Con | NodeType::FloatingCon) || !node.nodes.is_empty() {
+        anyhow::bail!("The focused node is not a window");
+    }
+    let home = home_workspace(rules, node).with_context(|| {
+        format!(
+            "No rule gives a home workspace to windows of '{}'",
+            node.app_id.as_deref().unwrap_or_default()
+        )
+    })?;
+    let current = focused.workspace().and_then(|w| w.get_node().name.clone());
+    if current.as_deref() == Some(home) {
+        tracing::info!(home, "the window is home already");
+        return Ok(Plan::default());
+    }
+    Ok(Plan {
+        ids: vec![node.id],
+        forward: vec![format!(
+            "[con_id={}] move container to workspace {}",
+            node.id,
+            quote(home)
+        )],
+        inverse: move_back(tree, &[node.id]),
+    })
+}
+
+/// Move the focused window to its home workspace, see [plan_send_home] and for `journal`
+/// [run_plan].
+#[tracing::instrument(skip(socket, rules))]
+pub fn send_home(socket: &mut UnixStream, rules: &[Rule], journal: bool) -> Result<()> {
+    // The slim tree leaves out the app id.
+    let tree = get_tree(socket)?;
+    run_plan(socket, &plan_send_home(&tree, rules)?, journal)
+}
+
+/// The workspace holding the contents of the first workspace while [plan_swap_workspaces] moves
+/// the second one's there. Sway reserves names starting with `__`.
+const SWAP_WORKSPACE: &str = "haswaynav-swap";
+
+/// What [swap_commands] moves off a workspace: a top-level tiled child, whose parent is what gets
+/// moved, and the floating windows.
+struct Contents {
+    workspace: String,
+    tiled: Option<i32>,
+    floating: Vec<i32>,
+}
+
+/// Plan the commands exchanging everything on the two workspaces, the second defaulting to the
+/// focused one. The tiled containers of a workspace are moved together, which makes sway wrap them
+/// in a container with the layout of the workspace, so the layouts and marks within are kept.
+/// The first workspace's contents wait on a temporary workspace while the second one's move over.
+/// The focus stays on the focused window.
+pub fn plan_swap_workspaces(tree: &TreeNode, first: &str, second: Option<&str>) -> Result<Plan> {
+    let focused = find_focused_fast(tree).context("Nothing is focused")?;
+    let second = match second {
+        Some(name) => name.to_string(),
+        None => focused
+            .workspace()
+            .and_then(|w| w.get_node().name.clone())
+            .context("The focused node is not on a workspace")?,
+    };
+    if first == second {
+        anyhow::bail!("Can not swap workspace '{}' with itself", first);
+    }
+    let contents = |name: &str| {
+        let ws = tree
+            .into_iter()
+            .find(|c| {
+                let node = c.get_node();
+                node.node_type == NodeType::Workspace && node.name.as_deref() == Some(name)
+            })
+            .with_context(|| format!("No workspace named '{}'", name))?;
+        let node = ws.get_node();
+        Ok::<_, anyhow::Error>(Contents {
+            workspace: name.to_string(),
+            tiled: node.nodes.first().map(|n| n.id),
+            floating: node.floating_nodes.iter().map(|n| n.id).collect(),
+        })
+    };
+    let (a, b) = (contents(first)?, contents(&second)?);
+    let ids: Vec<i32> = [&a, &b]
+        .iter()
+        .flat_map(|c| c.tiled.iter().chain(&c.floating).copied())
+        .collect();
+    let focus = format!("[con_id={}] focus", focused.get_node().id);
+
+    let mut forward = swap_commands(&a, &b);
+    forward.push(focus.clone());
+    // Now the parents of the tiled children are the containers sway wrapped them in, so moving
+    // them the same way with the roles of the workspaces exchanged swaps back.
+    let mut inverse = swap_commands(
+        &Contents {
+            workspace: b.workspace.clone(),
+            ..a
+        },
+        &Contents {
+            workspace: a.workspace.clone(),
+            ..b
+        },
+    );
+    inverse.push(focus);
+    Ok(Plan {
+        ids,
+        forward,
+        inverse,
+    })
+}
+
+/// The commands moving the contents of `a` to a temporary workspace, then those of `b` to the
+/// workspace of `a` and then the ones of `a` to the workspace of `b`.
+fn swap_commands(a: &Contents, b: &Contents) -> Vec<String> {
+    let mut commands = Vec::new();
+    let mut move_to = |contents: &Contents, workspace: &str| {
+        if let Some(id) = contents.tiled {
+            commands.push(format!("[con_id={}] focus", id));
+            commands.push("focus parent".to_string());
+            commands.push(format!("move container to workspace {}", quote(workspace)));
+        }
+        for id in &contents.floating {
+            commands.push(format!(
+                "[con_id={}] move container to workspace {}",
+                id,
+                quote(workspace)
+            ));
+        }
+    };
+    move_to(a, SWAP_WORKSPACE);
+    move_to(b, &a.workspace);
+    move_to(a, &b.workspace);
+    commands
+}
+
+/// Exchange the contents of the two workspaces, see [plan_swap_workspaces] and for `journal`
+/// [run_plan].
+#[tracing::instrument(skip(socket))]
+pub fn swap_workspace_contents(
+    socket: &mut UnixStream,
+    first: &str,
+    second: Option<&str>,
+    journal: bool,
+) -> Result<()> {
+    let tree = get_tree_slim(socket)?;
+    run_plan(
+        socket,
+        &plan_swap_workspaces(&tree, first, second)?,
+        journal,
+    )
+}
+
+#[test]
+fn test_plan_send_home() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+    let rules: Vec<Rule> = toml::from_str::<BTreeMap<String, Vec<Rule>>>(
+        r#"
+        rules = [
+            { app_id = "^firefox$", workspace = "web" },
+            { app_id = "^Alacritty$" },
+            { app_id = "^Alacritty$", workspace = "2" },
+        ]
+        "#,
+    )
+    .unwrap()
+    .remove("rules")
+    .unwrap();
+
+    // The focused terminal is on workspace 2 already.
+    assert_eq!(plan_send_home(&tree, &rules).unwrap(), Plan::default());
+    let rules = &rules[..2];
+    assert!(plan_send_home(&tree, rules).is_err());
+    let rules = [Rule {
+        workspace: Some("term".to_string()),
+        ..rules[1].clone()
+    }];
+    let plan = plan_send_home(&tree, &rules).unwrap();
+    assert_eq!(
+        plan.forward,
+        ["[con_id=126] move container to workspace \"term\""]
+    );
+    assert_eq!(plan.inverse, move_back(&tree, &[126]));
+}
+
+#[test]
+fn test_plan_swap_workspaces() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+
+    let plan = plan_swap_workspaces(&tree, "3", None).unwrap();
+    assert_eq!(
+        plan.forward,
+        [
+            "[con_id=242] focus",
+            "focus parent",
+            "move container to workspace \"haswaynav-swap\"",
+            "[con_id=13] focus",
+            "focus parent",
+            "move container to workspace \"3\"",
+            "[con_id=242] focus",
+            "focus parent",
+            "move container to workspace \"2\"",
+            "[con_id=126] focus",
+        ]
+    );
+    assert_eq!(
+        plan.inverse[2],
+        "move container to workspace \"haswaynav-swap\""
+    );
+    assert_eq!(plan.inverse[5], "move container to workspace \"2\"");
+    assert_eq!(plan.inverse[8], "move container to workspace \"3\"");
+    assert!(plan_swap_workspaces(&tree, "2", None).is_err());
+    assert!(plan_swap_workspaces(&tree, "nope", None).is_err());
+}
+
+#[test]
+fn test_plan_isolate_app() {
+    let tree = crate::tree::fixtures::load("multi-output").unwrap();
+
+    let plan = plan_isolate_app(&tree, None).unwrap();
+    assert_eq!(plan.ids, [409, 126, 130, 139, 334, 255, 304]);
+    assert_eq!(
+        plan.forward,
+        [
+            "[con_id=409] move container to workspace number 8",
+            "[con_id=126] move container to workspace number 8",
+            "[con_id=130] move container to workspace number 8",
+            "[con_id=139] move container to workspace number 8",
+            "[con_id=334] move container to workspace number 8",
+            "[con_id=255] move container to workspace number 8",
+            "[con_id=304] move container to workspace number 8",
+            "[con_id=126] layout tabbed",
+            "[con_id=126] focus",
+        ]
+    );
+    assert_eq!(
+        plan.inverse,
+        [
+            "[con_id=409] move container to workspace \"10\"",
+            "[con_id=126] move container to workspace \"2\"",
+            "[con_id=130] move container to workspace \"2\"",
+            "[con_id=139] move container to workspace \"2\"",
+            "[con_id=334] move container to workspace \"3\"",
+            "[con_id=255] move container to workspace \"9\"",
+            "[con_id=304] move container to workspace \"9\"",
+            "[con_id=126] focus",
+        ]
+    );
+    assert_eq!(
+        plan_isolate_app(&tree, Some("my \"term\""))
+            .unwrap()
+            .forward[0],
+        "[con_id=409] move container to workspace \"my \\\"term\\\"\""
+    );
+    assert_eq!(workspace_number("10:web"), Some(10));
+    assert_eq!(workspace_number("web"), None);
+}
+
+#[test]
+fn test_plan_workspace_outputs() {
+    let preferred = BTreeMap::from([
+        (
+            "1".to_string(),
+            vec!["DP-1".to_string(), "eDP-1".to_string()],
+        ),
+        ("2".to_string(), vec!["eDP-1".to_string()]),
+        (
+            "3.x".to_string(),
+            vec!["HDMI-A-1".to_string(), "eDP-1".to_string()],
+        ),
+    ]);
+    let workspace = |name: &str, output: &str| WorkspaceReply {
+        name: name.to_string(),
+        output: output.to_string(),
+        ..Default::default()
+    };
+    let output = |name: &str, active| OutputReply {
+        name: name.to_string(),
+        active,
+        ..Default::default()
+    };
+    let workspaces = [
+        workspace("1", "eDP-1"),
+        workspace("2", "eDP-1"),
+        workspace("3.x", "DP-1"),
+        workspace("4", "DP-1"),
+    ];
+
+    assert_eq!(
+        plan_workspace_outputs(
+            &preferred,
+            &workspaces,
+            &[output("eDP-1", true), output("DP-1", true)]
+        ),
+        [
+            "[workspace=\"^1$\"] move workspace to output \"DP-1\"",
+            "[workspace=\"^3\\.x$\"] move workspace to output \"eDP-1\"",
+        ]
+    );
+    assert_eq!(
+        plan_workspace_outputs(&preferred, &workspaces, &[output("eDP-1", true)]),
+        ["[workspace=\"^3\\.x$\"] move workspace to output \"eDP-1\""]
+    );
+}
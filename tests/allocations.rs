@@ -0,0 +1,29 @@
+//! Counting the heap allocations of joining the commands for sway with dhat. Only the buffer is
+//! covered, fetching the tree and planning allocate. Every test binary can only profile once, so
+//! all the checks live in a single test.
+
+use haswaynav::CommandBuffer;
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+#[test]
+fn reused_command_buffer_does_not_allocate() {
+    let _profiler = dhat::Profiler::builder().testing().build();
+    let mut commands = CommandBuffer::default();
+    let assemble = |commands: &mut CommandBuffer, id: i32| {
+        commands.clear();
+        commands.push("focus mode_toggle");
+        commands.push(format_args!("[con_id={}] focus", id));
+    };
+    assemble(&mut commands, i32::MAX);
+    let warmed_up = dhat::HeapStats::get();
+
+    for id in 0..1000 {
+        assemble(&mut commands, id);
+    }
+
+    let stats = dhat::HeapStats::get();
+    dhat::assert_eq!(stats.total_blocks, warmed_up.total_blocks);
+    dhat::assert_eq!(commands.as_str(), "focus mode_toggle; [con_id=999] focus");
+}